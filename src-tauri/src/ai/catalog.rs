@@ -0,0 +1,436 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::client::{AnthropicClient, OpenAIClient};
+use super::AIError;
+
+/// ローカルの Ollama サーバー
+pub const OLLAMA_API_BASE: &str = "http://localhost:11434";
+
+/// Ollama は起動していなければすぐ失敗させる
+const OLLAMA_TIMEOUT_SECS: u64 = 5;
+
+/// 一覧 API がない Vertex AI で選べるモデル
+pub const VERTEX_MODELS: &[&str] = &["gemini-2.5-flash", "gemini-2.0-flash", "gemini-1.5-pro"];
+
+/// 一覧 API を使わない Groq で選べるモデル
+pub const GROQ_MODELS: &[&str] = &[
+    "llama-3.3-70b-versatile",
+    "llama-3.1-8b-instant",
+    "gemma2-9b-it",
+];
+
+/// OpenAI のモデル一覧のうちチャットで使うモデルの接頭辞
+const OPENAI_CHAT_PREFIXES: &[&str] = &["gpt-", "o1", "o3", "o4", "chatgpt-"];
+
+/// 接頭辞が一致してもチャットには使えないモデル（音声・画像・検索用）
+const OPENAI_NON_CHAT_MARKERS: &[&str] =
+    &["audio", "realtime", "transcribe", "tts", "image", "search"];
+
+/// モデル選択に表示する情報（`get_ai_model_info` の戻り値）
+///
+/// 料金表にないモデルは `context_window` と料金を 0 にする。
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelInfo {
+    pub id: String,
+    pub display_name: String,
+    pub context_window: u32,
+    /// USD / 1000 トークン
+    pub cost_per_1k_input: f64,
+    /// USD / 1000 トークン
+    pub cost_per_1k_output: f64,
+    pub supports_streaming: bool,
+    pub supports_vision: bool,
+}
+
+/// モデルの表示名・コンテキストウィンドウ・料金（USD / 100万トークン）・対応機能
+struct ModelSpec {
+    display_name: &'static str,
+    context_window: u32,
+    input_usd_per_mtok: f64,
+    output_usd_per_mtok: f64,
+    supports_streaming: bool,
+    supports_vision: bool,
+}
+
+/// API のモデル一覧に付け足す表示名・料金・対応機能
+///
+/// `MODEL_CONTEXT_WINDOWS` / `MODEL_INPUT_PRICES` と重なるモデルは値をそろえる。
+/// Ollama はタグ（`:latest` など）を除いた名前で引き、ローカル実行なので料金は 0。
+static MODEL_SPECS: phf::Map<&'static str, ModelSpec> = phf::phf_map! {
+    "gpt-4o" => ModelSpec {
+        display_name: "GPT-4o",
+        context_window: 128_000,
+        input_usd_per_mtok: 2.50,
+        output_usd_per_mtok: 10.00,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gpt-4o-mini" => ModelSpec {
+        display_name: "GPT-4o mini",
+        context_window: 128_000,
+        input_usd_per_mtok: 0.15,
+        output_usd_per_mtok: 0.60,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gpt-4.1" => ModelSpec {
+        display_name: "GPT-4.1",
+        context_window: 1_047_576,
+        input_usd_per_mtok: 2.00,
+        output_usd_per_mtok: 8.00,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gpt-4.1-mini" => ModelSpec {
+        display_name: "GPT-4.1 mini",
+        context_window: 1_047_576,
+        input_usd_per_mtok: 0.40,
+        output_usd_per_mtok: 1.60,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gpt-4.1-nano" => ModelSpec {
+        display_name: "GPT-4.1 nano",
+        context_window: 1_047_576,
+        input_usd_per_mtok: 0.10,
+        output_usd_per_mtok: 0.40,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "claude-3-haiku-20240307" => ModelSpec {
+        display_name: "Claude 3 Haiku",
+        context_window: 200_000,
+        input_usd_per_mtok: 0.25,
+        output_usd_per_mtok: 1.25,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "claude-3-5-haiku-20241022" => ModelSpec {
+        display_name: "Claude 3.5 Haiku",
+        context_window: 200_000,
+        input_usd_per_mtok: 0.80,
+        output_usd_per_mtok: 4.00,
+        supports_streaming: true,
+        supports_vision: false,
+    },
+    "claude-haiku-4-5-20251001" => ModelSpec {
+        display_name: "Claude Haiku 4.5",
+        context_window: 200_000,
+        input_usd_per_mtok: 1.00,
+        output_usd_per_mtok: 5.00,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "claude-sonnet-4-5-20250929" => ModelSpec {
+        display_name: "Claude Sonnet 4.5",
+        context_window: 200_000,
+        input_usd_per_mtok: 3.00,
+        output_usd_per_mtok: 15.00,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gemini-1.5-pro" => ModelSpec {
+        display_name: "Gemini 1.5 Pro",
+        context_window: 2_097_152,
+        input_usd_per_mtok: 1.25,
+        output_usd_per_mtok: 5.00,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gemini-2.0-flash" => ModelSpec {
+        display_name: "Gemini 2.0 Flash",
+        context_window: 1_048_576,
+        input_usd_per_mtok: 0.10,
+        output_usd_per_mtok: 0.40,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "gemini-2.5-flash" => ModelSpec {
+        display_name: "Gemini 2.5 Flash",
+        context_window: 1_048_576,
+        input_usd_per_mtok: 0.30,
+        output_usd_per_mtok: 2.50,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+    "llama-3.3-70b-versatile" => ModelSpec {
+        display_name: "Llama 3.3 70B (Groq)",
+        context_window: 131_072,
+        input_usd_per_mtok: 0.59,
+        output_usd_per_mtok: 0.79,
+        supports_streaming: true,
+        supports_vision: false,
+    },
+    "llama-3.1-8b-instant" => ModelSpec {
+        display_name: "Llama 3.1 8B (Groq)",
+        context_window: 131_072,
+        input_usd_per_mtok: 0.05,
+        output_usd_per_mtok: 0.08,
+        supports_streaming: true,
+        supports_vision: false,
+    },
+    "gemma2-9b-it" => ModelSpec {
+        display_name: "Gemma 2 9B (Groq)",
+        context_window: 8_192,
+        input_usd_per_mtok: 0.20,
+        output_usd_per_mtok: 0.20,
+        supports_streaming: true,
+        supports_vision: false,
+    },
+    "llama3.2" => ModelSpec {
+        display_name: "Llama 3.2",
+        context_window: 131_072,
+        input_usd_per_mtok: 0.0,
+        output_usd_per_mtok: 0.0,
+        supports_streaming: true,
+        supports_vision: false,
+    },
+    "qwen2.5" => ModelSpec {
+        display_name: "Qwen 2.5",
+        context_window: 32_768,
+        input_usd_per_mtok: 0.0,
+        output_usd_per_mtok: 0.0,
+        supports_streaming: true,
+        supports_vision: false,
+    },
+    "llava" => ModelSpec {
+        display_name: "LLaVA",
+        context_window: 4_096,
+        input_usd_per_mtok: 0.0,
+        output_usd_per_mtok: 0.0,
+        supports_streaming: true,
+        supports_vision: true,
+    },
+};
+
+/// モデルIDに料金表の情報を付ける（Ollama のタグは除いて引く）
+pub fn model_info(id: &str) -> ModelInfo {
+    let base = id.split(':').next().unwrap_or(id);
+    match MODEL_SPECS.get(id).or_else(|| MODEL_SPECS.get(base)) {
+        Some(spec) => ModelInfo {
+            id: id.to_string(),
+            display_name: spec.display_name.to_string(),
+            context_window: spec.context_window,
+            cost_per_1k_input: spec.input_usd_per_mtok / 1000.0,
+            cost_per_1k_output: spec.output_usd_per_mtok / 1000.0,
+            supports_streaming: spec.supports_streaming,
+            supports_vision: spec.supports_vision,
+        },
+        None => ModelInfo {
+            id: id.to_string(),
+            display_name: id.to_string(),
+            context_window: 0,
+            cost_per_1k_input: 0.0,
+            cost_per_1k_output: 0.0,
+            supports_streaming: true,
+            supports_vision: false,
+        },
+    }
+}
+
+/// モデルID一覧を ModelInfo にして ID 順に並べる
+fn to_model_info(ids: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<ModelInfo> {
+    let mut models: Vec<ModelInfo> = ids.into_iter().map(|id| model_info(id.as_ref())).collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models
+}
+
+fn is_openai_chat_model(id: &str) -> bool {
+    OPENAI_CHAT_PREFIXES.iter().any(|p| id.starts_with(p))
+        && !OPENAI_NON_CHAT_MARKERS.iter().any(|m| id.contains(m))
+}
+
+/// OpenAI のモデル一覧（1時間キャッシュ）からチャット用のモデルを返す
+pub async fn openai_model_info(client: &OpenAIClient) -> Result<Vec<ModelInfo>, AIError> {
+    let ids = client.list_models().await?;
+    Ok(to_model_info(
+        ids.iter().filter(|id| is_openai_chat_model(id)),
+    ))
+}
+
+/// Anthropic のモデル一覧（1時間キャッシュ）を返す
+pub async fn anthropic_model_info(client: &AnthropicClient) -> Result<Vec<ModelInfo>, AIError> {
+    Ok(to_model_info(client.list_models().await?))
+}
+
+/// 一覧 API のないプロバイダーの固定リストを返す
+pub fn static_model_info(ids: &[&str]) -> Vec<ModelInfo> {
+    to_model_info(ids)
+}
+
+/// Ollama の `GET /api/tags` でローカルにあるモデルを返す
+pub async fn ollama_model_info(base_url: &str) -> Result<Vec<ModelInfo>, AIError> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(OLLAMA_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+    let response = client
+        .get(format!("{}/api/tags", base_url))
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                AIError::Timeout
+            } else {
+                AIError::RequestFailed(e.to_string())
+            }
+        })?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(AIError::RequestFailed(format!("HTTP {}: {}", status, text)));
+    }
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AIError::ParseError(e.to_string()))?;
+    let names = json["models"]
+        .as_array()
+        .ok_or_else(|| AIError::ParseError("missing models array in Ollama tags".to_string()))?
+        .iter()
+        .filter_map(|model| model["name"].as_str());
+    Ok(to_model_info(names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{model_context_window, model_input_price};
+
+    /// `path` に GET されたら `body` を返すモックサーバーを起動し、ベース URL を返す
+    async fn spawn_json_server(path: &'static str, body: serde_json::Value) -> String {
+        let app = axum::Router::new().route(
+            path,
+            axum::routing::get(move || {
+                let body = body.clone();
+                async move { axum::Json(body) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    fn ids(models: &[ModelInfo]) -> Vec<&str> {
+        models.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_specs_match_pricing_tables() {
+        for (id, spec) in MODEL_SPECS.entries() {
+            if let Some(window) = model_context_window(id) {
+                assert_eq!(spec.context_window, window, "{}", id);
+            }
+            if let Some(price) = model_input_price(id) {
+                assert_eq!(spec.input_usd_per_mtok, price, "{}", id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_model_info_for_known_and_unknown_models() {
+        let info = model_info("gpt-4o-mini");
+        assert_eq!(info.display_name, "GPT-4o mini");
+        assert_eq!(info.context_window, 128_000);
+        assert!((info.cost_per_1k_input - 0.00015).abs() < 1e-12);
+        assert!((info.cost_per_1k_output - 0.0006).abs() < 1e-12);
+        assert!(info.supports_vision);
+
+        let info = model_info("some-new-model");
+        assert_eq!(info.display_name, "some-new-model");
+        assert_eq!(info.context_window, 0);
+        assert_eq!(info.cost_per_1k_input, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_openai_model_info_keeps_chat_models() {
+        let base_url = spawn_json_server(
+            "/models",
+            serde_json::json!({
+                "object": "list",
+                "data": [
+                    { "id": "gpt-4o-mini", "object": "model" },
+                    { "id": "whisper-1", "object": "model" },
+                    { "id": "text-embedding-3-small", "object": "model" },
+                    { "id": "gpt-4o-realtime-preview", "object": "model" },
+                    { "id": "gpt-4.1", "object": "model" }
+                ]
+            }),
+        )
+        .await;
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url);
+
+        let models = openai_model_info(&client).await.unwrap();
+        assert_eq!(ids(&models), vec!["gpt-4.1", "gpt-4o-mini"]);
+        assert_eq!(models[0].context_window, 1_047_576);
+        assert!((models[0].cost_per_1k_output - 0.008).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_model_info_merges_specs() {
+        let base_url = spawn_json_server(
+            "/models",
+            serde_json::json!({
+                "data": [
+                    { "id": "claude-sonnet-4-5-20250929", "type": "model" },
+                    { "id": "claude-haiku-4-5-20251001", "type": "model" },
+                    { "id": "claude-future-1", "type": "model" }
+                ],
+                "has_more": false
+            }),
+        )
+        .await;
+        let client = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url);
+
+        let models = anthropic_model_info(&client).await.unwrap();
+        assert_eq!(
+            ids(&models),
+            vec![
+                "claude-future-1",
+                "claude-haiku-4-5-20251001",
+                "claude-sonnet-4-5-20250929"
+            ]
+        );
+        assert_eq!(models[1].display_name, "Claude Haiku 4.5");
+        assert!((models[2].cost_per_1k_input - 0.003).abs() < 1e-12);
+        // 料金表にないモデルも選べるよう残す
+        assert_eq!(models[0].context_window, 0);
+    }
+
+    #[tokio::test]
+    async fn test_ollama_model_info_strips_tags() {
+        let base_url = spawn_json_server(
+            "/api/tags",
+            serde_json::json!({
+                "models": [
+                    { "name": "llama3.2:latest", "model": "llama3.2:latest" },
+                    { "name": "llava:7b", "model": "llava:7b" }
+                ]
+            }),
+        )
+        .await;
+
+        let models = ollama_model_info(&base_url).await.unwrap();
+        assert_eq!(ids(&models), vec!["llama3.2:latest", "llava:7b"]);
+        assert_eq!(models[0].display_name, "Llama 3.2");
+        assert_eq!(models[0].cost_per_1k_input, 0.0);
+        assert!(models[1].supports_vision);
+    }
+
+    #[test]
+    fn test_static_lists_are_priced() {
+        for id in VERTEX_MODELS.iter().chain(GROQ_MODELS) {
+            assert!(MODEL_SPECS.contains_key(*id), "{}", id);
+        }
+        assert_eq!(static_model_info(GROQ_MODELS).len(), GROQ_MODELS.len());
+    }
+}
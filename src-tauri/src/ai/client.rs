@@ -1,74 +1,462 @@
 use async_trait::async_trait;
 use reqwest::Client;
-use std::time::Duration;
+use reqwest_middleware::ClientWithMiddleware;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use super::streaming::{parse_anthropic_stream, parse_openai_stream};
-use super::{AIError, AIProvider, AIResponse, ProviderType, StreamChunk, TokenUsage};
+use super::catalog::OLLAMA_API_BASE;
+use super::fallback::FallbackProvider;
+use super::rotation::ModelRotation;
+use super::streaming::{
+    parse_anthropic_stream, parse_ollama_stream, parse_openai_stream, parse_vertex_stream,
+};
+use super::vertex_auth::{exchange_jwt, needs_refresh, ServiceAccountKey};
+use super::{
+    normalize_finish_reason, AIError, AIProvider, AIResponse, CostEstimate, ProviderOptions,
+    ProviderType, StreamChunk, TokenUsage,
+};
+use crate::config::app_config::AppConfig;
+use crate::http_debug::wrap_client;
 
-const DEFAULT_TIMEOUT_SECS: u64 = 30;
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
-const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+/// HTTP リクエスト1回のタイムアウトの既定値（AI_TIMEOUT_SECS）
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const OPENAI_DEFAULT_MODEL: &str = "gpt-4o-mini";
+/// TEST_MODE=1 で固定される OpenAI モデル（安価な小型モデル）
+const TEST_MODE_OPENAI_MODEL: &str = "gpt-4.1-nano";
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_DEFAULT_MODEL: &str = "claude-haiku-4-5-20251001";
+/// Anthropic は `max_tokens` が必須なので、モードで指定がなければこの値を送る
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 1024;
+const VERTEX_DEFAULT_MODEL: &str = "gemini-2.0-flash";
+const OLLAMA_DEFAULT_MODEL: &str = "llama3.2";
+/// Anthropic のプロンプトキャッシュ読み込みの単価（通常の入力単価に対する比率）
+const ANTHROPIC_CACHE_READ_RATIO: f64 = 0.1;
+
+/// Vertex AI で文字数課金になるモデルの入力単価（USD / 1000文字、空白を除く）
+static VERTEX_CHARACTER_PRICES: phf::Map<&'static str, f64> = phf::phf_map! {
+    "gemini-1.5-pro" => 0.0003125,
+    "gemini-1.5-flash" => 0.00001875,
+};
+
+/// OpenAI のキャッシュ入力の単価（通常の入力単価に対する比率）
+fn openai_cached_input_ratio(model: &str) -> Option<f64> {
+    if model.starts_with("gpt-4.1") {
+        Some(0.25)
+    } else if model.starts_with("gpt-4o") {
+        Some(0.5)
+    } else {
+        None
+    }
+}
+
+/// モデル一覧をキャッシュする時間
+const MODEL_LIST_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// 取得したモデル一覧と取得時刻（クライアント間で共有する）
+type ModelListCache = Arc<Mutex<Option<(Instant, Vec<String>)>>>;
+
+static OPENAI_MODEL_LIST: OnceLock<ModelListCache> = OnceLock::new();
+static ANTHROPIC_MODEL_LIST: OnceLock<ModelListCache> = OnceLock::new();
+
+/// サービスアカウントで取得したアクセストークンと失効時刻（クライアント間で共有する）
+type AccessTokenCache = Arc<Mutex<Option<(String, Instant)>>>;
+
+static VERTEX_ACCESS_TOKEN: OnceLock<AccessTokenCache> = OnceLock::new();
+static GCLOUD_ACCESS_TOKEN: OnceLock<AccessTokenCache> = OnceLock::new();
+
+/// `gcloud auth print-access-token` のトークンの有効期間（出力に含まれないため約1時間とみなす）
+const GCLOUD_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// gcloud のトークンを取り直すまでの余裕（失効5分前に更新する）
+const GCLOUD_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// キャッシュしたトークンが失効 `margin` 前までならそれを返し、そうでなければ
+/// `fetch` で取り直してキャッシュする
+async fn cached_access_token<F, Fut>(
+    cache: &AccessTokenCache,
+    margin: Duration,
+    fetch: F,
+) -> Result<String, AIError>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<(String, Instant), AIError>>,
+{
+    if let Ok(cached) = cache.lock() {
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if expires_at.saturating_duration_since(Instant::now()) > margin {
+                return Ok(token.clone());
+            }
+        }
+    }
+
+    let (token, expires_at) = fetch().await?;
+    if let Ok(mut cached) = cache.lock() {
+        *cached = Some((token.clone(), expires_at));
+    }
+    Ok(token)
+}
+
+/// `GET {base}/models` の `data[*].id` を返す（TTL 内ならキャッシュを使う）
+async fn fetch_model_ids(
+    request: reqwest_middleware::RequestBuilder,
+    cache: &ModelListCache,
+) -> Result<Vec<String>, AIError> {
+    if let Ok(cached) = cache.lock() {
+        if let Some((fetched_at, models)) = cached.as_ref() {
+            if fetched_at.elapsed() < MODEL_LIST_TTL {
+                return Ok(models.clone());
+            }
+        }
+    }
+
+    let response = request.send().await.map_err(|e| {
+        if e.is_timeout() {
+            AIError::Timeout
+        } else {
+            AIError::RequestFailed(e.to_string())
+        }
+    })?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        let message = format!("HTTP {}: {}", status, text);
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(AIError::RateLimited(message));
+        }
+        return Err(AIError::RequestFailed(message));
+    }
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AIError::ParseError(e.to_string()))?;
+    let models: Vec<String> = json["data"]
+        .as_array()
+        .ok_or_else(|| AIError::ParseError("missing data array in model list".to_string()))?
+        .iter()
+        .filter_map(|model| model["id"].as_str().map(str::to_string))
+        .collect();
+
+    if let Ok(mut cached) = cache.lock() {
+        *cached = Some((Instant::now(), models.clone()));
+    }
+    Ok(models)
+}
+
+/// AI API 用の HTTP クライアントを作る
+///
+/// タイムアウトは各クライアントがリクエストごとに指定する（`with_timeout`）。
+/// ここでは認証トークンの取得などそれ以外のリクエスト用に既定値を設定しておく。
+fn build_http_client() -> Result<ClientWithMiddleware, AIError> {
+    Ok(wrap_client(build_plain_client()?))
+}
+
+/// `DebugInterceptor` を挟まない HTTP クライアントを作る
+///
+/// OAuth のトークン交換のように、レスポンスボディに認証情報が入るリクエストに使う。
+fn build_plain_client() -> Result<Client, AIError> {
+    Client::builder()
+        .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AIError::RequestFailed(format!("failed to build HTTP client: {}", e)))
+}
+
+/// 既定の試行回数（初回を含む）
+const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 3;
+
+/// 既定の再試行までの待ち時間（再試行ごとに倍にする: 500ms, 1s, 2s…）
+const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// 一時的な失敗として再試行する HTTP ステータス
+const RETRYABLE_STATUSES: [u16; 5] = [429, 500, 502, 503, 504];
+
+/// 送信に失敗したリクエストを再試行するかの判定に使う失敗の種類
+enum SendFailure {
+    /// 接続できなかった（再試行する）
+    Connect(String),
+    /// 接続以外の送信エラー（タイムアウトは `AIError::Timeout`）
+    Fatal(AIError),
+    /// 成功以外のステータスが返った
+    Status(reqwest::StatusCode, String),
+}
+
+/// 429 / 5xx と接続エラーを指数バックオフで再試行してリクエストを送る
+///
+/// `build` は試行ごとに呼んでリクエストを組み立て直す。`max_attempts` は初回を含む回数で、
+/// n 回目の再試行の前に `base_delay * 2^(n-1)` 待つ。400 / 401 などそれ以外の失敗は
+/// 再試行しない。`retry_rate_limited` が false なら 429 はすぐ `AIError::RateLimited` で返す
+/// （モデルのローテーションで別のモデルに切り替えるため）。
+async fn retry_request(
+    max_attempts: u32,
+    base_delay: Duration,
+    retry_rate_limited: bool,
+    build: impl Fn() -> reqwest_middleware::RequestBuilder,
+) -> Result<reqwest::Response, AIError> {
+    let mut attempt = 1;
+    loop {
+        let failure = match build().send().await {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                SendFailure::Status(status, format!("HTTP {}: {}", status, text))
+            }
+            Err(e) if e.is_timeout() => SendFailure::Fatal(AIError::Timeout),
+            Err(e) if e.is_connect() => SendFailure::Connect(e.to_string()),
+            Err(e) => SendFailure::Fatal(AIError::RequestFailed(e.to_string())),
+        };
+
+        let (retryable, error) = match failure {
+            SendFailure::Connect(message) => (true, AIError::RequestFailed(message)),
+            SendFailure::Fatal(error) => (false, error),
+            SendFailure::Status(status, message)
+                if status == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                (retry_rate_limited, AIError::RateLimited(message))
+            }
+            SendFailure::Status(status, message) => (
+                RETRYABLE_STATUSES.contains(&status.as_u16()),
+                AIError::RequestFailed(message),
+            ),
+        };
+        if !retryable || attempt >= max_attempts {
+            return Err(error);
+        }
+
+        let delay = base_delay * 2u32.pow(attempt - 1);
+        eprintln!(
+            "[ai] attempt {}/{} failed ({}), retrying in {}ms",
+            attempt,
+            max_attempts,
+            error,
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
 
 /// OpenAI APIクライアント
+///
+/// `seed` を指定すると同一入力に対してほぼ同じ出力が得られるが、
+/// OpenAI 側のモデル更新（`system_fingerprint` の変化）を跨いだ完全な決定性は保証されない。
 pub struct OpenAIClient {
-    client: Client,
+    client: ClientWithMiddleware,
     api_key: String,
+    base_url: String,
     model: String,
+    seed: Option<u64>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    /// 新しいモデル向けに `max_tokens` の代わりに `max_completion_tokens` を送る
+    use_max_completion_tokens: bool,
+    models_cache: ModelListCache,
+    /// 指定されていればリクエストごとにモデルを切り替える
+    rotation: Option<Arc<ModelRotation>>,
+    /// ローテーションで最後に送ったモデル（`served_model` で返す）
+    last_rotated_model: Mutex<Option<String>>,
+    /// HTTP リクエスト1回のタイムアウト
+    timeout: Duration,
+    /// 一時的な失敗時の試行回数（初回を含む）
+    retry_max_attempts: u32,
+    /// 最初の再試行までの待ち時間（以降は倍にする）
+    retry_base_delay: Duration,
 }
 
 impl OpenAIClient {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
-                .build()
-                .unwrap(),
+    pub fn new(api_key: String) -> Result<Self, AIError> {
+        Ok(Self {
+            client: build_http_client()?,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             api_key,
-            model: "gpt-4o-mini".to_string(),
-        }
+            base_url: OPENAI_API_BASE.to_string(),
+            model: OPENAI_DEFAULT_MODEL.to_string(),
+            seed: None,
+            temperature: None,
+            max_tokens: None,
+            use_max_completion_tokens: false,
+            models_cache: OPENAI_MODEL_LIST.get_or_init(Default::default).clone(),
+            rotation: None,
+            last_rotated_model: Mutex::new(None),
+            retry_max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            retry_base_delay: DEFAULT_RETRY_BASE_DELAY,
+        })
+    }
+
+    /// HTTP リクエスト1回のタイムアウトを設定する（AI_TIMEOUT_SECS、再試行は1回ずつ数える）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 429 / 5xx と接続エラーの再試行を設定する（`max_attempts` は初回を含む）
+    pub fn with_retry(mut self, max_attempts: u32, base_delay: Duration) -> Self {
+        self.retry_max_attempts = max_attempts.max(1);
+        self.retry_base_delay = base_delay;
+        self
+    }
+
+    /// API のベース URL を差し替える（モックサーバーでのテスト用）
+    ///
+    /// 別のサーバーなのでモデル一覧のキャッシュは共有しない。
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self.models_cache = ModelListCache::default();
+        self
+    }
+
+    /// 利用可能なモデルID一覧を取得する（1時間キャッシュ）
+    pub async fn list_models(&self) -> Result<Vec<String>, AIError> {
+        let request = self
+            .client
+            .get(format!("{}/models", self.base_url))
+            .timeout(self.timeout)
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        fetch_model_ids(request, &self.models_cache).await
+    }
+
+    /// seed を上書きする（None で seed なし）
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 出力トークン上限を設定する
+    pub fn with_max_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// temperature を指定する（None ならリクエストに含めない）
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// `max_completion_tokens` フィールドを使うかを設定する
+    pub fn with_max_completion_tokens(mut self, enabled: bool) -> Self {
+        self.use_max_completion_tokens = enabled;
+        self
+    }
+
+    /// モデルを上書きする（OPENAI_MODEL）
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// モデルのローテーションを設定する
+    ///
+    /// 呼び出しごとに次のモデルを使い、429 を受けたら次のモデルで再試行する。
+    pub fn with_model_rotation(mut self, rotation: Arc<ModelRotation>) -> Self {
+        self.model = rotation.current_model().to_string();
+        self.rotation = Some(rotation);
+        self
     }
 
     fn build_request_body(&self, prompt: &str, stream: bool) -> serde_json::Value {
-        serde_json::json!({
-            "model": self.model,
+        self.build_request_body_for(&self.model, prompt, stream)
+    }
+
+    fn build_request_body_for(&self, model: &str, prompt: &str, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": model,
             "messages": [
                 { "role": "user", "content": prompt }
             ],
             "stream": stream,
-        })
+        });
+        if let Some(seed) = self.seed {
+            body["seed"] = serde_json::json!(seed);
+        }
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            let field = if self.use_max_completion_tokens {
+                "max_completion_tokens"
+            } else {
+                "max_tokens"
+            };
+            body[field] = serde_json::json!(max_tokens);
+        }
+        body
+    }
+}
+
+impl OpenAIClient {
+    /// chat/completions にリクエストを送り、成功したレスポンスを返す
+    ///
+    /// 一時的な失敗は再試行する。ローテーション中の 429 は次のモデルに切り替えるので再試行しない。
+    async fn send_chat(&self, body: &serde_json::Value) -> Result<reqwest::Response, AIError> {
+        retry_request(
+            self.retry_max_attempts,
+            self.retry_base_delay,
+            self.rotation.is_none(),
+            || {
+                self.client
+                    .post(format!("{}/chat/completions", self.base_url))
+                    .timeout(self.timeout)
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .header("Content-Type", "application/json")
+                    .json(body)
+            },
+        )
+        .await
+    }
+
+    /// ローテーションがあれば次のモデルで送り、429 なら残りのモデルで再試行する
+    ///
+    /// 使ったモデル名とレスポンスを返す。
+    async fn send_rotating(
+        &self,
+        prompt: &str,
+        stream: bool,
+    ) -> Result<(String, reqwest::Response), AIError> {
+        let Some(rotation) = &self.rotation else {
+            let body = self.build_request_body(prompt, stream);
+            return Ok((self.model.clone(), self.send_chat(&body).await?));
+        };
+
+        let mut last_err = None;
+        for _ in 0..rotation.models().len() {
+            let model = rotation.next_model().to_string();
+            if let Ok(mut last) = self.last_rotated_model.lock() {
+                *last = Some(model.clone());
+            }
+            let body = self.build_request_body_for(&model, prompt, stream);
+            match self.send_chat(&body).await {
+                Ok(response) => return Ok((model, response)),
+                Err(AIError::RateLimited(msg)) => {
+                    eprintln!("[ai] {} rate limited, rotating to next model", model);
+                    rotation.record_rate_limit(&model);
+                    last_err = Some(AIError::RateLimited(msg));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| AIError::RateLimited("no models to rotate".to_string())))
     }
 }
 
 #[async_trait]
 impl AIProvider for OpenAIClient {
-    async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
-        let body = self.build_request_body(prompt, false);
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 
-        let response = self
-            .client
-            .post(OPENAI_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    AIError::Timeout
-                } else {
-                    AIError::RequestFailed(e.to_string())
-                }
-            })?;
+    fn served_model(&self) -> String {
+        self.last_rotated_model
+            .lock()
+            .ok()
+            .and_then(|last| last.clone())
+            .unwrap_or_else(|| self.model.clone())
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(AIError::RequestFailed(format!(
-                "HTTP {}: {}",
-                status, text
-            )));
-        }
+    async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
+        let (model, response) = self.send_rotating(prompt, false).await?;
 
         let json: serde_json::Value = response
             .json()
@@ -86,10 +474,20 @@ impl AIProvider for OpenAIClient {
             total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
         });
 
+        let system_fingerprint = json["system_fingerprint"].as_str().map(|s| s.to_string());
+
+        // incomplete_details は上限到達などで生成が打ち切られた場合にのみ含まれる
+        let finish_reason = json["incomplete_details"]["reason"]
+            .as_str()
+            .or_else(|| json["choices"][0]["finish_reason"].as_str())
+            .map(normalize_finish_reason);
+
         Ok(AIResponse {
             text,
-            model: self.model.clone(),
+            model,
             usage,
+            system_fingerprint,
+            finish_reason,
         })
     }
 
@@ -98,99 +496,158 @@ impl AIProvider for OpenAIClient {
         prompt: &str,
         sender: mpsc::Sender<StreamChunk>,
     ) -> Result<(), AIError> {
-        let body = self.build_request_body(prompt, true);
-
-        let response = self
-            .client
-            .post(OPENAI_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    AIError::Timeout
-                } else {
-                    AIError::RequestFailed(e.to_string())
-                }
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(AIError::RequestFailed(format!(
-                "HTTP {}: {}",
-                status, text
-            )));
-        }
-
+        let (_, response) = self.send_rotating(prompt, true).await?;
         parse_openai_stream(response, sender).await?;
         Ok(())
     }
+
+    /// ローテーション中は次に使うモデルの単価で見積もる
+    fn estimate_cost(&self, prompt: &str) -> CostEstimate {
+        let model = self
+            .rotation
+            .as_ref()
+            .map_or(self.model.as_str(), |rotation| rotation.current_model());
+        let mut estimate = CostEstimate::from_pricing_table(model, prompt);
+        estimate.cached_input_cost_usd = estimate
+            .input_cost_usd
+            .zip(openai_cached_input_ratio(model))
+            .map(|(cost, ratio)| cost * ratio);
+        estimate
+    }
 }
 
 /// Anthropic APIクライアント
 pub struct AnthropicClient {
-    client: Client,
+    client: ClientWithMiddleware,
     api_key: String,
+    base_url: String,
     model: String,
+    models_cache: ModelListCache,
+    temperature: Option<f32>,
+    max_tokens: u32,
+    /// HTTP リクエスト1回のタイムアウト
+    timeout: Duration,
+    /// 一時的な失敗時の試行回数（初回を含む）
+    retry_max_attempts: u32,
+    /// 最初の再試行までの待ち時間（以降は倍にする）
+    retry_base_delay: Duration,
 }
 
 impl AnthropicClient {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
-                .build()
-                .unwrap(),
+    pub fn new(api_key: String) -> Result<Self, AIError> {
+        Ok(Self {
+            client: build_http_client()?,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             api_key,
-            model: "claude-haiku-4-5-20251001".to_string(),
-        }
+            base_url: ANTHROPIC_API_BASE.to_string(),
+            model: ANTHROPIC_DEFAULT_MODEL.to_string(),
+            models_cache: ANTHROPIC_MODEL_LIST.get_or_init(Default::default).clone(),
+            temperature: None,
+            max_tokens: ANTHROPIC_DEFAULT_MAX_TOKENS,
+            retry_max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            retry_base_delay: DEFAULT_RETRY_BASE_DELAY,
+        })
+    }
+
+    /// HTTP リクエスト1回のタイムアウトを設定する（AI_TIMEOUT_SECS、再試行は1回ずつ数える）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 429 / 5xx と接続エラーの再試行を設定する（`max_attempts` は初回を含む）
+    pub fn with_retry(mut self, max_attempts: u32, base_delay: Duration) -> Self {
+        self.retry_max_attempts = max_attempts.max(1);
+        self.retry_base_delay = base_delay;
+        self
+    }
+
+    /// モデルを上書きする（ANTHROPIC_MODEL）
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// 出力の最大トークン数を上書きする（None なら既定の 1024）
+    pub fn with_max_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_tokens = max_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_TOKENS);
+        self
+    }
+
+    /// temperature を指定する（None ならリクエストに含めない）
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// API のベース URL を差し替える（モックサーバーでのテスト用）
+    ///
+    /// 別のサーバーなのでモデル一覧のキャッシュは共有しない。
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self.models_cache = ModelListCache::default();
+        self
+    }
+
+    /// 利用可能なモデルID一覧を取得する（1時間キャッシュ）
+    pub async fn list_models(&self) -> Result<Vec<String>, AIError> {
+        let request = self
+            .client
+            .get(format!("{}/models?limit=1000", self.base_url))
+            .timeout(self.timeout)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01");
+        fetch_model_ids(request, &self.models_cache).await
+    }
+
+    /// messages にリクエストを送り、成功したレスポンスを返す（一時的な失敗は再試行する）
+    async fn send_messages(&self, body: &serde_json::Value) -> Result<reqwest::Response, AIError> {
+        retry_request(self.retry_max_attempts, self.retry_base_delay, true, || {
+            self.client
+                .post(format!("{}/messages", self.base_url))
+                .timeout(self.timeout)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .header("Content-Type", "application/json")
+                .json(body)
+        })
+        .await
     }
 
     fn build_request_body(&self, prompt: &str, stream: bool) -> serde_json::Value {
-        serde_json::json!({
+        let mut body = serde_json::json!({
             "model": self.model,
-            "max_tokens": 1024,
+            "max_tokens": self.max_tokens,
             "messages": [
                 { "role": "user", "content": prompt }
             ],
             "stream": stream,
-        })
+        });
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        body
     }
 }
 
 #[async_trait]
 impl AIProvider for AnthropicClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn estimate_cost(&self, prompt: &str) -> CostEstimate {
+        let mut estimate = CostEstimate::from_pricing_table(&self.model, prompt);
+        estimate.cached_input_cost_usd = estimate
+            .input_cost_usd
+            .map(|cost| cost * ANTHROPIC_CACHE_READ_RATIO);
+        estimate
+    }
+
     async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
         let body = self.build_request_body(prompt, false);
 
-        let response = self
-            .client
-            .post(ANTHROPIC_API_URL)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    AIError::Timeout
-                } else {
-                    AIError::RequestFailed(e.to_string())
-                }
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(AIError::RequestFailed(format!(
-                "HTTP {}: {}",
-                status, text
-            )));
-        }
+        let response = self.send_messages(&body).await?;
 
         let json: serde_json::Value = response
             .json()
@@ -209,10 +666,14 @@ impl AIProvider for AnthropicClient {
                 + u["output_tokens"].as_u64().unwrap_or(0)) as u32,
         });
 
+        let finish_reason = json["stop_reason"].as_str().map(normalize_finish_reason);
+
         Ok(AIResponse {
             text,
             model: self.model.clone(),
             usage,
+            system_fingerprint: None,
+            finish_reason,
         })
     }
 
@@ -223,31 +684,7 @@ impl AIProvider for AnthropicClient {
     ) -> Result<(), AIError> {
         let body = self.build_request_body(prompt, true);
 
-        let response = self
-            .client
-            .post(ANTHROPIC_API_URL)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    AIError::Timeout
-                } else {
-                    AIError::RequestFailed(e.to_string())
-                }
-            })?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(AIError::RequestFailed(format!(
-                "HTTP {}: {}",
-                status, text
-            )));
-        }
+        let response = self.send_messages(&body).await?;
 
         parse_anthropic_stream(response, sender).await?;
         Ok(())
@@ -256,36 +693,162 @@ impl AIProvider for AnthropicClient {
 
 /// Vertex AI Gemini Flash クライアント
 ///
+/// `GOOGLE_APPLICATION_CREDENTIALS` のサービスアカウントキー、なければ
 /// `gcloud auth print-access-token` で OAuth2 トークンを取得し、
 /// Vertex AI の generateContent エンドポイントを呼び出す。
 pub struct VertexAIClient {
-    client: Client,
+    client: ClientWithMiddleware,
+    /// サービスアカウントのトークン交換用（アクセストークンを HTTP デバッグログに残さない）
+    token_client: Client,
     project: String,
     location: String,
     model: String,
+    credentials: Option<PathBuf>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    token_cache: AccessTokenCache,
+    /// gcloud CLI で取得したトークンと失効時刻（リクエストごとに gcloud を起動しないため）
+    gcloud_token_cache: AccessTokenCache,
+    /// HTTP リクエスト1回のタイムアウト
+    timeout: Duration,
 }
 
 impl VertexAIClient {
-    pub fn new(project: String, location: String) -> Self {
-        Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
-                .build()
-                .unwrap(),
+    pub fn new(project: String, location: String) -> Result<Self, AIError> {
+        Ok(Self {
+            client: build_http_client()?,
+            token_client: build_plain_client()?,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             project,
             location,
-            model: "gemini-2.0-flash".to_string(),
+            model: VERTEX_DEFAULT_MODEL.to_string(),
+            credentials: None,
+            temperature: None,
+            max_tokens: None,
+            token_cache: VERTEX_ACCESS_TOKEN.get_or_init(Default::default).clone(),
+            gcloud_token_cache: GCLOUD_ACCESS_TOKEN.get_or_init(Default::default).clone(),
+        })
+    }
+
+    /// HTTP リクエスト1回のタイムアウトを設定する（AI_TIMEOUT_SECS）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// モデルを上書きする（VERTEX_MODEL）
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// temperature と最大トークン数（`maxOutputTokens`）を指定する（None なら送らない）
+    pub fn with_generation_config(
+        mut self,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        self.temperature = temperature;
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// サービスアカウントキーの JSON で認証する（gcloud CLI は使わない）
+    pub fn with_credentials(mut self, path: Option<PathBuf>) -> Self {
+        self.credentials = path;
+        self
+    }
+
+    fn build_request_body(&self, prompt: &str) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "contents": [
+                { "role": "user", "parts": [{ "text": prompt }] }
+            ]
+        });
+        if let Some(temperature) = self.temperature {
+            body["generationConfig"]["temperature"] = serde_json::json!(temperature);
         }
+        if let Some(max_tokens) = self.max_tokens {
+            body["generationConfig"]["maxOutputTokens"] = serde_json::json!(max_tokens);
+        }
+        body
     }
 
-    fn endpoint(&self) -> String {
+    /// `stream` なら SSE で返す `streamGenerateContent` を使う
+    fn endpoint(&self, stream: bool) -> String {
+        let method = if stream {
+            "streamGenerateContent?alt=sse"
+        } else {
+            "generateContent"
+        };
         format!(
-            "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:generateContent",
-            self.location, self.project, self.location, self.model
+            "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:{}",
+            self.location, self.project, self.location, self.model, method
         )
     }
 
-    async fn get_access_token() -> Result<String, AIError> {
+    /// プロンプトを送り、成功したレスポンスを返す
+    async fn send(&self, prompt: &str, stream: bool) -> Result<reqwest::Response, AIError> {
+        let token = self.get_access_token().await?;
+        let body = self.build_request_body(prompt);
+
+        let response = self
+            .client
+            .post(self.endpoint(stream))
+            .timeout(self.timeout)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    AIError::Timeout
+                } else {
+                    AIError::RequestFailed(e.to_string())
+                }
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(AIError::RequestFailed(format!("HTTP {}: {}", status, text)));
+        }
+        Ok(response)
+    }
+
+    async fn get_access_token(&self) -> Result<String, AIError> {
+        match &self.credentials {
+            Some(path) => self.service_account_token(path).await,
+            None => {
+                cached_access_token(&self.gcloud_token_cache, GCLOUD_REFRESH_MARGIN, || async {
+                    let token = Self::gcloud_access_token().await?;
+                    Ok((token, Instant::now() + GCLOUD_TOKEN_LIFETIME))
+                })
+                .await
+            }
+        }
+    }
+
+    /// サービスアカウントのトークンを返す（失効60秒前まではキャッシュを使う）
+    async fn service_account_token(&self, path: &std::path::Path) -> Result<String, AIError> {
+        if let Ok(cached) = self.token_cache.lock() {
+            if let Some((token, expires_at)) = cached.as_ref() {
+                if !needs_refresh(*expires_at, Instant::now()) {
+                    return Ok(token.clone());
+                }
+            }
+        }
+
+        let key = ServiceAccountKey::from_file(path)?;
+        let (token, expires_at) = exchange_jwt(&self.token_client, &key).await?;
+        if let Ok(mut cached) = self.token_cache.lock() {
+            *cached = Some((token.clone(), expires_at));
+        }
+        Ok(token)
+    }
+
+    async fn gcloud_access_token() -> Result<String, AIError> {
         let output = tokio::process::Command::new("gcloud")
             .args(["auth", "print-access-token"])
             .output()
@@ -306,20 +869,140 @@ impl VertexAIClient {
 
 #[async_trait]
 impl AIProvider for VertexAIClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    /// Gemini 1.5 系はトークンではなく文字数（空白を除く）で課金される
+    fn estimate_cost(&self, prompt: &str) -> CostEstimate {
+        let mut estimate = CostEstimate::from_pricing_table(&self.model, prompt);
+        if let Some(price) = VERTEX_CHARACTER_PRICES.get(self.model.as_str()) {
+            let chars = prompt.chars().filter(|c| !c.is_whitespace()).count();
+            estimate.input_cost_usd = Some(chars as f64 * price / 1000.0);
+        }
+        estimate
+    }
+
     async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
-        let token = Self::get_access_token().await?;
+        let response = self.send(prompt, false).await?;
 
-        let body = serde_json::json!({
-            "contents": [
-                { "role": "user", "parts": [{ "text": prompt }] }
-            ]
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AIError::ParseError(e.to_string()))?;
+
+        let text = json["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+
+        let usage = json.get("usageMetadata").map(|u| TokenUsage {
+            prompt_tokens: u["promptTokenCount"].as_u64().unwrap_or(0) as u32,
+            completion_tokens: u["candidatesTokenCount"].as_u64().unwrap_or(0) as u32,
+            total_tokens: u["totalTokenCount"].as_u64().unwrap_or(0) as u32,
         });
 
+        let finish_reason = json["candidates"][0]["finishReason"]
+            .as_str()
+            .map(normalize_finish_reason);
+
+        Ok(AIResponse {
+            text,
+            model: self.model.clone(),
+            usage,
+            system_fingerprint: None,
+            finish_reason,
+        })
+    }
+
+    async fn process_stream(
+        &self,
+        prompt: &str,
+        sender: mpsc::Sender<StreamChunk>,
+    ) -> Result<(), AIError> {
+        let response = self.send(prompt, true).await?;
+        parse_vertex_stream(response, sender).await?;
+        Ok(())
+    }
+}
+
+/// Ollama クライアント（ローカルの `/api/generate` を呼ぶ）
+///
+/// 文字起こしを外部の API に送らずに処理できる。
+pub struct OllamaClient {
+    client: ClientWithMiddleware,
+    /// HTTP リクエスト1回のタイムアウト
+    timeout: Duration,
+    base_url: String,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+impl OllamaClient {
+    pub fn new() -> Result<Self, AIError> {
+        Ok(Self {
+            client: build_http_client()?,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            base_url: OLLAMA_API_BASE.to_string(),
+            model: OLLAMA_DEFAULT_MODEL.to_string(),
+            temperature: None,
+            max_tokens: None,
+        })
+    }
+
+    /// HTTP リクエスト1回のタイムアウトを設定する（AI_TIMEOUT_SECS）
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Ollama サーバーの URL を差し替える（OLLAMA_HOST）
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// モデルを上書きする（OLLAMA_MODEL）
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// temperature と最大トークン数（`num_predict`）を指定する（None なら送らない）
+    pub fn with_generation_config(
+        mut self,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        self.temperature = temperature;
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    fn build_request_body(&self, prompt: &str, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "prompt": prompt,
+            "stream": stream,
+        });
+        if let Some(temperature) = self.temperature {
+            body["options"]["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body["options"]["num_predict"] = serde_json::json!(max_tokens);
+        }
+        body
+    }
+
+    /// プロンプトを送り、成功したレスポンスを返す
+    async fn send(&self, prompt: &str, stream: bool) -> Result<reqwest::Response, AIError> {
+        let body = self.build_request_body(prompt, stream);
+
         let response = self
             .client
-            .post(&self.endpoint())
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Content-Type", "application/json")
+            .post(format!("{}/api/generate", self.base_url))
+            .timeout(self.timeout)
             .json(&body)
             .send()
             .await
@@ -336,27 +1019,54 @@ impl AIProvider for VertexAIClient {
             let text = response.text().await.unwrap_or_default();
             return Err(AIError::RequestFailed(format!("HTTP {}: {}", status, text)));
         }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AIProvider for OllamaClient {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    /// ローカル実行なので料金はかからない
+    fn estimate_cost(&self, prompt: &str) -> CostEstimate {
+        CostEstimate {
+            input_cost_usd: Some(0.0),
+            ..CostEstimate::from_pricing_table(&self.model, prompt)
+        }
+    }
+
+    async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
+        let response = self.send(prompt, false).await?;
 
         let json: serde_json::Value = response
             .json()
             .await
             .map_err(|e| AIError::ParseError(e.to_string()))?;
 
-        let text = json["candidates"][0]["content"]["parts"][0]["text"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
+        let text = json["response"].as_str().unwrap_or("").to_string();
 
-        let usage = json.get("usageMetadata").map(|u| TokenUsage {
-            prompt_tokens: u["promptTokenCount"].as_u64().unwrap_or(0) as u32,
-            completion_tokens: u["candidatesTokenCount"].as_u64().unwrap_or(0) as u32,
-            total_tokens: u["totalTokenCount"].as_u64().unwrap_or(0) as u32,
+        let prompt_tokens = json["prompt_eval_count"].as_u64();
+        let completion_tokens = json["eval_count"].as_u64();
+        let usage = (prompt_tokens.is_some() || completion_tokens.is_some()).then(|| {
+            let prompt_tokens = prompt_tokens.unwrap_or(0) as u32;
+            let completion_tokens = completion_tokens.unwrap_or(0) as u32;
+            TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            }
         });
 
+        let finish_reason = json["done_reason"].as_str().map(normalize_finish_reason);
+
         Ok(AIResponse {
             text,
             model: self.model.clone(),
             usage,
+            system_fingerprint: None,
+            finish_reason,
         })
     }
 
@@ -365,36 +1075,775 @@ impl AIProvider for VertexAIClient {
         prompt: &str,
         sender: mpsc::Sender<StreamChunk>,
     ) -> Result<(), AIError> {
-        let response = self.process(prompt).await?;
-        let _ = sender
-            .send(StreamChunk {
-                content: response.text,
-                done: true,
-            })
-            .await;
+        let response = self.send(prompt, true).await?;
+        parse_ollama_stream(response, sender).await?;
         Ok(())
     }
 }
 
 /// プロバイダーに応じたクライアントを生成する
-pub fn create_provider(provider_type: &ProviderType) -> Result<Box<dyn AIProvider>, AIError> {
+pub fn create_provider(
+    provider_type: &ProviderType,
+    options: &ProviderOptions,
+    config: &AppConfig,
+) -> Result<Box<dyn AIProvider>, AIError> {
     match provider_type {
         ProviderType::VertexAI => {
-            let project = std::env::var("GOOGLE_CLOUD_PROJECT")
-                .map_err(|_| AIError::ApiKeyMissing("GOOGLE_CLOUD_PROJECT".to_string()))?;
-            let location =
-                std::env::var("GOOGLE_CLOUD_LOCATION").unwrap_or_else(|_| "us-central1".into());
-            Ok(Box::new(VertexAIClient::new(project, location)))
+            let project = config
+                .google_cloud_project
+                .clone()
+                .ok_or_else(|| AIError::ApiKeyMissing("GOOGLE_CLOUD_PROJECT".to_string()))?;
+            let client = VertexAIClient::new(project, config.google_cloud_location.clone())?
+                .with_credentials(config.google_application_credentials.clone())
+                .with_generation_config(options.temperature, options.max_tokens)
+                .with_timeout(Duration::from_secs(config.ai_timeout_secs));
+            Ok(Box::new(match &config.vertex_model {
+                Some(model) => client.with_model(model.clone()),
+                None => client,
+            }))
         }
         ProviderType::OpenAI => {
-            let api_key = std::env::var("OPENAI_API_KEY")
-                .map_err(|_| AIError::ApiKeyMissing("OPENAI_API_KEY".to_string()))?;
-            Ok(Box::new(OpenAIClient::new(api_key)))
+            let api_key = config
+                .openai_api_key
+                .clone()
+                .ok_or_else(|| AIError::ApiKeyMissing("OPENAI_API_KEY".to_string()))?;
+            let mut client = OpenAIClient::new(api_key)?
+                .with_timeout(Duration::from_secs(config.ai_timeout_secs))
+                .with_seed(options.openai_seed)
+                .with_temperature(options.temperature)
+                .with_max_tokens(options.max_tokens)
+                .with_max_completion_tokens(
+                    options
+                        .use_max_completion_tokens
+                        .unwrap_or(config.openai_use_max_completion_tokens),
+                );
+            // テストモード > モードの model_rotation > OPENAI_MODEL の順に優先する
+            if config.test_mode {
+                client = client.with_model(TEST_MODE_OPENAI_MODEL);
+            } else if let Some(rotation) = &options.model_rotation {
+                client = client.with_model_rotation(rotation.clone());
+            } else if let Some(model) = &config.openai_model {
+                client = client.with_model(model.clone());
+            }
+            Ok(Box::new(client))
+        }
+        ProviderType::Anthropic => Ok(Box::new(
+            anthropic_client(config)?
+                .with_temperature(options.temperature)
+                .with_max_tokens(options.max_tokens),
+        )),
+        ProviderType::Ollama => {
+            let client = OllamaClient::new()?
+                .with_base_url(config.ollama_host.clone())
+                .with_generation_config(options.temperature, options.max_tokens)
+                .with_timeout(Duration::from_secs(config.ai_timeout_secs));
+            Ok(Box::new(match &config.ollama_model {
+                Some(model) => client.with_model(model.clone()),
+                None => client,
+            }))
+        }
+    }
+}
+
+/// 主プロバイダー（AI_PROVIDER）と AI_PROVIDER_FALLBACK を順に試すプロバイダーを生成する
+///
+/// AI_PROVIDER_FALLBACK が空なら `create_provider` と同じ。API キーがないなどで
+/// 作れないプロバイダーは飛ばし、1つも作れなければ最初のエラーを返す。
+pub fn create_provider_with_fallback(
+    options: &ProviderOptions,
+    config: &AppConfig,
+) -> Result<Box<dyn AIProvider>, AIError> {
+    if config.ai_provider_fallback.is_empty() {
+        return create_provider(&config.ai_provider, options, config);
+    }
+
+    let mut providers: Vec<(ProviderType, Box<dyn AIProvider>)> = Vec::new();
+    let mut first_error = None;
+    let chain = std::iter::once(&config.ai_provider).chain(&config.ai_provider_fallback);
+    for provider_type in chain {
+        if providers.iter().any(|(t, _)| t == provider_type) {
+            continue;
         }
-        ProviderType::Anthropic => {
-            let api_key = std::env::var("ANTHROPIC_API_KEY")
-                .map_err(|_| AIError::ApiKeyMissing("ANTHROPIC_API_KEY".to_string()))?;
-            Ok(Box::new(AnthropicClient::new(api_key)))
+        match create_provider(provider_type, options, config) {
+            Ok(provider) => providers.push((provider_type.clone(), provider)),
+            Err(e) => {
+                eprintln!("[ai] skipping {} provider: {}", provider_type.as_str(), e);
+                first_error.get_or_insert(e);
+            }
         }
     }
+    match FallbackProvider::new(providers) {
+        Some(provider) => Ok(Box::new(provider)),
+        None => Err(first_error.expect("at least one provider was tried")),
+    }
+}
+
+/// 設定から Anthropic クライアントを生成する（ANTHROPIC_MODEL があれば適用）
+pub fn anthropic_client(config: &AppConfig) -> Result<AnthropicClient, AIError> {
+    let api_key = config
+        .anthropic_api_key
+        .clone()
+        .ok_or_else(|| AIError::ApiKeyMissing("ANTHROPIC_API_KEY".to_string()))?;
+    let client =
+        AnthropicClient::new(api_key)?.with_timeout(Duration::from_secs(config.ai_timeout_secs));
+    Ok(match &config.anthropic_model {
+        Some(model) => client.with_model(model.clone()),
+        None => client,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::app_config::TEST_MODE_SEED;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn assert_cost(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("cost should be estimated");
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_openai_estimate_cost() {
+        let prompt = "a".repeat(4_000);
+        let estimate = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .estimate_cost(&prompt);
+        assert_eq!(estimate.model, "gpt-4o-mini");
+        assert_eq!(estimate.input_tokens, 1_000);
+        assert_cost(estimate.input_cost_usd, 0.00015);
+        assert_cost(estimate.cached_input_cost_usd, 0.000075);
+
+        // ローテーション中は次に使うモデルの単価になる
+        let rotation =
+            ModelRotation::new(vec!["gpt-4.1".to_string(), "gpt-4o".to_string()]).unwrap();
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_model_rotation(Arc::new(rotation));
+        let estimate = client.estimate_cost(&prompt);
+        assert_eq!(estimate.model, "gpt-4.1");
+        assert_cost(estimate.input_cost_usd, 0.002);
+        assert_cost(estimate.cached_input_cost_usd, 0.0005);
+    }
+
+    #[test]
+    fn test_anthropic_estimate_cost() {
+        let estimate = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .estimate_cost(&"a".repeat(4_000));
+        assert_eq!(estimate.model, ANTHROPIC_DEFAULT_MODEL);
+        assert_cost(estimate.input_cost_usd, 0.001);
+        assert_cost(estimate.cached_input_cost_usd, 0.0001);
+
+        let estimate = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_model("claude-unknown")
+            .estimate_cost("hello");
+        assert_eq!(estimate.input_cost_usd, None);
+        assert_eq!(estimate.cached_input_cost_usd, None);
+    }
+
+    #[test]
+    fn test_vertex_estimate_cost() {
+        let mut client =
+            VertexAIClient::new("project".to_string(), "us-central1".to_string()).unwrap();
+        let estimate = client.estimate_cost(&"a".repeat(4_000));
+        assert_cost(estimate.input_cost_usd, 0.0001);
+        assert_eq!(estimate.cached_input_cost_usd, None);
+
+        // Gemini 1.5 は空白を除いた文字数で課金される
+        client.model = "gemini-1.5-pro".to_string();
+        let estimate = client.estimate_cost("ab cd\nef");
+        assert_eq!(estimate.input_tokens, 2);
+        assert_cost(estimate.input_cost_usd, 6.0 * 0.0003125 / 1000.0);
+    }
+
+    #[test]
+    fn test_openai_body_without_seed() {
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_seed(None);
+        let body = client.build_request_body("hello", false);
+        assert!(body.get("seed").is_none());
+    }
+
+    #[test]
+    fn test_openai_body_max_tokens_field() {
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_max_tokens(Some(256))
+            .with_max_completion_tokens(false);
+        let body = client.build_request_body("hello", false);
+        assert_eq!(body["max_tokens"], 256);
+        assert!(body.get("max_completion_tokens").is_none());
+
+        let client = client.with_max_completion_tokens(true);
+        let body = client.build_request_body("hello", false);
+        assert_eq!(body["max_completion_tokens"], 256);
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_request_bodies_omit_unset_generation_options() {
+        let body = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .build_request_body("hello", false);
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("max_tokens").is_none());
+
+        let body = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .build_request_body("hello", false);
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["max_tokens"], ANTHROPIC_DEFAULT_MAX_TOKENS);
+
+        let body = VertexAIClient::new("proj".to_string(), "us-central1".to_string())
+            .unwrap()
+            .build_request_body("hello");
+        assert!(body.get("generationConfig").is_none());
+
+        let body = OllamaClient::new()
+            .unwrap()
+            .build_request_body("hello", false);
+        assert!(body.get("options").is_none());
+    }
+
+    #[test]
+    fn test_request_bodies_include_generation_options() {
+        let body = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_temperature(Some(0.5))
+            .with_max_tokens(Some(256))
+            .build_request_body("hello", false);
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["max_tokens"], 256);
+
+        let body = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_temperature(Some(0.0))
+            .with_max_tokens(Some(4096))
+            .build_request_body("hello", false);
+        assert_eq!(body["temperature"], 0.0);
+        assert_eq!(body["max_tokens"], 4096);
+
+        let body = VertexAIClient::new("proj".to_string(), "us-central1".to_string())
+            .unwrap()
+            .with_generation_config(Some(1.5), Some(512))
+            .build_request_body("hello");
+        assert_eq!(body["generationConfig"]["temperature"], 1.5);
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 512);
+
+        let body = OllamaClient::new()
+            .unwrap()
+            .with_generation_config(Some(0.25), Some(128))
+            .build_request_body("hello", true);
+        assert_eq!(body["options"]["temperature"], 0.25);
+        assert_eq!(body["options"]["num_predict"], 128);
+        assert_eq!(body["stream"], true);
+    }
+
+    /// 最初の `failures` 回は `status` を返し、その後は成功を返すモックサーバー
+    ///
+    /// OpenAI と Anthropic の両方の形式で本文を返す。ベース URL と受信回数を返す。
+    async fn spawn_flaky_server(
+        status: axum::http::StatusCode,
+        failures: usize,
+    ) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let handler = move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    return (status, axum::Json(serde_json::json!({ "error": "flaky" })));
+                }
+                (
+                    axum::http::StatusCode::OK,
+                    axum::Json(serde_json::json!({
+                        "choices": [{ "message": { "content": "ok" }, "finish_reason": "stop" }],
+                        "content": [{ "type": "text", "text": "ok" }],
+                        "stop_reason": "end_turn"
+                    })),
+                )
+            }
+        };
+        let app = axum::Router::new()
+            .route("/chat/completions", axum::routing::post(handler.clone()))
+            .route("/messages", axum::routing::post(handler));
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}", addr), hits)
+    }
+
+    #[tokio::test]
+    async fn test_openai_retries_503_then_succeeds() {
+        let (base_url, hits) =
+            spawn_flaky_server(axum::http::StatusCode::SERVICE_UNAVAILABLE, 2).await;
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url)
+            .with_retry(3, Duration::from_millis(1));
+
+        let response = client.process("hello").await.unwrap();
+        assert_eq!(response.text, "ok");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_retries_503_then_succeeds() {
+        let (base_url, hits) =
+            spawn_flaky_server(axum::http::StatusCode::SERVICE_UNAVAILABLE, 2).await;
+        let client = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url)
+            .with_retry(3, Duration::from_millis(1));
+
+        let response = client.process("hello").await.unwrap();
+        assert_eq!(response.text, "ok");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_attempts() {
+        let (base_url, hits) =
+            spawn_flaky_server(axum::http::StatusCode::TOO_MANY_REQUESTS, 10).await;
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url)
+            .with_retry(3, Duration::from_millis(1));
+
+        assert!(matches!(
+            client.process("hello").await,
+            Err(AIError::RateLimited(_))
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_skips_client_errors() {
+        for status in [
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::http::StatusCode::UNAUTHORIZED,
+        ] {
+            let (base_url, hits) = spawn_flaky_server(status, 10).await;
+            let client = AnthropicClient::new("test".to_string())
+                .unwrap()
+                .with_base_url(base_url)
+                .with_retry(3, Duration::from_millis(1));
+
+            assert!(matches!(
+                client.process("hello").await,
+                Err(AIError::RequestFailed(_))
+            ));
+            assert_eq!(hits.load(Ordering::SeqCst), 1, "{}", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_access_token_reuses_valid_token() {
+        let cache = AccessTokenCache::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            let n = fetches.fetch_add(1, Ordering::SeqCst);
+            Ok((
+                format!("token-{}", n),
+                Instant::now() + GCLOUD_TOKEN_LIFETIME,
+            ))
+        };
+
+        let first = cached_access_token(&cache, GCLOUD_REFRESH_MARGIN, fetch)
+            .await
+            .unwrap();
+        let second = cached_access_token(&cache, GCLOUD_REFRESH_MARGIN, fetch)
+            .await
+            .unwrap();
+        assert_eq!(first, "token-0");
+        assert_eq!(second, "token-0");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cached_access_token_refreshes_near_expiry() {
+        let cache = AccessTokenCache::default();
+        *cache.lock().unwrap() =
+            Some(("old".to_string(), Instant::now() + Duration::from_secs(299)));
+
+        let token = cached_access_token(&cache, GCLOUD_REFRESH_MARGIN, || async {
+            Ok(("new".to_string(), Instant::now() + GCLOUD_TOKEN_LIFETIME))
+        })
+        .await
+        .unwrap();
+        assert_eq!(token, "new");
+        assert_eq!(cache.lock().unwrap().as_ref().unwrap().0, "new");
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout_fails_slow_requests() {
+        let app = axum::Router::new().route(
+            "/chat/completions",
+            axum::routing::post(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "{}"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(format!("http://{}", addr))
+            .with_timeout(Duration::from_millis(50));
+        assert!(matches!(
+            client.process("hello").await,
+            Err(AIError::Timeout)
+        ));
+    }
+
+    /// Ollama の `/api/generate` を真似るモックサーバー（`stream` に応じて NDJSON を返す）
+    async fn spawn_ollama_server() -> String {
+        let app = axum::Router::new().route(
+            "/api/generate",
+            axum::routing::post(
+                |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                    assert_eq!(body["model"], "llama3.2");
+                    if body["stream"] == true {
+                        "{\"response\":\"こんにちは\",\"done\":false}\n\
+                     {\"response\":\"、世界\",\"done\":false}\n\
+                     {\"response\":\"\",\"done\":true,\"eval_count\":3}\n"
+                            .to_string()
+                    } else {
+                        serde_json::json!({
+                            "model": "llama3.2",
+                            "response": "こんにちは、世界",
+                            "done": true,
+                            "done_reason": "stop",
+                            "prompt_eval_count": 12,
+                            "eval_count": 5
+                        })
+                        .to_string()
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_ollama_process_maps_usage() {
+        let client = OllamaClient::new()
+            .unwrap()
+            .with_base_url(spawn_ollama_server().await);
+
+        let response = client.process("hello").await.unwrap();
+        assert_eq!(response.text, "こんにちは、世界");
+        assert_eq!(response.model, "llama3.2");
+        assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 12);
+        assert_eq!(usage.completion_tokens, 5);
+        assert_eq!(usage.total_tokens, 17);
+    }
+
+    #[tokio::test]
+    async fn test_ollama_process_stream() {
+        let client = OllamaClient::new()
+            .unwrap()
+            .with_base_url(spawn_ollama_server().await);
+        let (tx, mut rx) = mpsc::channel(16);
+
+        client.process_stream("hello", tx).await.unwrap();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push((chunk.content, chunk.done));
+        }
+        assert_eq!(
+            chunks,
+            vec![
+                ("こんにちは".to_string(), false),
+                ("、世界".to_string(), false),
+                (String::new(), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_connection_error() {
+        // 接続を拒否させるため、確保したポートをすぐ閉じる
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(format!("http://{}", addr))
+            .with_retry(2, Duration::from_millis(1));
+        assert!(matches!(
+            client.process("hello").await,
+            Err(AIError::RequestFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_provider_uses_configured_models() {
+        let options = ProviderOptions::default();
+        let mut config = AppConfig {
+            openai_api_key: Some("sk-test".to_string()),
+            anthropic_api_key: Some("sk-ant-test".to_string()),
+            google_cloud_project: Some("proj".to_string()),
+            ..AppConfig::default()
+        };
+
+        // 未設定なら各クライアントの既定モデル
+        let model = |config: &AppConfig, provider: ProviderType| {
+            create_provider(&provider, &options, config)
+                .unwrap()
+                .model_name()
+                .to_string()
+        };
+        assert_eq!(model(&config, ProviderType::OpenAI), OPENAI_DEFAULT_MODEL);
+        assert_eq!(
+            model(&config, ProviderType::Anthropic),
+            ANTHROPIC_DEFAULT_MODEL
+        );
+        assert_eq!(model(&config, ProviderType::VertexAI), VERTEX_DEFAULT_MODEL);
+
+        config.openai_model = Some("gpt-4.1".to_string());
+        config.anthropic_model = Some("claude-sonnet-4-5-20250929".to_string());
+        config.vertex_model = Some("gemini-2.5-flash".to_string());
+        assert_eq!(model(&config, ProviderType::OpenAI), "gpt-4.1");
+        assert_eq!(
+            model(&config, ProviderType::Anthropic),
+            "claude-sonnet-4-5-20250929"
+        );
+        assert_eq!(model(&config, ProviderType::VertexAI), "gemini-2.5-flash");
+
+        // テストモードでは OPENAI_MODEL より小型モデルを優先する
+        config.test_mode = true;
+        assert_eq!(model(&config, ProviderType::OpenAI), TEST_MODE_OPENAI_MODEL);
+        assert_ne!(TEST_MODE_OPENAI_MODEL, OPENAI_DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn test_create_provider_with_fallback_skips_unconfigured() {
+        let options = ProviderOptions::default();
+        let config = AppConfig {
+            ai_provider: ProviderType::Anthropic,
+            ai_provider_fallback: vec![ProviderType::OpenAI],
+            openai_api_key: Some("sk-test".to_string()),
+            ..AppConfig::default()
+        };
+        // ANTHROPIC_API_KEY がないので OpenAI だけのチェーンになる
+        let provider = create_provider_with_fallback(&options, &config).unwrap();
+        assert_eq!(provider.model_name(), OPENAI_DEFAULT_MODEL);
+
+        let config = AppConfig {
+            ai_provider: ProviderType::Anthropic,
+            ai_provider_fallback: vec![ProviderType::OpenAI],
+            ..AppConfig::default()
+        };
+        assert!(matches!(
+            create_provider_with_fallback(&options, &config),
+            Err(AIError::ApiKeyMissing(key)) if key == "ANTHROPIC_API_KEY"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_openai_response_reports_overridden_model() {
+        let base_url = spawn_rate_limited_server(&[]).await;
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url)
+            .with_model("gpt-4.1-mini");
+
+        let response = client.process("hello").await.unwrap();
+        assert_eq!(response.model, "gpt-4.1-mini");
+        // モックサーバーはリクエストの model をそのまま返す
+        assert_eq!(response.text, "gpt-4.1-mini");
+    }
+
+    #[test]
+    fn test_vertex_stream_endpoint_uses_sse() {
+        let client = VertexAIClient::new("proj".to_string(), "us-central1".to_string()).unwrap();
+        assert!(client
+            .endpoint(false)
+            .ends_with("/models/gemini-2.0-flash:generateContent"));
+        assert!(client
+            .endpoint(true)
+            .ends_with("/models/gemini-2.0-flash:streamGenerateContent?alt=sse"));
+    }
+
+    #[test]
+    fn test_openai_body_with_seed() {
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_seed(Some(TEST_MODE_SEED));
+        let body = client.build_request_body("hello", false);
+        assert_eq!(body["seed"], 42);
+    }
+
+    /// `GET /models` を返すモックサーバーを起動し、ベース URL と受信回数を返す
+    async fn spawn_models_server(body: serde_json::Value) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/models",
+            axum::routing::get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let body = body.clone();
+                async move { axum::Json(body) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}", addr), hits)
+    }
+
+    #[tokio::test]
+    async fn test_openai_list_models() {
+        let (base_url, hits) = spawn_models_server(serde_json::json!({
+            "object": "list",
+            "data": [
+                { "id": "gpt-4o-mini", "object": "model" },
+                { "id": "gpt-4o", "object": "model" }
+            ]
+        }))
+        .await;
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url);
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models, vec!["gpt-4o-mini", "gpt-4o"]);
+        // 2回目はキャッシュから返す
+        client.list_models().await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_list_models() {
+        let (base_url, _) = spawn_models_server(serde_json::json!({
+            "data": [
+                { "id": "claude-haiku-4-5-20251001", "type": "model" },
+                { "id": "claude-sonnet-4-5-20250929", "type": "model" }
+            ],
+            "has_more": false
+        }))
+        .await;
+        let client = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url);
+
+        let models = client.list_models().await.unwrap();
+        assert!(models.contains(&ANTHROPIC_DEFAULT_MODEL.to_string()));
+        assert_eq!(models.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_rejects_malformed_body() {
+        let (base_url, _) = spawn_models_server(serde_json::json!({ "error": "nope" })).await;
+        let client = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url);
+        assert!(matches!(
+            client.list_models().await,
+            Err(AIError::ParseError(_))
+        ));
+    }
+
+    /// `limited` のモデルには 429、それ以外にはモデル名をそのまま本文として返すモックサーバー
+    async fn spawn_rate_limited_server(limited: &'static [&'static str]) -> String {
+        let app = axum::Router::new().route(
+            "/chat/completions",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                if limited.contains(&model.as_str()) {
+                    return (
+                        axum::http::StatusCode::TOO_MANY_REQUESTS,
+                        axum::Json(serde_json::json!({ "error": "rate limited" })),
+                    );
+                }
+                (
+                    axum::http::StatusCode::OK,
+                    axum::Json(serde_json::json!({
+                        "choices": [{ "message": { "content": model }, "finish_reason": "stop" }]
+                    })),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    fn rotation(models: &[&str]) -> Arc<ModelRotation> {
+        Arc::new(ModelRotation::new(models.iter().map(|m| m.to_string()).collect()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_rotation_skips_rate_limited_model() {
+        let base_url = spawn_rate_limited_server(&["gpt-a"]).await;
+        let rotation = rotation(&["gpt-a", "gpt-b", "gpt-c"]);
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url)
+            .with_model_rotation(rotation.clone());
+
+        // gpt-a は 429 なので同じ呼び出しの中で gpt-b に切り替わる
+        let response = client.process("hello").await.unwrap();
+        assert_eq!(response.model, "gpt-b");
+        assert_eq!(response.text, "gpt-b");
+        // 次の呼び出しは gpt-c から
+        assert_eq!(client.process("hello").await.unwrap().model, "gpt-c");
+        assert_eq!(client.served_model(), "gpt-c");
+
+        let rate_limited = rotation.state("gpt-a").unwrap();
+        assert_eq!(rate_limited.call_count, 1);
+        assert!(rate_limited.last_429_at.is_some());
+        assert_eq!(rate_limited.current_model, "gpt-a");
+        let served = rotation.state("gpt-b").unwrap();
+        assert_eq!(served.call_count, 1);
+        assert!(served.last_429_at.is_none());
+        assert_eq!(rotation.state("gpt-c").unwrap().call_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_all_models_rate_limited() {
+        let base_url = spawn_rate_limited_server(&["gpt-a", "gpt-b"]).await;
+        let rotation = rotation(&["gpt-a", "gpt-b"]);
+        let client = OpenAIClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url)
+            .with_model_rotation(rotation.clone());
+
+        let err = client.process("hello").await.unwrap_err();
+        assert!(matches!(err, AIError::RateLimited(_)));
+        // 失敗したリクエストは最後に送ったモデルに数える
+        assert_eq!(client.served_model(), "gpt-b");
+        assert_eq!(rotation.state("gpt-a").unwrap().call_count, 1);
+        assert_eq!(rotation.state("gpt-b").unwrap().call_count, 1);
+    }
 }
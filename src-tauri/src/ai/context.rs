@@ -1,10 +1,61 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Mutex;
 
+use serde::{Deserialize, Serialize};
+
+/// `{context}` に展開するときの履歴の書式
+///
+/// プロバイダーやプロンプトによって扱いやすい形式が異なるため切り替えられる。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextFormat {
+    /// `[1] text`
+    #[default]
+    Numbered,
+    /// `- text`
+    Bullets,
+    /// 本文のみ
+    Plain,
+    /// `{index}`（1始まり）と `{text}` を置換するテンプレート
+    Custom(String),
+}
+
+impl ContextFormat {
+    /// 履歴を古い順に1行ずつ整形し、改行で連結する（空なら None）
+    pub fn format(&self, entries: &[String]) -> Option<String> {
+        if entries.is_empty() {
+            return None;
+        }
+        Some(
+            entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| self.format_entry(i + 1, entry))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    fn format_entry(&self, index: usize, text: &str) -> String {
+        match self {
+            ContextFormat::Numbered => format!("[{}] {}", index, text),
+            ContextFormat::Bullets => format!("- {}", text),
+            ContextFormat::Plain => text.to_string(),
+            // 本文中の `{index}` を置換しないよう {text} は最後に展開する
+            ContextFormat::Custom(template) => template
+                .replace("{index}", &index.to_string())
+                .replace("{text}", text),
+        }
+    }
+}
+
 /// 直近の入力履歴を保持するコンテキストマネージャ
 pub struct ContextManager {
     history: Mutex<VecDeque<String>>,
     max_entries: usize,
+    format: ContextFormat,
+    /// Some なら `*_for` 系の呼び出しはこのモードのものだけを受け付ける
+    mode_id: Option<String>,
 }
 
 impl ContextManager {
@@ -15,9 +66,35 @@ impl ContextManager {
         Self {
             history: Mutex::new(VecDeque::with_capacity(max_entries)),
             max_entries,
+            format: ContextFormat::default(),
+            mode_id: None,
         }
     }
 
+    /// 特定のモード専用にする
+    pub fn with_mode(mut self, mode_id: impl Into<String>) -> Self {
+        self.mode_id = Some(mode_id.into());
+        self
+    }
+
+    /// 専用のモード（全モード共通なら None）
+    pub fn mode_id(&self) -> Option<&str> {
+        self.mode_id.as_deref()
+    }
+
+    fn accepts(&self, mode_id: &str) -> bool {
+        match &self.mode_id {
+            Some(id) => id == mode_id,
+            None => true,
+        }
+    }
+
+    /// 履歴の書式を指定する
+    pub fn with_format(mut self, format: ContextFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     /// 入力テキストを履歴に追加する
     pub fn add_entry(&self, text: &str) {
         let mut history = self.history.lock().unwrap();
@@ -27,29 +104,43 @@ impl ContextManager {
         history.push_back(text.to_string());
     }
 
-    /// 直近の履歴を改行区切りの文字列として取得する
+    /// 直近の履歴を `ContextFormat` に従った改行区切りの文字列として取得する
     ///
     /// 履歴が空の場合は None を返す。
     pub fn get_context(&self) -> Option<String> {
-        let history = self.history.lock().unwrap();
-        if history.is_empty() {
-            None
-        } else {
-            Some(
-                history
-                    .iter()
-                    .enumerate()
-                    .map(|(i, entry)| format!("[{}] {}", i + 1, entry))
-                    .collect::<Vec<_>>()
-                    .join("\n"),
-            )
-        }
+        self.format.format(&self.entries())
+    }
+
+    /// 履歴を古い順に取得する
+    pub fn entries(&self) -> Vec<String> {
+        self.history.lock().unwrap().iter().cloned().collect()
     }
 
     /// 履歴をクリアする
     pub fn clear(&self) {
         self.history.lock().unwrap().clear();
     }
+
+    /// `mode_id` の入力を履歴に追加する（別のモード専用なら何もしない）
+    pub fn add_entry_for(&self, mode_id: &str, text: &str) {
+        if self.accepts(mode_id) {
+            self.add_entry(text);
+        }
+    }
+
+    /// `mode_id` 向けのコンテキストを取得する（別のモード専用なら None）
+    pub fn get_context_for(&self, mode_id: &str) -> Option<String> {
+        self.format.format(&self.entries_for(mode_id))
+    }
+
+    /// `mode_id` 向けの履歴を古い順に取得する（別のモード専用なら空）
+    pub fn entries_for(&self, mode_id: &str) -> Vec<String> {
+        if self.accepts(mode_id) {
+            self.entries()
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 impl Default for ContextManager {
@@ -58,6 +149,91 @@ impl Default for ContextManager {
     }
 }
 
+/// 全モード共通の履歴を保持するマネージャのキー
+pub const GLOBAL_CONTEXT_KEY: &str = "global";
+
+/// モードごとのコンテキストマネージャを管理する Tauri State
+///
+/// モードを切り替えたときに前のモードの履歴がプロンプトに混ざらないよう、
+/// モード ID ごとに別のマネージャを持つ。`"global"` はモードを限定しない
+/// マネージャで、すべてのモードの入力を記録する。
+pub struct ContextRegistry {
+    managers: Mutex<HashMap<String, ContextManager>>,
+    max_entries: usize,
+}
+
+impl ContextRegistry {
+    /// `max_entries`: 各マネージャが保持する最大履歴数
+    pub fn new(max_entries: usize) -> Self {
+        let mut managers = HashMap::new();
+        managers.insert(
+            GLOBAL_CONTEXT_KEY.to_string(),
+            ContextManager::new(max_entries),
+        );
+        Self {
+            managers: Mutex::new(managers),
+            max_entries,
+        }
+    }
+
+    /// `mode_id` のマネージャ（なければ作成）と `"global"` に入力を追加する
+    pub fn add_entry(&self, mode_id: &str, text: &str) {
+        let mut managers = self.managers.lock().unwrap();
+        if mode_id != GLOBAL_CONTEXT_KEY {
+            managers
+                .entry(mode_id.to_string())
+                .or_insert_with(|| ContextManager::new(self.max_entries).with_mode(mode_id))
+                .add_entry_for(mode_id, text);
+        }
+        if let Some(global) = managers.get(GLOBAL_CONTEXT_KEY) {
+            global.add_entry(text);
+        }
+    }
+
+    /// `mode_id` の履歴を古い順に取得する
+    pub fn entries(&self, mode_id: &str) -> Vec<String> {
+        self.managers
+            .lock()
+            .unwrap()
+            .get(mode_id)
+            .map(|manager| manager.entries_for(mode_id))
+            .unwrap_or_default()
+    }
+
+    /// `mode_id` のコンテキストを取得する（履歴がなければ None）
+    pub fn get_context(&self, mode_id: &str) -> Option<String> {
+        self.managers
+            .lock()
+            .unwrap()
+            .get(mode_id)
+            .and_then(|manager| manager.get_context_for(mode_id))
+    }
+
+    /// `mode_id` の履歴をクリアする（マネージャがなければ false）
+    pub fn clear_mode(&self, mode_id: &str) -> bool {
+        match self.managers.lock().unwrap().get(mode_id) {
+            Some(manager) => {
+                manager.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `"global"` を含むすべての履歴をクリアする
+    pub fn clear_all(&self) {
+        for manager in self.managers.lock().unwrap().values() {
+            manager.clear();
+        }
+    }
+}
+
+impl Default for ContextRegistry {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,6 +266,121 @@ mod tests {
         assert!(ctx.contains("[2] three"));
     }
 
+    fn two_entries() -> Vec<String> {
+        vec!["first".to_string(), "second".to_string()]
+    }
+
+    #[test]
+    fn test_format_numbered() {
+        assert_eq!(
+            ContextFormat::Numbered.format(&two_entries()).unwrap(),
+            "[1] first\n[2] second"
+        );
+    }
+
+    #[test]
+    fn test_format_bullets() {
+        assert_eq!(
+            ContextFormat::Bullets.format(&two_entries()).unwrap(),
+            "- first\n- second"
+        );
+    }
+
+    #[test]
+    fn test_format_plain() {
+        assert_eq!(
+            ContextFormat::Plain.format(&two_entries()).unwrap(),
+            "first\nsecond"
+        );
+    }
+
+    #[test]
+    fn test_format_custom() {
+        let format = ContextFormat::Custom("<turn n=\"{index}\">{text}</turn>".to_string());
+        assert_eq!(
+            format.format(&two_entries()).unwrap(),
+            "<turn n=\"1\">first</turn>\n<turn n=\"2\">second</turn>"
+        );
+        // 本文中のプレースホルダーは置換しない
+        let entries = vec!["{index} literal".to_string()];
+        assert_eq!(
+            ContextFormat::Custom("{index}: {text}".to_string())
+                .format(&entries)
+                .unwrap(),
+            "1: {index} literal"
+        );
+        assert!(format.format(&[]).is_none());
+    }
+
+    #[test]
+    fn test_mode_manager_ignores_other_modes() {
+        let cm = ContextManager::new(3).with_mode("proofread");
+        cm.add_entry_for("proofread", "校正の入力");
+        cm.add_entry_for("summarize", "要約の入力");
+        assert_eq!(cm.entries(), vec!["校正の入力"]);
+        assert!(cm.get_context_for("summarize").is_none());
+        assert_eq!(cm.get_context_for("proofread").unwrap(), "[1] 校正の入力");
+
+        // モードを限定しなければすべて受け付ける
+        let cm = ContextManager::new(3);
+        cm.add_entry_for("proofread", "a");
+        cm.add_entry_for("summarize", "b");
+        assert_eq!(cm.entries_for("summarize"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_registry_isolates_modes() {
+        let registry = ContextRegistry::default();
+        registry.add_entry("proofread", "校正の入力");
+        registry.add_entry("summarize", "要約の入力");
+
+        assert_eq!(registry.entries("proofread"), vec!["校正の入力"]);
+        assert_eq!(registry.entries("summarize"), vec!["要約の入力"]);
+        assert!(!registry
+            .get_context("summarize")
+            .unwrap()
+            .contains("校正の入力"));
+        assert!(registry.entries("translate").is_empty());
+        assert_eq!(
+            registry.entries(GLOBAL_CONTEXT_KEY),
+            vec!["校正の入力", "要約の入力"]
+        );
+    }
+
+    #[test]
+    fn test_registry_clear() {
+        let registry = ContextRegistry::default();
+        registry.add_entry("proofread", "a");
+        registry.add_entry("summarize", "b");
+
+        assert!(registry.clear_mode("proofread"));
+        assert!(registry.entries("proofread").is_empty());
+        assert_eq!(registry.entries("summarize"), vec!["b"]);
+        assert!(!registry.clear_mode("translate"));
+
+        registry.clear_all();
+        assert!(registry.entries("summarize").is_empty());
+        assert!(registry.entries(GLOBAL_CONTEXT_KEY).is_empty());
+    }
+
+    #[test]
+    fn test_manager_with_format() {
+        let cm = ContextManager::new(3).with_format(ContextFormat::Bullets);
+        cm.add_entry("first");
+        cm.add_entry("second");
+        assert_eq!(cm.get_context().unwrap(), "- first\n- second");
+    }
+
+    #[test]
+    fn test_context_format_serde() {
+        assert_eq!(
+            serde_json::to_value(ContextFormat::Bullets).unwrap(),
+            "bullets"
+        );
+        let custom: ContextFormat = serde_json::from_str(r#"{"custom":"{text}"}"#).unwrap();
+        assert_eq!(custom, ContextFormat::Custom("{text}".to_string()));
+    }
+
     #[test]
     fn test_clear() {
         let cm = ContextManager::default();
@@ -0,0 +1,301 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::{AIError, AIProvider, AIResponse, CostEstimate, ProviderType, StreamChunk};
+
+/// 主プロバイダーが失敗したら AI_PROVIDER_FALLBACK のプロバイダーを順に試す
+///
+/// 認証切れや接続失敗、再試行しても解消しないレート制限（`ApiKeyMissing` / `RequestFailed` /
+/// `RateLimited`）のときだけ次に進む。レスポンスの解釈に失敗した場合などは
+/// 同じ結果になりやすいので、そのままエラーを返す。
+pub struct FallbackProvider {
+    /// 試す順のプロバイダー（先頭が主プロバイダー）
+    providers: Vec<(ProviderType, Box<dyn AIProvider>)>,
+    /// 直前のリクエストで最後に試したプロバイダーの位置（`served_*` で返す）
+    last_attempted: AtomicUsize,
+}
+
+impl FallbackProvider {
+    /// プロバイダーが1つもなければ None
+    pub fn new(providers: Vec<(ProviderType, Box<dyn AIProvider>)>) -> Option<Self> {
+        (!providers.is_empty()).then_some(Self {
+            providers,
+            last_attempted: AtomicUsize::new(0),
+        })
+    }
+
+    fn primary(&self) -> &dyn AIProvider {
+        self.providers[0].1.as_ref()
+    }
+
+    fn last_attempted(&self) -> &(ProviderType, Box<dyn AIProvider>) {
+        &self.providers[self.last_attempted.load(Ordering::Relaxed)]
+    }
+}
+
+/// 次のプロバイダーで回復できる可能性があるエラーか
+pub fn is_recoverable(error: &AIError) -> bool {
+    matches!(
+        error,
+        AIError::ApiKeyMissing(_) | AIError::RequestFailed(_) | AIError::RateLimited(_)
+    )
+}
+
+#[async_trait]
+impl AIProvider for FallbackProvider {
+    fn model_name(&self) -> &str {
+        self.primary().model_name()
+    }
+
+    fn served_model(&self) -> String {
+        self.last_attempted().1.served_model()
+    }
+
+    fn served_provider(&self) -> Option<ProviderType> {
+        Some(self.last_attempted().0.clone())
+    }
+
+    fn estimate_cost(&self, prompt: &str) -> CostEstimate {
+        self.primary().estimate_cost(prompt)
+    }
+
+    /// 代わりのプロバイダーが応答した場合、`model` は `"openai/gpt-4o-mini"` のように
+    /// プロバイダー名を付けて返す
+    async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
+        let mut last_error = None;
+        for (i, (provider_type, provider)) in self.providers.iter().enumerate() {
+            if let Some(error) = &last_error {
+                eprintln!(
+                    "[ai] falling back to {} ({}) after: {}",
+                    provider_type.as_str(),
+                    provider.model_name(),
+                    error
+                );
+            }
+            self.last_attempted.store(i, Ordering::Relaxed);
+            match provider.process(prompt).await {
+                Ok(mut response) => {
+                    if i > 0 {
+                        response.model = format!("{}/{}", provider_type.as_str(), response.model);
+                    }
+                    return Ok(response);
+                }
+                Err(e) if is_recoverable(&e) => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("FallbackProvider has at least one provider"))
+    }
+
+    /// チャンクを送る前の失敗（接続・認証）だけ次のプロバイダーで送り直す
+    async fn process_stream(
+        &self,
+        prompt: &str,
+        sender: mpsc::Sender<StreamChunk>,
+    ) -> Result<(), AIError> {
+        let mut last_error = None;
+        for (i, (provider_type, provider)) in self.providers.iter().enumerate() {
+            if let Some(error) = &last_error {
+                eprintln!(
+                    "[ai] falling back to {} ({}) after: {}",
+                    provider_type.as_str(),
+                    provider.model_name(),
+                    error
+                );
+            }
+            self.last_attempted.store(i, Ordering::Relaxed);
+            match provider.process_stream(prompt, sender.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) if is_recoverable(&e) => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("FallbackProvider has at least one provider"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// 決まった結果を返し、呼ばれた回数を数えるモックプロバイダー
+    struct MockProvider {
+        model: &'static str,
+        result: fn() -> Result<String, AIError>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AIProvider for MockProvider {
+        fn model_name(&self) -> &str {
+            self.model
+        }
+
+        async fn process(&self, _prompt: &str) -> Result<AIResponse, AIError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            (self.result)().map(|text| AIResponse {
+                text,
+                model: self.model.to_string(),
+                usage: None,
+                system_fingerprint: None,
+                finish_reason: None,
+            })
+        }
+
+        async fn process_stream(
+            &self,
+            _prompt: &str,
+            sender: mpsc::Sender<StreamChunk>,
+        ) -> Result<(), AIError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let content = (self.result)()?;
+            let _ = sender
+                .send(StreamChunk {
+                    content,
+                    done: true,
+                })
+                .await;
+            Ok(())
+        }
+    }
+
+    fn mock(
+        provider_type: ProviderType,
+        model: &'static str,
+        result: fn() -> Result<String, AIError>,
+    ) -> (ProviderType, Box<dyn AIProvider>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider {
+            model,
+            result,
+            calls: calls.clone(),
+        };
+        (provider_type, Box::new(provider), calls)
+    }
+
+    fn chain(
+        mocks: Vec<(ProviderType, Box<dyn AIProvider>, Arc<AtomicUsize>)>,
+    ) -> (FallbackProvider, Vec<Arc<AtomicUsize>>) {
+        let mut providers = Vec::new();
+        let mut calls = Vec::new();
+        for (provider_type, provider, count) in mocks {
+            providers.push((provider_type, provider));
+            calls.push(count);
+        }
+        (FallbackProvider::new(providers).unwrap(), calls)
+    }
+
+    #[tokio::test]
+    async fn test_primary_success_keeps_model_name() {
+        let (provider, calls) = chain(vec![
+            mock(ProviderType::VertexAI, "gemini-2.0-flash", || {
+                Ok("primary".to_string())
+            }),
+            mock(ProviderType::OpenAI, "gpt-4o-mini", || {
+                Ok("fallback".to_string())
+            }),
+        ]);
+
+        let response = provider.process("hello").await.unwrap();
+        assert_eq!(response.text, "primary");
+        assert_eq!(response.model, "gemini-2.0-flash");
+        assert_eq!(provider.served_provider(), Some(ProviderType::VertexAI));
+        assert_eq!(provider.served_model(), "gemini-2.0-flash");
+        assert_eq!(calls[1].load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_recoverable_errors() {
+        let (provider, calls) = chain(vec![
+            mock(ProviderType::VertexAI, "gemini-2.0-flash", || {
+                Err(AIError::ApiKeyMissing("gcloud auth failed".to_string()))
+            }),
+            mock(ProviderType::OpenAI, "gpt-4o-mini", || {
+                Err(AIError::RequestFailed("HTTP 500".to_string()))
+            }),
+            mock(ProviderType::Anthropic, "claude-haiku", || {
+                Ok("served".to_string())
+            }),
+        ]);
+
+        let response = provider.process("hello").await.unwrap();
+        assert_eq!(response.text, "served");
+        assert_eq!(response.model, "anthropic/claude-haiku");
+        assert_eq!(provider.served_provider(), Some(ProviderType::Anthropic));
+        assert_eq!(provider.served_model(), "claude-haiku");
+        assert!(calls.iter().all(|c| c.load(Ordering::SeqCst) == 1));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_rate_limit() {
+        let (provider, _) = chain(vec![
+            mock(ProviderType::OpenAI, "gpt-4o-mini", || {
+                Err(AIError::RateLimited("HTTP 429".to_string()))
+            }),
+            mock(ProviderType::Anthropic, "claude-haiku", || {
+                Ok("served".to_string())
+            }),
+        ]);
+
+        let response = provider.process("hello").await.unwrap();
+        assert_eq!(response.model, "anthropic/claude-haiku");
+    }
+
+    #[tokio::test]
+    async fn test_does_not_fall_back_on_parse_error() {
+        let (provider, calls) = chain(vec![
+            mock(ProviderType::VertexAI, "gemini-2.0-flash", || {
+                Err(AIError::ParseError("bad json".to_string()))
+            }),
+            mock(ProviderType::OpenAI, "gpt-4o-mini", || {
+                Ok("fallback".to_string())
+            }),
+        ]);
+
+        assert!(matches!(
+            provider.process("hello").await,
+            Err(AIError::ParseError(_))
+        ));
+        assert_eq!(calls[1].load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_error_when_all_fail() {
+        let (provider, _) = chain(vec![
+            mock(ProviderType::VertexAI, "gemini-2.0-flash", || {
+                Err(AIError::ApiKeyMissing("gcloud auth failed".to_string()))
+            }),
+            mock(ProviderType::OpenAI, "gpt-4o-mini", || {
+                Err(AIError::RequestFailed("HTTP 503".to_string()))
+            }),
+        ]);
+
+        match provider.process("hello").await {
+            Err(AIError::RequestFailed(msg)) => assert_eq!(msg, "HTTP 503"),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.text)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_before_first_chunk() {
+        let (provider, _) = chain(vec![
+            mock(ProviderType::VertexAI, "gemini-2.0-flash", || {
+                Err(AIError::RequestFailed("HTTP 401".to_string()))
+            }),
+            mock(ProviderType::OpenAI, "gpt-4o-mini", || {
+                Ok("streamed".to_string())
+            }),
+        ]);
+        let (tx, mut rx) = mpsc::channel(4);
+
+        provider.process_stream("hello", tx).await.unwrap();
+        let chunk = rx.recv().await.unwrap();
+        assert_eq!(chunk.content, "streamed");
+        assert!(chunk.done);
+        assert_eq!(provider.served_provider(), Some(ProviderType::OpenAI));
+        assert_eq!(provider.served_model(), "gpt-4o-mini");
+    }
+}
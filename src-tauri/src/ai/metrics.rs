@@ -0,0 +1,141 @@
+use std::sync::OnceLock;
+
+use prometheus::{Counter, IntCounterVec, Opts, Registry, TextEncoder};
+
+use super::{ProviderType, TokenUsage};
+
+/// Prometheus 形式で公開するアプリのメトリクス
+///
+/// プロセス全体で1つの `Registry` に登録し、`GET /metrics` で出力する。
+pub struct Metrics {
+    registry: Registry,
+    ai_requests: IntCounterVec,
+    transcriptions: IntCounterVec,
+    recording_duration_seconds: Counter,
+    tokens_used: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let ai_requests = IntCounterVec::new(
+            Opts::new("taponsen_ai_requests_total", "AI processing requests"),
+            &["provider", "model", "status"],
+        )
+        .expect("valid metric");
+        let transcriptions = IntCounterVec::new(
+            Opts::new("taponsen_transcriptions_total", "Completed transcriptions"),
+            &["backend", "language"],
+        )
+        .expect("valid metric");
+        let recording_duration_seconds = Counter::new(
+            "taponsen_recording_duration_seconds_sum",
+            "Total recorded audio duration in seconds",
+        )
+        .expect("valid metric");
+        let tokens_used = IntCounterVec::new(
+            Opts::new(
+                "taponsen_tokens_used_total",
+                "Tokens consumed by AI processing",
+            ),
+            &["provider", "model", "type"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(ai_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(transcriptions.clone()),
+            Box::new(recording_duration_seconds.clone()),
+            Box::new(tokens_used.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            ai_requests,
+            transcriptions,
+            recording_duration_seconds,
+            tokens_used,
+        }
+    }
+}
+
+/// プロセス全体で共有するメトリクス
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// AI リクエストの結果を記録する（`usage` があればトークン数も加算）
+pub fn record_ai_request(
+    provider: &ProviderType,
+    model: &str,
+    success: bool,
+    usage: Option<&TokenUsage>,
+) {
+    let m = metrics();
+    let provider = provider.as_str();
+    let status = if success { "success" } else { "error" };
+    m.ai_requests
+        .with_label_values(&[provider, model, status])
+        .inc();
+    if let Some(usage) = usage {
+        m.tokens_used
+            .with_label_values(&[provider, model, "prompt"])
+            .inc_by(usage.prompt_tokens as u64);
+        m.tokens_used
+            .with_label_values(&[provider, model, "completion"])
+            .inc_by(usage.completion_tokens as u64);
+    }
+}
+
+/// 文字起こしの完了を記録する（言語が不明なら "unknown"）
+pub fn record_transcription(backend: &str, language: Option<&str>) {
+    metrics()
+        .transcriptions
+        .with_label_values(&[backend, language.unwrap_or("unknown")])
+        .inc();
+}
+
+/// 録音時間を加算する
+pub fn record_recording_duration(duration_ms: u64) {
+    metrics()
+        .recording_duration_seconds
+        .inc_by(duration_ms as f64 / 1000.0);
+}
+
+/// 登録済みメトリクスを Prometheus のテキスト形式で出力する
+pub fn encode_to_string() -> String {
+    TextEncoder::new()
+        .encode_to_string(&metrics().registry.gather())
+        .unwrap_or_else(|e| {
+            eprintln!("[metrics] failed to encode metrics: {}", e);
+            String::new()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_ai_request_counts_tokens() {
+        let usage = TokenUsage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+        };
+        record_ai_request(&ProviderType::Anthropic, "metrics-test", true, Some(&usage));
+
+        let text = encode_to_string();
+        assert!(text.contains(
+            r#"taponsen_ai_requests_total{model="metrics-test",provider="anthropic",status="success"} 1"#
+        ));
+        assert!(text.contains(
+            r#"taponsen_tokens_used_total{model="metrics-test",provider="anthropic",type="prompt"} 10"#
+        ));
+    }
+}
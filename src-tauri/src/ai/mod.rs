@@ -1,12 +1,23 @@
+pub mod catalog;
 pub mod client;
 pub mod context;
+pub mod fallback;
+pub mod metrics;
+pub mod postprocess;
 pub mod prompt;
+pub mod queue;
+pub mod rotation;
 pub mod streaming;
+pub mod vertex_auth;
+
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
+use crate::ai::rotation::ModelRotation;
+
 /// AI処理のエラー型
 #[derive(Debug)]
 pub enum AIError {
@@ -15,6 +26,8 @@ pub enum AIError {
     ParseError(String),
     Timeout,
     StreamError(String),
+    /// HTTP 429
+    RateLimited(String),
 }
 
 impl std::fmt::Display for AIError {
@@ -25,6 +38,7 @@ impl std::fmt::Display for AIError {
             AIError::ParseError(msg) => write!(f, "Failed to parse response: {}", msg),
             AIError::Timeout => write!(f, "Request timed out"),
             AIError::StreamError(msg) => write!(f, "Stream error: {}", msg),
+            AIError::RateLimited(msg) => write!(f, "Rate limited: {}", msg),
         }
     }
 }
@@ -44,6 +58,22 @@ pub struct AIResponse {
     pub text: String,
     pub model: String,
     pub usage: Option<TokenUsage>,
+    /// OpenAI のバックエンド構成を示す識別子（seed 利用時の再現性確認用）
+    pub system_fingerprint: Option<String>,
+    /// 生成の終了理由（`normalize_finish_reason` でプロバイダー間の表記を統一済み）
+    pub finish_reason: Option<String>,
+}
+
+/// プロバイダーごとに異なる終了理由の表記を統一する
+///
+/// 出力上限で打ち切られた場合は "max_tokens"、正常終了は "stop" に揃える。
+/// それ以外は小文字化してそのまま返す。
+pub fn normalize_finish_reason(raw: &str) -> String {
+    match raw.to_ascii_lowercase().as_str() {
+        "length" | "max_tokens" | "max_output_tokens" => "max_tokens".to_string(),
+        "stop" | "end_turn" | "stop_sequence" => "stop".to_string(),
+        other => other.to_string(),
+    }
 }
 
 /// トークン使用量
@@ -54,9 +84,120 @@ pub struct TokenUsage {
     pub total_tokens: u32,
 }
 
+/// モデルごとのコンテキストウィンドウ（トークン数）
+///
+/// 新しいモデルを追加したらここも更新する。
+static MODEL_CONTEXT_WINDOWS: phf::Map<&'static str, u32> = phf::phf_map! {
+    "gpt-4o" => 128_000,
+    "gpt-4o-mini" => 128_000,
+    "gpt-4.1" => 1_047_576,
+    "gpt-4.1-mini" => 1_047_576,
+    "gpt-4.1-nano" => 1_047_576,
+    "claude-3-haiku-20240307" => 200_000,
+    "claude-3-5-haiku-20241022" => 200_000,
+    "claude-haiku-4-5-20251001" => 200_000,
+    "claude-sonnet-4-5-20250929" => 200_000,
+    "gemini-1.5-pro" => 2_097_152,
+    "gemini-2.0-flash" => 1_048_576,
+    "gemini-2.5-flash" => 1_048_576,
+};
+
+/// モデル名からコンテキストウィンドウのサイズを引く（未知のモデルは None）
+pub fn model_context_window(model: &str) -> Option<u32> {
+    MODEL_CONTEXT_WINDOWS.get(model).copied()
+}
+
+/// テキストのトークン数を概算する
+///
+/// ASCII は約4文字で1トークン、日本語などの非ASCII文字は1文字1トークンとして数える。
+pub fn estimate_tokens(text: &str) -> u32 {
+    let ascii = text.chars().filter(|c| c.is_ascii()).count();
+    let non_ascii = text.chars().count() - ascii;
+    (ascii.div_ceil(4) + non_ascii) as u32
+}
+
+/// モデルごとの入力単価（USD / 100万トークン）
+///
+/// プロバイダー固有の料金体系（キャッシュ割引や文字単位課金）は各クライアントの
+/// `estimate_cost` で上書きする。
+static MODEL_INPUT_PRICES: phf::Map<&'static str, f64> = phf::phf_map! {
+    "gpt-4o" => 2.50,
+    "gpt-4o-mini" => 0.15,
+    "gpt-4.1" => 2.00,
+    "gpt-4.1-mini" => 0.40,
+    "gpt-4.1-nano" => 0.10,
+    "claude-3-haiku-20240307" => 0.25,
+    "claude-3-5-haiku-20241022" => 0.80,
+    "claude-haiku-4-5-20251001" => 1.00,
+    "claude-sonnet-4-5-20250929" => 3.00,
+    "gemini-1.5-pro" => 1.25,
+    "gemini-2.0-flash" => 0.10,
+    "gemini-2.5-flash" => 0.30,
+};
+
+/// モデル名から入力単価（USD / 100万トークン）を引く（未知のモデルは None）
+pub fn model_input_price(model: &str) -> Option<f64> {
+    MODEL_INPUT_PRICES.get(model).copied()
+}
+
+/// 100万トークンあたりの単価からトークン数分の料金を求める
+pub fn tokens_cost_usd(tokens: u32, usd_per_mtok: f64) -> f64 {
+    tokens as f64 * usd_per_mtok / 1_000_000.0
+}
+
+/// プロンプト送信（入力）にかかる料金の見積もり
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostEstimate {
+    pub model: String,
+    /// 入力全体の推定トークン数（コンテキストを含む）
+    pub input_tokens: u32,
+    /// `input_tokens` のうちコンテキスト分
+    pub context_tokens: u32,
+    /// 入力の料金（USD）。料金表にないモデルは None
+    pub input_cost_usd: Option<f64>,
+    /// プロンプトキャッシュにヒットした場合の料金（割引がないプロバイダーは None）
+    pub cached_input_cost_usd: Option<f64>,
+}
+
+impl CostEstimate {
+    /// `estimate_tokens` と料金表から見積もる
+    pub fn from_pricing_table(model: &str, prompt: &str) -> Self {
+        let input_tokens = estimate_tokens(prompt);
+        Self {
+            model: model.to_string(),
+            input_tokens,
+            context_tokens: 0,
+            input_cost_usd: model_input_price(model).map(|p| tokens_cost_usd(input_tokens, p)),
+            cached_input_cost_usd: None,
+        }
+    }
+}
+
+/// プロンプトがモデルのコンテキストウィンドウに収まるかを判定する
+///
+/// 未知のモデルは判定できないため None を返す。
+pub fn can_fit_prompt(model: &str, prompt: &str) -> Option<bool> {
+    model_context_window(model).map(|max| estimate_tokens(prompt) <= max)
+}
+
 /// AIプロバイダーの抽象trait
 #[async_trait]
 pub trait AIProvider: Send + Sync {
+    /// リクエストに使用するモデル名
+    fn model_name(&self) -> &str;
+
+    /// 直前のリクエストを送ったモデル（ローテーションで `model_name` と変わることがある）
+    fn served_model(&self) -> String {
+        self.model_name().to_string()
+    }
+
+    /// 直前のリクエストを処理したプロバイダー（フォールバックで AI_PROVIDER と変わることがある）
+    ///
+    /// None なら呼び出し側で選んだプロバイダーのまま。
+    fn served_provider(&self) -> Option<ProviderType> {
+        None
+    }
+
     /// テキストを処理して結果を返す（非ストリーミング）
     async fn process(&self, prompt: &str) -> Result<AIResponse, AIError>;
 
@@ -66,6 +207,33 @@ pub trait AIProvider: Send + Sync {
         prompt: &str,
         sender: mpsc::Sender<StreamChunk>,
     ) -> Result<(), AIError>;
+
+    /// プロンプト送信の料金を見積もる（既定は共通の料金表を使う）
+    fn estimate_cost(&self, prompt: &str) -> CostEstimate {
+        CostEstimate::from_pricing_table(self.model_name(), prompt)
+    }
+
+    /// コンテキストを付けてプロンプトを送る場合の料金を見積もる
+    async fn estimate_cost_with_context(&self, prompt: &str, context: &str) -> CostEstimate {
+        let mut estimate = self.estimate_cost(&format!("{}\n\n{}", context, prompt));
+        estimate.context_tokens = estimate_tokens(context);
+        estimate
+    }
+}
+
+/// プロバイダー生成時に渡す実行時オプション
+#[derive(Debug, Clone, Default)]
+pub struct ProviderOptions {
+    /// OpenAI の `seed` パラメータ
+    pub openai_seed: Option<u64>,
+    /// OpenAI で `max_tokens` の代わりに `max_completion_tokens` を使うか（None なら AppConfig に従う）
+    pub use_max_completion_tokens: Option<bool>,
+    /// OpenAI でリクエストごとに切り替えるモデル（モードの `model_rotation`）
+    pub model_rotation: Option<Arc<ModelRotation>>,
+    /// 生成の temperature（モードの `temperature`）
+    pub temperature: Option<f32>,
+    /// 出力の最大トークン数（モードの `max_tokens`）
+    pub max_tokens: Option<u32>,
 }
 
 /// サポートするAIプロバイダーの種別
@@ -75,6 +243,8 @@ pub enum ProviderType {
     VertexAI,
     OpenAI,
     Anthropic,
+    /// ローカルの Ollama サーバー（文字起こしを外部に送らない）
+    Ollama,
 }
 
 impl Default for ProviderType {
@@ -82,3 +252,111 @@ impl Default for ProviderType {
         ProviderType::VertexAI
     }
 }
+
+impl ProviderType {
+    /// AI_PROVIDER と同じ小文字の名前を返す
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderType::VertexAI => "vertexai",
+            ProviderType::OpenAI => "openai",
+            ProviderType::Anthropic => "anthropic",
+            ProviderType::Ollama => "ollama",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_context_window_known_models() {
+        assert_eq!(model_context_window("gpt-4o"), Some(128_000));
+        assert_eq!(
+            model_context_window("claude-haiku-4-5-20251001"),
+            Some(200_000)
+        );
+        assert_eq!(model_context_window("gemini-2.0-flash"), Some(1_048_576));
+    }
+
+    #[test]
+    fn test_model_context_window_unknown_model() {
+        assert_eq!(model_context_window("unknown-model"), None);
+    }
+
+    #[test]
+    fn test_normalize_finish_reason() {
+        // OpenAI / Anthropic / Vertex AI の上限到達
+        assert_eq!(normalize_finish_reason("length"), "max_tokens");
+        assert_eq!(normalize_finish_reason("max_tokens"), "max_tokens");
+        assert_eq!(normalize_finish_reason("MAX_TOKENS"), "max_tokens");
+        // 正常終了
+        assert_eq!(normalize_finish_reason("stop"), "stop");
+        assert_eq!(normalize_finish_reason("end_turn"), "stop");
+        assert_eq!(normalize_finish_reason("STOP"), "stop");
+        assert_eq!(normalize_finish_reason("SAFETY"), "safety");
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("こんにちは"), 5);
+    }
+
+    /// 料金表のモデルを返すだけのプロバイダー
+    struct PricedProvider(&'static str);
+
+    #[async_trait]
+    impl AIProvider for PricedProvider {
+        fn model_name(&self) -> &str {
+            self.0
+        }
+
+        async fn process(&self, _prompt: &str) -> Result<AIResponse, AIError> {
+            Err(AIError::RequestFailed(
+                "PricedProvider only estimates cost".to_string(),
+            ))
+        }
+
+        async fn process_stream(
+            &self,
+            _prompt: &str,
+            _sender: mpsc::Sender<StreamChunk>,
+        ) -> Result<(), AIError> {
+            Err(AIError::StreamError(
+                "PricedProvider only estimates cost".to_string(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_default_estimate_cost_uses_pricing_table() {
+        let estimate = PricedProvider("gpt-4o").estimate_cost(&"a".repeat(4_000));
+        assert_eq!(estimate.input_tokens, 1_000);
+        assert_eq!(estimate.input_cost_usd, Some(0.0025));
+        assert_eq!(estimate.cached_input_cost_usd, None);
+
+        let estimate = PricedProvider("unknown-model").estimate_cost("hello");
+        assert_eq!(estimate.input_tokens, 2);
+        assert_eq!(estimate.input_cost_usd, None);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_cost_with_context() {
+        let provider = PricedProvider("gpt-4o");
+        let context = "あ".repeat(100);
+        let estimate = provider.estimate_cost_with_context("hello", &context).await;
+        assert_eq!(estimate.context_tokens, 100);
+        // 区切りの改行を含めて入力全体を数える
+        assert_eq!(estimate.input_tokens, 102);
+        assert!(estimate.input_cost_usd > provider.estimate_cost("hello").input_cost_usd);
+    }
+
+    #[test]
+    fn test_can_fit_prompt() {
+        assert_eq!(can_fit_prompt("gpt-4o", "hello"), Some(true));
+        assert_eq!(can_fit_prompt("gpt-4o", &"あ".repeat(128_001)), Some(false));
+        assert_eq!(can_fit_prompt("unknown-model", "hello"), None);
+    }
+}
@@ -0,0 +1,453 @@
+use textwrap::core::Word;
+use textwrap::{Options, WordSeparator};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::modes::{LineWrapConfig, TypographicOpts};
+
+/// `max_response_chars` で切り詰めたことを示す末尾の印
+pub const TRUNCATION_MARKER: &str = "…[truncated]";
+
+/// `max_chars` 文字を超える AI 出力を直前の単語境界で切り詰め、末尾に印を付ける
+///
+/// 空白が見つからない場合（日本語など）は文字数ちょうどで切る。
+/// 上限以内なら None を返す。
+pub fn truncate_response(text: &str, max_chars: usize) -> Option<String> {
+    let (cut, _) = text.char_indices().nth(max_chars)?;
+    let head = &text[..cut];
+    // 上限の直後が空白なら単語の途中ではない
+    let head = if text[cut..].starts_with(char::is_whitespace) {
+        head
+    } else {
+        match head.rfind(char::is_whitespace) {
+            Some(i) if !head[..i].trim().is_empty() => &head[..i],
+            _ => head,
+        }
+    };
+    Some(format!("{} {}", head.trim_end(), TRUNCATION_MARKER))
+}
+
+/// AI 出力を指定の文字数で折り返す
+///
+/// 段落内の改行はいったん結合してから折り返すため、極端に長い行と
+/// 1語ごとの改行のどちらも正規化される。
+/// `preserve_paragraphs` が true なら空行を段落区切りとして残し、段落ごとに折り返す。
+pub fn apply_line_wrap(text: &str, config: &LineWrapConfig) -> String {
+    let width = config.max_chars_per_line.max(1);
+    if config.preserve_paragraphs {
+        split_paragraphs(text)
+            .iter()
+            .map(|p| wrap_paragraph(p, width))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else {
+        let lines: Vec<&str> = text.lines().collect();
+        wrap_paragraph(&lines, width)
+    }
+}
+
+/// 空行で区切られた段落ごとに行をまとめる
+fn split_paragraphs(text: &str) -> Vec<Vec<&str>> {
+    let mut paragraphs = Vec::new();
+    let mut current = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs
+}
+
+/// 段落内の行を結合して折り返す
+fn wrap_paragraph(lines: &[&str], width: usize) -> String {
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let cjk = lines.iter().any(|l| contains_cjk(l));
+    // 日本語は単語間に空白が無いため、行同士も空白なしで結合する
+    let joined = lines.join(if cjk { "" } else { " " });
+
+    let separator = if cjk {
+        WordSeparator::Custom(split_every_char)
+    } else {
+        WordSeparator::AsciiSpace
+    };
+    let options = Options::new(width).word_separator(separator);
+    textwrap::wrap(&joined, options).join("\n")
+}
+
+/// 任意の文字境界で改行できるよう1文字ずつ単語として扱う
+fn split_every_char(line: &str) -> Box<dyn Iterator<Item = Word<'_>> + '_> {
+    Box::new(
+        line.char_indices()
+            .map(move |(i, c)| Word::from(&line[i..i + c.len_utf8()])),
+    )
+}
+
+/// ひらがな・カタカナ・漢字・全角記号を含むか
+fn contains_cjk(text: &str) -> bool {
+    text.chars().any(is_cjk)
+}
+
+/// ひらがな・カタカナ・漢字・全角記号か（全角スペースは含まない）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3001}'..='\u{30FF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF01}'..='\u{FFEF}')
+}
+
+/// 半角カタカナ（半角の句読点・括弧を含む）か
+fn is_halfwidth_katakana(c: char) -> bool {
+    matches!(c, '\u{FF61}'..='\u{FF9F}')
+}
+
+/// 行内の空白（半角・全角スペース）か
+fn is_inline_space(c: char) -> bool {
+    matches!(c, ' ' | '\u{3000}')
+}
+
+/// 音声入力の日本語テキストによくある表記ゆれを正規化する
+///
+/// - 半角カタカナを全角に変換（NFKC、濁点・半濁点も合成）
+/// - 日本語の文字に挟まれた不要な空白を削除
+/// - 日本語に挟まれた半角数字を全角に変換
+/// - 重複した句読点（「。。」など）を1つにまとめる
+///
+/// NFKC は半角カタカナの連続部分にだけ適用し、全角の記号（！や（）など）は変えない。
+pub fn normalize_japanese(text: &str) -> String {
+    let text = normalize_halfwidth_katakana(text);
+    let text = remove_spaces_between_cjk(&text);
+    let text = widen_digits_between_cjk(&text);
+    collapse_duplicate_punctuation(&text)
+}
+
+fn normalize_halfwidth_katakana(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+    for c in text.chars() {
+        if is_halfwidth_katakana(c) {
+            run.push(c);
+            continue;
+        }
+        if !run.is_empty() {
+            out.extend(run.nfkc());
+            run.clear();
+        }
+        out.push(c);
+    }
+    out.extend(run.nfkc());
+    out
+}
+
+/// `pred` に一致する連続部分のうち、前後を日本語の文字に挟まれたものを `replace` で置き換える
+fn replace_runs_between_cjk(
+    text: &str,
+    pred: impl Fn(char) -> bool,
+    replace: impl Fn(&[char]) -> String,
+) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if !pred(chars[i]) {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && pred(chars[i]) {
+            i += 1;
+        }
+        let run = &chars[start..i];
+        let surrounded =
+            start > 0 && is_cjk(chars[start - 1]) && i < chars.len() && is_cjk(chars[i]);
+        if surrounded {
+            out.push_str(&replace(run));
+        } else {
+            out.extend(run);
+        }
+    }
+    out
+}
+
+fn remove_spaces_between_cjk(text: &str) -> String {
+    replace_runs_between_cjk(text, is_inline_space, |_| String::new())
+}
+
+fn widen_digits_between_cjk(text: &str) -> String {
+    replace_runs_between_cjk(
+        text,
+        |c| c.is_ascii_digit(),
+        |run| {
+            run.iter()
+                .filter_map(|&c| char::from_u32(c as u32 - '0' as u32 + '０' as u32))
+                .collect()
+        },
+    )
+}
+
+fn collapse_duplicate_punctuation(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prev = None;
+    for c in text.chars() {
+        if matches!(c, '。' | '、') && prev == Some(c) {
+            continue;
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// 直線の引用符とハイフンの連続を組版用の文字に置き換える
+///
+/// - `em_dash`: ` -- ` / ` --- ` を ` — ` に
+/// - `typographic_quotes`: 英語では `"` を “ ” に交互に、`'` を直前の文字に応じて ‘ ’ に、
+///   日本語では `"` を「」、`'` を『』に交互に置き換える（英単語中のアポストロフィは ’）
+///
+/// 日本語かどうかは `language_hint`（"ja" で始まるか）、未指定なら本文に日本語を含むかで決める。
+pub fn apply_typographic_corrections(text: &str, opts: &TypographicOpts) -> String {
+    let mut text = text.to_string();
+    if opts.em_dash {
+        text = text.replace(" --- ", " — ").replace(" -- ", " — ");
+    }
+    if !opts.typographic_quotes {
+        return text;
+    }
+    let japanese = match opts.language_hint.as_deref() {
+        Some(lang) => lang.to_ascii_lowercase().starts_with("ja"),
+        None => contains_cjk(&text),
+    };
+    if japanese {
+        japanese_quotes(&text)
+    } else {
+        curly_quotes(&text)
+    }
+}
+
+fn curly_quotes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut double_open = false;
+    let mut prev = None;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                out.push(if double_open { '”' } else { '“' });
+                double_open = !double_open;
+            }
+            // 語頭なら開き、それ以外（アポストロフィを含む）は閉じ
+            '\'' => out.push(if opens_quote(prev) { '‘' } else { '’' }),
+            _ => out.push(c),
+        }
+        prev = Some(c);
+    }
+    out
+}
+
+fn opens_quote(prev: Option<char>) -> bool {
+    prev.is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '[' | '{' | '“' | '—'))
+}
+
+fn japanese_quotes(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut double_open = false;
+    let mut single_open = false;
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '"' => {
+                out.push(if double_open { '」' } else { '「' });
+                double_open = !double_open;
+            }
+            '\'' => {
+                let in_word = i > 0
+                    && chars[i - 1].is_ascii_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|n| n.is_ascii_alphanumeric());
+                if in_word {
+                    out.push('’');
+                } else {
+                    out.push(if single_open { '』' } else { '『' });
+                    single_open = !single_open;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_chars_per_line: usize, preserve_paragraphs: bool) -> LineWrapConfig {
+        LineWrapConfig {
+            max_chars_per_line,
+            preserve_paragraphs,
+        }
+    }
+
+    #[test]
+    fn test_wrap_long_english_line() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let result = apply_line_wrap(text, &config(16, false));
+        assert_eq!(result, "The quick brown\nfox jumps over\nthe lazy dog");
+        assert!(result.lines().all(|l| l.chars().count() <= 16));
+    }
+
+    #[test]
+    fn test_join_one_word_per_line() {
+        let text = "one\ntwo\nthree\nfour";
+        let result = apply_line_wrap(text, &config(40, false));
+        assert_eq!(result, "one two three four");
+    }
+
+    #[test]
+    fn test_preserve_paragraphs() {
+        let text = "first paragraph here\n\nsecond\nparagraph";
+        let result = apply_line_wrap(text, &config(10, true));
+        assert_eq!(result, "first\nparagraph\nhere\n\nsecond\nparagraph");
+    }
+
+    #[test]
+    fn test_paragraphs_collapsed_when_not_preserved() {
+        let text = "alpha\n\nbeta";
+        let result = apply_line_wrap(text, &config(40, false));
+        assert_eq!(result, "alpha beta");
+    }
+
+    #[test]
+    fn test_wrap_japanese() {
+        let text = "今日は天気が良いので散歩に行きました";
+        let result = apply_line_wrap(text, &config(6, false));
+        assert_eq!(result, "今日は天気が\n良いので散歩\nに行きました");
+    }
+
+    #[test]
+    fn test_japanese_paragraphs() {
+        let text = "一行目の\n文章です\n\n二段落目";
+        let result = apply_line_wrap(text, &config(20, true));
+        assert_eq!(result, "一行目の文章です\n\n二段落目");
+    }
+
+    #[test]
+    fn test_normalize_halfwidth_katakana() {
+        assert_eq!(normalize_japanese("ｶﾀｶﾅ"), "カタカナ");
+        assert_eq!(normalize_japanese("ｶﾞｷﾞｸﾞ ﾊﾟﾝ"), "ガギグパン");
+        assert_eq!(normalize_japanese("すごい！（笑）"), "すごい！（笑）");
+    }
+
+    #[test]
+    fn test_normalize_removes_spaces_between_cjk() {
+        assert_eq!(normalize_japanese("今日 は　晴れ"), "今日は晴れ");
+        assert_eq!(normalize_japanese("Hello world"), "Hello world");
+        assert_eq!(normalize_japanese("日本 Japan"), "日本 Japan");
+    }
+
+    #[test]
+    fn test_normalize_widens_digits_in_japanese() {
+        assert_eq!(normalize_japanese("今日は3時に集合"), "今日は３時に集合");
+        assert_eq!(normalize_japanese("2024年"), "2024年");
+        assert_eq!(normalize_japanese("version 3 です"), "version 3 です");
+    }
+
+    #[test]
+    fn test_normalize_collapses_duplicate_punctuation() {
+        assert_eq!(normalize_japanese("終わり。。"), "終わり。");
+        assert_eq!(normalize_japanese("「。。」"), "「。」");
+        assert_eq!(normalize_japanese("はい、、そうです"), "はい、そうです");
+    }
+
+    #[test]
+    fn test_truncate_response_at_word_boundary() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            truncate_response(text, 12).as_deref(),
+            Some("The quick …[truncated]")
+        );
+        // 上限の直後が空白ならその位置で切る
+        assert_eq!(
+            truncate_response(text, 9).as_deref(),
+            Some("The quick …[truncated]")
+        );
+        assert!(truncate_response(text, text.chars().count()).is_none());
+    }
+
+    #[test]
+    fn test_truncate_response_without_spaces() {
+        assert_eq!(
+            truncate_response("こんにちは、世界。", 5).as_deref(),
+            Some("こんにちは …[truncated]")
+        );
+        // 先頭の単語が上限より長い場合は単語の途中で切る
+        assert_eq!(
+            truncate_response("Supercalifragilistic word", 5).as_deref(),
+            Some("Super …[truncated]")
+        );
+    }
+
+    fn typography(language_hint: Option<&str>) -> TypographicOpts {
+        TypographicOpts {
+            typographic_quotes: true,
+            em_dash: true,
+            language_hint: language_hint.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_typographic_corrections_english() {
+        let text = r#"He said "hello" and 'bye' -- don't stop --- "ok""#;
+        assert_eq!(
+            apply_typographic_corrections(text, &typography(Some("en"))),
+            "He said “hello” and ‘bye’ — don’t stop — “ok”"
+        );
+        // ハイフン1つや空白のない -- はそのまま
+        assert_eq!(
+            apply_typographic_corrections("well-known a--b", &typography(None)),
+            "well-known a--b"
+        );
+    }
+
+    #[test]
+    fn test_typographic_corrections_japanese() {
+        let text = r#"彼は"こんにちは"と言い、'吾輩は猫である'を読んだ"#;
+        let expected = "彼は「こんにちは」と言い、『吾輩は猫である』を読んだ";
+        assert_eq!(
+            apply_typographic_corrections(text, &typography(Some("ja-JP"))),
+            expected
+        );
+        // ヒントがなければ本文から日本語と判定する
+        assert_eq!(
+            apply_typographic_corrections(text, &typography(None)),
+            expected
+        );
+        // 英単語中のアポストロフィは鉤括弧にしない
+        assert_eq!(
+            apply_typographic_corrections("it's 'OK' です", &typography(Some("ja"))),
+            "it’s 『OK』 です"
+        );
+    }
+
+    #[test]
+    fn test_typographic_corrections_disabled() {
+        let text = r#""quote" -- 'x'"#;
+        let opts = TypographicOpts::default();
+        assert_eq!(apply_typographic_corrections(text, &opts), text);
+
+        let opts = TypographicOpts {
+            em_dash: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            apply_typographic_corrections(text, &opts),
+            r#""quote" — 'x'"#
+        );
+    }
+}
@@ -1,13 +1,146 @@
-use crate::config::modes::ModeConfig;
+use serde::Serialize;
+
+use crate::ai::context::ContextFormat;
+use crate::ai::estimate_tokens;
+use crate::config::modes::{ModeConfig, TruncationStrategy};
+use crate::error::{AppError, TaprResult};
+
+/// テンプレートで使用できるプレースホルダー一覧
+pub const KNOWN_PLACEHOLDERS: &[&str] = &[
+    "input",
+    "context",
+    "word_count",
+    "sentence_count",
+    "truncated_input_100",
+    "clipboard",
+];
+
+/// {truncated_input_100} で挿入する最大文字数
+const TRUNCATED_INPUT_CHARS: usize = 100;
+
+/// {clipboard} で挿入する最大文字数
+const CLIPBOARD_MAX_CHARS: usize = 500;
+
+/// clipboard_label 未指定時のラベル
+const DEFAULT_CLIPBOARD_LABEL: &str = "Clipboard";
+
+/// 入力テキストから算出するプロンプト変数
+#[derive(Debug, Clone, Default)]
+pub struct RenderContext {
+    /// 空白区切りの単語数
+    pub word_count: usize,
+    /// `.` `!` `?` の出現数 + 1
+    pub sentence_count: usize,
+    /// 入力の先頭100文字（超過時は末尾に `…`）
+    pub truncated_input_100: String,
+    /// ラベル付きのクリップボード内容（オプトイン時のみ設定）
+    pub clipboard: Option<String>,
+    /// `fit_prompt` が履歴を `{context}` 用に整形するときの書式
+    pub context_format: ContextFormat,
+}
+
+impl RenderContext {
+    /// 入力テキストから各変数を算出する
+    pub fn from_input(input: &str) -> Self {
+        Self {
+            word_count: input.split_whitespace().count(),
+            sentence_count: input
+                .chars()
+                .filter(|c| matches!(c, '.' | '!' | '?'))
+                .count()
+                + 1,
+            truncated_input_100: truncate_chars(input, TRUNCATED_INPUT_CHARS),
+            clipboard: None,
+            context_format: ContextFormat::default(),
+        }
+    }
+
+    /// 履歴の書式を指定する
+    pub fn with_context_format(mut self, format: ContextFormat) -> Self {
+        self.context_format = format;
+        self
+    }
+
+    /// クリップボード内容を500文字に切り詰め、ラベルを付けて設定する
+    pub fn with_clipboard(mut self, text: &str, label: Option<&str>) -> Self {
+        let label = label.unwrap_or(DEFAULT_CLIPBOARD_LABEL);
+        self.clipboard = Some(format!(
+            "{}:\n{}",
+            label,
+            truncate_chars(text, CLIPBOARD_MAX_CHARS)
+        ));
+        self
+    }
+}
+
+/// 先頭 `max_chars` 文字に切り詰め、超過していれば `…` を付与する
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// テンプレート中の `{name}` 形式のプレースホルダー名を出現順に列挙する
+pub fn extract_placeholders(template: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    names.push(name.to_string());
+                }
+                rest = &after[end + 1..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+/// テンプレートに未知のプレースホルダーが含まれていないか検証する
+///
+/// 未知のプレースホルダーがあれば、その名前の一覧を `Err` で返す。
+pub fn validate_prompt_template(template: &str) -> Result<(), Vec<String>> {
+    let mut unknown: Vec<String> = extract_placeholders(template)
+        .into_iter()
+        .filter(|name| !KNOWN_PLACEHOLDERS.contains(&name.as_str()))
+        .collect();
+    unknown.dedup();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(unknown)
+    }
+}
 
 /// プロンプトテンプレートを展開する
 ///
-/// modes.yaml の ai_prompt に含まれる {input} と {context} プレースホルダーを置換する。
+/// modes.yaml の ai_prompt に含まれる {input} と {context} プレースホルダー、
+/// および `vars` の各変数（{word_count} 等）を置換する。
 /// ai_prompt が未設定の場合はユーザー入力をそのまま返す。
-pub fn render_prompt(mode: &ModeConfig, input: &str, context: Option<&str>) -> String {
+///
+/// `context` は整形済みの文字列をそのまま埋め込む。書式（`ContextFormat`）は
+/// 呼び出し側で適用しておく必要があり、`fit_prompt` は `vars.context_format` で整形してから渡す。
+pub fn render_prompt(
+    mode: &ModeConfig,
+    input: &str,
+    context: Option<&str>,
+    vars: &RenderContext,
+) -> String {
     match &mode.ai_prompt {
         Some(template) => {
-            let mut result = template.clone();
+            // 入力テキスト中の波括弧を誤って置換しないよう、変数展開を {input} より先に行う
+            let mut result = template
+                .replace("{word_count}", &vars.word_count.to_string())
+                .replace("{sentence_count}", &vars.sentence_count.to_string())
+                .replace("{truncated_input_100}", &vars.truncated_input_100)
+                .replace("{clipboard}", vars.clipboard.as_deref().unwrap_or(""));
 
             // {input} をユーザー入力で置換
             if result.contains("{input}") {
@@ -30,6 +163,168 @@ pub fn render_prompt(mode: &ModeConfig, input: &str, context: Option<&str>) -> S
     }
 }
 
+/// `max_prompt_chars` に収めたプロンプト
+#[derive(Debug, Clone)]
+pub struct FittedPrompt {
+    pub prompt: String,
+    /// 入力またはコンテキストを切り詰めたか
+    pub truncated: bool,
+}
+
+/// プロンプトを組み立て、モードの `max_prompt_chars` を超える場合は
+/// `truncation_strategy` に従って入力・コンテキストを切り詰める
+///
+/// `context` は古い順の履歴。切り詰めても収まらない場合はエラーを返す。
+pub fn fit_prompt(
+    mode: &ModeConfig,
+    input: &str,
+    context: &[String],
+    vars: &RenderContext,
+) -> TaprResult<FittedPrompt> {
+    let render = |input: &str, context: &[String]| {
+        render_prompt(
+            mode,
+            input,
+            vars.context_format.format(context).as_deref(),
+            vars,
+        )
+    };
+    let prompt = render(input, context);
+    let Some(max_chars) = mode.max_prompt_chars else {
+        return Ok(FittedPrompt {
+            prompt,
+            truncated: false,
+        });
+    };
+    let total = prompt.chars().count();
+    if total <= max_chars {
+        return Ok(FittedPrompt {
+            prompt,
+            truncated: false,
+        });
+    }
+
+    let too_long = || {
+        AppError::Ai(format!(
+            "prompt exceeds max_prompt_chars for mode {}: {} > {}",
+            mode.id, total, max_chars
+        ))
+    };
+    // テンプレート部分を除いた、入力とコンテキストに使える文字数
+    let overhead = render("", &[]).chars().count();
+    let budget = max_chars.checked_sub(overhead).ok_or_else(too_long)?;
+    let context_len = |context: &[String]| {
+        vars.context_format
+            .format(context)
+            .map_or(0, |c| c.chars().count())
+    };
+
+    let (input, context) = match mode.truncation_strategy {
+        TruncationStrategy::Fail => return Err(too_long()),
+        TruncationStrategy::TruncateInput => {
+            let input_budget = budget.saturating_sub(context_len(context));
+            (keep_tail(input, input_budget), context)
+        }
+        TruncationStrategy::TruncateContext => {
+            let context_budget = budget.saturating_sub(input.chars().count());
+            (
+                input.to_string(),
+                drop_oldest(context, context_budget, context_len),
+            )
+        }
+        TruncationStrategy::TruncateBoth => {
+            let context = drop_oldest(context, budget / 2, context_len);
+            let input_budget = budget - context_len(context);
+            (keep_tail(input, input_budget), context)
+        }
+    };
+
+    let prompt = render(&input, context);
+    if prompt.chars().count() > max_chars {
+        return Err(too_long());
+    }
+    eprintln!(
+        "[ai] mode '{}' prompt truncated ({:?}): {} -> {} chars",
+        mode.id,
+        mode.truncation_strategy,
+        total,
+        prompt.chars().count()
+    );
+    Ok(FittedPrompt {
+        prompt,
+        truncated: true,
+    })
+}
+
+/// 末尾 `max_chars` 文字を残す
+fn keep_tail(text: &str, max_chars: usize) -> String {
+    let len = text.chars().count();
+    text.chars().skip(len.saturating_sub(max_chars)).collect()
+}
+
+/// 収まるまで古いコンテキストから捨てる
+fn drop_oldest(
+    context: &[String],
+    max_chars: usize,
+    context_len: impl Fn(&[String]) -> usize,
+) -> &[String] {
+    let mut start = 0;
+    while start < context.len() && context_len(&context[start..]) > max_chars {
+        start += 1;
+    }
+    &context[start..]
+}
+
+/// AI を呼ばずに確認するためのプロンプトのプレビュー
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptPreview {
+    /// システムプロンプト（現状モード設定では持たないため常に None）
+    pub system: Option<String>,
+    /// 展開後のユーザープロンプト
+    pub user: String,
+    pub estimated_tokens: u32,
+    /// テンプレート中で値が埋め込まれたプレースホルダー
+    pub variables_used: Vec<String>,
+    /// テンプレート中にあるが値が無かったプレースホルダー（未知のものを含む）
+    pub variables_missing: Vec<String>,
+}
+
+/// サンプルテキストでプロンプトを展開し、プレースホルダーの埋まり具合を調べる
+///
+/// プレビューではコンテキストとクリップボードは読まないため、それらは missing になる。
+pub fn preview_prompt(mode: &ModeConfig, sample_text: &str) -> PromptPreview {
+    let vars = RenderContext::from_input(sample_text);
+    let user = render_prompt(mode, sample_text, None, &vars);
+
+    let mut variables_used = Vec::new();
+    let mut variables_missing = Vec::new();
+    if let Some(template) = &mode.ai_prompt {
+        for name in extract_placeholders(template) {
+            if variables_used.contains(&name) || variables_missing.contains(&name) {
+                continue;
+            }
+            let has_value = match name.as_str() {
+                "context" => false,
+                "clipboard" => vars.clipboard.is_some(),
+                other => KNOWN_PLACEHOLDERS.contains(&other),
+            };
+            if has_value {
+                variables_used.push(name);
+            } else {
+                variables_missing.push(name);
+            }
+        }
+    }
+
+    PromptPreview {
+        system: None,
+        estimated_tokens: estimate_tokens(&user),
+        user,
+        variables_used,
+        variables_missing,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,34 +336,207 @@ mod tests {
             description: "Test mode".to_string(),
             ai_enabled: true,
             ai_prompt: ai_prompt.map(|s| s.to_string()),
+            ..Default::default()
         }
     }
 
     #[test]
     fn test_render_with_input_placeholder() {
         let mode = make_mode(Some("修正してください: {input}"));
-        let result = render_prompt(&mode, "こんにちわ", None);
+        let result = render_prompt(&mode, "こんにちわ", None, &RenderContext::default());
         assert_eq!(result, "修正してください: こんにちわ");
     }
 
     #[test]
     fn test_render_without_input_placeholder() {
         let mode = make_mode(Some("以下のテキストを校正してください"));
-        let result = render_prompt(&mode, "テスト文", None);
+        let result = render_prompt(&mode, "テスト文", None, &RenderContext::default());
         assert_eq!(result, "以下のテキストを校正してください\n\nテスト文");
     }
 
     #[test]
     fn test_render_with_context() {
         let mode = make_mode(Some("コンテキスト: {context}\n入力: {input}"));
-        let result = render_prompt(&mode, "新しい入力", Some("過去の入力"));
+        let result = render_prompt(
+            &mode,
+            "新しい入力",
+            Some("過去の入力"),
+            &RenderContext::default(),
+        );
         assert_eq!(result, "コンテキスト: 過去の入力\n入力: 新しい入力");
     }
 
     #[test]
     fn test_render_no_ai_prompt() {
         let mode = make_mode(None);
-        let result = render_prompt(&mode, "そのまま返す", None);
+        let result = render_prompt(&mode, "そのまま返す", None, &RenderContext::default());
         assert_eq!(result, "そのまま返す");
     }
+
+    const PARAGRAPH: &str =
+        "The quick brown fox jumps over the lazy dog. It was not amused! Why would it be?";
+
+    #[test]
+    fn test_render_context_counts() {
+        let vars = RenderContext::from_input(PARAGRAPH);
+        assert_eq!(vars.word_count, 17);
+        // "." "!" "?" の3つ + 1
+        assert_eq!(vars.sentence_count, 4);
+        assert_eq!(vars.truncated_input_100, PARAGRAPH);
+    }
+
+    #[test]
+    fn test_render_word_and_sentence_count() {
+        let mode = make_mode(Some("{word_count}語 / {sentence_count}文: {input}"));
+        let vars = RenderContext::from_input(PARAGRAPH);
+        let result = render_prompt(&mode, PARAGRAPH, None, &vars);
+        assert_eq!(result, format!("17語 / 4文: {}", PARAGRAPH));
+    }
+
+    #[test]
+    fn test_truncated_input_100() {
+        let long_input = "あ".repeat(150);
+        let vars = RenderContext::from_input(&long_input);
+        assert_eq!(vars.truncated_input_100.chars().count(), 101);
+        assert!(vars.truncated_input_100.ends_with('…'));
+
+        let mode = make_mode(Some("概要: {truncated_input_100}\n{input}"));
+        let result = render_prompt(&mode, &long_input, None, &vars);
+        assert!(result.starts_with(&format!("概要: {}…", "あ".repeat(100))));
+    }
+
+    #[test]
+    fn test_validate_prompt_template() {
+        assert!(validate_prompt_template(
+            "{input} {context} {word_count} {sentence_count} {truncated_input_100} {clipboard}"
+        )
+        .is_ok());
+        assert_eq!(
+            validate_prompt_template("{input} {unknown}"),
+            Err(vec!["unknown".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_render_clipboard_context() {
+        let mode = make_mode(Some("{clipboard}\n\n入力: {input}"));
+        let vars = RenderContext::from_input("要約して").with_clipboard("コピーした文章", None);
+        let result = render_prompt(&mode, "要約して", None, &vars);
+        assert_eq!(result, "Clipboard:\nコピーした文章\n\n入力: 要約して");
+    }
+
+    #[test]
+    fn test_render_clipboard_custom_label_and_truncation() {
+        let mode = make_mode(Some("{clipboard}\n{input}"));
+        let long_clip = "x".repeat(600);
+        let vars = RenderContext::from_input("in").with_clipboard(&long_clip, Some("参考"));
+        let result = render_prompt(&mode, "in", None, &vars);
+        assert!(result.starts_with(&format!("参考:\n{}…\n", "x".repeat(500))));
+    }
+
+    #[test]
+    fn test_render_clipboard_absent_is_removed() {
+        let mode = make_mode(Some("{clipboard}{input}"));
+        let result = render_prompt(&mode, "入力", None, &RenderContext::default());
+        assert_eq!(result, "入力");
+    }
+
+    #[test]
+    fn test_preview_prompt_user_and_tokens() {
+        let mode = make_mode(Some("Fix: {input}"));
+        let preview = preview_prompt(&mode, "hello world");
+        assert_eq!(preview.system, None);
+        assert_eq!(preview.user, "Fix: hello world");
+        assert_eq!(
+            preview.estimated_tokens,
+            estimate_tokens("Fix: hello world")
+        );
+    }
+
+    #[test]
+    fn test_preview_prompt_variables() {
+        let mode = make_mode(Some(
+            "{word_count} {input} {context} {clipboard} {unknown} {input}",
+        ));
+        let preview = preview_prompt(&mode, "hello world");
+        assert_eq!(preview.variables_used, vec!["word_count", "input"]);
+        assert_eq!(
+            preview.variables_missing,
+            vec!["context", "clipboard", "unknown"]
+        );
+        assert!(preview.user.starts_with("2 hello world"));
+    }
+
+    #[test]
+    fn test_preview_prompt_without_template() {
+        let preview = preview_prompt(&make_mode(None), "そのまま");
+        assert_eq!(preview.user, "そのまま");
+        assert!(preview.variables_used.is_empty());
+        assert!(preview.variables_missing.is_empty());
+    }
+
+    fn limited_mode(max_prompt_chars: usize, strategy: TruncationStrategy) -> ModeConfig {
+        ModeConfig {
+            max_prompt_chars: Some(max_prompt_chars),
+            truncation_strategy: strategy,
+            ..make_mode(Some("{context}|{input}"))
+        }
+    }
+
+    fn history() -> Vec<String> {
+        vec!["old".to_string(), "new".to_string()]
+    }
+
+    #[test]
+    fn test_fit_prompt_within_limit_is_untouched() {
+        let mode = limited_mode(100, TruncationStrategy::Fail);
+        let fitted = fit_prompt(&mode, "abc", &history(), &RenderContext::default()).unwrap();
+        assert_eq!(fitted.prompt, "[1] old\n[2] new|abc");
+        assert!(!fitted.truncated);
+    }
+
+    #[test]
+    fn test_fit_prompt_truncate_input_keeps_end() {
+        // コンテキスト15文字 + 区切り1文字 + 入力4文字
+        let mode = limited_mode(20, TruncationStrategy::TruncateInput);
+        let fitted =
+            fit_prompt(&mode, "0123456789", &history(), &RenderContext::default()).unwrap();
+        assert_eq!(fitted.prompt, "[1] old\n[2] new|6789");
+        assert!(fitted.truncated);
+    }
+
+    #[test]
+    fn test_fit_prompt_truncate_context_drops_oldest() {
+        let mode = limited_mode(15, TruncationStrategy::TruncateContext);
+        let fitted =
+            fit_prompt(&mode, "0123456789", &history(), &RenderContext::default()).unwrap();
+        assert_eq!(fitted.prompt, "|0123456789");
+        assert!(fitted.truncated);
+
+        let mode = limited_mode(18, TruncationStrategy::TruncateContext);
+        let fitted = fit_prompt(&mode, "0123456", &history(), &RenderContext::default()).unwrap();
+        assert_eq!(fitted.prompt, "[1] new|0123456");
+    }
+
+    #[test]
+    fn test_fit_prompt_truncate_both_splits_budget() {
+        // 予算16文字: コンテキストは8文字以内（"[1] new" のみ）、入力は残り9文字
+        let mode = limited_mode(17, TruncationStrategy::TruncateBoth);
+        let fitted = fit_prompt(
+            &mode,
+            "abcdefghijklmnop",
+            &history(),
+            &RenderContext::default(),
+        )
+        .unwrap();
+        assert_eq!(fitted.prompt, "[1] new|hijklmnop");
+        assert!(fitted.truncated);
+    }
+
+    #[test]
+    fn test_fit_prompt_fail_strategy() {
+        let mode = limited_mode(5, TruncationStrategy::Fail);
+        let err = fit_prompt(&mode, "0123456789", &[], &RenderContext::default()).unwrap_err();
+        assert!(err.to_string().contains("max_prompt_chars"));
+    }
 }
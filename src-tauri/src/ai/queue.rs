@@ -0,0 +1,287 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::ai::{AIError, AIProvider, AIResponse};
+use crate::error::{AppError, TaprResult};
+
+/// キューに積める最大件数
+pub const MAX_QUEUE_LEN: usize = 100;
+
+/// AI リクエストの優先度（値が小さいほど先に処理する）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Priority {
+    /// PTT など、ユーザーが結果を待っている処理
+    #[default]
+    #[serde(rename = "realtime")]
+    RealTime = 0,
+    /// バックグラウンドの一括処理
+    #[serde(rename = "batch")]
+    Batch = 10,
+}
+
+/// 優先度ごとの待ち件数
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QueueDepth {
+    pub realtime: u32,
+    pub batch: u32,
+}
+
+/// 積まれた1件（優先度が同じなら先に積んだものから取り出す）
+struct Queued<T, P> {
+    priority: P,
+    seq: u64,
+    item: T,
+}
+
+impl<T, P: Ord> Ord for Queued<T, P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap は最大値から取り出すため、優先度の値と積んだ順を逆にする
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T, P: Ord> PartialOrd for Queued<T, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, P: Ord> PartialEq for Queued<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, P: Ord> Eq for Queued<T, P> {}
+
+struct QueueState<T, P> {
+    heap: BinaryHeap<Queued<T, P>>,
+    next_seq: u64,
+}
+
+/// 上限付きの優先度キュー
+///
+/// `pop` は要素が積まれるまでスレッドをブロックする。
+pub struct PriorityQueue<T, P> {
+    state: Mutex<QueueState<T, P>>,
+    available: Condvar,
+    capacity: usize,
+}
+
+impl<T, P: Ord + Copy> PriorityQueue<T, P> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                heap: BinaryHeap::new(),
+                next_seq: 0,
+            }),
+            available: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// 要素を積む（上限に達していればエラー）
+    pub fn push(&self, item: T, priority: P) -> TaprResult<()> {
+        let mut state = self.state.lock()?;
+        if state.heap.len() >= self.capacity {
+            return Err(AppError::Ai(format!(
+                "AI request queue is full ({} pending)",
+                self.capacity
+            )));
+        }
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.heap.push(Queued {
+            priority,
+            seq,
+            item,
+        });
+        self.available.notify_one();
+        Ok(())
+    }
+
+    /// 最も優先度の高い要素を取り出す（空なら積まれるまで待つ）
+    pub fn pop(&self) -> TaprResult<T> {
+        let mut state = self.state.lock()?;
+        loop {
+            if let Some(queued) = state.heap.pop() {
+                return Ok(queued.item);
+            }
+            state = self.available.wait(state)?;
+        }
+    }
+
+    /// 指定優先度の待ち件数
+    pub fn count(&self, priority: P) -> TaprResult<usize> {
+        let state = self.state.lock()?;
+        Ok(state.heap.iter().filter(|q| q.priority == priority).count())
+    }
+}
+
+/// キューに積む AI リクエスト
+pub struct AIRequest {
+    provider: Arc<dyn AIProvider>,
+    prompt: String,
+    respond: oneshot::Sender<Result<AIResponse, AIError>>,
+}
+
+/// AI リクエストのキュー（Tauri State）
+///
+/// 一括処理の後ろで PTT の結果が待たされないよう、1件ずつ優先度順に処理する。
+pub type AiQueue = PriorityQueue<AIRequest, Priority>;
+
+impl AiQueue {
+    /// リクエストを積み、応答を受け取るレシーバーを返す
+    ///
+    /// 呼び出し側は `provider` の複製を残しておけば、応答後に `served_model` を読める。
+    pub fn submit(
+        &self,
+        provider: Arc<dyn AIProvider>,
+        prompt: String,
+        priority: Priority,
+    ) -> TaprResult<oneshot::Receiver<Result<AIResponse, AIError>>> {
+        let (respond, receiver) = oneshot::channel();
+        self.push(
+            AIRequest {
+                provider,
+                prompt,
+                respond,
+            },
+            priority,
+        )?;
+        Ok(receiver)
+    }
+
+    pub fn depth(&self) -> TaprResult<QueueDepth> {
+        Ok(QueueDepth {
+            realtime: self.count(Priority::RealTime)? as u32,
+            batch: self.count(Priority::Batch)? as u32,
+        })
+    }
+
+    /// キューを処理するバックグラウンドスレッドを起動する
+    ///
+    /// 呼び出し元がタイムアウトなどで待つのをやめたリクエストは送信せずに捨てる。
+    pub fn spawn_consumer(self: &Arc<Self>) {
+        let queue = Arc::clone(self);
+        std::thread::spawn(move || loop {
+            let request = match queue.pop() {
+                Ok(request) => request,
+                Err(e) => {
+                    eprintln!("[ai] request queue stopped: {}", e);
+                    return;
+                }
+            };
+            if request.respond.is_closed() {
+                continue;
+            }
+            let result = tauri::async_runtime::block_on(request.provider.process(&request.prompt));
+            let _ = request.respond.send(result);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::StreamChunk;
+    use async_trait::async_trait;
+
+    /// プロンプトをそのまま返すモックプロバイダー
+    struct EchoProvider;
+
+    #[async_trait]
+    impl AIProvider for EchoProvider {
+        fn model_name(&self) -> &str {
+            "echo"
+        }
+
+        async fn process(&self, prompt: &str) -> Result<AIResponse, AIError> {
+            Ok(AIResponse {
+                text: prompt.to_string(),
+                model: "echo".to_string(),
+                usage: None,
+                system_fingerprint: None,
+                finish_reason: None,
+            })
+        }
+
+        /// キューはストリーミングを使わないので、何も流さずに終える
+        async fn process_stream(
+            &self,
+            _prompt: &str,
+            _sender: tokio::sync::mpsc::Sender<StreamChunk>,
+        ) -> Result<(), AIError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_pop_order() {
+        let queue = PriorityQueue::new(MAX_QUEUE_LEN);
+        queue.push("batch-1", Priority::Batch).unwrap();
+        queue.push("batch-2", Priority::Batch).unwrap();
+        queue.push("ptt-1", Priority::RealTime).unwrap();
+        queue.push("ptt-2", Priority::RealTime).unwrap();
+
+        let order: Vec<&str> = (0..4).map(|_| queue.pop().unwrap()).collect();
+        assert_eq!(order, vec!["ptt-1", "ptt-2", "batch-1", "batch-2"]);
+    }
+
+    #[test]
+    fn test_push_rejects_when_full() {
+        let queue = PriorityQueue::new(2);
+        queue.push(1, Priority::Batch).unwrap();
+        queue.push(2, Priority::Batch).unwrap();
+        let err = queue.push(3, Priority::RealTime).unwrap_err();
+        assert!(err.to_string().contains("queue is full"));
+        assert_eq!(queue.pop().unwrap(), 1);
+        queue.push(3, Priority::RealTime).unwrap();
+    }
+
+    #[test]
+    fn test_depth() {
+        let queue = AiQueue::new(MAX_QUEUE_LEN);
+        let _receivers: Vec<_> = [Priority::Batch, Priority::RealTime, Priority::Batch]
+            .into_iter()
+            .map(|p| queue.submit(Arc::new(EchoProvider), "x".into(), p).unwrap())
+            .collect();
+        assert_eq!(
+            queue.depth().unwrap(),
+            QueueDepth {
+                realtime: 1,
+                batch: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_priority_serde() {
+        assert_eq!(
+            serde_json::to_string(&Priority::RealTime).unwrap(),
+            "\"realtime\""
+        );
+        let priority: Priority = serde_json::from_str("\"batch\"").unwrap();
+        assert_eq!(priority, Priority::Batch);
+    }
+
+    #[tokio::test]
+    async fn test_consumer_processes_requests() {
+        let queue = Arc::new(AiQueue::new(MAX_QUEUE_LEN));
+        queue.spawn_consumer();
+
+        let receiver = queue
+            .submit(Arc::new(EchoProvider), "hello".into(), Priority::RealTime)
+            .unwrap();
+        let response = receiver.await.unwrap().unwrap();
+        assert_eq!(response.text, "hello");
+        assert_eq!(queue.depth().unwrap(), QueueDepth::default());
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::{Serialize, Serializer};
+
+/// モードの `model_rotation` で指定したモデルを順番に使い回す
+///
+/// レート制限の厳しい Tier でも、リクエストを複数のモデルに分散させて
+/// 429 に当たりにくくする。インデックスはリクエストをまたいで共有する。
+#[derive(Debug)]
+pub struct ModelRotation {
+    models: Vec<String>,
+    index: AtomicUsize,
+    /// `models` と同じ順のモデルごとの使用状況
+    usage: Vec<ModelUsage>,
+}
+
+#[derive(Debug, Default)]
+struct ModelUsage {
+    call_count: AtomicU32,
+    last_429_at: Mutex<Option<Instant>>,
+}
+
+/// `get_model_rotation_stats` で返すモデルごとの状況
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelRotationState {
+    /// このモデルを含むローテーションが次のリクエストで使うモデル
+    pub current_model: String,
+    /// このモデルに送ったリクエストの回数（429 で切り替えた分も含む）
+    pub call_count: u32,
+    /// 最後に 429 を受けてからの経過秒数（受けていなければ None）
+    #[serde(
+        rename = "last_429_secs_ago",
+        serialize_with = "serialize_elapsed_secs"
+    )]
+    pub last_429_at: Option<Instant>,
+}
+
+fn serialize_elapsed_secs<S: Serializer>(
+    at: &Option<Instant>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    at.map(|at| at.elapsed().as_secs()).serialize(serializer)
+}
+
+impl ModelRotation {
+    /// モデル一覧が空なら None
+    pub fn new(models: Vec<String>) -> Option<Self> {
+        (!models.is_empty()).then(|| Self {
+            usage: models.iter().map(|_| ModelUsage::default()).collect(),
+            models,
+            index: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    /// 次のリクエストで使うモデル（インデックスは進めない）
+    pub fn current_model(&self) -> &str {
+        &self.models[self.index.load(Ordering::Relaxed) % self.models.len()]
+    }
+
+    /// 今回のリクエストで使うモデルを選び、インデックスを1つ進める
+    pub fn next_model(&self) -> &str {
+        let i = self.index.fetch_add(1, Ordering::Relaxed) % self.models.len();
+        self.usage[i].call_count.fetch_add(1, Ordering::Relaxed);
+        &self.models[i]
+    }
+
+    /// `model` が 429 を返したことを記録する
+    ///
+    /// まだそのモデルを指していればインデックスを進め、次の呼び出しで使わないようにする。
+    pub fn record_rate_limit(&self, model: &str) {
+        for (name, usage) in self.models.iter().zip(&self.usage) {
+            if name == model {
+                if let Ok(mut last) = usage.last_429_at.lock() {
+                    *last = Some(Instant::now());
+                }
+            }
+        }
+        let _ = self
+            .index
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |i| {
+                (self.models[i % self.models.len()] == model).then_some(i + 1)
+            });
+    }
+
+    /// `model` の使用状況（ローテーションに含まれなければ None）
+    ///
+    /// 同じモデルが複数回並んでいる場合は回数を合算し、429 は最後のものを返す。
+    pub fn state(&self, model: &str) -> Option<ModelRotationState> {
+        self.states()
+            .into_iter()
+            .find(|(name, _)| name == model)
+            .map(|(_, state)| state)
+    }
+
+    /// ローテーションに含まれるモデルごとの使用状況
+    pub fn states(&self) -> Vec<(String, ModelRotationState)> {
+        let current_model = self.current_model();
+        let mut states: Vec<(String, ModelRotationState)> = Vec::new();
+        for (name, usage) in self.models.iter().zip(&self.usage) {
+            let state = ModelRotationState {
+                current_model: current_model.to_string(),
+                call_count: usage.call_count.load(Ordering::Relaxed),
+                last_429_at: usage.last_429_at.lock().ok().and_then(|last| *last),
+            };
+            match states.iter_mut().find(|(existing, _)| existing == name) {
+                Some((_, existing)) => existing.merge(&state),
+                None => states.push((name.clone(), state)),
+            }
+        }
+        states
+    }
+}
+
+impl ModelRotationState {
+    /// 同じモデルの別の状況を合算する（回数は足し、429 は新しい方を残す）
+    pub fn merge(&mut self, other: &ModelRotationState) {
+        self.call_count += other.call_count;
+        self.last_429_at = self.last_429_at.max(other.last_429_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation(models: &[&str]) -> ModelRotation {
+        ModelRotation::new(models.iter().map(|m| m.to_string()).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_next_model_round_robin() {
+        let rotation = rotation(&["a", "b", "c"]);
+        let picked: Vec<String> = (0..4).map(|_| rotation.next_model().to_string()).collect();
+        assert_eq!(picked, vec!["a", "b", "c", "a"]);
+        assert_eq!(rotation.state("a").unwrap().call_count, 2);
+        assert_eq!(rotation.state("b").unwrap().call_count, 1);
+        assert_eq!(rotation.state("c").unwrap().current_model, "b");
+        assert_eq!(rotation.current_model(), "b");
+    }
+
+    #[test]
+    fn test_rate_limit_skips_current_model() {
+        let rotation = rotation(&["a", "b", "c"]);
+        assert_eq!(rotation.current_model(), "a");
+        rotation.record_rate_limit("a");
+        assert_eq!(rotation.current_model(), "b");
+        assert!(rotation.state("a").unwrap().last_429_at.is_some());
+        assert!(rotation.state("b").unwrap().last_429_at.is_none());
+
+        // すでに別のモデルに進んでいれば動かさない
+        rotation.record_rate_limit("a");
+        assert_eq!(rotation.current_model(), "b");
+    }
+
+    #[test]
+    fn test_states_merge_duplicate_models() {
+        let rotation = rotation(&["a", "b", "a"]);
+        for _ in 0..3 {
+            rotation.next_model();
+        }
+        let states = rotation.states();
+        assert_eq!(states.len(), 2);
+        assert_eq!(rotation.state("a").unwrap().call_count, 2);
+        assert!(rotation.state("x").is_none());
+    }
+
+    #[test]
+    fn test_empty_rotation() {
+        assert!(ModelRotation::new(Vec::new()).is_none());
+    }
+}
@@ -146,3 +146,273 @@ pub async fn parse_anthropic_stream(
 
     Ok(full_text)
 }
+
+/// Vertex AI（Gemini）の SSE をイベント単位に組み立てる
+///
+/// OpenAI / Anthropic のパーサーは1行に1つの JSON が収まる前提だが、Vertex では
+/// 1つの JSON が TCP チャンクや複数の `data:` 行にまたがることがある。
+/// 行が揃うまでバイト列のまま保持し（UTF-8 の途中で切れても壊さない）、
+/// `data:` の内容を JSON として読めた時点、または空行でイベントを確定する。
+#[derive(Default)]
+struct VertexSseDecoder {
+    /// 改行がまだ来ていない受信バイト列
+    pending: Vec<u8>,
+    /// 確定前のイベントの `data:` の内容
+    data: String,
+}
+
+impl VertexSseDecoder {
+    /// 受信したバイト列を追加し、確定したイベントのテキストを返す
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut texts = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            texts.extend(self.process_line(&String::from_utf8_lossy(&line)));
+        }
+        texts
+    }
+
+    /// ストリーム終了時に、改行で終わっていない最後の行と未確定のイベントを処理する
+    fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.pending);
+        let text = self.process_line(&String::from_utf8_lossy(&rest));
+        text.or_else(|| self.take_event())
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            return self.take_event();
+        }
+        let data = line.strip_prefix("data:")?;
+        if !self.data.is_empty() {
+            self.data.push('\n');
+        }
+        self.data.push_str(data.strip_prefix(' ').unwrap_or(data));
+
+        // JSON として完結していれば空行を待たずに確定する
+        let json = serde_json::from_str::<serde_json::Value>(&self.data).ok()?;
+        self.data.clear();
+        vertex_chunk_text(&json)
+    }
+
+    /// 空行で区切られたイベントを確定する（JSON として読めなければ捨てる）
+    fn take_event(&mut self) -> Option<String> {
+        let data = std::mem::take(&mut self.data);
+        let json = serde_json::from_str::<serde_json::Value>(&data).ok()?;
+        vertex_chunk_text(&json)
+    }
+}
+
+/// `candidates[0].content.parts[0].text` を取り出す（空文字列は None）
+fn vertex_chunk_text(json: &serde_json::Value) -> Option<String> {
+    json["candidates"][0]["content"]["parts"][0]["text"]
+        .as_str()
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// Vertex AI の `streamGenerateContent?alt=sse` をパースして StreamChunk に変換する
+///
+/// Vertex は終了のイベントを送らないので、ストリームが閉じたら `done: true` を送る。
+pub async fn parse_vertex_stream(
+    response: Response,
+    sender: tokio::sync::mpsc::Sender<StreamChunk>,
+) -> Result<String, AIError> {
+    let mut full_text = String::new();
+    let mut stream = response.bytes_stream();
+    let mut decoder = VertexSseDecoder::default();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AIError::StreamError(e.to_string()))?;
+        for text in decoder.push(&chunk) {
+            full_text.push_str(&text);
+            let _ = sender
+                .send(StreamChunk {
+                    content: text,
+                    done: false,
+                })
+                .await;
+        }
+    }
+    if let Some(text) = decoder.finish() {
+        full_text.push_str(&text);
+        let _ = sender
+            .send(StreamChunk {
+                content: text,
+                done: false,
+            })
+            .await;
+    }
+
+    let _ = sender
+        .send(StreamChunk {
+            content: String::new(),
+            done: true,
+        })
+        .await;
+    Ok(full_text)
+}
+
+/// Ollama の改行区切り JSON（NDJSON）を1行ずつ読む
+///
+/// 行が揃うまでバイト列のまま保持する（UTF-8 の途中で切れても壊さない）。
+#[derive(Default)]
+struct NdjsonDecoder {
+    /// 改行がまだ来ていない受信バイト列
+    pending: Vec<u8>,
+}
+
+impl NdjsonDecoder {
+    /// 受信したバイト列を追加し、揃った行の JSON を返す（読めない行は捨てる）
+    fn push(&mut self, bytes: &[u8]) -> Vec<serde_json::Value> {
+        self.pending.extend_from_slice(bytes);
+        let mut values = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            values.extend(serde_json::from_slice(line.trim_ascii()).ok());
+        }
+        values
+    }
+
+    /// ストリーム終了時に、改行で終わっていない最後の行を読む
+    fn finish(&mut self) -> Option<serde_json::Value> {
+        let rest = std::mem::take(&mut self.pending);
+        serde_json::from_slice(rest.trim_ascii()).ok()
+    }
+}
+
+/// Ollama の `/api/generate`（`stream: true`）をパースして StreamChunk に変換する
+///
+/// 各行の `response` を送り、`done: true` の行で終える。`error` の行はエラーにする。
+pub async fn parse_ollama_stream(
+    response: Response,
+    sender: tokio::sync::mpsc::Sender<StreamChunk>,
+) -> Result<String, AIError> {
+    let mut full_text = String::new();
+    let mut stream = response.bytes_stream();
+    let mut decoder = NdjsonDecoder::default();
+    let mut done = false;
+
+    while !done {
+        let values = match stream.next().await {
+            Some(chunk) => decoder.push(&chunk.map_err(|e| AIError::StreamError(e.to_string()))?),
+            None => {
+                done = true;
+                decoder.finish().into_iter().collect()
+            }
+        };
+        for json in values {
+            if let Some(error) = json["error"].as_str() {
+                return Err(AIError::StreamError(error.to_string()));
+            }
+            if let Some(text) = json["response"].as_str().filter(|t| !t.is_empty()) {
+                full_text.push_str(text);
+                let _ = sender
+                    .send(StreamChunk {
+                        content: text.to_string(),
+                        done: false,
+                    })
+                    .await;
+            }
+            if json["done"].as_bool() == Some(true) {
+                done = true;
+                break;
+            }
+        }
+    }
+
+    let _ = sender
+        .send(StreamChunk {
+            content: String::new(),
+            done: true,
+        })
+        .await;
+    Ok(full_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(text: &str) -> String {
+        format!(
+            "data: {}\r\n\r\n",
+            serde_json::json!({
+                "candidates": [{ "content": { "role": "model", "parts": [{ "text": text }] } }]
+            })
+        )
+    }
+
+    #[test]
+    fn test_vertex_decoder_reads_events() {
+        let mut decoder = VertexSseDecoder::default();
+        let body = format!("{}{}", event("こんにちは"), event("、世界"));
+        assert_eq!(decoder.push(body.as_bytes()), vec!["こんにちは", "、世界"]);
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn test_vertex_decoder_joins_json_split_across_chunks() {
+        let mut decoder = VertexSseDecoder::default();
+        let body = event("分割されたJSON");
+        let (first, second) = body.as_bytes().split_at(body.len() / 2);
+
+        assert!(decoder.push(first).is_empty());
+        assert_eq!(decoder.push(second), vec!["分割されたJSON"]);
+    }
+
+    #[test]
+    fn test_vertex_decoder_keeps_utf8_split_mid_character() {
+        let mut decoder = VertexSseDecoder::default();
+        let body = event("日本語");
+        // 「日」の3バイトの途中で切る
+        let split = body.find('日').unwrap() + 1;
+        let (first, second) = body.as_bytes().split_at(split);
+
+        assert!(decoder.push(first).is_empty());
+        assert_eq!(decoder.push(second), vec!["日本語"]);
+    }
+
+    #[test]
+    fn test_vertex_decoder_joins_multiline_data() {
+        let mut decoder = VertexSseDecoder::default();
+        let body = "data: {\"candidates\": [{\"content\":\n\
+                    data: {\"parts\": [{\"text\": \"複数行\"}]}}]}\n\n";
+        assert_eq!(decoder.push(body.as_bytes()), vec!["複数行"]);
+    }
+
+    #[test]
+    fn test_vertex_decoder_flushes_last_event_without_newline() {
+        let mut decoder = VertexSseDecoder::default();
+        let body = event("最後");
+        let body = body.trim_end();
+
+        assert!(decoder.push(body.as_bytes()).is_empty());
+        assert_eq!(decoder.finish(), Some("最後".to_string()));
+    }
+
+    #[test]
+    fn test_ndjson_decoder_reads_lines_split_across_chunks() {
+        let mut decoder = NdjsonDecoder::default();
+        let body =
+            "{\"response\":\"こん\",\"done\":false}\n{\"response\":\"にちは\",\"done\":true}";
+        let split = body.find('ん').unwrap() + 1;
+        let (first, second) = body.as_bytes().split_at(split);
+
+        assert!(decoder.push(first).is_empty());
+        let values = decoder.push(second);
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0]["response"], "こん");
+        assert_eq!(decoder.finish().unwrap()["response"], "にちは");
+    }
+
+    #[test]
+    fn test_vertex_decoder_skips_chunks_without_text() {
+        let mut decoder = VertexSseDecoder::default();
+        let body =
+            "data: {\"candidates\": [{\"finishReason\": \"STOP\"}], \"usageMetadata\": {}}\n\n";
+        assert!(decoder.push(body.as_bytes()).is_empty());
+    }
+}
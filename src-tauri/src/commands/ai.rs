@@ -1,19 +1,55 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
 
-use crate::ai::client::create_provider;
-use crate::ai::prompt::render_prompt;
-use crate::ai::ProviderType;
-use crate::config::modes::load_modes;
-use crate::error::AppError;
+use crate::ai::catalog::{self, ModelInfo, GROQ_MODELS, VERTEX_MODELS};
+use crate::ai::client::{
+    anthropic_client, create_provider, create_provider_with_fallback, AnthropicClient, OpenAIClient,
+};
+use crate::ai::context::{ContextFormat, ContextRegistry};
+use crate::ai::metrics::record_ai_request;
+use crate::ai::postprocess::{
+    apply_line_wrap, apply_typographic_corrections, normalize_japanese, truncate_response,
+    TRUNCATION_MARKER,
+};
+use crate::ai::prompt::{
+    fit_prompt, preview_prompt, validate_prompt_template, FittedPrompt, PromptPreview,
+    RenderContext,
+};
+use crate::ai::queue::{AiQueue, Priority, QueueDepth};
+use crate::ai::rotation::{ModelRotation, ModelRotationState};
+use crate::ai::{
+    can_fit_prompt, estimate_tokens, model_context_window, AIError, AIProvider, ProviderOptions,
+    ProviderType, StreamChunk,
+};
+use crate::config::app_config::AppConfig;
+use crate::config::modes::{load_modes, resolve_mode, ModeConfig};
+use crate::db::repository::{self, NewEntry};
+use crate::db::DbState;
+use crate::error::{AppError, TaprResult};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AIResponse {
     pub text: String,
     pub model: String,
     pub usage: Option<TokenUsage>,
+    pub system_fingerprint: Option<String>,
+    /// 生成の終了理由（"stop" / "max_tokens" など）
+    pub finish_reason: Option<String>,
+    /// フロントエンドとバックエンドログを突き合わせるためのリクエストID
+    pub request_id: String,
+    /// mode_id が完全一致せず、近いモードで代用したか
+    pub suggestion_used: bool,
+    /// `max_prompt_chars` に収めるため入力やコンテキストを、
+    /// または `max_response_chars` に収めるため AI 出力を切り詰めたか
+    pub truncated: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -21,7 +57,7 @@ pub struct TokenUsage {
 }
 
 /// コマンド用の AIResponse を ai モジュールの型から変換する
-fn from_ai_response(resp: crate::ai::AIResponse) -> AIResponse {
+fn from_ai_response(resp: crate::ai::AIResponse, request_id: &str) -> AIResponse {
     AIResponse {
         text: resp.text,
         model: resp.model,
@@ -30,22 +66,191 @@ fn from_ai_response(resp: crate::ai::AIResponse) -> AIResponse {
             completion_tokens: u.completion_tokens,
             total_tokens: u.total_tokens,
         }),
+        system_fingerprint: resp.system_fingerprint,
+        finish_reason: resp.finish_reason,
+        request_id: request_id.to_string(),
+        suggestion_used: false,
+        truncated: false,
+    }
+}
+
+/// AI処理の実行時設定を管理する Tauri State
+pub struct AiState {
+    openai_seed: Mutex<Option<u64>>,
+    context_format: Mutex<ContextFormat>,
+    /// モード ID ごとのモデルローテーション（呼び出しをまたいで順番を保つ）
+    model_rotations: Mutex<HashMap<String, Arc<ModelRotation>>>,
+}
+
+impl AiState {
+    /// 設定（OPENAI_SEED / TEST_MODE）から初期値を読み込む
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            openai_seed: Mutex::new(config.openai_seed),
+            context_format: Mutex::new(ContextFormat::default()),
+            model_rotations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// モードのローテーションを返す（モデル一覧が変わっていれば作り直す）
+    fn model_rotation(&self, mode: &ModeConfig) -> TaprResult<Option<Arc<ModelRotation>>> {
+        let Some(models) = &mode.model_rotation else {
+            return Ok(None);
+        };
+        let mut rotations = self.model_rotations.lock()?;
+        if let Some(rotation) = rotations.get(&mode.id) {
+            if rotation.models() == models.as_slice() {
+                return Ok(Some(rotation.clone()));
+            }
+        }
+        let Some(rotation) = ModelRotation::new(models.clone()).map(Arc::new) else {
+            rotations.remove(&mode.id);
+            return Ok(None);
+        };
+        rotations.insert(mode.id.clone(), rotation.clone());
+        Ok(Some(rotation))
+    }
+
+    /// 現在の設定からプロバイダー生成オプションを組み立てる
+    fn provider_options(&self) -> TaprResult<ProviderOptions> {
+        let openai_seed = *self.openai_seed.lock()?;
+        Ok(ProviderOptions {
+            openai_seed,
+            ..Default::default()
+        })
+    }
+}
+
+/// OpenAI の seed を設定する（None で seed 指定を解除）
+///
+/// seed を固定しても、モデル更新を跨いだ完全な再現性は保証されない。
+#[tauri::command]
+pub fn set_openai_seed(state: State<'_, AiState>, seed: Option<u64>) -> TaprResult<()> {
+    let mut current = state.openai_seed.lock()?;
+    *current = seed;
+    Ok(())
+}
+
+/// `{context}` に展開する履歴の書式を設定する
+#[tauri::command]
+pub fn set_context_format(state: State<'_, AiState>, format: ContextFormat) -> TaprResult<()> {
+    let mut current = state.context_format.lock()?;
+    *current = format;
+    Ok(())
+}
+
+/// モデルローテーションのモデルごとの状況を返す（キーはモデル名）
+///
+/// 一度も使われていないモードのモデルは含まない。複数のモードのローテーションに
+/// 入っているモデルは回数を合算し、429 は最後のものを返す。
+#[tauri::command]
+pub fn get_model_rotation_stats(
+    state: State<'_, AiState>,
+) -> TaprResult<HashMap<String, ModelRotationState>> {
+    let mut stats: HashMap<String, ModelRotationState> = HashMap::new();
+    for rotation in state.model_rotations.lock()?.values() {
+        for (model, model_state) in rotation.states() {
+            match stats.get_mut(&model) {
+                Some(existing) => existing.merge(&model_state),
+                None => {
+                    stats.insert(model, model_state);
+                }
+            }
+        }
     }
+    Ok(stats)
+}
+
+/// 指定したモードのコンテキスト（直近の入力履歴）を消去する
+///
+/// 履歴がまだないモードなら false を返す。
+#[tauri::command]
+pub fn clear_mode_context(contexts: State<'_, ContextRegistry>, mode_id: String) -> bool {
+    contexts.clear_mode(&mode_id)
+}
+
+/// すべてのモードと `"global"` のコンテキストを消去する
+#[tauri::command]
+pub fn clear_all_contexts(contexts: State<'_, ContextRegistry>) {
+    contexts.clear_all();
+}
+
+/// AI リクエストキューの優先度ごとの待ち件数を返す
+#[tauri::command]
+pub fn get_queue_depth(queue: State<'_, Arc<AiQueue>>) -> TaprResult<QueueDepth> {
+    queue.depth()
 }
 
 /// テキストをAIで処理する
 ///
 /// 指定されたモードに応じてプロンプトを組み立て、AIプロバイダーに送信する。
 /// モードの ai_enabled が false の場合はテキストをそのまま返す。
+/// 呼び出しごとにリクエストIDを発行し、結果とエラーの両方に含める。
+///
+/// リクエストは `AiQueue` に積まれ、`priority`（省略時は `realtime`）の順に処理される。
+/// PTT からの呼び出しは `realtime`、一括処理は `batch` を指定する。
 #[tauri::command]
-pub async fn process_with_ai(text: String, mode_id: String) -> Result<AIResponse, AppError> {
-    // モード設定を取得
-    let modes =
-        load_modes().map_err(|e| AppError::Config(format!("Failed to load modes: {}", e)))?;
-    let mode = modes
-        .iter()
-        .find(|m| m.id == mode_id)
-        .ok_or_else(|| AppError::Config(format!("Mode not found: {}", mode_id)))?;
+pub async fn process_with_ai(
+    state: State<'_, AiState>,
+    config: State<'_, AppConfig>,
+    queue: State<'_, Arc<AiQueue>>,
+    contexts: State<'_, ContextRegistry>,
+    text: String,
+    mode_id: String,
+    priority: Option<Priority>,
+) -> TaprResult<AIResponse> {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let priority = priority.unwrap_or_default();
+    eprintln!(
+        "[ai] request_id={} mode={} priority={:?} start",
+        request_id, mode_id, priority
+    );
+
+    match run_process_with_ai(
+        &state,
+        &config,
+        &queue,
+        &contexts,
+        text,
+        &mode_id,
+        priority,
+        &request_id,
+    )
+    .await
+    {
+        Ok(response) => {
+            eprintln!(
+                "[ai] request_id={} done model={}",
+                request_id, response.model
+            );
+            Ok(response)
+        }
+        Err(e) => {
+            eprintln!("[ai] request_id={} failed: {}", request_id, e);
+            Err(e.with_request_id(&request_id))
+        }
+    }
+}
+
+async fn run_process_with_ai(
+    state: &AiState,
+    config: &AppConfig,
+    queue: &AiQueue,
+    contexts: &ContextRegistry,
+    text: String,
+    mode_id: &str,
+    priority: Priority,
+    request_id: &str,
+) -> TaprResult<AIResponse> {
+    let (mode, suggestion_used) = find_mode(config, mode_id)?;
+
+    // AI無効モードでは AI 処理前に正規化するしかない
+    let normalize_before = mode.normalize_japanese && (mode.apply_before_ai || !mode.ai_enabled);
+    let text = if normalize_before {
+        normalize_japanese(&text)
+    } else {
+        text
+    };
 
     // AI無効モードの場合はそのまま返す
     if !mode.ai_enabled {
@@ -53,38 +258,994 @@ pub async fn process_with_ai(text: String, mode_id: String) -> Result<AIResponse
             text,
             model: "none".to_string(),
             usage: None,
+            system_fingerprint: None,
+            finish_reason: None,
+            request_id: request_id.to_string(),
+            suggestion_used,
+            truncated: false,
         });
     }
 
-    // プロンプトを組み立て（コンテキストは今回なし — 将来的にステート管理で対応）
-    let prompt = render_prompt(mode, &text, None);
+    // 別のモードの履歴が混ざらないよう、コンテキストはモードごとに分ける
+    let context_format = state.context_format.lock()?.clone();
+    let context = contexts.entries(&mode.id);
+    let FittedPrompt { prompt, truncated } = build_prompt(&mode, &text, &context, context_format)?;
+
+    let options = provider_options_for_mode(state, &mode)?;
+    let provider: Arc<dyn AIProvider> = create_provider_with_fallback(&options, config)
+        .map_err(|e| AppError::Ai(e.to_string()))?
+        .into();
+    ensure_prompt_fits(provider.model_name(), &prompt)?;
+
+    // AI処理をキューに積んで結果を待つ（待ち時間もタイムアウトに含める）
+    let timeout = Duration::from_secs(config.ai_command_timeout_secs);
+    let receiver = queue.submit(Arc::clone(&provider), prompt, priority)?;
+    let result = with_ai_timeout(timeout, receiver).await.and_then(|r| {
+        r.map_err(|_| AppError::Ai("AI request queue stopped".to_string()))?
+            .map_err(|e| AppError::Ai(e.to_string()))
+    });
+    let (served_provider, served_model) = served_by(provider.as_ref(), &config.ai_provider);
+    record_ai_request(
+        &served_provider,
+        &served_model,
+        result.is_ok(),
+        result.as_ref().ok().and_then(|r| r.usage.as_ref()),
+    );
+    let mut response = result?;
+    contexts.add_entry(&mode.id, &text);
+
+    response.text = postprocess_response(&mode, &response.text);
+    let response_truncated = match mode
+        .max_response_chars
+        .and_then(|max| truncate_response(&response.text, max))
+    {
+        Some(text) => {
+            response.text = text;
+            true
+        }
+        None => false,
+    };
+
+    Ok(AIResponse {
+        suggestion_used,
+        truncated: truncated || response_truncated,
+        ..from_ai_response(response, request_id)
+    })
+}
+
+/// AI 出力にモードの後処理（正規化・組版・折り返し）をかける
+///
+/// `apply_before_ai` のモードでは正規化は AI 処理前の入力にかけるので、ここでは行わない。
+fn postprocess_response(mode: &ModeConfig, text: &str) -> String {
+    let mut text = text.to_string();
+    if mode.normalize_japanese && !mode.apply_before_ai {
+        text = normalize_japanese(&text);
+    }
+    if let Some(typography) = &mode.typography {
+        text = apply_typographic_corrections(&text, typography);
+    }
+    if let Some(line_wrap) = &mode.line_wrap {
+        text = apply_line_wrap(&text, line_wrap);
+    }
+    text
+}
+
+/// ストリーミングの全文に `postprocess_response` をかける
+///
+/// 打ち切った場合は末尾の `…[truncated]` を変換しないよう、本文だけに後処理をかけて付け直す。
+fn postprocess_stream_output(mode: &ModeConfig, output: &StreamOutput) -> String {
+    match output.text.strip_suffix(TRUNCATION_MARKER) {
+        Some(body) if output.truncated => {
+            format!("{}{}", postprocess_response(mode, body), TRUNCATION_MARKER)
+        }
+        _ => postprocess_response(mode, &output.text),
+    }
+}
+
+/// AI 処理前の入力に正規化をかける（`apply_before_ai` のモードのみ）
+fn preprocess_input(mode: &ModeConfig, text: String) -> String {
+    if mode.normalize_japanese && mode.apply_before_ai {
+        normalize_japanese(&text)
+    } else {
+        text
+    }
+}
+
+/// AI を呼ばずにモードのプロンプトをサンプルテキストで展開して返す
+#[tauri::command]
+pub fn get_mode_prompt_preview(
+    config: State<'_, AppConfig>,
+    mode_id: String,
+    sample_text: String,
+) -> TaprResult<PromptPreview> {
+    let (mode, _) = find_mode(&config, &mode_id)?;
+    Ok(preview_prompt(&mode, &sample_text))
+}
+
+/// モード設定を取得する
+///
+/// 完全一致しない場合はタイプミスとみなして近いモードで代用する（FUZZY_MODE_MATCHING=0 で無効）。
+/// 戻り値の bool は代用したかどうか。
+fn find_mode(config: &AppConfig, mode_id: &str) -> TaprResult<(ModeConfig, bool)> {
+    let modes =
+        load_modes().map_err(|e| AppError::Config(format!("Failed to load modes: {}", e)))?;
+    let (mode, suggestion_used) = resolve_mode(&modes, mode_id, config.fuzzy_mode_matching)?;
+    if suggestion_used {
+        eprintln!("[ai] mode '{}' not found, using '{}'", mode_id, mode.id);
+    }
+    Ok((mode, suggestion_used))
+}
+
+/// 実行時設定にモード固有の設定を重ねたプロバイダー生成オプションを作る
+fn provider_options_for_mode(state: &AiState, mode: &ModeConfig) -> TaprResult<ProviderOptions> {
+    let mut options = state.provider_options()?;
+    if mode.use_max_completion_tokens.is_some() {
+        options.use_max_completion_tokens = mode.use_max_completion_tokens;
+    }
+    options.model_rotation = state.model_rotation(mode)?;
+    options.temperature = mode.temperature;
+    options.max_tokens = mode.max_tokens;
+    Ok(options)
+}
+
+/// モードのテンプレートからプロンプトを組み立てる
+///
+/// `max_prompt_chars` を超える場合はモードの `truncation_strategy` で切り詰める。
+fn build_prompt(
+    mode: &ModeConfig,
+    text: &str,
+    context: &[String],
+    context_format: ContextFormat,
+) -> TaprResult<FittedPrompt> {
+    // 未知のプレースホルダーはそのまま送信されるため警告だけ出す
+    if let Some(template) = &mode.ai_prompt {
+        if let Err(unknown) = validate_prompt_template(template) {
+            eprintln!(
+                "[ai] mode '{}' has unknown placeholders: {}",
+                mode.id,
+                unknown.join(", ")
+            );
+        }
+    }
+
+    let mut vars = RenderContext::from_input(text).with_context_format(context_format);
+    if mode.include_clipboard_context {
+        // プライバシー上、クリップボードを読むときは必ずログに残す
+        eprintln!("[ai] mode '{}' reads clipboard as context", mode.id);
+        if let Some(clip) = read_clipboard_text() {
+            vars = vars.with_clipboard(&clip, mode.clipboard_label.as_deref());
+        }
+    }
+    fit_prompt(mode, text, context, &vars)
+}
+
+/// クリップボードのテキストを取得する（取得できなければ None）
+fn read_clipboard_text() -> Option<String> {
+    arboard::Clipboard::new().ok()?.get_text().ok()
+}
+
+/// 起動時に AI プロバイダーの設定（AI_PROVIDER と必須の環境変数）を検証する
+/// クライアントを組み立てるだけで API への通信は行わない
+pub(crate) fn validate_environment(config: &AppConfig) -> TaprResult<()> {
+    create_provider(&config.ai_provider, &ProviderOptions::default(), config)
+        .map(|_| ())
+        .map_err(|e| AppError::Ai(e.to_string()))
+}
+
+/// AI プロバイダーの設定を確認する（起動時に呼ぶ）
+///
+/// Anthropic ではモデル一覧を取得し、設定したモデルが存在するかも確かめる。
+/// 推論時まで気づけないモデル名の誤りを起動時の警告にするため。
+/// 通信できずにモデルを確かめられなかった場合は設定の誤りとせず、
+/// `Ok(Some(理由))` を返す（認証エラーなど API が拒否した場合は `Err`）。
+pub(crate) async fn check_ai_provider(config: &AppConfig) -> TaprResult<Option<String>> {
+    validate_environment(config)?;
+    if config.ai_provider == ProviderType::Anthropic {
+        let client = anthropic_client(config).map_err(|e| AppError::Ai(e.to_string()))?;
+        return check_anthropic_model(&client).await;
+    }
+    Ok(None)
+}
 
-    // AI_PROVIDER 環境変数でプロバイダーを選択（vertexai / openai / anthropic）
-    let provider_type = match std::env::var("AI_PROVIDER").as_deref() {
-        Ok("vertexai") => ProviderType::VertexAI,
-        Ok("openai") => ProviderType::OpenAI,
-        Ok("anthropic") => ProviderType::Anthropic,
-        Ok(other) => {
+/// 設定したモデルが Anthropic のモデル一覧にあるかを確かめる（`check_ai_provider` 参照）
+async fn check_anthropic_model(client: &AnthropicClient) -> TaprResult<Option<String>> {
+    let models = match client.list_models().await {
+        Ok(models) => models,
+        Err(e) if is_unreachable(&e) => {
+            return Ok(Some(format!(
+                "could not verify ANTHROPIC_MODEL (Anthropic API unreachable): {}",
+                e
+            )))
+        }
+        Err(e) => {
             return Err(AppError::Ai(format!(
-                "Unknown AI_PROVIDER: '{}'. Use vertexai, openai, or anthropic.",
+                "failed to list Anthropic models: {}",
+                e
+            )))
+        }
+    };
+    if !models.iter().any(|m| m == client.model_name()) {
+        return Err(AppError::Config(format!(
+            "Unknown ANTHROPIC_MODEL: '{}'",
+            client.model_name()
+        )));
+    }
+    Ok(None)
+}
+
+/// 通信障害やサーバー側の問題で API から答えを得られなかったか
+///
+/// 4xx（429 を除く）は認証や設定の誤りとみなす。
+fn is_unreachable(error: &AIError) -> bool {
+    match error {
+        AIError::Timeout | AIError::RateLimited(_) => true,
+        AIError::RequestFailed(msg) => !msg.starts_with("HTTP 4"),
+        _ => false,
+    }
+}
+
+/// 指定したモデル名が Anthropic のモデル一覧に存在するかを返す
+#[tauri::command]
+pub async fn validate_anthropic_model(
+    config: State<'_, AppConfig>,
+    model_name: String,
+) -> TaprResult<bool> {
+    let models = anthropic_client(&config)
+        .map_err(|e| AppError::Ai(e.to_string()))?
+        .list_models()
+        .await
+        .map_err(|e| AppError::Ai(e.to_string()))?;
+    Ok(models.contains(&model_name))
+}
+
+/// プロバイダーで選べるモデルを料金・対応機能付きで返す（設定画面のモデル選択用）
+///
+/// `provider` は "openai" / "anthropic" / "vertexai" / "groq" / "ollama"。
+/// OpenAI と Anthropic は API のモデル一覧（1時間キャッシュ）、Vertex AI と Groq は
+/// 固定のリスト、Ollama はローカルの `GET /api/tags` から取得する。
+#[tauri::command]
+pub async fn get_ai_model_info(
+    config: State<'_, AppConfig>,
+    provider: String,
+) -> TaprResult<Vec<ModelInfo>> {
+    let models = match provider.as_str() {
+        "openai" => match config.openai_api_key.clone() {
+            Some(api_key) => match OpenAIClient::new(api_key) {
+                Ok(client) => {
+                    let client = client.with_timeout(Duration::from_secs(config.ai_timeout_secs));
+                    catalog::openai_model_info(&client).await
+                }
+                Err(e) => Err(e),
+            },
+            None => Err(AIError::ApiKeyMissing("OPENAI_API_KEY".to_string())),
+        },
+        "anthropic" => match anthropic_client(&config) {
+            Ok(client) => catalog::anthropic_model_info(&client).await,
+            Err(e) => Err(e),
+        },
+        "vertexai" => Ok(catalog::static_model_info(VERTEX_MODELS)),
+        "groq" => Ok(catalog::static_model_info(GROQ_MODELS)),
+        "ollama" => catalog::ollama_model_info(&config.ollama_host).await,
+        other => {
+            return Err(AppError::Config(format!(
+                "Unknown AI provider: '{}'",
                 other
-            )));
+            )))
         }
-        Err(_) => {
-            return Err(AppError::Ai(
-                "AI_PROVIDER not set. Set to vertexai, openai, or anthropic.".to_string(),
-            ));
+    };
+    models.map_err(|e| AppError::Ai(format!("failed to list {} models: {}", provider, e)))
+}
+
+/// コンテキストウィンドウを超えるプロンプトは API に送る前に弾く
+fn ensure_prompt_fits(model: &str, prompt: &str) -> TaprResult<()> {
+    if can_fit_prompt(model, prompt) == Some(false) {
+        return Err(AppError::Ai(format!(
+            "prompt too long for model {}: {} tokens estimated, {} max",
+            model,
+            estimate_tokens(prompt),
+            model_context_window(model).unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+/// ストリーミング中の各チャンク（`ai-stream-chunk` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamChunkEvent {
+    pub stream_id: String,
+    /// `process_with_ai_streaming_persistent` で指定したセッション（それ以外は None）
+    pub session_id: Option<String>,
+    pub content: String,
+    pub done: bool,
+}
+
+/// ストリーミングの完了（`ai-stream-done` イベント）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamDoneEvent {
+    pub stream_id: String,
+    pub text: String,
+    pub model: String,
+    /// ストリーミングAPIは使用量を返さないため `estimate_tokens` による推定値
+    pub usage: TokenUsage,
+    /// `max_response_chars` で出力を打ち切ったか
+    pub truncated: bool,
+}
+
+/// テキストをAIでストリーミング処理する（履歴には保存しない）
+///
+/// 各チャンクを `ai-stream-chunk` で送り、完了したら全文と使用量を `ai-stream-done` で
+/// 送ってから同じ内容を返す。`stream_id` はフロントエンドが決め、同時に流れる
+/// 複数のストリームのイベントを区別するのに使う。
+#[tauri::command]
+pub async fn process_with_ai_stream(
+    app: AppHandle,
+    state: State<'_, AiState>,
+    config: State<'_, AppConfig>,
+    contexts: State<'_, ContextRegistry>,
+    text: String,
+    mode_id: String,
+    stream_id: String,
+) -> TaprResult<StreamDoneEvent> {
+    let (mode, _) = find_mode(&config, &mode_id)?;
+    if !mode.ai_enabled {
+        return Err(AppError::Config(format!(
+            "AI is disabled for mode: {}",
+            mode_id
+        )));
+    }
+    let text = preprocess_input(&mode, text);
+
+    let context_format = state.context_format.lock()?.clone();
+    let context = contexts.entries(&mode.id);
+    let FittedPrompt { prompt, .. } = build_prompt(&mode, &text, &context, context_format)?;
+    let options = provider_options_for_mode(&state, &mode)?;
+    let provider = create_provider_with_fallback(&options, &config)
+        .map_err(|e| AppError::Ai(e.to_string()))?;
+    ensure_prompt_fits(provider.model_name(), &prompt)?;
+
+    eprintln!("[ai] stream_id={} mode={} start", stream_id, mode.id);
+    let limits = StreamLimits {
+        timeout: Duration::from_secs(config.ai_command_timeout_secs),
+        max_response_chars: mode.max_response_chars,
+    };
+    let result = stream_response(provider.as_ref(), &prompt, limits, |chunk| {
+        let _ = app.emit(
+            "ai-stream-chunk",
+            StreamChunkEvent {
+                stream_id: stream_id.clone(),
+                session_id: None,
+                content: chunk.content.clone(),
+                done: chunk.done,
+            },
+        );
+    })
+    .await;
+    let (served_provider, served_model) = served_by(provider.as_ref(), &config.ai_provider);
+    record_ai_request(&served_provider, &served_model, result.is_ok(), None);
+    let output = result.map_err(|e| {
+        eprintln!("[ai] stream_id={} failed: {}", stream_id, e);
+        e
+    })?;
+    contexts.add_entry(&mode.id, &text);
+
+    let done = StreamDoneEvent {
+        stream_id,
+        usage: estimated_usage(&prompt, &output.text),
+        text: postprocess_stream_output(&mode, &output),
+        model: served_model_label(provider.as_ref(), &config.ai_provider),
+        truncated: output.truncated,
+    };
+    let _ = app.emit("ai-stream-done", done.clone());
+    Ok(done)
+}
+
+/// 直前のリクエストを処理したプロバイダーとモデル（フォールバック・ローテーション後のもの）
+///
+/// メトリクスに使う。`primary` は AI_PROVIDER。
+fn served_by(provider: &dyn AIProvider, primary: &ProviderType) -> (ProviderType, String) {
+    let provider_type = provider
+        .served_provider()
+        .unwrap_or_else(|| primary.clone());
+    (provider_type, provider.served_model())
+}
+
+/// 履歴やイベントに残すモデル名
+///
+/// 代わりのプロバイダーが処理した場合は、`FallbackProvider::process` と同じく
+/// `"openai/gpt-4o-mini"` のようにプロバイダー名を付ける。
+fn served_model_label(provider: &dyn AIProvider, primary: &ProviderType) -> String {
+    match served_by(provider, primary) {
+        (provider_type, model) if &provider_type == primary => model,
+        (provider_type, model) => format!("{}/{}", provider_type.as_str(), model),
+    }
+}
+
+/// プロンプトと出力のトークン数を推定する
+fn estimated_usage(prompt: &str, completion: &str) -> TokenUsage {
+    let prompt_tokens = estimate_tokens(prompt);
+    let completion_tokens = estimate_tokens(completion);
+    TokenUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+    }
+}
+
+/// ストリーミング結果の保存完了（`stream-saved` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamSavedEvent {
+    pub entry_id: i64,
+    pub stream_id: String,
+}
+
+/// ストリーミング結果の保存失敗（`stream-save-failed` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamSaveFailedEvent {
+    pub stream_id: String,
+    pub error: String,
+}
+
+/// テキストをAIでストリーミング処理し、完了時に履歴へ保存する
+///
+/// 即座に `stream_id` を返し、処理はバックグラウンドで行う。各チャンクは
+/// `ai-stream-chunk`、保存完了は `stream-saved`、失敗は `stream-save-failed` で通知する。
+#[tauri::command]
+pub async fn process_with_ai_streaming_persistent(
+    app: AppHandle,
+    state: State<'_, AiState>,
+    config: State<'_, AppConfig>,
+    contexts: State<'_, ContextRegistry>,
+    text: String,
+    mode_id: String,
+    session_id: String,
+) -> TaprResult<String> {
+    let stream_id = uuid::Uuid::new_v4().to_string();
+
+    let (mode, _) = find_mode(&config, &mode_id)?;
+    if !mode.ai_enabled {
+        return Err(AppError::Config(format!(
+            "AI is disabled for mode: {}",
+            mode_id
+        )));
+    }
+    let text = preprocess_input(&mode, text);
+
+    let context_format = state.context_format.lock()?.clone();
+    let context = contexts.entries(&mode.id);
+    let FittedPrompt { prompt, .. } = build_prompt(&mode, &text, &context, context_format)?;
+    let options = provider_options_for_mode(&state, &mode)?;
+    let provider = create_provider_with_fallback(&options, &config)
+        .map_err(|e| AppError::Ai(e.to_string()))?;
+    ensure_prompt_fits(provider.model_name(), &prompt)?;
+
+    let task_stream_id = stream_id.clone();
+    let primary = config.ai_provider.clone();
+    let limits = StreamLimits {
+        timeout: Duration::from_secs(config.ai_command_timeout_secs),
+        max_response_chars: mode.max_response_chars,
+    };
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<DbState>();
+        let result = stream_and_persist(
+            provider.as_ref(),
+            &primary,
+            &prompt,
+            &text,
+            &mode,
+            &task_stream_id,
+            &db,
+            limits,
+            |chunk| {
+                let _ = app.emit(
+                    "ai-stream-chunk",
+                    StreamChunkEvent {
+                        stream_id: task_stream_id.clone(),
+                        session_id: Some(session_id.clone()),
+                        content: chunk.content.clone(),
+                        done: chunk.done,
+                    },
+                );
+            },
+        )
+        .await;
+        let (served_provider, served_model) = served_by(provider.as_ref(), &primary);
+        record_ai_request(&served_provider, &served_model, result.is_ok(), None);
+
+        match result {
+            Ok(entry_id) => {
+                app.state::<ContextRegistry>().add_entry(&mode.id, &text);
+                let _ = app.emit(
+                    "stream-saved",
+                    StreamSavedEvent {
+                        entry_id,
+                        stream_id: task_stream_id.clone(),
+                    },
+                );
+            }
+            Err(e) => {
+                eprintln!("[ai] stream_id={} failed: {}", task_stream_id, e);
+                let _ = app.emit(
+                    "stream-save-failed",
+                    StreamSaveFailedEvent {
+                        stream_id: task_stream_id.clone(),
+                        error: e.to_string(),
+                    },
+                );
+            }
+        }
+    });
+
+    Ok(stream_id)
+}
+
+/// AI 呼び出しを `timeout`（AI_COMMAND_TIMEOUT）で打ち切る
+async fn with_ai_timeout<F: Future>(timeout: Duration, fut: F) -> TaprResult<F::Output> {
+    tokio::time::timeout(timeout, fut).await.map_err(|_| {
+        AppError::Ai(format!(
+            "AI command timed out after {} seconds",
+            timeout.as_secs()
+        ))
+    })
+}
+
+/// ストリーミング1回分の上限
+#[derive(Debug, Clone, Copy)]
+struct StreamLimits {
+    /// ストリーム全体の最大待ち時間（AI_COMMAND_TIMEOUT）
+    timeout: Duration,
+    /// 出力の最大文字数（モードの `max_response_chars`）
+    max_response_chars: Option<usize>,
+}
+
+/// ストリーミングで受け取った出力
+struct StreamOutput {
+    text: String,
+    /// `max_response_chars` で打ち切ったか
+    truncated: bool,
+}
+
+/// プロバイダーのストリーミング出力を `on_chunk` に転送し、全文を集めて返す
+///
+/// ストリーム全体が `limits.timeout` を超えた場合はエラーを返す。
+/// 出力が `limits.max_response_chars` に達したらプロバイダーの読み取りを打ち切り、
+/// `…[truncated]` の最終チャンクを送って、そこまでの内容を返す。
+async fn stream_response(
+    provider: &dyn AIProvider,
+    prompt: &str,
+    limits: StreamLimits,
+    on_chunk: impl Fn(&StreamChunk),
+) -> TaprResult<StreamOutput> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<StreamChunk>(64);
+
+    // 上限で打ち切ったら (本文, true) を返す
+    let collect = async {
+        let mut full_text = String::new();
+        let mut char_count = 0;
+        while let Some(chunk) = rx.recv().await {
+            let len = chunk.content.chars().count();
+            if let Some(max) = limits.max_response_chars {
+                if char_count + len > max {
+                    let rest: String = chunk.content.chars().take(max - char_count).collect();
+                    if !rest.is_empty() {
+                        full_text.push_str(&rest);
+                        on_chunk(&StreamChunk {
+                            content: rest,
+                            done: false,
+                        });
+                    }
+                    full_text.push_str(TRUNCATION_MARKER);
+                    on_chunk(&StreamChunk {
+                        content: TRUNCATION_MARKER.to_string(),
+                        done: true,
+                    });
+                    return (full_text, true);
+                }
+            }
+            char_count += len;
+            full_text.push_str(&chunk.content);
+            on_chunk(&chunk);
+            if chunk.done {
+                break;
+            }
         }
+        (full_text, false)
     };
+    let run = async {
+        let stream = provider.process_stream(prompt, tx);
+        tokio::pin!(stream, collect);
+        tokio::select! {
+            (full_text, truncated) = &mut collect => {
+                // 打ち切った場合はプロバイダーの残りの出力を待たずに破棄する
+                let stream_result = if truncated { Ok(()) } else { stream.await };
+                (stream_result, full_text, truncated)
+            }
+            stream_result = &mut stream => {
+                let (full_text, truncated) = collect.await;
+                (stream_result, full_text, truncated)
+            }
+        }
+    };
+    let (stream_result, text, truncated) = with_ai_timeout(limits.timeout, run).await?;
+    stream_result.map_err(|e| AppError::Ai(e.to_string()))?;
+    Ok(StreamOutput { text, truncated })
+}
 
-    let provider =
-        create_provider(&provider_type).map_err(|e| AppError::Ai(e.to_string()))?;
+/// プロバイダーのストリーミング出力を `on_chunk` に転送し、完了時に DB へ保存する
+///
+/// タイムアウトや打ち切りは `stream_response` と同じ。タイムアウトした場合は保存しない。
+/// 保存する全文には `process_with_ai` と同じモードの後処理をかける。
+/// 保存したエントリの ID を返す。
+#[allow(clippy::too_many_arguments)]
+async fn stream_and_persist(
+    provider: &dyn AIProvider,
+    primary: &ProviderType,
+    prompt: &str,
+    raw_text: &str,
+    mode: &ModeConfig,
+    stream_id: &str,
+    db: &DbState,
+    limits: StreamLimits,
+    on_chunk: impl Fn(&StreamChunk),
+) -> TaprResult<i64> {
+    let output = stream_response(provider, prompt, limits, on_chunk).await?;
+
+    let entry = NewEntry {
+        raw_text: raw_text.to_string(),
+        processed_text: postprocess_stream_output(mode, &output),
+        mode_id: mode.id.clone(),
+        model: served_model_label(provider, primary),
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+        request_id: Some(stream_id.to_string()),
+        device_name: None,
+        audio_duration_ms: None,
+        audio_hash: None,
+    };
+    let conn = db.conn()?;
+    repository::insert_entry(&conn, &entry)
+}
 
-    // AI処理を実行
-    let response = provider
-        .process(&prompt)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AIError, AIResponse as ProviderResponse};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// 固定チャンクを順に流すモックプロバイダー
+    struct MockStreamProvider;
+
+    #[async_trait]
+    impl AIProvider for MockStreamProvider {
+        fn model_name(&self) -> &str {
+            "mock-model"
+        }
+
+        async fn process(&self, _prompt: &str) -> Result<ProviderResponse, AIError> {
+            Err(AIError::RequestFailed(
+                "MockStreamProvider only streams".to_string(),
+            ))
+        }
+
+        async fn process_stream(
+            &self,
+            _prompt: &str,
+            sender: tokio::sync::mpsc::Sender<StreamChunk>,
+        ) -> Result<(), AIError> {
+            for (content, done) in [("こんにちは", false), ("、世界。", false), ("", true)]
+            {
+                let _ = sender
+                    .send(StreamChunk {
+                        content: content.to_string(),
+                        done,
+                    })
+                    .await;
+            }
+            Ok(())
+        }
+    }
+
+    /// 応答を返さないモックプロバイダー
+    struct HangingProvider;
+
+    #[async_trait]
+    impl AIProvider for HangingProvider {
+        fn model_name(&self) -> &str {
+            "hanging-model"
+        }
+
+        async fn process(&self, _prompt: &str) -> Result<ProviderResponse, AIError> {
+            std::future::pending().await
+        }
+
+        async fn process_stream(
+            &self,
+            _prompt: &str,
+            _sender: tokio::sync::mpsc::Sender<StreamChunk>,
+        ) -> Result<(), AIError> {
+            std::future::pending().await
+        }
+    }
+
+    fn setup_db() -> DbState {
+        DbState::in_memory().unwrap()
+    }
+
+    fn ai_mode(id: &str) -> ModeConfig {
+        ModeConfig {
+            id: id.to_string(),
+            ai_enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_and_persist_saves_entry() {
+        let db = setup_db();
+        let chunk_count = Mutex::new(0);
+
+        let entry_id = stream_and_persist(
+            &MockStreamProvider,
+            &ProviderType::OpenAI,
+            "prompt",
+            "こんにちは世界",
+            &ai_mode("correct"),
+            "stream-1",
+            &db,
+            StreamLimits {
+                timeout: Duration::from_secs(60),
+                max_response_chars: None,
+            },
+            |_| *chunk_count.lock().unwrap() += 1,
+        )
         .await
-        .map_err(|e| AppError::Ai(e.to_string()))?;
+        .unwrap();
+
+        assert_eq!(*chunk_count.lock().unwrap(), 3);
+
+        let conn = db.conn().unwrap();
+        let entry = repository::get_entry(&conn, entry_id)
+            .unwrap()
+            .expect("entry should exist");
+        assert_eq!(entry.raw_text, "こんにちは世界");
+        assert_eq!(entry.processed_text, "こんにちは、世界。");
+        assert_eq!(entry.model, "mock-model");
+        assert_eq!(entry.request_id.as_deref(), Some("stream-1"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_response_collects_text() {
+        let chunks = Mutex::new(Vec::new());
+
+        let output = stream_response(
+            &MockStreamProvider,
+            "prompt",
+            StreamLimits {
+                timeout: Duration::from_secs(60),
+                max_response_chars: None,
+            },
+            |chunk| chunks.lock().unwrap().push(chunk.content.clone()),
+        )
+        .await
+        .unwrap();
 
-    Ok(from_ai_response(response))
+        assert_eq!(output.text, "こんにちは、世界。");
+        assert!(!output.truncated);
+        assert_eq!(*chunks.lock().unwrap(), vec!["こんにちは", "、世界。", ""]);
+    }
+
+    #[test]
+    fn test_estimated_usage() {
+        let usage = estimated_usage("abcdefgh", "こんにちは");
+        assert_eq!(usage.prompt_tokens, 2);
+        assert_eq!(usage.completion_tokens, 5);
+        assert_eq!(usage.total_tokens, 7);
+    }
+
+    #[tokio::test]
+    async fn test_process_times_out() {
+        tokio::time::pause();
+        let err = with_ai_timeout(Duration::from_secs(60), HangingProvider.process("prompt"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "AI processing error: AI command timed out after 60 seconds"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_and_persist_times_out_without_saving() {
+        tokio::time::pause();
+        let db = setup_db();
+
+        let err = stream_and_persist(
+            &HangingProvider,
+            &ProviderType::OpenAI,
+            "prompt",
+            "こんにちは世界",
+            &ai_mode("correct"),
+            "stream-2",
+            &db,
+            StreamLimits {
+                timeout: Duration::from_secs(5),
+                max_response_chars: None,
+            },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("timed out after 5 seconds"));
+
+        let conn = db.conn().unwrap();
+        assert!(repository::get_entries(&conn, 10, 0).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_and_persist_truncates_at_max_chars() {
+        let db = setup_db();
+        let chunks = Mutex::new(Vec::new());
+
+        let entry_id = stream_and_persist(
+            &MockStreamProvider,
+            &ProviderType::OpenAI,
+            "prompt",
+            "こんにちは世界",
+            &ai_mode("correct"),
+            "stream-3",
+            &db,
+            StreamLimits {
+                timeout: Duration::from_secs(60),
+                max_response_chars: Some(7),
+            },
+            |chunk| {
+                chunks
+                    .lock()
+                    .unwrap()
+                    .push((chunk.content.clone(), chunk.done))
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *chunks.lock().unwrap(),
+            vec![
+                ("こんにちは".to_string(), false),
+                ("、世".to_string(), false),
+                ("…[truncated]".to_string(), true),
+            ]
+        );
+        let conn = db.conn().unwrap();
+        let entry = repository::get_entry(&conn, entry_id).unwrap().unwrap();
+        assert_eq!(entry.processed_text, "こんにちは、世…[truncated]");
+    }
+
+    #[tokio::test]
+    async fn test_stream_and_persist_applies_mode_postprocessing() {
+        let db = setup_db();
+        let line_wrap = crate::config::modes::LineWrapConfig {
+            max_chars_per_line: 5,
+            preserve_paragraphs: false,
+        };
+        let mode = ModeConfig {
+            line_wrap: Some(line_wrap.clone()),
+            ..ai_mode("correct")
+        };
+
+        let entry_id = stream_and_persist(
+            &MockStreamProvider,
+            &ProviderType::OpenAI,
+            "prompt",
+            "こんにちは世界",
+            &mode,
+            "stream-4",
+            &db,
+            StreamLimits {
+                timeout: Duration::from_secs(60),
+                max_response_chars: None,
+            },
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        let conn = db.conn().unwrap();
+        let entry = repository::get_entry(&conn, entry_id).unwrap().unwrap();
+        assert_eq!(
+            entry.processed_text,
+            apply_line_wrap("こんにちは、世界。", &line_wrap)
+        );
+        assert!(entry.processed_text.contains('\n'));
+    }
+
+    #[test]
+    fn test_postprocess_stream_output() {
+        let mode = ModeConfig {
+            normalize_japanese: true,
+            ..ai_mode("correct")
+        };
+        let output = StreamOutput {
+            text: "ｶﾀｶﾅです。。".to_string(),
+            truncated: false,
+        };
+        assert_eq!(postprocess_stream_output(&mode, &output), "カタカナです。");
+
+        // 打ち切りの印は変換せずに残す
+        let output = StreamOutput {
+            text: format!("ｶﾀｶﾅ{}", TRUNCATION_MARKER),
+            truncated: true,
+        };
+        assert_eq!(
+            postprocess_stream_output(&mode, &output),
+            format!("カタカナ{}", TRUNCATION_MARKER)
+        );
+
+        // AI 処理前に正規化するモードでは出力に正規化をかけない
+        let mode = ModeConfig {
+            apply_before_ai: true,
+            ..mode
+        };
+        assert_eq!(
+            postprocess_stream_output(
+                &mode,
+                &StreamOutput {
+                    text: "ｶﾀｶﾅ".to_string(),
+                    truncated: false
+                }
+            ),
+            "ｶﾀｶﾅ"
+        );
+        assert_eq!(preprocess_input(&mode, "ｶﾀｶﾅ".to_string()), "カタカナ");
+    }
+
+    #[test]
+    fn test_is_unreachable() {
+        assert!(is_unreachable(&AIError::Timeout));
+        assert!(is_unreachable(&AIError::RateLimited("HTTP 429".into())));
+        assert!(is_unreachable(&AIError::RequestFailed(
+            "error sending request".into()
+        )));
+        assert!(is_unreachable(&AIError::RequestFailed(
+            "HTTP 503 Service Unavailable: ".into()
+        )));
+        assert!(!is_unreachable(&AIError::RequestFailed(
+            "HTTP 401 Unauthorized: invalid x-api-key".into()
+        )));
+        assert!(!is_unreachable(&AIError::ParseError("bad json".into())));
+    }
+
+    #[tokio::test]
+    async fn test_check_anthropic_model_distinguishes_network_and_auth_errors() {
+        // 接続できないポート: 設定の誤りではなく、確認できなかった理由を返す
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let closed = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let client = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(closed);
+        let unverified = check_anthropic_model(&client).await.unwrap();
+        assert!(unverified
+            .unwrap()
+            .contains("could not verify ANTHROPIC_MODEL"));
+
+        // 401: API キーの誤りなのでエラー
+        let app = axum::Router::new().route(
+            "/models",
+            axum::routing::get(|| async {
+                (axum::http::StatusCode::UNAUTHORIZED, "invalid x-api-key")
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = AnthropicClient::new("test".to_string())
+            .unwrap()
+            .with_base_url(base_url);
+        let err = check_anthropic_model(&client).await.unwrap_err();
+        assert!(err.to_string().contains("401"));
+    }
 }
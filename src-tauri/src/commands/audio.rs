@@ -1,22 +1,49 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 
-use crate::error::AppError;
-use crate::voice::format::pcm_bytes_to_wav;
+use crate::ai::metrics::{record_recording_duration, record_transcription};
+use crate::commands::fs::available_disk_bytes;
+use crate::config::app_config::{AppConfig, WhisperBackend};
+use crate::db::repository::{self, AudioMetrics};
+use crate::db::DbState;
+use crate::error::{AppError, TaprResult};
+use crate::voice::format::{
+    apply_echo_cancellation, fingerprint_audio, pcm_bytes_to_f32, peak_amplitude, rms,
+    speech_ratio, AudioFingerprint, EchoCanceller, WHISPER_SAMPLE_RATE,
+};
+use crate::voice::openai_realtime::{OpenAIRealtimeClient, REALTIME_SAMPLE_RATE};
+use crate::voice::pipeline::TranscriptionPipeline;
+use crate::voice::resample::{
+    convert_interleaved, pcm_bytes_to_whisper_wav, to_whisper_mono, StreamResampler,
+};
 use crate::voice::whisper_api::WhisperApiClient;
-use crate::voice::SpeechRecognizer;
+use crate::voice::{Segment, SpeechRecognizer};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionResult {
     pub text: String,
     pub confidence: f64,
     pub is_final: bool,
     pub timestamp: u64,
+    /// フロントエンドとバックエンドログを突き合わせるためのリクエストID
+    pub request_id: String,
+    /// エンジンが判定した言語（判定できない場合は None）
+    pub language_detected: Option<String>,
+    /// フォールバックのエンジンで認識した場合 true
+    #[serde(default)]
+    pub used_fallback: bool,
+    /// 区間ごとのタイミング（Whisper API / whisper.cpp のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<Segment>>,
+    /// 直前の録音と重複したため文字起こしを省略した場合 true（text は空）
+    #[serde(default)]
+    pub duplicate_skipped: bool,
 }
 
 impl From<crate::voice::TranscriptionResult> for TranscriptionResult {
@@ -26,18 +53,207 @@ impl From<crate::voice::TranscriptionResult> for TranscriptionResult {
             confidence: r.confidence,
             is_final: r.is_final,
             timestamp: r.timestamp,
+            request_id: String::new(),
+            language_detected: r.language_detected,
+            used_fallback: r.used_fallback,
+            segments: r.segments,
+            duplicate_skipped: false,
         }
     }
 }
 
+/// 直前の録音と同一とみなすフィンガープリントのハミング距離の上限（未満）
+const DUPLICATE_HAMMING_THRESHOLD: u32 = 5;
+
+/// ノイズフロアの計算に使う録音冒頭の長さ（発話前の無音とみなす）
+const NOISE_FLOOR_WINDOW_MS: u64 = 500;
+
+/// 指定デバイスの接続を確認する間隔
+const DEVICE_WATCHDOG_INTERVAL: Duration = Duration::from_secs(2);
+
+/// この時間 RMS が 0 のままならストリームが止まったとみなす
+const DEAD_STREAM_SILENCE: Duration = Duration::from_secs(3);
+
+/// 録音中に `audio-level` イベントを送る間隔
+const AUDIO_LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 録音開始からこの時間は無音でも自動停止しない（話し始めの遅れを許容する）
+const AUTO_STOP_GRACE: Duration = Duration::from_secs(1);
+
+/// `merge_recordings` で録音の間に挟む無音の長さ
+const MERGE_GAP_MS: u64 = 200;
+
 #[derive(Debug, Serialize)]
 pub struct RecordingResult {
     pub audio_data: Vec<u8>,
     pub sample_rate: u32,
     pub channels: u16,
     pub duration_ms: u64,
+    /// 直前の録音とほぼ同じ音声なら true（フロントエンドは文字起こしを省略する）
+    pub duplicate_detected: bool,
+    pub meta: RecordingMeta,
+}
+
+/// 結合する録音1件分（`RecordingResult` の音声部分）
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingData {
+    /// PCM i16 リトルエンディアン
+    pub audio_data: Vec<u8>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// 録音セッションのメタデータ
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingMeta {
+    /// 録音ごとに発行する ID（`recording_metrics.session_id`）
+    pub session_id: String,
+    pub started_at_unix_ms: u64,
+    pub stopped_at_unix_ms: u64,
+    pub device_name: String,
+    /// cpal のサンプル形式（"F32" / "I16" など）
+    pub sample_format: String,
+    /// 録音冒頭 0.5 秒の RMS（発話前の環境ノイズの目安）
+    pub noise_floor_rms: f32,
+}
+
+/// ストリーミング文字起こしの各チャンク結果（`streaming-transcription` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamingTranscriptionEvent {
+    pub mode_id: String,
+    pub result: TranscriptionResult,
+}
+
+/// 録音中のデバイスが戻り、ストリームを張り直した（`audio-device-reconnected` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioDeviceReconnectedEvent {
+    pub device_name: String,
+    /// 切断前のサンプルを残したまま録音を続けられたか
+    /// （フォーマットが変わった場合も新しい形式に変換して残す）
+    pub buffered_samples_preserved: bool,
+}
+
+/// 録音中のデバイスが一覧から消えた（`audio-device-disconnected` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioDeviceDisconnectedEvent {
+    pub device_name: String,
+}
+
+/// 録音中の入力レベル（`audio-level` イベント）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AudioLevelEvent {
+    /// 直近のコールバック1回分の RMS
+    pub rms: f32,
+    /// 前回のイベント以降の最大振幅
+    pub peak: f32,
+}
+
+/// 入力デバイスの情報（`list_input_devices`）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioDeviceInfo {
+    /// `start_recording` の `device_name` に渡す名前
+    pub name: String,
+    /// OS のデフォルト入力デバイスか
+    pub is_default: bool,
+}
+
+/// ストリーミング文字起こしのエンジン
+enum StreamingTranscriber {
+    /// 16kHz のチャンクごとに Whisper API へ送る
+    Chunked(TranscriptionPipeline<WhisperApiClient>),
+    /// 24kHz で OpenAI Realtime API に逐次送る（`chunk_duration_secs` は使わない）
+    Realtime(OpenAIRealtimeClient),
+}
+
+/// 無音による自動停止の設定（`set_auto_stop`）
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AutoStopConfig {
+    /// この時間 RMS が `threshold` 未満のままなら停止する
+    silence_ms: u64,
+    threshold: f32,
+}
+
+/// 録音コールバックで無音の継続を数える
+///
+/// サンプル数だけで判定するので、cpal なしでテストできる。
+struct SilenceTracker {
+    threshold: f32,
+    grace_samples: u64,
+    limit_samples: u64,
+    elapsed_samples: u64,
+    silent_samples: u64,
+    fired: bool,
+}
+
+impl SilenceTracker {
+    fn new(config: AutoStopConfig, sample_rate: u32, channels: u16) -> Self {
+        let samples_per_sec = sample_rate as u64 * channels as u64;
+        Self {
+            threshold: config.threshold,
+            grace_samples: samples_per_sec * AUTO_STOP_GRACE.as_millis() as u64 / 1000,
+            limit_samples: samples_per_sec * config.silence_ms / 1000,
+            elapsed_samples: 0,
+            silent_samples: 0,
+            fired: false,
+        }
+    }
+
+    /// コールバック1回分（`len` サンプル、RMS が `rms`）を数え、停止すべきときに1度だけ true を返す
+    fn push(&mut self, len: usize, rms: f32) -> bool {
+        if self.fired {
+            return false;
+        }
+        self.elapsed_samples += len as u64;
+        if rms >= self.threshold {
+            self.silent_samples = 0;
+            return false;
+        }
+        self.silent_samples += len as u64;
+        self.fired =
+            self.elapsed_samples >= self.grace_samples && self.silent_samples >= self.limit_samples;
+        self.fired
+    }
+}
+
+/// 録音コールバックが書き込み、`audio-level` の送信スレッドが読み出す入力レベル
+///
+/// f32 のビット列をアトミックに持つので、コールバックはロックを取らない。
+#[derive(Default)]
+struct LevelMeter {
+    rms: AtomicU32,
+    peak: AtomicU32,
+}
+
+impl LevelMeter {
+    /// コールバック1回分のサンプルのレベルを記録し、RMS を返す
+    ///
+    /// ピークは `take` で読み出すまで最大値を保つ。非負の f32 はビット列の大小と
+    /// 値の大小が一致するので `fetch_max` で比較できる。
+    fn record(&self, samples: &[f32]) -> f32 {
+        let rms = rms(samples);
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+        self.peak
+            .fetch_max(peak_amplitude(samples).to_bits(), Ordering::Relaxed);
+        rms
+    }
+
+    /// 現在のレベルを読み出し、ピークをリセットする
+    fn take(&self) -> AudioLevelEvent {
+        AudioLevelEvent {
+            rms: f32::from_bits(self.rms.load(Ordering::Relaxed)),
+            peak: f32::from_bits(self.peak.swap(0, Ordering::Relaxed)),
+        }
+    }
+
+    fn reset(&self) {
+        self.rms.store(0, Ordering::Relaxed);
+        self.peak.store(0, Ordering::Relaxed);
+    }
 }
 
+/// 録音コールバックからストリーミング文字起こしへサンプルを流すための送信口
+type StreamFeed = Arc<Mutex<Option<tokio::sync::mpsc::Sender<Vec<f32>>>>>;
+
 /// 録音状態を管理する Tauri State
 pub struct AudioState {
     inner: Mutex<AudioInner>,
@@ -49,6 +265,30 @@ struct AudioInner {
     stop_tx: Option<mpsc::Sender<()>>,
     sample_rate: u32,
     channels: u16,
+    /// ストリーミング文字起こし中のみ Some
+    stream_feed: StreamFeed,
+    /// ストリーミング文字起こしのタスク（録音の中止時に abort する）
+    stream_tasks: Vec<tauri::async_runtime::JoinHandle<()>>,
+    /// 直前の録音のフィンガープリント（PTT の二度押し検出用）
+    last_fingerprint: Option<AudioFingerprint>,
+    /// 録音開始時の単調時刻と UNIX 時刻（停止時刻は経過時間から求める）
+    started_at: Option<(Instant, u64)>,
+    device_name: String,
+    sample_format: String,
+    /// `start_recording` で名前指定されたデバイス（指定時のみ再接続を監視する）
+    requested_device: Option<String>,
+    /// 最後に RMS が 0 でないサンプルを受け取った UNIX 時刻（ms）
+    last_active_unix_ms: Arc<AtomicU64>,
+    /// 録音コールバックが更新する入力レベル
+    level: Arc<LevelMeter>,
+    /// 無音による自動停止の設定（None なら無効）
+    auto_stop: Option<AutoStopConfig>,
+    /// 録音コールバックが無音で自動停止したら true
+    auto_stopped: Arc<AtomicBool>,
+    /// 録音ごとに増やし、前の録音の監視スレッドを止める
+    recording_generation: u64,
+    /// エコーキャンセルの設定とスピーカー出力（`with_echo_cancellation` で有効化）
+    echo_cancellation: Option<(EchoCanceller, Arc<Mutex<Vec<f32>>>)>,
 }
 
 impl AudioState {
@@ -60,9 +300,86 @@ impl AudioState {
                 stop_tx: None,
                 sample_rate: 0,
                 channels: 0,
+                stream_feed: Arc::new(Mutex::new(None)),
+                stream_tasks: Vec::new(),
+                last_fingerprint: None,
+                started_at: None,
+                device_name: String::new(),
+                sample_format: String::new(),
+                requested_device: None,
+                last_active_unix_ms: Arc::new(AtomicU64::new(0)),
+                level: Arc::new(LevelMeter::default()),
+                auto_stop: None,
+                auto_stopped: Arc::new(AtomicBool::new(false)),
+                recording_generation: 0,
+                echo_cancellation: None,
             }),
         }
     }
+
+    /// 録音停止時にスピーカー出力のエコーを差し引くようにする
+    ///
+    /// `speaker_feed` には再生側が録音と同じサンプルレート・チャンネル配置の
+    /// サンプルを追記する。録音開始時に空にし、停止時に取り出して使う。
+    pub fn with_echo_cancellation(
+        mut self,
+        config: EchoCanceller,
+        speaker_feed: Arc<Mutex<Vec<f32>>>,
+    ) -> Self {
+        if let Ok(inner) = self.inner.get_mut() {
+            inner.echo_cancellation = Some((config, speaker_feed));
+        }
+        self
+    }
+}
+
+/// 録音コールバックからストリーミング文字起こしへサンプルを送る
+///
+/// オーディオコールバックをブロックしないよう try_lock / try_send のみ使う。
+fn feed_stream(feed: &StreamFeed, samples: &[f32]) {
+    if let Ok(guard) = feed.try_lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.try_send(samples.to_vec());
+        }
+    }
+}
+
+fn unix_ms_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// 録音冒頭 `NOISE_FLOOR_WINDOW_MS` のインターリーブ済みサンプルの RMS
+fn noise_floor_rms(samples: &[f32], sample_rate: u32, channels: u16) -> f32 {
+    let window = (sample_rate as u64 * channels as u64 * NOISE_FLOOR_WINDOW_MS / 1000) as usize;
+    rms(&samples[..samples.len().min(window)])
+}
+
+/// 変換前の生サンプル（インターリーブ済み）から録音メトリクスを算出する
+fn compute_audio_metrics(
+    session_id: &str,
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    duration_ms: u64,
+) -> AudioMetrics {
+    AudioMetrics {
+        session_id: session_id.to_string(),
+        duration_ms,
+        sample_rate,
+        channels,
+        noise_floor_rms: noise_floor_rms(samples, sample_rate, channels),
+        peak_amplitude: peak_amplitude(samples),
+        vad_speech_ratio: speech_ratio(samples, sample_rate, channels),
+    }
+}
+
+/// 録音バッファに上限までサンプルを追加する
+fn append_capped(buffer: &mut Vec<f32>, samples: &[f32], max_samples: usize) {
+    let room = max_samples.saturating_sub(buffer.len());
+    buffer.extend_from_slice(&samples[..samples.len().min(room)]);
 }
 
 /// 音声データを文字起こしする
@@ -72,105 +389,359 @@ impl AudioState {
 ///
 /// # Arguments
 /// * `engine` - 認識エンジン: "native"（macOS Speech Framework）/ "whisper"（OpenAI API）
-///              省略時は "native"
+///              / "realtime"（OpenAI Realtime API）/ "whisper_cpp"（ローカルの whisper.cpp、
+///              `whisper-cpp` feature 有効時のみ）。省略時は `WHISPER_BACKEND`（デフォルト "native"）
+/// * `language` - 言語コード（例: "ja", "en"。native は "ja-JP" などのロケール）。
+///   省略時はエンジンが判定し、判定結果は `language_detected` に入る（native は "ja-JP" で認識する）
+///
+/// 呼び出しごとにリクエストIDを発行し、結果とエラーの両方に含める。
 #[tauri::command]
 pub async fn transcribe_audio(
+    config: State<'_, AppConfig>,
+    audio_data: Vec<u8>,
+    sample_rate: u32,
+    channels: u16,
+    engine: Option<String>,
+    language: Option<String>,
+) -> TaprResult<TranscriptionResult> {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    eprintln!(
+        "[audio] request_id={} transcribe start ({} bytes)",
+        request_id,
+        audio_data.len()
+    );
+
+    let backend = engine
+        .clone()
+        .unwrap_or_else(|| config.whisper_backend.as_str().to_string());
+    match run_transcribe_audio(&config, audio_data, sample_rate, channels, engine, language).await {
+        Ok(mut result) => {
+            eprintln!("[audio] request_id={} transcribe done", request_id);
+            record_transcription(&backend, result.language_detected.as_deref());
+            result.request_id = request_id;
+            Ok(result)
+        }
+        Err(e) => {
+            eprintln!("[audio] request_id={} transcribe failed: {}", request_id, e);
+            Err(e.with_request_id(&request_id))
+        }
+    }
+}
+
+async fn run_transcribe_audio(
+    config: &AppConfig,
     audio_data: Vec<u8>,
     sample_rate: u32,
     channels: u16,
     engine: Option<String>,
-) -> Result<TranscriptionResult, AppError> {
-    let wav_data = pcm_bytes_to_wav(&audio_data, sample_rate, channels)
+    language: Option<String>,
+) -> TaprResult<TranscriptionResult> {
+    let wav_data = pcm_bytes_to_whisper_wav(&audio_data, sample_rate, channels)
         .map_err(|e| AppError::Audio(e.to_string()))?;
+    let timeout = Duration::from_secs(config.whisper_timeout_secs);
 
-    match engine.as_deref().unwrap_or("native") {
+    match engine.as_deref().unwrap_or(config.whisper_backend.as_str()) {
         #[cfg(target_os = "macos")]
         "native" => {
             use crate::voice::macos_speech::MacOSSpeechRecognizer;
             let recognizer = MacOSSpeechRecognizer::new("ja-JP")
                 .map_err(|e| AppError::Audio(e.to_string()))?;
-            let result = recognizer
-                .transcribe(&wav_data, "ja-JP")
-                .await
+            transcribe_with_timeout(&recognizer, &wav_data, language.as_deref(), timeout).await
+        }
+        "realtime" => {
+            let client = OpenAIRealtimeClient::from_config(config)
                 .map_err(|e| AppError::Audio(e.to_string()))?;
-            Ok(result.into())
+            transcribe_with_timeout(&client, &wav_data, language.as_deref(), timeout).await
         }
         "whisper" => {
-            let client = WhisperApiClient::from_env()
-                .map_err(|e| AppError::Audio(e.to_string()))?;
-            let result = client
-                .transcribe(&wav_data, "ja")
+            let client = WhisperApiClient::from_config(config)
+                .map_err(|e| AppError::Audio(e.to_string()))?
+                .with_detect_language(true);
+            transcribe_with_timeout(&client, &wav_data, language.as_deref(), timeout).await
+        }
+        #[cfg(feature = "whisper-cpp")]
+        "whisper_cpp" => {
+            use crate::voice::whisper_cpp::WhisperCppRecognizer;
+            let recognizer = WhisperCppRecognizer::load(config)
                 .await
                 .map_err(|e| AppError::Audio(e.to_string()))?;
-            Ok(result.into())
+            transcribe_with_timeout(&recognizer, &wav_data, language.as_deref(), timeout).await
         }
         other => Err(AppError::Audio(format!("Unknown engine: {}", other))),
     }
 }
 
+/// 認識エンジンの応答を `timeout`（WHISPER_TIMEOUT）まで待つ
+pub(crate) async fn transcribe_with_timeout(
+    recognizer: &dyn SpeechRecognizer,
+    wav_data: &[u8],
+    language: Option<&str>,
+    timeout: Duration,
+) -> TaprResult<TranscriptionResult> {
+    let result = tokio::time::timeout(timeout, recognizer.transcribe(wav_data, language))
+        .await
+        .map_err(|_| {
+            AppError::Audio(format!(
+                "Transcription timed out after {} seconds",
+                timeout.as_secs()
+            ))
+        })?
+        .map_err(|e| AppError::Audio(e.to_string()))?;
+    Ok(result.into())
+}
+
 /// マイクからの録音を開始する
 ///
-/// cpal でデフォルト入力デバイスを取得し、専用スレッドで音声データを
+/// cpal で入力デバイスを取得し、専用スレッドで音声データを
 /// バッファに蓄積する。録音スレッドとの同期は mpsc チャンネルで行う。
+/// 空き容量が `MIN_FREE_DISK_BYTES`（デフォルト100MB）未満の場合は開始しない。
+/// `MAX_RECORDING_SECS` を超えた分のサンプルは捨てる。
+///
+/// # Arguments
+/// * `device_name` - 入力デバイス名。省略時はデフォルト入力デバイス。
+///   指定した場合は2秒ごとにデバイス一覧を確認し、切断時は `audio-device-disconnected`、
+///   デバイスが戻ってストリームを張り直したら `audio-device-reconnected` を送る
+///
+/// 録音中は `AUDIO_LEVEL_INTERVAL`（50ms）ごとに入力レベルを `audio-level` で送る。
+/// `set_auto_stop` で有効にしている場合、無音が続くと録音スレッドを止めて `auto-stop` を送る。
+/// バッファは残るので、フロントエンドは `stop_recording` などで録音を確定させる。
 #[tauri::command]
-pub fn start_recording(state: State<'_, AudioState>) -> Result<(), AppError> {
-    let mut inner = state
-        .inner
-        .lock()
-        .map_err(|_| AppError::Audio("State lock poisoned".into()))?;
+pub fn start_recording(
+    app: AppHandle,
+    state: State<'_, AudioState>,
+    config: State<'_, AppConfig>,
+    device_name: Option<String>,
+) -> TaprResult<()> {
+    let mut inner = state.inner.lock()?;
 
     if inner.is_recording {
         return Err(AppError::Audio("Already recording".into()));
     }
 
-    // デフォルト入力デバイスと設定を取得
+    // 長時間録音でディスクが埋まり WAV が壊れるのを防ぐ
+    if available_disk_bytes(&config)? < config.min_free_disk_bytes {
+        return Err(AppError::Audio("insufficient disk space".into()));
+    }
+
+    // 入力デバイスと設定を取得
     let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .ok_or_else(|| AppError::Audio("No input device available".into()))?;
+    let device = match &device_name {
+        Some(name) => find_input_device(&host, name).ok_or_else(|| {
+            AppError::Audio(format!(
+                "Input device not found: {} (available: {})",
+                name,
+                input_device_names(&host).join(", ")
+            ))
+        })?,
+        None => host
+            .default_input_device()
+            .ok_or_else(|| AppError::Audio("No input device available".into()))?,
+    };
     let supported_config = device
         .default_input_config()
         .map_err(|e| AppError::Audio(format!("Failed to get input config: {}", e)))?;
 
+    let actual_device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
     let sample_rate = supported_config.sample_rate().0;
     let channels = supported_config.channels();
     let sample_format = supported_config.sample_format();
-    let stream_config: cpal::StreamConfig = supported_config.into();
+    let max_samples = (config.max_recording_secs * sample_rate as u64 * channels as u64) as usize;
 
     let buffer = Arc::new(Mutex::new(Vec::<f32>::new()));
-    let buffer_for_thread = Arc::clone(&buffer);
+    inner
+        .last_active_unix_ms
+        .store(unix_ms_now(), Ordering::Relaxed);
+    inner.level.reset();
+    inner.auto_stopped.store(false, Ordering::Relaxed);
+    let stop_tx = spawn_capture_stream(
+        device,
+        supported_config,
+        Arc::clone(&buffer),
+        Arc::clone(&inner.stream_feed),
+        Arc::clone(&inner.last_active_unix_ms),
+        Arc::clone(&inner.level),
+        inner.auto_stop,
+        Arc::clone(&inner.auto_stopped),
+        max_samples,
+    )?;
+
+    // 録音開始より前のスピーカー出力は参照信号として使えない
+    if let Some((_, speaker_feed)) = &inner.echo_cancellation {
+        speaker_feed.lock()?.clear();
+    }
+    inner.buffer = buffer;
+    inner.stop_tx = Some(stop_tx);
+    inner.sample_rate = sample_rate;
+    inner.channels = channels;
+    inner.started_at = Some((Instant::now(), unix_ms_now()));
+    inner.device_name = actual_device_name;
+    inner.sample_format = format!("{:?}", sample_format);
+    inner.requested_device = device_name;
+    inner.recording_generation += 1;
+    inner.is_recording = true;
+
+    spawn_level_emitter(app.clone(), inner.recording_generation);
+    if let Some(name) = inner.requested_device.clone() {
+        spawn_device_watchdog(
+            app,
+            name,
+            inner.recording_generation,
+            config.max_recording_secs,
+        );
+    }
+    Ok(())
+}
+
+/// 無音による自動停止を設定する（デフォルトは無効）
+///
+/// 次の `start_recording` から反映される。録音開始から1秒間は無音でも停止しない。
+///
+/// # Arguments
+/// * `silence_ms` - RMS が `threshold` 未満のままこの時間続いたら停止する
+/// * `threshold` - 無音とみなす RMS の上限（0.0〜1.0）
+#[tauri::command]
+pub fn set_auto_stop(
+    state: State<'_, AudioState>,
+    enabled: bool,
+    silence_ms: u64,
+    threshold: f32,
+) -> TaprResult<()> {
+    let auto_stop = auto_stop_config(enabled, silence_ms, threshold)?;
+    state.inner.lock()?.auto_stop = auto_stop;
+    Ok(())
+}
+
+/// `set_auto_stop` の引数を検証して設定にする
+fn auto_stop_config(
+    enabled: bool,
+    silence_ms: u64,
+    threshold: f32,
+) -> TaprResult<Option<AutoStopConfig>> {
+    if !enabled {
+        return Ok(None);
+    }
+    if silence_ms == 0 {
+        return Err(AppError::Audio(
+            "Auto-stop silence_ms must be greater than 0".into(),
+        ));
+    }
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(AppError::Audio(format!(
+            "Auto-stop threshold must be between 0.0 and 1.0: {}",
+            threshold
+        )));
+    }
+    Ok(Some(AutoStopConfig {
+        silence_ms,
+        threshold,
+    }))
+}
+
+/// 接続されている入力デバイスの一覧を返す
+///
+/// `name` を `start_recording` の `device_name` に渡すと、そのデバイスで録音する。
+#[tauri::command]
+pub fn list_input_devices() -> Vec<AudioDeviceInfo> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    device_infos(input_device_names(&host), default_name.as_deref())
+}
+
+/// デバイス名の一覧に、デフォルト入力デバイスかどうかを付ける
+fn device_infos(names: Vec<String>, default_name: Option<&str>) -> Vec<AudioDeviceInfo> {
+    names
+        .into_iter()
+        .map(|name| AudioDeviceInfo {
+            is_default: default_name == Some(name.as_str()),
+            name,
+        })
+        .collect()
+}
+
+/// 名前が一致する入力デバイスを探す
+fn find_input_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    host.input_devices()
+        .ok()?
+        .find(|d| d.name().is_ok_and(|n| n == name))
+}
+
+/// 現在接続されている入力デバイス名の一覧
+fn input_device_names(host: &cpal::Host) -> Vec<String> {
+    host.input_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// 入力ストリームを専用スレッドで開始し、停止用の送信口を返す
+///
+/// サンプルは `buffer` に追記するので、再接続時に同じバッファを渡せば
+/// それまでのサンプルを残したまま録音を続けられる。
+/// RMS が 0 でないサンプルを受け取るたびに `last_active_unix_ms` を更新し、
+/// コールバックごとのレベルを `level` に書き込む。
+/// `auto_stop` が Some なら無音の継続を数え、閾値を超えたら `auto_stopped` を立てて自ら停止する。
+#[allow(clippy::too_many_arguments)]
+fn spawn_capture_stream(
+    device: cpal::Device,
+    supported_config: cpal::SupportedStreamConfig,
+    buffer: Arc<Mutex<Vec<f32>>>,
+    feed: StreamFeed,
+    last_active_unix_ms: Arc<AtomicU64>,
+    level: Arc<LevelMeter>,
+    auto_stop: Option<AutoStopConfig>,
+    auto_stopped: Arc<AtomicBool>,
+    max_samples: usize,
+) -> TaprResult<mpsc::Sender<()>> {
+    let sample_format = supported_config.sample_format();
+    let stream_config: cpal::StreamConfig = supported_config.into();
     let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let auto_stop_tx = stop_tx.clone();
+    let mut silence = auto_stop.map(|config| {
+        SilenceTracker::new(config, stream_config.sample_rate.0, stream_config.channels)
+    });
     let (ready_tx, ready_rx) = mpsc::sync_channel::<Result<(), String>>(1);
 
     // 録音スレッド: cpal::Stream を保持し、stop シグナルで終了
     thread::spawn(move || {
         let build_result = match sample_format {
-            cpal::SampleFormat::F32 => {
-                let buf = Arc::clone(&buffer_for_thread);
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        if let Ok(mut b) = buf.lock() {
-                            b.extend_from_slice(data);
-                        }
-                    },
-                    |err| eprintln!("Audio stream error: {}", err),
-                    None,
-                )
-            }
-            cpal::SampleFormat::I16 => {
-                let buf = Arc::clone(&buffer_for_thread);
-                device.build_input_stream(
-                    &stream_config,
-                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                        if let Ok(mut b) = buf.lock() {
-                            b.extend(data.iter().map(|&s| s as f32 / 32768.0));
-                        }
-                    },
-                    |err| eprintln!("Audio stream error: {}", err),
-                    None,
-                )
-            }
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &stream_config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    let rms = level.record(data);
+                    if rms > 0.0 {
+                        last_active_unix_ms.store(unix_ms_now(), Ordering::Relaxed);
+                    }
+                    if let Ok(mut b) = buffer.lock() {
+                        append_capped(&mut b, data, max_samples);
+                    }
+                    feed_stream(&feed, data);
+                    if silence.as_mut().is_some_and(|s| s.push(data.len(), rms)) {
+                        auto_stopped.store(true, Ordering::Relaxed);
+                        let _ = auto_stop_tx.send(());
+                    }
+                },
+                |err| eprintln!("Audio stream error: {}", err),
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &stream_config,
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    let samples: Vec<f32> = data.iter().map(|&s| s as f32 / 32768.0).collect();
+                    let rms = level.record(&samples);
+                    if rms > 0.0 {
+                        last_active_unix_ms.store(unix_ms_now(), Ordering::Relaxed);
+                    }
+                    if let Ok(mut b) = buffer.lock() {
+                        append_capped(&mut b, &samples, max_samples);
+                    }
+                    feed_stream(&feed, &samples);
+                    if silence.as_mut().is_some_and(|s| s.push(samples.len(), rms)) {
+                        auto_stopped.store(true, Ordering::Relaxed);
+                        let _ = auto_stop_tx.send(());
+                    }
+                },
+                |err| eprintln!("Audio stream error: {}", err),
+                None,
+            ),
             _ => {
                 let _ = ready_tx.send(Err(format!(
                     "Unsupported sample format: {:?}",
@@ -198,29 +769,372 @@ pub fn start_recording(state: State<'_, AudioState>) -> Result<(), AppError> {
 
     // 録音スレッドの準備完了を待機（タイムアウト5秒）
     match ready_rx.recv_timeout(Duration::from_secs(5)) {
-        Ok(Ok(())) => {
-            inner.buffer = buffer;
-            inner.stop_tx = Some(stop_tx);
-            inner.sample_rate = sample_rate;
-            inner.channels = channels;
-            inner.is_recording = true;
-            Ok(())
-        }
+        Ok(Ok(())) => Ok(stop_tx),
         Ok(Err(e)) => Err(AppError::Audio(e)),
         Err(_) => Err(AppError::Audio("Recording thread timed out".into())),
     }
 }
 
+/// 監視スレッドが取るべき動作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchdogAction {
+    None,
+    /// デバイスが一覧から消えた（切断ごとに1回だけ返す）
+    Disconnected,
+    /// デバイスはあるがストリームが止まっているので張り直す
+    Reconnect,
+}
+
+/// 名前指定した入力デバイスの切断と再接続を判定する
+///
+/// デバイス名の一覧と無音の継続時間だけから判定するので、cpal なしでテストできる。
+struct DeviceWatchdog {
+    device_name: String,
+    connected: bool,
+}
+
+impl DeviceWatchdog {
+    fn new(device_name: impl Into<String>) -> Self {
+        Self {
+            device_name: device_name.into(),
+            connected: true,
+        }
+    }
+
+    fn poll(&mut self, available: &[String], silent_for: Duration) -> WatchdogAction {
+        if !available.iter().any(|name| *name == self.device_name) {
+            let was_connected = std::mem::replace(&mut self.connected, false);
+            return if was_connected {
+                WatchdogAction::Disconnected
+            } else {
+                WatchdogAction::None
+            };
+        }
+        self.connected = true;
+        if silent_for >= DEAD_STREAM_SILENCE {
+            WatchdogAction::Reconnect
+        } else {
+            WatchdogAction::None
+        }
+    }
+}
+
+/// 名前指定したデバイスを録音中に監視するスレッドを起動する
+///
+/// 録音が止まるか次の録音が始まると終了する。
+fn spawn_device_watchdog(
+    app: AppHandle,
+    device_name: String,
+    generation: u64,
+    max_recording_secs: u64,
+) {
+    thread::spawn(move || {
+        let mut watchdog = DeviceWatchdog::new(device_name.clone());
+        loop {
+            thread::sleep(DEVICE_WATCHDOG_INTERVAL);
+            let host = cpal::default_host();
+            let available = input_device_names(&host);
+
+            let state = app.state::<AudioState>();
+            let Ok(mut inner) = state.inner.lock() else {
+                return;
+            };
+            // 無音で自動停止したストリームを張り直さない
+            if !inner.is_recording
+                || inner.recording_generation != generation
+                || inner.auto_stopped.load(Ordering::Relaxed)
+            {
+                return;
+            }
+            let last_active = inner.last_active_unix_ms.load(Ordering::Relaxed);
+            let silent_for = Duration::from_millis(unix_ms_now().saturating_sub(last_active));
+
+            match watchdog.poll(&available, silent_for) {
+                WatchdogAction::None => {}
+                WatchdogAction::Disconnected => {
+                    eprintln!("[audio] input device disconnected: {}", device_name);
+                    let _ = app.emit(
+                        "audio-device-disconnected",
+                        AudioDeviceDisconnectedEvent {
+                            device_name: device_name.clone(),
+                        },
+                    );
+                }
+                WatchdogAction::Reconnect => {
+                    match reconnect_stream(&mut inner, &host, &device_name, max_recording_secs) {
+                        Ok(()) => {
+                            eprintln!("[audio] input device reconnected: {}", device_name);
+                            let _ = app.emit(
+                                "audio-device-reconnected",
+                                AudioDeviceReconnectedEvent {
+                                    device_name: device_name.clone(),
+                                    buffered_samples_preserved: true,
+                                },
+                            );
+                        }
+                        Err(e) => eprintln!("[audio] failed to reconnect {}: {}", device_name, e),
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// 録音中の入力レベルを `AUDIO_LEVEL_INTERVAL` ごとに `audio-level` で送るスレッドを起動する
+///
+/// 無音で自動停止していたら `auto-stop` を送って終了する。
+/// 録音が止まるか次の録音が始まった場合も終了する。
+fn spawn_level_emitter(app: AppHandle, generation: u64) {
+    thread::spawn(move || loop {
+        thread::sleep(AUDIO_LEVEL_INTERVAL);
+        let state = app.state::<AudioState>();
+        let (level, auto_stopped) = {
+            let Ok(inner) = state.inner.lock() else {
+                return;
+            };
+            if !inner.is_recording || inner.recording_generation != generation {
+                return;
+            }
+            (
+                Arc::clone(&inner.level),
+                inner.auto_stopped.load(Ordering::Relaxed),
+            )
+        };
+        if auto_stopped {
+            eprintln!("[audio] recording auto-stopped after silence");
+            let _ = app.emit("auto-stop", ());
+            return;
+        }
+        let _ = app.emit("audio-level", level.take());
+    });
+}
+
+/// 止まったストリームを破棄し、同じ名前のデバイスで録音を再開する
+///
+/// 切断前と同じサンプルレート・チャンネル数で開ければ既存のバッファに追記を続ける。
+/// 開けなければデバイスのデフォルト設定で開き直し、フォーマットが混在しないよう
+/// 既存のバッファを新しいサンプルレート・チャンネル数に変換してから追記する。
+fn reconnect_stream(
+    inner: &mut AudioInner,
+    host: &cpal::Host,
+    device_name: &str,
+    max_recording_secs: u64,
+) -> TaprResult<()> {
+    let device = find_input_device(host, device_name)
+        .ok_or_else(|| AppError::Audio(format!("Input device not found: {}", device_name)))?;
+
+    if let Some(tx) = inner.stop_tx.take() {
+        let _ = tx.send(());
+    }
+
+    let (supported_config, same_format) =
+        match matching_input_config(&device, inner.sample_rate, inner.channels) {
+            Some(config) => (config, true),
+            None => (
+                device
+                    .default_input_config()
+                    .map_err(|e| AppError::Audio(format!("Failed to get input config: {}", e)))?,
+                false,
+            ),
+        };
+    let sample_rate = supported_config.sample_rate().0;
+    let channels = supported_config.channels();
+    let sample_format = supported_config.sample_format();
+    let max_samples = (max_recording_secs * sample_rate as u64 * channels as u64) as usize;
+
+    if !same_format {
+        let mut buffer = inner.buffer.lock()?;
+        let mut converted = convert_interleaved(
+            &buffer,
+            inner.sample_rate,
+            inner.channels,
+            sample_rate,
+            channels,
+        );
+        converted.truncate(max_samples);
+        *buffer = converted;
+        drop(buffer);
+        // ストリーミング文字起こしは開始時のフォーマットで変換しているので止める
+        *inner.stream_feed.lock()? = None;
+    }
+    inner
+        .last_active_unix_ms
+        .store(unix_ms_now(), Ordering::Relaxed);
+    let stop_tx = spawn_capture_stream(
+        device,
+        supported_config,
+        Arc::clone(&inner.buffer),
+        Arc::clone(&inner.stream_feed),
+        Arc::clone(&inner.last_active_unix_ms),
+        Arc::clone(&inner.level),
+        inner.auto_stop,
+        Arc::clone(&inner.auto_stopped),
+        max_samples,
+    )?;
+
+    inner.stop_tx = Some(stop_tx);
+    inner.sample_rate = sample_rate;
+    inner.channels = channels;
+    inner.sample_format = format!("{:?}", sample_format);
+    Ok(())
+}
+
+/// 指定のサンプルレート・チャンネル数で開ける入力設定を探す
+fn matching_input_config(
+    device: &cpal::Device,
+    sample_rate: u32,
+    channels: u16,
+) -> Option<cpal::SupportedStreamConfig> {
+    device
+        .supported_input_configs()
+        .ok()?
+        .find(|range| {
+            range.channels() == channels
+                && range.min_sample_rate().0 <= sample_rate
+                && sample_rate <= range.max_sample_rate().0
+                && matches!(
+                    range.sample_format(),
+                    cpal::SampleFormat::F32 | cpal::SampleFormat::I16
+                )
+        })
+        .map(|range| range.with_sample_rate(cpal::SampleRate(sample_rate)))
+}
+
 /// 録音を停止して音声データを返す
 ///
 /// 録音スレッドに停止シグナルを送り、バッファの f32 サンプルを
 /// i16 PCM (little-endian) バイト列に変換して返す。
 #[tauri::command]
-pub fn stop_recording(state: State<'_, AudioState>) -> Result<RecordingResult, AppError> {
-    let mut inner = state
-        .inner
-        .lock()
-        .map_err(|_| AppError::Audio("State lock poisoned".into()))?;
+pub fn stop_recording(
+    state: State<'_, AudioState>,
+    db: State<'_, DbState>,
+) -> TaprResult<RecordingResult> {
+    finish_recording(&state, &db)
+}
+
+/// 録音を中止し、バッファを捨てる
+///
+/// 言い間違えたときなど、文字起こしせずに録音をやめるために使う。
+/// `stop_recording` と違い、録音中でなければ何もせず `Ok` を返す。
+#[tauri::command]
+pub fn cancel_recording(state: State<'_, AudioState>) -> TaprResult<()> {
+    let mut inner = state.inner.lock()?;
+    if discard_recording(&mut inner)? {
+        eprintln!("[audio] recording cancelled");
+    }
+    Ok(())
+}
+
+/// 録音スレッドを止めてバッファを捨てる。録音中でなければ false
+///
+/// ストリーミング文字起こしは送信口を閉じると残りを最終チャンクとして送ってしまうので、
+/// 先にタスクを abort してから閉じる。
+fn discard_recording(inner: &mut AudioInner) -> TaprResult<bool> {
+    if !inner.is_recording {
+        return Ok(false);
+    }
+    if let Some(tx) = inner.stop_tx.take() {
+        let _ = tx.send(());
+    }
+    inner.is_recording = false;
+    inner.requested_device = None;
+    inner.started_at = None;
+    for task in inner.stream_tasks.drain(..) {
+        task.abort();
+    }
+    *inner.stream_feed.lock()? = None;
+    drop(std::mem::take(&mut *inner.buffer.lock()?));
+    if let Some((_, speaker_feed)) = &inner.echo_cancellation {
+        speaker_feed.lock()?.clear();
+    }
+    Ok(true)
+}
+
+/// 録音を停止して WAV に変換し、Whisper API で文字起こしした結果だけを返す
+///
+/// `stop_recording` → `transcribe_audio` の2往復を1回にまとめ、
+/// 音声データを IPC でやり取りしない。生の音声が必要な場合は従来の2コマンドを使う。
+/// 呼び出しごとにリクエストIDを発行し、結果とエラーの両方に含める。
+/// `language` を省略すると Whisper が言語を判定する。
+/// 直前と同じ音声なら文字起こしせず、`duplicate_skipped` を立てた空の結果を返す。
+#[tauri::command]
+pub async fn stop_recording_and_transcribe(
+    state: State<'_, AudioState>,
+    db: State<'_, DbState>,
+    config: State<'_, AppConfig>,
+    language: Option<String>,
+) -> TaprResult<TranscriptionResult> {
+    let recording = finish_recording(&state, &db)?;
+    let request_id = uuid::Uuid::new_v4().to_string();
+    eprintln!(
+        "[audio] request_id={} stop and transcribe ({} bytes)",
+        request_id,
+        recording.audio_data.len()
+    );
+
+    let timeout = Duration::from_secs(config.whisper_timeout_secs);
+    let result = match WhisperApiClient::from_config(&config) {
+        Ok(client) => {
+            let client = client.with_detect_language(true);
+            transcribe_recording(&client, &recording, language.as_deref(), timeout).await
+        }
+        Err(e) => Err(AppError::Audio(e.to_string())),
+    };
+    match result {
+        Ok(mut result) => {
+            if result.duplicate_skipped {
+                eprintln!(
+                    "[audio] request_id={} duplicate recording, transcription skipped",
+                    request_id
+                );
+            } else {
+                eprintln!("[audio] request_id={} transcribe done", request_id);
+                record_transcription("whisper", result.language_detected.as_deref());
+            }
+            result.request_id = request_id;
+            Ok(result)
+        }
+        Err(e) => {
+            eprintln!("[audio] request_id={} transcribe failed: {}", request_id, e);
+            Err(e.with_request_id(&request_id))
+        }
+    }
+}
+
+/// 録音結果の PCM を 16kHz モノラルの WAV に変換して文字起こしする
+///
+/// 直前の録音と重複している場合（PTT の二度押しなど）は認識エンジンを呼ばずに空の結果を返す。
+async fn transcribe_recording(
+    recognizer: &dyn SpeechRecognizer,
+    recording: &RecordingResult,
+    language: Option<&str>,
+    timeout: Duration,
+) -> TaprResult<TranscriptionResult> {
+    if recording.duplicate_detected {
+        return Ok(TranscriptionResult {
+            text: String::new(),
+            confidence: 1.0,
+            is_final: true,
+            timestamp: 0,
+            request_id: String::new(),
+            language_detected: None,
+            used_fallback: false,
+            segments: None,
+            duplicate_skipped: true,
+        });
+    }
+    let wav_data = pcm_bytes_to_whisper_wav(
+        &recording.audio_data,
+        recording.sample_rate,
+        recording.channels,
+    )
+    .map_err(|e| AppError::Audio(e.to_string()))?;
+    transcribe_with_timeout(recognizer, &wav_data, language, timeout).await
+}
+
+/// 録音スレッドを止めてバッファを PCM に変換し、メトリクスを保存する
+fn finish_recording(state: &AudioState, db: &DbState) -> TaprResult<RecordingResult> {
+    let mut inner = state.inner.lock()?;
 
     if !inner.is_recording {
         return Err(AppError::Audio("Not recording".into()));
@@ -230,17 +1144,29 @@ pub fn stop_recording(state: State<'_, AudioState>) -> Result<RecordingResult, A
         let _ = tx.send(());
     }
     inner.is_recording = false;
+    inner.requested_device = None;
+    let (started_at_unix_ms, stopped_at_unix_ms) = match inner.started_at.take() {
+        Some((instant, unix_ms)) => (unix_ms, unix_ms + instant.elapsed().as_millis() as u64),
+        None => (0, unix_ms_now()),
+    };
+
+    // ストリーミング文字起こし中なら送信口を閉じて残りを確定させる
+    if let Ok(mut feed) = inner.stream_feed.lock() {
+        *feed = None;
+    }
+    inner.stream_tasks.clear();
 
     // ストリーム終了の猶予
     thread::sleep(Duration::from_millis(100));
 
-    let samples = {
-        let mut buf = inner
-            .buffer
-            .lock()
-            .map_err(|_| AppError::Audio("Buffer lock poisoned".into()))?;
+    let mut samples = {
+        let mut buf = inner.buffer.lock()?;
         std::mem::take(&mut *buf)
     };
+    if let Some((canceller, speaker_feed)) = &inner.echo_cancellation {
+        let speaker = std::mem::take(&mut *speaker_feed.lock()?);
+        samples = apply_echo_cancellation(&samples, &speaker, canceller);
+    }
 
     let sample_rate = inner.sample_rate;
     let channels = inner.channels;
@@ -258,10 +1184,751 @@ pub fn stop_recording(state: State<'_, AudioState>) -> Result<RecordingResult, A
         0
     };
 
+    // 直前の録音とほぼ同一なら重複として通知する
+    let fingerprint = fingerprint_audio(
+        &to_whisper_mono(&samples, sample_rate, channels),
+        WHISPER_SAMPLE_RATE,
+    );
+    let duplicate_detected = inner
+        .last_fingerprint
+        .is_some_and(|prev| prev.hamming_distance(&fingerprint) < DUPLICATE_HAMMING_THRESHOLD);
+    inner.last_fingerprint = Some(fingerprint);
+    if duplicate_detected {
+        eprintln!("[audio] duplicate recording detected");
+    }
+    record_recording_duration(duration_ms);
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let metrics = compute_audio_metrics(&session_id, &samples, sample_rate, channels, duration_ms);
+    // メトリクスの保存に失敗しても録音結果は返す
+    if let Err(e) = db
+        .conn()
+        .and_then(|conn| repository::insert_recording_metrics(&conn, &metrics))
+    {
+        eprintln!("[audio] failed to save recording metrics: {}", e);
+    }
+
+    let meta = RecordingMeta {
+        session_id,
+        started_at_unix_ms,
+        stopped_at_unix_ms,
+        device_name: inner.device_name.clone(),
+        sample_format: inner.sample_format.clone(),
+        noise_floor_rms: metrics.noise_floor_rms,
+    };
+
     Ok(RecordingResult {
         audio_data,
         sample_rate,
         channels,
         duration_ms,
+        duplicate_detected,
+        meta,
+    })
+}
+
+/// 一時停止を挟んだ複数の録音を1つにつなげる
+///
+/// すべての録音のサンプルレートとチャンネル数が一致している必要がある。
+/// `insert_gap` が true なら録音の間に 200ms の無音を挟む。
+#[tauri::command]
+pub fn merge_recordings(
+    recordings: Vec<RecordingData>,
+    insert_gap: bool,
+) -> TaprResult<RecordingResult> {
+    let (audio_data, sample_rate, channels) = merge_pcm(&recordings, insert_gap)?;
+    let samples = pcm_bytes_to_f32(&audio_data).map_err(|e| AppError::Audio(e.to_string()))?;
+    let duration_ms = (samples.len() as u64 * 1000) / (sample_rate as u64 * channels as u64);
+    let stopped_at_unix_ms = unix_ms_now();
+
+    Ok(RecordingResult {
+        duration_ms,
+        duplicate_detected: false,
+        meta: RecordingMeta {
+            session_id: uuid::Uuid::new_v4().to_string(),
+            started_at_unix_ms: stopped_at_unix_ms.saturating_sub(duration_ms),
+            stopped_at_unix_ms,
+            device_name: String::new(),
+            sample_format: "I16".to_string(),
+            noise_floor_rms: noise_floor_rms(&samples, sample_rate, channels),
+        },
+        audio_data,
+        sample_rate,
+        channels,
     })
 }
+
+/// 録音の PCM バイト列を連結し、(PCM, サンプルレート, チャンネル数) を返す
+fn merge_pcm(recordings: &[RecordingData], insert_gap: bool) -> TaprResult<(Vec<u8>, u32, u16)> {
+    let first = recordings
+        .first()
+        .ok_or_else(|| AppError::Audio("no recordings to merge".into()))?;
+    let (sample_rate, channels) = (first.sample_rate, first.channels);
+    if sample_rate == 0 || channels == 0 {
+        return Err(AppError::Audio("invalid recording format".into()));
+    }
+    for recording in recordings {
+        if recording.sample_rate != sample_rate {
+            return Err(AppError::Audio(format!(
+                "sample rate mismatch: {} Hz and {} Hz",
+                sample_rate, recording.sample_rate
+            )));
+        }
+        if recording.channels != channels {
+            return Err(AppError::Audio(format!(
+                "channel count mismatch: {} and {}",
+                channels, recording.channels
+            )));
+        }
+        if recording.audio_data.len() % 2 != 0 {
+            return Err(AppError::Audio(
+                "PCM byte data length must be even (i16 samples)".into(),
+            ));
+        }
+    }
+
+    let gap_samples = (sample_rate as u64 * channels as u64 * MERGE_GAP_MS / 1000) as usize;
+    let mut merged = Vec::new();
+    for (i, recording) in recordings.iter().enumerate() {
+        if insert_gap && i > 0 {
+            merged.extend(vec![0u8; gap_samples * 2]);
+        }
+        merged.extend_from_slice(&recording.audio_data);
+    }
+    Ok((merged, sample_rate, channels))
+}
+
+/// Whisper API が失敗したときに macOS Speech Framework で認識し直す
+///
+/// macOS 以外、または Speech Framework を初期化できない場合はそのまま返す。
+fn with_native_fallback(
+    pipeline: TranscriptionPipeline<WhisperApiClient>,
+) -> TranscriptionPipeline<WhisperApiClient> {
+    #[cfg(target_os = "macos")]
+    {
+        use crate::voice::macos_speech::MacOSSpeechRecognizer;
+        match MacOSSpeechRecognizer::new("ja-JP") {
+            Ok(recognizer) => return pipeline.with_fallback_recognizer(Box::new(recognizer)),
+            Err(e) => eprintln!("[audio] native fallback unavailable: {}", e),
+        }
+    }
+    pipeline
+}
+
+/// 録音中の音声をチャンクごとに逐次文字起こしする
+///
+/// 録音コールバックのサンプルを 16kHz モノラルに変換してパイプラインへ流し、
+/// `chunk_duration_secs` 秒ごとの結果を `streaming-transcription` イベントで通知する。
+/// `WHISPER_BACKEND=realtime` なら 24kHz で Realtime API に送り、発話ごとに通知する。
+/// `language` を省略するとエンジンが言語を判定する。
+/// `start_recording` の後に呼び出す。
+#[tauri::command]
+pub fn start_streaming_transcription(
+    app: AppHandle,
+    state: State<'_, AudioState>,
+    config: State<'_, AppConfig>,
+    mode_id: String,
+    chunk_duration_secs: f32,
+    language: Option<String>,
+) -> TaprResult<()> {
+    if !chunk_duration_secs.is_finite() || chunk_duration_secs <= 0.0 {
+        return Err(AppError::Audio(
+            "chunk_duration_secs must be positive".into(),
+        ));
+    }
+
+    let mut inner = state.inner.lock()?;
+
+    if !inner.is_recording {
+        return Err(AppError::Audio("Not recording".into()));
+    }
+
+    let stream_feed = Arc::clone(&inner.stream_feed);
+    let mut feed = stream_feed.lock()?;
+    if feed.is_some() {
+        return Err(AppError::Audio(
+            "Streaming transcription already running".into(),
+        ));
+    }
+
+    // WHISPER_BACKEND=realtime なら WebSocket で逐次送り、それ以外は Whisper API でチャンクごとに送る
+    let transcriber = match config.whisper_backend {
+        WhisperBackend::Realtime => StreamingTranscriber::Realtime(
+            OpenAIRealtimeClient::from_config(&config)
+                .map_err(|e| AppError::Audio(e.to_string()))?,
+        ),
+        _ => StreamingTranscriber::Chunked(with_native_fallback(
+            TranscriptionPipeline::new(
+                WhisperApiClient::from_config(&config)
+                    .map_err(|e| AppError::Audio(e.to_string()))?
+                    .with_detect_language(true),
+                "ja",
+            )
+            .with_language(language.as_deref()),
+        )),
+    };
+    let target_rate = match transcriber {
+        StreamingTranscriber::Realtime(_) => REALTIME_SAMPLE_RATE,
+        StreamingTranscriber::Chunked(_) => WHISPER_SAMPLE_RATE,
+    };
+
+    let sample_rate = inner.sample_rate;
+    let channels = inner.channels;
+    let (raw_tx, mut raw_rx) = tokio::sync::mpsc::channel::<Vec<f32>>(256);
+    let (pcm_tx, pcm_rx) = tokio::sync::mpsc::channel::<Vec<f32>>(256);
+    let (result_tx, mut result_rx) = tokio::sync::mpsc::channel(16);
+
+    // デバイスのフォーマット → エンジンのサンプリングレートのモノラル
+    let convert_task = tauri::async_runtime::spawn(async move {
+        let mut resampler = StreamResampler::new(sample_rate, channels, target_rate);
+        while let Some(samples) = raw_rx.recv().await {
+            if pcm_tx.send(resampler.process(&samples)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let transcribe_task = tauri::async_runtime::spawn(async move {
+        let result = match transcriber {
+            StreamingTranscriber::Realtime(client) => {
+                client
+                    .transcribe_stream(pcm_rx, result_tx, language.as_deref())
+                    .await
+            }
+            StreamingTranscriber::Chunked(pipeline) => {
+                pipeline
+                    .transcribe_stream(pcm_rx, chunk_duration_secs, result_tx)
+                    .await
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("[audio] streaming transcription failed: {}", e);
+        }
+    });
+
+    let emit_task = tauri::async_runtime::spawn(async move {
+        while let Some(result) = result_rx.recv().await {
+            let _ = app.emit(
+                "streaming-transcription",
+                StreamingTranscriptionEvent {
+                    mode_id: mode_id.clone(),
+                    result: result.into(),
+                },
+            );
+        }
+    });
+
+    *feed = Some(raw_tx);
+    inner.stream_tasks = vec![convert_task, transcribe_task, emit_task];
+    Ok(())
+}
+
+/// ストリーミング文字起こしを停止する
+///
+/// 送信口を閉じると、パイプラインは残りのサンプルを最終チャンクとして処理して終了する。
+#[tauri::command]
+pub fn stop_streaming_transcription(state: State<'_, AudioState>) -> TaprResult<()> {
+    let mut inner = state.inner.lock()?;
+    *inner.stream_feed.lock()? = None;
+    inner.stream_tasks.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::format::wav_to_pcm_f32;
+    use crate::voice::VoiceError;
+    use async_trait::async_trait;
+
+    /// 応答を返さない認識エンジン
+    struct HangingRecognizer;
+
+    #[async_trait]
+    impl SpeechRecognizer for HangingRecognizer {
+        async fn transcribe(
+            &self,
+            _audio_data: &[u8],
+            _language: Option<&str>,
+        ) -> Result<crate::voice::TranscriptionResult, VoiceError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_times_out() {
+        tokio::time::pause();
+        let err =
+            transcribe_with_timeout(&HangingRecognizer, &[], Some("ja"), Duration::from_secs(30))
+                .await
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Audio error: Transcription timed out after 30 seconds"
+        );
+    }
+
+    /// 受け取った音声と言語を記録して固定の結果を返す認識エンジン
+    #[derive(Default)]
+    struct MockRecognizer {
+        received: Mutex<Option<(Vec<u8>, Option<String>)>>,
+    }
+
+    #[async_trait]
+    impl SpeechRecognizer for MockRecognizer {
+        async fn transcribe(
+            &self,
+            audio_data: &[u8],
+            language: Option<&str>,
+        ) -> Result<crate::voice::TranscriptionResult, VoiceError> {
+            *self.received.lock().unwrap() =
+                Some((audio_data.to_vec(), language.map(str::to_string)));
+            Ok(crate::voice::TranscriptionResult {
+                text: "こんにちは".to_string(),
+                confidence: 0.9,
+                is_final: true,
+                timestamp: 0,
+                language_detected: Some("japanese".to_string()),
+                used_fallback: false,
+                segments: None,
+            })
+        }
+    }
+
+    fn recording_result(samples: &[i16], sample_rate: u32, channels: u16) -> RecordingResult {
+        RecordingResult {
+            audio_data: samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+            sample_rate,
+            channels,
+            duration_ms: 0,
+            duplicate_detected: false,
+            meta: RecordingMeta {
+                session_id: "test".to_string(),
+                started_at_unix_ms: 0,
+                stopped_at_unix_ms: 0,
+                device_name: "mock".to_string(),
+                sample_format: "I16".to_string(),
+                noise_floor_rms: 0.0,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_recording_sends_wav() {
+        let recognizer = MockRecognizer::default();
+        let recording = recording_result(&[0, 1000, -1000, 0], 16_000, 1);
+        let result =
+            transcribe_recording(&recognizer, &recording, Some("en"), Duration::from_secs(30))
+                .await
+                .unwrap();
+        assert_eq!(result.text, "こんにちは");
+        assert_eq!(result.language_detected.as_deref(), Some("japanese"));
+
+        let (wav, language) = recognizer.received.lock().unwrap().take().unwrap();
+        assert_eq!(language.as_deref(), Some("en"));
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        // PCM 本体はそのまま WAV のデータ部に入る
+        assert!(wav.ends_with(&recording.audio_data));
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_recording_skips_duplicate() {
+        let recognizer = MockRecognizer::default();
+        let mut recording = recording_result(&[0, 1000, -1000, 0], 16_000, 1);
+        recording.duplicate_detected = true;
+        let result =
+            transcribe_recording(&recognizer, &recording, Some("ja"), Duration::from_secs(30))
+                .await
+                .unwrap();
+        assert!(result.duplicate_skipped);
+        assert!(result.text.is_empty());
+        // 認識エンジンには送らない
+        assert!(recognizer.received.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_recording_resamples_to_whisper_format() {
+        let recognizer = MockRecognizer::default();
+        // 48kHz ステレオ 480 フレーム（10ms）
+        let samples: Vec<i16> = (0..480).flat_map(|_| [8000i16, 8000]).collect();
+        let recording = recording_result(&samples, 48_000, 2);
+        transcribe_recording(&recognizer, &recording, Some("ja"), Duration::from_secs(30))
+            .await
+            .unwrap();
+
+        let (wav, _) = recognizer.received.lock().unwrap().take().unwrap();
+        let (pcm, sample_rate, channels) = wav_to_pcm_f32(&wav).unwrap();
+        assert_eq!((sample_rate, channels), (WHISPER_SAMPLE_RATE, 1));
+        assert_eq!(pcm.len(), 160);
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_recording_rejects_invalid_pcm() {
+        let recognizer = MockRecognizer::default();
+        let mut recording = recording_result(&[0, 1000], 16_000, 1);
+        // i16 の境界で切れていない PCM
+        recording.audio_data.pop();
+        let err =
+            transcribe_recording(&recognizer, &recording, Some("ja"), Duration::from_secs(30))
+                .await
+                .unwrap_err();
+        assert!(matches!(err, AppError::Audio(_)));
+        assert!(recognizer.received.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_transcription_result_keeps_detected_language() {
+        let result = TranscriptionResult::from(crate::voice::TranscriptionResult {
+            text: "hello".to_string(),
+            confidence: 1.0,
+            is_final: true,
+            timestamp: 0,
+            language_detected: Some("english".to_string()),
+            used_fallback: false,
+            segments: None,
+        });
+        assert_eq!(result.language_detected.as_deref(), Some("english"));
+        assert!(result.request_id.is_empty());
+        assert!(!result.duplicate_skipped);
+    }
+
+    fn recording(samples: &[i16], sample_rate: u32, channels: u16) -> RecordingData {
+        RecordingData {
+            audio_data: samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+            sample_rate,
+            channels,
+        }
+    }
+
+    #[test]
+    fn test_merge_recordings_concatenates() {
+        let result = merge_recordings(
+            vec![
+                recording(&[1, 2], 1_000, 1),
+                recording(&[3], 1_000, 1),
+                recording(&[4, 5, 6, 7], 1_000, 1),
+            ],
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            result.audio_data,
+            recording(&[1, 2, 3, 4, 5, 6, 7], 1_000, 1).audio_data
+        );
+        assert_eq!(result.sample_rate, 1_000);
+        assert_eq!(result.channels, 1);
+        // 1kHz で 7 サンプル
+        assert_eq!(result.duration_ms, 7);
+    }
+
+    #[test]
+    fn test_merge_recordings_inserts_gap() {
+        // 1kHz ステレオ: 200ms の無音 = 400 サンプル = 800 バイト
+        let (merged, _, _) = merge_pcm(
+            &[recording(&[1, 1], 1_000, 2), recording(&[2, 2], 1_000, 2)],
+            true,
+        )
+        .unwrap();
+        assert_eq!(merged.len(), 4 + 800 + 4);
+        assert!(merged[4..804].iter().all(|&b| b == 0));
+        assert_eq!(&merged[804..], &recording(&[2, 2], 1_000, 2).audio_data[..]);
+
+        let result = merge_recordings(
+            vec![
+                recording(&[0; 2_000], 1_000, 2),
+                recording(&[0; 2_000], 1_000, 2),
+            ],
+            true,
+        )
+        .unwrap();
+        assert_eq!(result.duration_ms, 2_200);
+    }
+
+    #[test]
+    fn test_merge_recordings_rejects_mismatched_format() {
+        let err = merge_pcm(
+            &[recording(&[1], 16_000, 1), recording(&[1], 48_000, 1)],
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("sample rate mismatch"));
+
+        let err = merge_pcm(
+            &[recording(&[1], 16_000, 1), recording(&[1, 1], 16_000, 2)],
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("channel count mismatch"));
+
+        assert!(merge_pcm(&[], false).is_err());
+    }
+
+    #[test]
+    fn test_noise_floor_uses_first_half_second() {
+        // 1kHz ステレオ: 冒頭 0.5 秒 = 1000 サンプルは静か、その後は大きい
+        let mut samples = vec![0.01f32; 1000];
+        samples.extend(vec![0.8f32; 3000]);
+        assert!((noise_floor_rms(&samples, 1000, 2) - 0.01).abs() < 1e-6);
+        // 0.5 秒未満の録音は全体を使う
+        assert!((noise_floor_rms(&[0.5, -0.5], 1000, 2) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_compute_audio_metrics_from_synthetic_audio() {
+        // 16kHz モノラル 2 秒: 前半 1 秒は無音、後半 1 秒は振幅 0.6 の 440Hz サイン波
+        let mut samples = vec![0.0f32; 16_000];
+        samples.extend(
+            (0..16_000)
+                .map(|i| 0.6 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16_000.0).sin()),
+        );
+
+        let metrics = compute_audio_metrics("session-1", &samples, 16_000, 1, 2_000);
+        assert_eq!(metrics.session_id, "session-1");
+        assert_eq!((metrics.sample_rate, metrics.channels), (16_000, 1));
+        assert_eq!(metrics.duration_ms, 2_000);
+        assert_eq!(metrics.noise_floor_rms, 0.0);
+        assert!((metrics.peak_amplitude - 0.6).abs() < 0.01);
+        // 30ms フレームの境界が 1 秒目と揃わないため、ちょうど半分からわずかにずれる
+        assert!((metrics.vad_speech_ratio - 0.5).abs() < 0.02);
+    }
+
+    fn devices(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_watchdog_ignores_live_stream() {
+        let mut watchdog = DeviceWatchdog::new("AirPods Pro");
+        let available = devices(&["MacBook Pro Microphone", "AirPods Pro"]);
+
+        assert_eq!(
+            watchdog.poll(&available, Duration::from_millis(100)),
+            WatchdogAction::None
+        );
+        // 3秒未満の無音は発話の切れ目とみなす
+        assert_eq!(
+            watchdog.poll(&available, Duration::from_millis(2_900)),
+            WatchdogAction::None
+        );
+    }
+
+    #[test]
+    fn test_watchdog_reports_disconnect_once() {
+        let mut watchdog = DeviceWatchdog::new("AirPods Pro");
+        let without = devices(&["MacBook Pro Microphone"]);
+
+        assert_eq!(
+            watchdog.poll(&without, Duration::from_secs(1)),
+            WatchdogAction::Disconnected
+        );
+        assert_eq!(
+            watchdog.poll(&without, Duration::from_secs(5)),
+            WatchdogAction::None
+        );
+    }
+
+    #[test]
+    fn test_watchdog_reconnects_when_device_returns() {
+        let mut watchdog = DeviceWatchdog::new("AirPods Pro");
+        let without = devices(&["MacBook Pro Microphone"]);
+        let with = devices(&["MacBook Pro Microphone", "AirPods Pro"]);
+
+        assert_eq!(
+            watchdog.poll(&without, Duration::from_secs(1)),
+            WatchdogAction::Disconnected
+        );
+        assert_eq!(
+            watchdog.poll(&with, Duration::from_secs(3)),
+            WatchdogAction::Reconnect
+        );
+        // 張り直した後は無音の計測がリセットされる
+        assert_eq!(
+            watchdog.poll(&with, Duration::from_millis(500)),
+            WatchdogAction::None
+        );
+        // 再び切断されたらもう一度通知する
+        assert_eq!(
+            watchdog.poll(&without, Duration::from_secs(1)),
+            WatchdogAction::Disconnected
+        );
+    }
+
+    #[test]
+    fn test_watchdog_reconnects_dead_stream_without_device_loss() {
+        // 2秒のポーリングの間に抜き差しされた場合は一覧から消えたことに気づけない
+        let mut watchdog = DeviceWatchdog::new("AirPods Pro");
+        let with = devices(&["AirPods Pro"]);
+
+        assert_eq!(
+            watchdog.poll(&with, Duration::from_secs(4)),
+            WatchdogAction::Reconnect
+        );
+    }
+
+    #[test]
+    fn test_discard_recording_clears_buffer() {
+        let state = AudioState::new();
+        let mut inner = state.inner.lock().unwrap();
+        // 録音中でなければ何もしない
+        assert!(!discard_recording(&mut inner).unwrap());
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        inner.is_recording = true;
+        inner.stop_tx = Some(stop_tx);
+        inner.started_at = Some((Instant::now(), unix_ms_now()));
+        inner
+            .buffer
+            .lock()
+            .unwrap()
+            .extend_from_slice(&[0.1, 0.2, 0.3]);
+
+        assert!(discard_recording(&mut inner).unwrap());
+        assert!(!inner.is_recording);
+        assert!(inner.started_at.is_none());
+        assert!(inner.buffer.lock().unwrap().is_empty());
+        assert!(stop_rx.try_recv().is_ok());
+        assert!(!discard_recording(&mut inner).unwrap());
+    }
+
+    #[test]
+    fn test_discard_recording_aborts_streaming_tasks() {
+        let state = AudioState::new();
+        let mut inner = state.inner.lock().unwrap();
+        inner.is_recording = true;
+
+        // 送信口が閉じたら最終チャンクを送るタスクの代わりに、閉じるまで待ってから通知する
+        let (feed_tx, mut feed_rx) = tokio::sync::mpsc::channel::<Vec<f32>>(1);
+        let (done_tx, mut done_rx) = tokio::sync::mpsc::channel::<()>(1);
+        *inner.stream_feed.lock().unwrap() = Some(feed_tx);
+        inner
+            .stream_tasks
+            .push(tauri::async_runtime::spawn(async move {
+                while feed_rx.recv().await.is_some() {}
+                let _ = done_tx.send(()).await;
+            }));
+
+        assert!(discard_recording(&mut inner).unwrap());
+        assert!(inner.stream_tasks.is_empty());
+        assert!(inner.stream_feed.lock().unwrap().is_none());
+        // abort されたタスクは通知せずに破棄され、done_tx が閉じる
+        let finished = tauri::async_runtime::block_on(async move {
+            tokio::time::timeout(Duration::from_secs(1), done_rx.recv()).await
+        });
+        assert_eq!(finished, Ok(None));
+    }
+
+    #[test]
+    fn test_silence_tracker_waits_for_grace_period() {
+        let config = AutoStopConfig {
+            silence_ms: 500,
+            threshold: 0.01,
+        };
+        // 1kHz モノラル、10ms ごとのコールバック
+        let mut tracker = SilenceTracker::new(config, 1_000, 1);
+        // 冒頭の無音は 500ms を超えても1秒経つまで止めない
+        for _ in 0..99 {
+            assert!(!tracker.push(10, 0.0));
+        }
+        assert!(tracker.push(10, 0.0));
+        // 停止の通知は1度だけ
+        assert!(!tracker.push(10, 0.0));
+    }
+
+    #[test]
+    fn test_silence_tracker_resets_on_speech() {
+        let config = AutoStopConfig {
+            silence_ms: 500,
+            threshold: 0.01,
+        };
+        let mut tracker = SilenceTracker::new(config, 1_000, 2);
+        for _ in 0..100 {
+            assert!(!tracker.push(20, 0.2));
+        }
+        for _ in 0..49 {
+            assert!(!tracker.push(20, 0.005));
+        }
+        // 発話で無音のカウントが戻る
+        assert!(!tracker.push(20, 0.2));
+        for _ in 0..49 {
+            assert!(!tracker.push(20, 0.005));
+        }
+        assert!(tracker.push(20, 0.005));
+    }
+
+    #[test]
+    fn test_auto_stop_config_validation() {
+        assert_eq!(auto_stop_config(false, 0, -1.0).unwrap(), None);
+        assert_eq!(
+            auto_stop_config(true, 1500, 0.02).unwrap(),
+            Some(AutoStopConfig {
+                silence_ms: 1500,
+                threshold: 0.02
+            })
+        );
+        assert!(auto_stop_config(true, 0, 0.02).is_err());
+        assert!(auto_stop_config(true, 1500, 1.5).is_err());
+        assert!(auto_stop_config(true, 1500, f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_level_meter_holds_peak_until_taken() {
+        let meter = LevelMeter::default();
+        assert_eq!(
+            meter.take(),
+            AudioLevelEvent {
+                rms: 0.0,
+                peak: 0.0
+            }
+        );
+
+        assert!((meter.record(&[0.5, -0.5]) - 0.5).abs() < 1e-6);
+        meter.record(&[0.1, -0.1]);
+        let level = meter.take();
+        // RMS は直近のコールバック、ピークは読み出しまでの最大値
+        assert!((level.rms - 0.1).abs() < 1e-6);
+        assert_eq!(level.peak, 0.5);
+
+        let level = meter.take();
+        assert_eq!(level.peak, 0.0);
+        meter.reset();
+        assert_eq!(meter.take().rms, 0.0);
+    }
+
+    #[test]
+    fn test_device_infos_marks_default() {
+        let infos = device_infos(
+            devices(&["MacBook Pro Microphone", "USB Audio Device"]),
+            Some("USB Audio Device"),
+        );
+        assert_eq!(
+            infos,
+            vec![
+                AudioDeviceInfo {
+                    name: "MacBook Pro Microphone".to_string(),
+                    is_default: false,
+                },
+                AudioDeviceInfo {
+                    name: "USB Audio Device".to_string(),
+                    is_default: true,
+                },
+            ]
+        );
+
+        // デフォルトが取れない環境ではどれも既定扱いにしない
+        assert!(device_infos(devices(&["USB Audio Device"]), None)
+            .iter()
+            .all(|d| !d.is_default));
+    }
+
+    #[test]
+    fn test_watchdog_matches_exact_device_name() {
+        let mut watchdog = DeviceWatchdog::new("AirPods Pro");
+        let similar = devices(&["AirPods Pro (2)", "airpods pro"]);
+
+        assert_eq!(
+            watchdog.poll(&similar, Duration::from_secs(5)),
+            WatchdogAction::Disconnected
+        );
+    }
+}
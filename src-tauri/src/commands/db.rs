@@ -1,37 +1,304 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
 use tauri::State;
 
-use crate::db::repository::{self, Entry, NewEntry};
-use crate::db::DbState;
-use crate::error::AppError;
+use crate::db::diff::{diff_texts, DiffStats, TextDiff};
+use crate::db::repository::{
+    self, DayCount, Entry, EntryFilter, ExportFormat, HourOfWeekCount, NewEntry,
+    RecordingMetricsSummary,
+};
+use crate::db::validate::{self, EntryValidationIssue, RepairResult};
+use crate::db::{with_read_connection, DbState, MigrationState, MigrationStatus};
+use crate::error::{AppError, TaprResult};
 
 /// エントリを保存し、挿入IDを返す
 #[tauri::command]
-pub fn save_entry(state: State<'_, DbState>, entry: NewEntry) -> Result<i64, AppError> {
-    let conn = state.conn.lock().map_err(|e| AppError::Database(e.to_string()))?;
+pub fn save_entry(state: State<'_, DbState>, entry: NewEntry) -> TaprResult<i64> {
+    let conn = state.conn()?;
     repository::insert_entry(&conn, &entry)
 }
 
 /// エントリ一覧を取得（新しい順）
 #[tauri::command]
-pub fn get_entries(
+pub fn get_entries(state: State<'_, DbState>, limit: u32, offset: u32) -> TaprResult<Vec<Entry>> {
+    let conn = state.conn()?;
+    repository::get_entries(&conn, limit, offset)
+}
+
+/// モード・作成日時で絞り込んだエントリ一覧を取得（新しい順）
+#[tauri::command]
+pub fn get_entries_filtered(
+    state: State<'_, DbState>,
+    filter: EntryFilter,
+) -> TaprResult<Vec<Entry>> {
+    let conn = state.conn()?;
+    repository::get_entries_filtered(&conn, &filter)
+}
+
+/// エントリの総件数を取得
+#[tauri::command]
+pub fn count_entries(state: State<'_, DbState>) -> TaprResult<u32> {
+    let conn = state.conn()?;
+    repository::count_entries(&conn)
+}
+
+/// 指定モードのエントリ件数を取得
+#[tauri::command]
+pub fn count_entries_by_mode(state: State<'_, DbState>, mode_id: String) -> TaprResult<u32> {
+    let conn = state.conn()?;
+    repository::count_entries_by_mode(&conn, &mode_id)
+}
+
+/// IDでエントリを1件取得（存在しなければ NotFound）
+#[tauri::command]
+pub fn get_entry(state: State<'_, DbState>, id: i64) -> TaprResult<Entry> {
+    let conn = state.conn()?;
+    repository::get_entry(&conn, id)?.ok_or_else(|| AppError::not_found("entry", id))
+}
+
+/// リクエストIDでエントリを1件取得（存在しなければ NotFound）
+#[tauri::command]
+pub fn get_entry_by_request_id(state: State<'_, DbState>, request_id: String) -> TaprResult<Entry> {
+    let conn = state.conn()?;
+    repository::get_entry_by_request_id(&conn, &request_id)?
+        .ok_or_else(|| AppError::not_found("entry", &request_id))
+}
+
+/// raw_text が似ているエントリを類似度付きで取得
+#[tauri::command]
+pub fn get_similar_entries(
+    state: State<'_, DbState>,
+    id: i64,
+    top_k: u32,
+) -> TaprResult<Vec<(Entry, f64)>> {
+    with_read_connection(&state, |conn| {
+        repository::get_similar_entries(conn, id, top_k)
+    })
+}
+
+/// raw_text / processed_text に `query` を含むエントリを新しい順に検索
+#[tauri::command]
+pub fn search_entries(
     state: State<'_, DbState>,
+    query: String,
     limit: u32,
     offset: u32,
-) -> Result<Vec<Entry>, AppError> {
-    let conn = state.conn.lock().map_err(|e| AppError::Database(e.to_string()))?;
-    repository::get_entries(&conn, limit, offset)
+) -> TaprResult<Vec<Entry>> {
+    with_read_connection(&state, |conn| {
+        repository::search_entries(conn, &query, limit, offset)
+    })
+}
+
+/// raw_text から processed_text への差分（AI が変更した箇所）を取得
+#[tauri::command]
+pub fn get_entry_diff(state: State<'_, DbState>, id: i64) -> TaprResult<TextDiff> {
+    let conn = state.conn()?;
+    let entry =
+        repository::get_entry(&conn, id)?.ok_or_else(|| AppError::not_found("entry", id))?;
+    Ok(diff_texts(&entry.raw_text, &entry.processed_text))
 }
 
-/// IDでエントリを1件取得
+/// similarity が `threshold` 未満のエントリを差分の集計付きで取得
 #[tauri::command]
-pub fn get_entry(state: State<'_, DbState>, id: i64) -> Result<Option<Entry>, AppError> {
-    let conn = state.conn.lock().map_err(|e| AppError::Database(e.to_string()))?;
-    repository::get_entry(&conn, id)
+pub fn get_entries_with_large_diffs(
+    state: State<'_, DbState>,
+    threshold: f64,
+    limit: u32,
+) -> TaprResult<Vec<(Entry, DiffStats)>> {
+    with_read_connection(&state, |conn| {
+        repository::get_entries_with_large_diffs(conn, threshold, limit)
+    })
+}
+
+/// エントリの processed_text を書き換える。成否を返す
+#[tauri::command]
+pub fn update_entry(
+    state: State<'_, DbState>,
+    id: i64,
+    processed_text: String,
+) -> TaprResult<bool> {
+    let conn = state.conn()?;
+    repository::update_entry(&conn, id, &processed_text)
+}
+
+/// エントリのお気に入りを設定・解除する。成否を返す
+#[tauri::command]
+pub fn set_favorite(state: State<'_, DbState>, id: i64, is_favorite: bool) -> TaprResult<bool> {
+    let conn = state.conn()?;
+    repository::set_favorite(&conn, id, is_favorite)
 }
 
 /// エントリを削除
 #[tauri::command]
-pub fn delete_entry(state: State<'_, DbState>, id: i64) -> Result<bool, AppError> {
-    let conn = state.conn.lock().map_err(|e| AppError::Database(e.to_string()))?;
+pub fn delete_entry(state: State<'_, DbState>, id: i64) -> TaprResult<bool> {
+    let conn = state.conn()?;
     repository::delete_entry(&conn, id)
 }
+
+/// 指定モードのエントリをすべて物理削除し、件数を返す
+///
+/// 誤操作を防ぐため `confirm` が true でなければ削除しない。
+/// `dry_run` が true の場合は削除せずに対象件数だけ返す。
+#[tauri::command]
+pub fn purge_entries_by_mode(
+    state: State<'_, DbState>,
+    mode_id: String,
+    confirm: bool,
+    dry_run: Option<bool>,
+) -> TaprResult<u32> {
+    let conn = state.conn()?;
+    if dry_run.unwrap_or(false) {
+        return repository::count_entries_by_mode(&conn, &mode_id);
+    }
+    if !confirm {
+        return Err(AppError::Database(
+            "confirmation required to purge entries".into(),
+        ));
+    }
+    let purged = repository::purge_entries_by_mode(&conn, &mode_id)?;
+    eprintln!("[db] purged {} entries for mode '{}'", purged, mode_id);
+    Ok(purged as u32)
+}
+
+/// アーカイブ済みのエントリ一覧を取得（新しい順）
+#[tauri::command]
+pub fn get_archived_entries(
+    state: State<'_, DbState>,
+    limit: u32,
+    offset: u32,
+) -> TaprResult<Vec<Entry>> {
+    let conn = state.conn()?;
+    repository::get_archived_entries(&conn, limit, offset)
+}
+
+/// 全エントリを `path` に書き出し、書き出したパスを返す
+///
+/// 同じディレクトリの `.part` に書き、完了後にリネームする。途中で失敗した場合は
+/// `.part` だけを消すので、`path` に既にあるファイルは残る。
+#[tauri::command]
+pub fn export_entries(
+    state: State<'_, DbState>,
+    format: ExportFormat,
+    path: String,
+) -> TaprResult<String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(AppError::FileSystem(format!(
+            "export path must be absolute: {}",
+            path.display()
+        )));
+    }
+
+    let part_path = part_path(&path);
+    let mut writer = BufWriter::new(File::create(&part_path)?);
+    let result = with_read_connection(&state, |conn| {
+        repository::export_entries(conn, format, &mut writer)
+    });
+    drop(writer);
+    let result = result.and_then(|count| {
+        std::fs::rename(&part_path, &path)?;
+        Ok(count)
+    });
+    match result {
+        Ok(count) => {
+            eprintln!("[db] exported {} entries to {}", count, path.display());
+            Ok(path.to_string_lossy().to_string())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&part_path);
+            Err(e)
+        }
+    }
+}
+
+/// 書き出し途中のデータを置く `<path>.part`
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// 指定日数より古いエントリをアーカイブし、移動件数を返す
+#[tauri::command]
+pub fn archive_old_entries(state: State<'_, DbState>, older_than_days: u32) -> TaprResult<usize> {
+    let conn = state.conn()?;
+    repository::archive_old_entries(&conn, older_than_days)
+}
+
+/// アーカイブからエントリを戻す
+#[tauri::command]
+pub fn restore_from_archive(state: State<'_, DbState>, id: i64) -> TaprResult<bool> {
+    let conn = state.conn()?;
+    repository::restore_from_archive(&conn, id)
+}
+
+/// 録音の長さが `min_ms` 以上 `max_ms` 以下のエントリを取得（新しい順）
+#[tauri::command]
+pub fn get_entries_by_duration(
+    state: State<'_, DbState>,
+    min_ms: u64,
+    max_ms: u64,
+    limit: u32,
+    offset: u32,
+) -> TaprResult<Vec<Entry>> {
+    let conn = state.conn()?;
+    repository::get_entries_by_duration(&conn, min_ms, max_ms, limit, offset)
+}
+
+/// 音声付きエントリの録音の長さの平均（ms）を返す
+#[tauri::command]
+pub fn get_avg_recording_duration(state: State<'_, DbState>) -> TaprResult<f64> {
+    let conn = state.conn()?;
+    repository::get_avg_recording_duration(&conn)
+}
+
+/// 直近 `days` 日の録音メトリクスを項目ごとに集計して返す
+#[tauri::command]
+pub fn get_recording_metrics_summary(
+    state: State<'_, DbState>,
+    days: u32,
+) -> TaprResult<RecordingMetricsSummary> {
+    let conn = state.conn()?;
+    repository::get_recording_metrics_summary(&conn, days)
+}
+
+/// 指定年のエントリ件数を日付ごとに返す（カレンダーヒートマップ用）
+#[tauri::command]
+pub fn get_entry_count_by_day(state: State<'_, DbState>, year: u32) -> TaprResult<Vec<DayCount>> {
+    with_read_connection(&state, |conn| {
+        repository::get_entry_count_by_day(conn, year)
+    })
+}
+
+/// 曜日×時間帯ごとのエントリ件数を返す
+#[tauri::command]
+pub fn get_entry_count_by_hour_of_week(
+    state: State<'_, DbState>,
+) -> TaprResult<Vec<HourOfWeekCount>> {
+    with_read_connection(&state, repository::get_entry_count_by_hour_of_week)
+}
+
+/// スキーマのバージョンとマイグレーションの実行状況を返す
+///
+/// DB の初期化中でも呼べるよう DbState ではなく MigrationState を参照する。
+#[tauri::command]
+pub fn get_migration_status(state: State<'_, MigrationState>) -> TaprResult<MigrationStatus> {
+    state.status()
+}
+
+/// 全エントリを検証し、不正な UTF-8・日時・空のモード/モデル名を一覧で返す
+#[tauri::command]
+pub fn validate_database_entries(
+    state: State<'_, DbState>,
+) -> TaprResult<Vec<EntryValidationIssue>> {
+    with_read_connection(&state, validate::validate_entries)
+}
+
+/// エントリの不正な UTF-8 と日時を修復する
+#[tauri::command]
+pub fn repair_database_entry(state: State<'_, DbState>, id: i64) -> TaprResult<RepairResult> {
+    let conn = state.conn()?;
+    validate::repair_entry(&conn, id)
+}
@@ -0,0 +1,27 @@
+use tauri::State;
+
+use crate::config::dotenv::EnvFilesState;
+use crate::error::{AppError, TaprResult};
+use crate::http_debug::{HttpDebugState, HttpExchange};
+use crate::voice::benchmark::{run_pipeline_benchmark, PipelineBenchmark};
+
+/// 記録済みの HTTP 交換ログを古い順に返す（TAPONSEN_HTTP_DEBUG=1 のときのみ記録される）
+#[tauri::command]
+pub fn get_http_debug_log(state: State<'_, HttpDebugState>) -> TaprResult<Vec<HttpExchange>> {
+    let log = state.log.lock()?;
+    Ok(log.to_vec())
+}
+
+/// 起動時に読み込んだ `.env` ファイルのパスを読み込み順に返す
+#[tauri::command]
+pub fn get_env_files_loaded(state: State<'_, EnvFilesState>) -> Vec<String> {
+    state.loaded.clone()
+}
+
+/// 合成音声とネットワークを使わない認識器で文字起こしパイプラインを計測する
+#[tauri::command]
+pub async fn benchmark_transcription_pipeline(n_iterations: u32) -> TaprResult<PipelineBenchmark> {
+    run_pipeline_benchmark(n_iterations)
+        .await
+        .map_err(|e| AppError::Audio(e.to_string()))
+}
@@ -1,38 +1,415 @@
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::error::AppError;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::audio::{transcribe_with_timeout, TranscriptionResult};
+use crate::config::app_config::AppConfig;
+use crate::db::repository::{self, Entry, NewEntry};
+use crate::db::DbState;
+use crate::error::{AppError, TaprResult};
+use crate::voice::format::{
+    encode_pcm_f32, get_wav_duration_ms, pcm_bytes_to_f32, pcm_f32_to_wav, pcm_from_mp3_with_spec,
+    probe_audio_format, repair_wav_header, AudioFormat, WAV_HEADER_LEN,
+};
+use crate::voice::whisper_api::WhisperApiClient;
+use crate::voice::SpeechRecognizer;
+
+/// 16kHz・16bit・モノラル録音の1秒あたりのバイト数
+const RECORDING_BYTES_PER_SEC: u64 = 16_000 * 2;
+
+/// ディスク空き容量の情報
+#[derive(Debug, Serialize)]
+pub struct DiskSpaceInfo {
+    pub available_bytes: u64,
+    pub temp_dir_path: String,
+    pub db_size_bytes: u64,
+    pub estimated_max_recording_secs: u64,
+}
+
+/// チェックサムを保存するコンパニオンファイルの拡張子
+const CHECKSUM_EXTENSION: &str = "sha";
+
+/// 検証付きで保存した音声ファイルの情報
+#[derive(Debug, Serialize)]
+pub struct SavedFileInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub blake3_hash: String,
+}
+
+/// `validate_and_repair_wav` の結果
+#[derive(Debug, Serialize)]
+pub struct WavRepairResult {
+    pub filename: String,
+    /// ヘッダーを書き直した場合 true
+    pub repaired: bool,
+    /// 修復後のヘッダーから求めた再生時間
+    pub duration_ms: u64,
+}
+
+/// `import_audio_file` で取り込める音声ファイルの上限サイズ
+const MAX_IMPORT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// `import_audio_file` の処理段階
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStage {
+    Reading,
+    Converting,
+    Transcribing,
+    Saving,
+}
+
+/// 音声ファイル取り込みの進捗（`import-progress` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgressEvent {
+    pub stage: ImportStage,
+}
 
 /// アプリ用の一時音声ファイルディレクトリを取得（なければ作成）
-fn audio_temp_dir() -> Result<PathBuf, AppError> {
-    let dir = std::env::temp_dir().join("tap-onsen").join("audio");
-    std::fs::create_dir_all(&dir)?;
-    Ok(dir)
+///
+/// 保存先は起動時に `AppConfig` で検証済み（`TAPONSEN_AUDIO_DIR` で上書き可能）。
+fn audio_temp_dir(config: &AppConfig) -> TaprResult<PathBuf> {
+    std::fs::create_dir_all(&config.audio_dir)?;
+    Ok(config.audio_dir.clone())
+}
+
+/// 現在使用している一時音声ファイルディレクトリのパスを返す
+#[tauri::command]
+pub fn get_audio_dir(config: State<'_, AppConfig>) -> TaprResult<String> {
+    Ok(audio_temp_dir(&config)?.to_string_lossy().to_string())
+}
+
+/// 一時音声ファイルディレクトリがあるボリュームの空き容量を取得する
+pub(crate) fn available_disk_bytes(config: &AppConfig) -> TaprResult<u64> {
+    let dir = audio_temp_dir(config)?;
+    Ok(fs2::available_space(&dir)?)
+}
+
+/// 録音前にディスク空き容量を確認する
+///
+/// `estimated_max_recording_secs` は 16kHz・16bit・モノラル換算の録音可能秒数。
+#[tauri::command]
+pub fn check_disk_space(
+    db: State<'_, DbState>,
+    config: State<'_, AppConfig>,
+) -> TaprResult<DiskSpaceInfo> {
+    let dir = audio_temp_dir(&config)?;
+    let available_bytes = fs2::available_space(&dir)?;
+
+    let db_size_bytes: u64 = {
+        let conn = db.conn()?;
+        conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?
+    };
+
+    Ok(DiskSpaceInfo {
+        available_bytes,
+        temp_dir_path: dir.to_string_lossy().to_string(),
+        db_size_bytes,
+        estimated_max_recording_secs: available_bytes / RECORDING_BYTES_PER_SEC,
+    })
 }
 
 /// 音声データを一時ファイルとして保存する
 #[tauri::command]
-pub fn save_audio_file(audio_data: Vec<u8>, filename: String) -> Result<String, AppError> {
-    let dir = audio_temp_dir()?;
+pub fn save_audio_file(
+    config: State<'_, AppConfig>,
+    audio_data: Vec<u8>,
+    filename: String,
+) -> TaprResult<String> {
+    let dir = audio_temp_dir(&config)?;
     let path = dir.join(&filename);
     std::fs::write(&path, &audio_data)?;
     Ok(path.to_string_lossy().to_string())
 }
 
+/// 録音データ（PCM i16 リトルエンディアン）を指定フォーマットに変換して保存する
+///
+/// 拡張子は `format` に合わせて付け替え、保存したパスを返す。
+#[tauri::command]
+pub fn save_audio_file_compressed(
+    config: State<'_, AppConfig>,
+    audio_data: Vec<u8>,
+    sample_rate: u32,
+    channels: u16,
+    filename: String,
+    format: AudioFormat,
+) -> TaprResult<String> {
+    let encoded = pcm_bytes_to_f32(&audio_data)
+        .and_then(|pcm| encode_pcm_f32(&pcm, sample_rate, channels, format))
+        .map_err(|e| AppError::Audio(e.to_string()))?;
+    let path = audio_temp_dir(&config)?
+        .join(&filename)
+        .with_extension(format.extension());
+    std::fs::write(&path, &encoded)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 音声データを一時ディレクトリに保存し、読み戻して BLAKE3 ハッシュで書き込みを検証する
+///
+/// ハッシュは `{filename}.sha` に保存し、`verify_audio_file` で後から検証できる。
+#[tauri::command]
+pub fn save_audio_file_verified(
+    config: State<'_, AppConfig>,
+    audio_data: Vec<u8>,
+    filename: String,
+) -> TaprResult<SavedFileInfo> {
+    write_verified(&audio_temp_dir(&config)?, &audio_data, &filename)
+}
+
+/// 保存済み音声ファイルのハッシュを再計算し、`.sha` ファイルと一致するか確認する
+#[tauri::command]
+pub fn verify_audio_file(config: State<'_, AppConfig>, filename: String) -> TaprResult<bool> {
+    verify_file(&audio_temp_dir(&config)?, &filename)
+}
+
+fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(CHECKSUM_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// `dir` 直下のファイルパスを返す
+///
+/// パス区切りや `..` を含むファイル名はディレクトリの外を指しうるので、ディスクに触れる前に弾く。
+fn file_in_dir(dir: &Path, filename: &str) -> TaprResult<PathBuf> {
+    let name = Path::new(filename);
+    if filename.contains(['/', '\\']) || name.file_name() != Some(name.as_os_str()) {
+        return Err(AppError::FileSystem(format!(
+            "invalid filename: {}",
+            filename
+        )));
+    }
+    Ok(dir.join(filename))
+}
+
+fn write_verified(dir: &Path, audio_data: &[u8], filename: &str) -> TaprResult<SavedFileInfo> {
+    let path = file_in_dir(dir, filename)?;
+    let expected = blake3::hash(audio_data);
+
+    std::fs::write(&path, audio_data)?;
+    let written = std::fs::read(&path)?;
+    if blake3::hash(&written) != expected {
+        return Err(AppError::FileSystem(
+            "write verification failed: hash mismatch".into(),
+        ));
+    }
+
+    let blake3_hash = expected.to_hex().to_string();
+    std::fs::write(checksum_path(&path), &blake3_hash)?;
+
+    Ok(SavedFileInfo {
+        path: path.to_string_lossy().to_string(),
+        size_bytes: written.len() as u64,
+        blake3_hash,
+    })
+}
+
+fn verify_file(dir: &Path, filename: &str) -> TaprResult<bool> {
+    let path = file_in_dir(dir, filename)?;
+    let stored = std::fs::read_to_string(checksum_path(&path))
+        .map_err(|e| AppError::FileSystem(format!("checksum not found for {}: {}", filename, e)))?;
+    let actual = blake3::hash(&std::fs::read(&path)?);
+    Ok(actual.to_hex().as_str() == stored.trim())
+}
+
+/// 一時音声ファイル（WAV）の再生時間をミリ秒で返す
+///
+/// ヘッダーの44バイトだけを読むため、大きなファイルでも高速。
+#[tauri::command]
+pub fn get_audio_file_duration(config: State<'_, AppConfig>, filename: String) -> TaprResult<u64> {
+    let path = audio_temp_dir(&config)?.join(&filename);
+    let mut header = [0u8; WAV_HEADER_LEN];
+    std::fs::File::open(&path)?
+        .read_exact(&mut header)
+        .map_err(|_| AppError::FileSystem(format!("invalid WAV header: {}", filename)))?;
+    get_wav_duration_ms(&header).map_err(|e| AppError::FileSystem(e.to_string()))
+}
+
+/// 一時音声ファイル（WAV）のヘッダーを検証し、サイズ欄が壊れていれば書き直す
+///
+/// 録音中にアプリが落ちて `finalize()` されなかったファイルを再生できるようにする。
+#[tauri::command]
+pub fn validate_and_repair_wav(
+    config: State<'_, AppConfig>,
+    filename: String,
+) -> TaprResult<WavRepairResult> {
+    repair_file(&audio_temp_dir(&config)?, &filename)
+}
+
+fn repair_file(dir: &Path, filename: &str) -> TaprResult<WavRepairResult> {
+    let path = file_in_dir(dir, filename)?;
+    let mut data = std::fs::read(&path)?;
+    let to_fs_error =
+        |e: crate::voice::VoiceError| AppError::FileSystem(format!("{}: {}", filename, e));
+    let repaired = repair_wav_header(&mut data).map_err(to_fs_error)?;
+    if repaired {
+        std::fs::write(&path, &data)?;
+        eprintln!("[fs] repaired WAV header: {}", filename);
+    }
+    Ok(WavRepairResult {
+        filename: filename.to_string(),
+        repaired,
+        duration_ms: get_wav_duration_ms(&data).map_err(to_fs_error)?,
+    })
+}
+
+/// Finder からドロップされた音声ファイル（WAV / MP3）を文字起こしし、履歴に保存する
+///
+/// MP3 は WAV に変換してから Whisper API に送る。保存するエントリは AI 無効時と同じく
+/// `mode_id` = "raw"、`model` = "none"。処理段階ごとに `import-progress` イベントを送る。
+/// ファイルの BLAKE3 ハッシュが一致するエントリがあれば、文字起こしせずにその結果を返す。
+/// `language` を省略すると Whisper が言語を判定する。
+#[tauri::command]
+pub async fn import_audio_file(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    config: State<'_, AppConfig>,
+    path: String,
+    language: Option<String>,
+) -> TaprResult<TranscriptionResult> {
+    let client = WhisperApiClient::from_config(&config)
+        .map_err(|e| AppError::Audio(e.to_string()))?
+        .with_detect_language(true);
+    let timeout = Duration::from_secs(config.whisper_timeout_secs);
+    import_file(
+        &client,
+        &db,
+        Path::new(&path),
+        language.as_deref(),
+        timeout,
+        |stage| {
+            let _ = app.emit("import-progress", ImportProgressEvent { stage });
+        },
+    )
+    .await
+}
+
+async fn import_file(
+    recognizer: &dyn SpeechRecognizer,
+    db: &DbState,
+    path: &Path,
+    language: Option<&str>,
+    timeout: Duration,
+    on_stage: impl Fn(ImportStage),
+) -> TaprResult<TranscriptionResult> {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    eprintln!("[fs] request_id={} import {}", request_id, path.display());
+
+    on_stage(ImportStage::Reading);
+    let data = read_import_file(path)?;
+
+    // バックアップなどから同じファイルを取り込み直した場合は文字起こしし直さない
+    let audio_hash = blake3::hash(&data).to_hex().to_string();
+    if let Some(entry) = repository::get_entry_by_audio_hash(&db.conn()?, &audio_hash)? {
+        eprintln!(
+            "[fs] request_id={} reuse entry {} with the same audio hash",
+            request_id, entry.id
+        );
+        return Ok(reused_transcription(entry, request_id));
+    }
+
+    on_stage(ImportStage::Converting);
+    let wav = import_to_wav(data)?;
+
+    on_stage(ImportStage::Transcribing);
+    let mut result = transcribe_with_timeout(recognizer, &wav, language, timeout)
+        .await
+        .map_err(|e| e.with_request_id(&request_id))?;
+    result.request_id = request_id.clone();
+
+    on_stage(ImportStage::Saving);
+    let entry = NewEntry {
+        raw_text: result.text.clone(),
+        processed_text: result.text.clone(),
+        mode_id: "raw".to_string(),
+        model: "none".to_string(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+        request_id: Some(request_id),
+        device_name: None,
+        audio_duration_ms: get_wav_duration_ms(&wav).ok(),
+        audio_hash: Some(audio_hash),
+    };
+    let conn = db.conn()?;
+    repository::insert_entry(&conn, &entry)?;
+    Ok(result)
+}
+
+/// 同じ音声から保存済みのエントリを文字起こし結果として返す
+///
+/// 元の信頼度は保存していないので 1.0 とし、リクエストIDは元のエントリのものを優先する。
+fn reused_transcription(entry: Entry, request_id: String) -> TranscriptionResult {
+    TranscriptionResult {
+        text: entry.raw_text,
+        confidence: 1.0,
+        is_final: true,
+        timestamp: 0,
+        request_id: entry.request_id.unwrap_or(request_id),
+        language_detected: None,
+        used_fallback: false,
+        segments: None,
+        duplicate_skipped: false,
+    }
+}
+
+/// 取り込み対象のファイルを読む（存在しない・大きすぎる場合はエラー）
+fn read_import_file(path: &Path) -> TaprResult<Vec<u8>> {
+    if !path.is_file() {
+        return Err(AppError::not_found("audio file", path.display()));
+    }
+    let size = std::fs::metadata(path)?.len();
+    if size > MAX_IMPORT_BYTES {
+        return Err(AppError::FileSystem(format!(
+            "audio file too large: {} bytes (max {} bytes)",
+            size, MAX_IMPORT_BYTES
+        )));
+    }
+    Ok(std::fs::read(path)?)
+}
+
+/// 取り込んだ音声を WAV にそろえる（WAV はそのまま、MP3 はデコードして変換）
+fn import_to_wav(data: Vec<u8>) -> TaprResult<Vec<u8>> {
+    match probe_audio_format(&data) {
+        Some(AudioFormat::Wav) => Ok(data),
+        Some(AudioFormat::Mp3) => pcm_from_mp3_with_spec(&data)
+            .and_then(|(pcm, sample_rate, channels)| pcm_f32_to_wav(&pcm, sample_rate, channels))
+            .map_err(|e| AppError::Audio(e.to_string())),
+        None => Err(AppError::Audio(
+            "unsupported audio format (expected WAV or MP3)".into(),
+        )),
+    }
+}
+
 /// 指定した一時音声ファイルを削除する
 #[tauri::command]
-pub fn delete_audio_file(filename: String) -> Result<(), AppError> {
-    let dir = audio_temp_dir()?;
+pub fn delete_audio_file(config: State<'_, AppConfig>, filename: String) -> TaprResult<()> {
+    let dir = audio_temp_dir(&config)?;
     let path = dir.join(&filename);
     if path.exists() {
         std::fs::remove_file(&path)?;
     }
+    // 検証付きで保存した場合のチェックサムも消す
+    let checksum = checksum_path(&path);
+    if checksum.exists() {
+        std::fs::remove_file(&checksum)?;
+    }
     Ok(())
 }
 
 /// すべての一時音声ファイルを削除し、削除件数を返す
 #[tauri::command]
-pub fn cleanup_audio_files() -> Result<u32, AppError> {
-    let dir = audio_temp_dir()?;
+pub fn cleanup_audio_files(config: State<'_, AppConfig>) -> TaprResult<u32> {
+    let dir = audio_temp_dir(&config)?;
     let mut count = 0u32;
     for entry in std::fs::read_dir(&dir)? {
         let entry = entry?;
@@ -43,3 +420,286 @@ pub fn cleanup_audio_files() -> Result<u32, AppError> {
     }
     Ok(count)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::VoiceError;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    const FIXTURE_WAV: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/sine_440hz_16k_mono.wav"
+    );
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("tap-onsen-test")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_save_verified_and_verify() {
+        let dir = test_dir();
+        let data = vec![1u8, 2, 3, 4, 5];
+
+        let info = write_verified(&dir, &data, "a.wav").unwrap();
+        assert_eq!(info.size_bytes, 5);
+        assert_eq!(info.blake3_hash, blake3::hash(&data).to_hex().to_string());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("a.wav.sha")).unwrap(),
+            info.blake3_hash
+        );
+        assert!(verify_file(&dir, "a.wav").unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_detects_corruption() {
+        let dir = test_dir();
+        write_verified(&dir, &[1u8, 2, 3, 4, 5], "b.wav").unwrap();
+
+        // 保存後にファイルが壊れた状態を再現する
+        std::fs::write(dir.join("b.wav"), [1u8, 2, 3, 4, 6]).unwrap();
+        assert!(!verify_file(&dir, "b.wav").unwrap());
+
+        // チェックサムがなければエラー
+        std::fs::write(dir.join("c.wav"), [0u8]).unwrap();
+        assert!(verify_file(&dir, "c.wav").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_verified_rejects_path_traversal() {
+        let dir = test_dir();
+        for filename in ["../escape.wav", "sub/a.wav", "sub\\a.wav", "..", ".", ""] {
+            let err = write_verified(&dir, &[1u8, 2, 3], filename).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("File system error: invalid filename: {}", filename)
+            );
+            assert!(verify_file(&dir, filename).is_err());
+        }
+        assert!(!dir.parent().unwrap().join("escape.wav").exists());
+        // 何も書き込んでいない
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repair_file() {
+        let dir = test_dir();
+        let mut wav = crate::voice::format::pcm_f32_to_wav(&[0.0f32; 8_000], 16_000, 1).unwrap();
+        // finalize() 前に落ちた録音を再現する
+        wav[4..8].copy_from_slice(&[0; 4]);
+        wav[40..44].copy_from_slice(&[0; 4]);
+        std::fs::write(dir.join("crash.wav"), &wav).unwrap();
+
+        let result = repair_file(&dir, "crash.wav").unwrap();
+        assert!(result.repaired);
+        assert_eq!(result.duration_ms, 500);
+        let fixed = std::fs::read(dir.join("crash.wav")).unwrap();
+        assert_eq!(get_wav_duration_ms(&fixed).unwrap(), 500);
+
+        assert!(!repair_file(&dir, "crash.wav").unwrap().repaired);
+
+        std::fs::write(dir.join("broken.wav"), b"not a wav").unwrap();
+        assert!(repair_file(&dir, "broken.wav").is_err());
+
+        // ディレクトリの外のファイルは読み書きしない
+        let outside = dir.join("sub");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("crash.wav"), &wav).unwrap();
+        let err = repair_file(&outside, "../crash.wav").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "File system error: invalid filename: ../crash.wav"
+        );
+        assert!(repair_file(&dir, "sub/crash.wav").is_err());
+        assert_eq!(std::fs::read(outside.join("crash.wav")).unwrap(), wav);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 受け取った音声を記録して固定の結果を返す認識エンジン
+    #[derive(Default)]
+    struct MockRecognizer {
+        received: Mutex<Option<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl SpeechRecognizer for MockRecognizer {
+        async fn transcribe(
+            &self,
+            audio_data: &[u8],
+            _language: Option<&str>,
+        ) -> Result<crate::voice::TranscriptionResult, VoiceError> {
+            *self.received.lock().unwrap() = Some(audio_data.to_vec());
+            Ok(crate::voice::TranscriptionResult {
+                text: "取り込みテスト".to_string(),
+                confidence: 0.9,
+                is_final: true,
+                timestamp: 0,
+                language_detected: Some("japanese".to_string()),
+                used_fallback: false,
+                segments: None,
+            })
+        }
+    }
+
+    async fn import(
+        recognizer: &MockRecognizer,
+        db: &DbState,
+        path: &Path,
+    ) -> (TaprResult<TranscriptionResult>, Vec<ImportStage>) {
+        let stages = Mutex::new(Vec::new());
+        let result = import_file(
+            recognizer,
+            db,
+            path,
+            None,
+            Duration::from_secs(30),
+            |stage| stages.lock().unwrap().push(stage),
+        )
+        .await;
+        (result, stages.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_import_wav_fixture() {
+        let db = DbState::in_memory().unwrap();
+        let recognizer = MockRecognizer::default();
+
+        let (result, stages) = import(&recognizer, &db, Path::new(FIXTURE_WAV)).await;
+        let result = result.unwrap();
+        assert_eq!(result.text, "取り込みテスト");
+        assert_eq!(
+            stages,
+            vec![
+                ImportStage::Reading,
+                ImportStage::Converting,
+                ImportStage::Transcribing,
+                ImportStage::Saving
+            ]
+        );
+        // WAV は変換せずにそのまま渡す
+        assert_eq!(
+            recognizer.received.lock().unwrap().as_deref(),
+            Some(std::fs::read(FIXTURE_WAV).unwrap().as_slice())
+        );
+
+        let conn = db.conn().unwrap();
+        let entry = repository::get_entry_by_request_id(&conn, &result.request_id)
+            .unwrap()
+            .expect("imported entry should be saved");
+        assert_eq!(entry.raw_text, "取り込みテスト");
+        assert_eq!(entry.mode_id, "raw");
+        assert_eq!(entry.model, "none");
+        assert_eq!(entry.audio_duration_ms, Some(1_000));
+        assert_eq!(
+            entry.audio_hash,
+            Some(
+                blake3::hash(&std::fs::read(FIXTURE_WAV).unwrap())
+                    .to_hex()
+                    .to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_same_file_reuses_transcription() {
+        let db = DbState::in_memory().unwrap();
+        let first = MockRecognizer::default();
+        let (result, _) = import(&first, &db, Path::new(FIXTURE_WAV)).await;
+        let original = result.unwrap();
+
+        let second = MockRecognizer::default();
+        let (result, stages) = import(&second, &db, Path::new(FIXTURE_WAV)).await;
+        let reused = result.unwrap();
+
+        assert!(second.received.lock().unwrap().is_none());
+        assert_eq!(stages, vec![ImportStage::Reading]);
+        assert_eq!(reused.text, original.text);
+        assert_eq!(reused.request_id, original.request_id);
+        let conn = db.conn().unwrap();
+        assert_eq!(repository::get_entries(&conn, 10, 0).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_different_file_transcribes() {
+        let dir = test_dir();
+        let mut data = std::fs::read(FIXTURE_WAV).unwrap();
+        // 末尾のサンプルだけ変えた別の音声
+        *data.last_mut().unwrap() ^= 0x01;
+        std::fs::write(dir.join("b.wav"), &data).unwrap();
+
+        let db = DbState::in_memory().unwrap();
+        let recognizer = MockRecognizer::default();
+        import(&recognizer, &db, Path::new(FIXTURE_WAV))
+            .await
+            .0
+            .unwrap();
+        let other = MockRecognizer::default();
+        let (result, _) = import(&other, &db, &dir.join("b.wav")).await;
+        assert!(result.is_ok());
+
+        assert!(other.received.lock().unwrap().is_some());
+        let conn = db.conn().unwrap();
+        let entries = repository::get_entries(&conn, 10, 0).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_ne!(entries[0].audio_hash, entries[1].audio_hash);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_mp3_converts_to_wav() {
+        let dir = test_dir();
+        let (pcm, _, _) =
+            crate::voice::format::wav_to_pcm_f32(&std::fs::read(FIXTURE_WAV).unwrap()).unwrap();
+        let mp3 = crate::voice::format::pcm_f32_to_mp3(&pcm, 16_000, 1, 64).unwrap();
+        std::fs::write(dir.join("a.mp3"), mp3).unwrap();
+
+        let db = DbState::in_memory().unwrap();
+        let recognizer = MockRecognizer::default();
+        let (result, _) = import(&recognizer, &db, &dir.join("a.mp3")).await;
+        assert!(result.is_ok());
+        let wav = recognizer.received.lock().unwrap().take().unwrap();
+        assert_eq!(probe_audio_format(&wav), Some(AudioFormat::Wav));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_invalid_files() {
+        let dir = test_dir();
+        let db = DbState::in_memory().unwrap();
+        let recognizer = MockRecognizer::default();
+
+        let (result, _) = import(&recognizer, &db, &dir.join("missing.wav")).await;
+        assert!(matches!(result, Err(AppError::NotFound { .. })));
+
+        std::fs::write(dir.join("a.ogg"), b"OggS\x00\x02 not supported").unwrap();
+        let (result, stages) = import(&recognizer, &db, &dir.join("a.ogg")).await;
+        assert!(matches!(result, Err(AppError::Audio(_))));
+        assert_eq!(stages.last(), Some(&ImportStage::Converting));
+
+        // 中身を書かずにサイズだけ上限を超えさせる
+        let large = std::fs::File::create(dir.join("large.wav")).unwrap();
+        large.set_len(MAX_IMPORT_BYTES + 1).unwrap();
+        let (result, _) = import(&recognizer, &db, &dir.join("large.wav")).await;
+        assert!(matches!(result, Err(AppError::FileSystem(_))));
+
+        assert!(recognizer.received.lock().unwrap().is_none());
+        let conn = db.conn().unwrap();
+        assert!(repository::get_entries(&conn, 10, 0).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::State;
+
+/// 起動処理の進捗を保持する。setup クロージャが各フラグを立てる
+pub struct StartupState {
+    pub db_ready: AtomicBool,
+    pub audio_ready: AtomicBool,
+    pub ai_config_valid: AtomicBool,
+    pub warnings: Mutex<Vec<String>>,
+}
+
+impl StartupState {
+    pub fn new() -> Self {
+        Self {
+            db_ready: AtomicBool::new(false),
+            audio_ready: AtomicBool::new(false),
+            ai_config_valid: AtomicBool::new(false),
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn add_warning(&self, warning: String) {
+        eprintln!("[startup] {}", warning);
+        if let Ok(mut warnings) = self.warnings.lock() {
+            warnings.push(warning);
+        }
+    }
+}
+
+impl Default for StartupState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PingResponse {
+    pub db_ready: bool,
+    pub audio_ready: bool,
+    pub ai_config_valid: bool,
+    pub startup_warnings: Vec<String>,
+}
+
+/// フロントエンドが起動完了を待つためのヘルスチェック
+/// フラグを読むだけなので重い処理は行わない
+#[tauri::command]
+pub fn ping(state: State<'_, StartupState>) -> PingResponse {
+    PingResponse {
+        db_ready: state.db_ready.load(Ordering::Acquire),
+        audio_ready: state.audio_ready.load(Ordering::Acquire),
+        ai_config_valid: state.ai_config_valid.load(Ordering::Acquire),
+        startup_warnings: state.warnings.lock().map(|w| w.clone()).unwrap_or_default(),
+    }
+}
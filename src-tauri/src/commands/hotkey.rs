@@ -0,0 +1,153 @@
+#[cfg(target_os = "macos")]
+use tauri::{AppHandle, State};
+
+#[cfg(target_os = "macos")]
+use crate::config::app_config::AppConfig;
+#[cfg(not(target_os = "macos"))]
+use crate::error::AppError;
+use crate::error::TaprResult;
+#[cfg(target_os = "macos")]
+use crate::hotkey::{self, PttConfig, PttMode};
+#[cfg(target_os = "macos")]
+use crate::ptt_hooks::validate_hook_command;
+
+/// Accessibility 権限の状態を返す（PTT機能に必要）
+/// prompt=true でmacOSの許可ダイアログを表示する
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn check_accessibility_permission(prompt: bool) -> bool {
+    hotkey::is_accessibility_trusted(prompt)
+}
+
+/// PTT が使えるか（Accessibility 権限があるか）をダイアログを出さずに返す
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn is_ptt_available() -> bool {
+    hotkey::is_accessibility_trusted(false)
+}
+
+/// macOS 以外では PTT は使えない
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn is_ptt_available() -> bool {
+    false
+}
+
+/// macOS 以外には Accessibility 権限がなく、PTT も使えない
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn check_accessibility_permission(_prompt: bool) -> bool {
+    false
+}
+
+#[cfg(not(target_os = "macos"))]
+fn ptt_unavailable() -> AppError {
+    AppError::Config("Push-to-Talk is only available on macOS".into())
+}
+
+/// 現在の PTT 設定を返す
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn get_ptt_config() -> PttConfig {
+    hotkey::current_config()
+}
+
+/// macOS 以外では PTT の設定はない
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn get_ptt_config() -> TaprResult<()> {
+    Err(ptt_unavailable())
+}
+
+/// PTT 設定を変更し、リスナーを再起動する
+///
+/// 開始・停止フックのコマンドは、起動時の設定で PTT_ALLOW_ADVANCED_HOOKS を
+/// 有効にしていなければシェル記法を拒否する（フロントエンドからは許可できない）。
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn set_ptt_config(
+    app: AppHandle,
+    app_config: State<'_, AppConfig>,
+    config: PttConfig,
+) -> TaprResult<()> {
+    for command in [&config.ptt_start_command, &config.ptt_stop_command]
+        .into_iter()
+        .flatten()
+    {
+        validate_hook_command(command, app_config.ptt_allow_advanced_hooks)?;
+    }
+    eprintln!(
+        "[hotkey] restarting PTT listener (keycode={}, max={}s, tap<{}ms)",
+        config.keycode, config.max_duration_secs, config.tap_threshold_ms
+    );
+    hotkey::start_listener(app, config);
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn set_ptt_config(_config: serde_json::Value) -> TaprResult<()> {
+    Err(ptt_unavailable())
+}
+
+/// PTT のキーをリスナーを再起動せずに切り替える（例: 右Command = 54、F13 = 105）
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn set_ptt_key(keycode: i64) -> TaprResult<()> {
+    hotkey::set_ptt_keycode(keycode)
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn set_ptt_key(_keycode: i64) -> TaprResult<()> {
+    Err(ptt_unavailable())
+}
+
+/// PTT の操作方法（"hold" = 押している間 / "toggle" = タップで開始・停止）を切り替える
+#[cfg(target_os = "macos")]
+#[tauri::command]
+pub fn set_ptt_mode(mode: PttMode) {
+    hotkey::set_ptt_mode(mode);
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+pub fn set_ptt_mode(_mode: String) -> TaprResult<()> {
+    Err(ptt_unavailable())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri::ipc::{CallbackFn, InvokeBody};
+    use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, INVOKE_KEY};
+    use tauri::webview::InvokeRequest;
+    use tauri::WebviewWindowBuilder;
+
+    #[test]
+    fn test_is_ptt_available_via_ipc() {
+        let app = mock_builder()
+            .invoke_handler(tauri::generate_handler![is_ptt_available])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let webview = WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+
+        let response = get_ipc_response(
+            &webview,
+            InvokeRequest {
+                cmd: "is_ptt_available".into(),
+                callback: CallbackFn(0),
+                error: CallbackFn(1),
+                url: "http://tauri.localhost".parse().unwrap(),
+                body: InvokeBody::default(),
+                headers: Default::default(),
+                invoke_key: INVOKE_KEY.to_string(),
+            },
+        )
+        .unwrap();
+        // 権限の有無は環境次第なので、bool が返ることだけ確認する
+        response.deserialize::<bool>().unwrap();
+    }
+}
@@ -1,21 +1,40 @@
 pub mod ai;
 pub mod audio;
 pub mod db;
+pub mod debug;
 pub mod fs;
+pub mod health;
+pub mod hotkey;
 pub mod paste;
+pub mod share;
+pub mod whisper;
 
+use crate::config::app_config::AppConfig;
 use crate::config::modes;
-use crate::error::AppError;
+use crate::error::TaprResult;
 
 #[tauri::command]
-pub fn get_modes(app: tauri::AppHandle) -> Result<Vec<modes::ModeConfig>, AppError> {
+pub fn get_modes(app: tauri::AppHandle) -> TaprResult<Vec<modes::ModeConfig>> {
     modes::load_modes_from_app(&app)
 }
 
-/// Accessibility 権限の状態を返す（PTT機能に必要）
-/// prompt=true でmacOSの許可ダイアログを表示する
-#[cfg(target_os = "macos")]
+/// 編集したプロンプトファイル（`ai_prompt_file`）を読み直し、新しいプロンプトを返す
+///
+/// モード一覧全体ではなく、指定モードのファイルだけを読み直す。
 #[tauri::command]
-pub fn check_accessibility_permission(prompt: bool) -> bool {
-    crate::hotkey::is_accessibility_trusted(prompt)
+pub fn reload_prompt_file(app: tauri::AppHandle, mode_id: String) -> TaprResult<String> {
+    modes::reload_prompt_file_from_app(&app, &mode_id)
+}
+
+/// 前面アプリ名から `app_affinity` が最もよく合うモードIDを返す
+#[tauri::command]
+pub fn suggest_mode_for_app(app: tauri::AppHandle, app_name: String) -> TaprResult<Option<String>> {
+    let modes = modes::load_modes_from_app(&app)?;
+    Ok(modes::suggest_mode_for_app(&modes, &app_name))
+}
+
+/// API キーなどの秘密情報を除いた現在の設定を返す
+#[tauri::command]
+pub fn get_config_summary(config: tauri::State<'_, AppConfig>) -> serde_json::Value {
+    config.summary()
 }
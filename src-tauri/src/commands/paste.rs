@@ -1,15 +1,33 @@
-use crate::error::AppError;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::config::app_config::{AppConfig, PasteMethod};
+use crate::config::modes::load_modes;
+use crate::error::{AppError, TaprResult};
+#[cfg(target_os = "macos")]
 use std::ffi::c_void;
 
 // --- Core Graphics FFI (⌘V シミュレーション用) ---
+#[cfg(target_os = "macos")]
 type CGEventRef = *mut c_void;
 
 /// kCGEventFlagMaskCommand — ⌘ キーフラグ
+#[cfg(target_os = "macos")]
 const CG_EVENT_FLAG_MASK_COMMAND: u64 = 0x00100000;
 
 /// macOS 仮想キーコード: V = 9
+#[cfg(target_os = "macos")]
 const KEYCODE_V: u16 = 9;
 
+/// kAXErrorSuccess
+#[cfg(target_os = "macos")]
+const AX_ERROR_SUCCESS: i32 = 0;
+
+#[cfg(target_os = "macos")]
 #[link(name = "CoreGraphics", kind = "framework")]
 extern "C" {
     fn CGEventCreateKeyboardEvent(
@@ -18,44 +36,272 @@ extern "C" {
         key_down: bool,
     ) -> CGEventRef;
     fn CGEventSetFlags(event: CGEventRef, flags: u64);
+    fn CGEventKeyboardSetUnicodeString(
+        event: CGEventRef,
+        string_length: usize,
+        unicode_string: *const u16,
+    );
     fn CGEventPost(tap: u32, event: CGEventRef);
 }
 
+#[cfg(target_os = "macos")]
 #[link(name = "CoreFoundation", kind = "framework")]
 extern "C" {
     fn CFRelease(cf: *const c_void);
 }
 
+// --- Accessibility FFI（クリップボードを使わない挿入用） ---
+#[cfg(target_os = "macos")]
+type AXUIElementRef = *const c_void;
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+    fn AXUIElementCopyAttributeValue(
+        element: AXUIElementRef,
+        attribute: *const c_void,
+        value: *mut *const c_void,
+    ) -> i32;
+    fn AXUIElementSetAttributeValue(
+        element: AXUIElementRef,
+        attribute: *const c_void,
+        value: *const c_void,
+    ) -> i32;
+}
+
+/// `Type` で1回のキーイベントに載せる UTF-16 コードユニット数
+///
+/// CGEvent は1イベントあたり20ユニットまでしか送れず、超えた分は黙って捨てられる。
+const TYPE_CHUNK_UTF16_UNITS: usize = 20;
+
+/// `Type` のチャンク間の待ち時間（前面アプリの入力処理が追いつかず文字が落ちるのを防ぐ）
+const TYPE_CHUNK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 実行中に変更されたペースト方法を保持する Tauri State
+pub struct PasteState {
+    method: Mutex<PasteMethod>,
+}
+
+impl PasteState {
+    /// 設定（PASTE_METHOD）から初期値を読み込む
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            method: Mutex::new(config.paste.paste_method),
+        }
+    }
+}
+
+/// ペースト処理の完了（`paste-completed` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct PasteCompletedEvent {
+    pub method_used: String,
+    pub success: bool,
+}
+
+/// ペースト方法を変更する
+///
+/// モードに `paste` が指定されている場合はそちらが優先される。
+#[tauri::command]
+pub fn set_paste_method(state: State<'_, PasteState>, method: PasteMethod) -> TaprResult<()> {
+    let mut current = state.method.lock()?;
+    *current = method;
+    eprintln!("[paste] paste method set to {}", method.as_str());
+    Ok(())
+}
+
 /// 前面アプリのカーソル位置にテキストをペーストする
 ///
-/// 処理フロー:
+/// ペースト方法はモードの `paste` → 全体設定の順で決まる。既定の `ClipboardSave` の処理フロー:
 /// 1. 現在のクリップボード内容を退避
 /// 2. クリップボードに指定テキストをセット
-/// 3. ⌘V キーイベントをシミュレーション
-/// 4. ペースト完了を待機
+/// 3. ペーストのキー操作（macOS は ⌘V、Windows / Linux は Ctrl+V）をシミュレーション
+/// 4. ペースト完了を待機（`PASTE_DELAY_MS`）
 /// 5. クリップボードを元の内容に復元
+///
+/// 成否にかかわらず `paste-completed` イベントを送る。
 #[tauri::command]
-pub async fn paste_to_foreground(text: String) -> Result<(), AppError> {
-    // 1. クリップボード退避
-    let mut clipboard =
-        arboard::Clipboard::new().map_err(|e| AppError::Ai(format!("Clipboard error: {e}")))?;
+pub async fn paste_to_foreground(
+    app: AppHandle,
+    config: State<'_, AppConfig>,
+    state: State<'_, PasteState>,
+    text: String,
+    mode_id: Option<String>,
+) -> TaprResult<()> {
+    let method = resolve_paste_method(&state, mode_id.as_deref())?;
+    let delay = Duration::from_millis(config.paste_delay_ms);
+
+    let result = perform_paste(&mut SystemPasteBackend::default(), method, &text, delay).await;
+    if let Err(e) = &result {
+        eprintln!("[paste] {} failed: {}", method.as_str(), e);
+    }
+    let _ = app.emit(
+        "paste-completed",
+        PasteCompletedEvent {
+            method_used: method.as_str().to_string(),
+            success: result.is_ok(),
+        },
+    );
+    result
+}
+
+/// モードの上書きがあればそれを、なければ全体のペースト方法を返す
+fn resolve_paste_method(state: &PasteState, mode_id: Option<&str>) -> TaprResult<PasteMethod> {
+    if let Some(mode_id) = mode_id {
+        let modes =
+            load_modes().map_err(|e| AppError::Config(format!("Failed to load modes: {}", e)))?;
+        if let Some(paste) = modes
+            .into_iter()
+            .find(|m| m.id == mode_id)
+            .and_then(|m| m.paste)
+        {
+            return Ok(paste.paste_method);
+        }
+    }
+    Ok(*state.method.lock()?)
+}
+
+/// ペーストに使う OS 操作（テストではモックに差し替える）
+///
+/// キー入力は Linux では xdotool の終了を待つので async にしている。
+#[async_trait]
+trait PasteBackend: Send {
+    fn get_clipboard(&mut self) -> Option<String>;
+    fn set_clipboard(&mut self, text: &str) -> TaprResult<()>;
+    fn clear_clipboard(&mut self);
+    async fn send_paste_keystroke(&mut self) -> TaprResult<()>;
+    fn insert_text(&mut self, text: &str) -> TaprResult<()>;
+    /// UTF-16 のチャンクをキー入力として送る
+    async fn type_chunk(&mut self, chunk: &[u16]) -> TaprResult<()>;
+}
+
+async fn perform_paste(
+    backend: &mut impl PasteBackend,
+    method: PasteMethod,
+    text: &str,
+    delay: Duration,
+) -> TaprResult<()> {
+    match method {
+        PasteMethod::Disabled => Ok(()),
+        PasteMethod::AccessibilityInsert => backend.insert_text(text),
+        PasteMethod::Type => {
+            for (i, chunk) in utf16_chunks(text, TYPE_CHUNK_UTF16_UNITS)
+                .iter()
+                .enumerate()
+            {
+                if i > 0 {
+                    tokio::time::sleep(TYPE_CHUNK_INTERVAL).await;
+                }
+                backend.type_chunk(chunk).await?;
+            }
+            Ok(())
+        }
+        PasteMethod::ClipboardReplace => {
+            backend.set_clipboard(text)?;
+            backend.send_paste_keystroke().await
+        }
+        PasteMethod::ClipboardSave => {
+            let saved = backend.get_clipboard();
+            backend.set_clipboard(text)?;
+            // キー操作に失敗してもクリップボードは必ず元に戻す
+            let result = backend.send_paste_keystroke().await;
 
-    let saved = clipboard.get_text().ok();
+            // ペースト完了前に復元すると元の内容が貼り付けられてしまう
+            tokio::time::sleep(delay).await;
 
-    // 2. テキストをセット
-    clipboard
-        .set_text(&text)
-        .map_err(|e| AppError::Ai(format!("Clipboard set error: {e}")))?;
+            match saved {
+                Some(original) => {
+                    let _ = backend.set_clipboard(&original);
+                }
+                None => backend.clear_clipboard(),
+            }
+            result
+        }
+    }
+}
 
-    // 3. ⌘V シミュレーション
+/// テキストを UTF-16 で最大 `max_units` ずつに分ける
+///
+/// 絵文字などのサロゲートペアは途中で切らない（1文字が `max_units` を超える場合はその文字だけのチャンクにする）。
+fn utf16_chunks(text: &str, max_units: usize) -> Vec<Vec<u16>> {
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut buf = [0u16; 2];
+    for c in text.chars() {
+        let units = c.encode_utf16(&mut buf);
+        if !current.is_empty() && current.len() + units.len() > max_units {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.extend_from_slice(units);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// arboard のクリップボードと OS ごとのキー操作を使う実装
+///
+/// `AccessibilityInsert` でクリップボードに一切触れないよう、クリップボードは必要になるまで開かない。
+#[derive(Default)]
+struct SystemPasteBackend {
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl SystemPasteBackend {
+    fn clipboard(&mut self) -> TaprResult<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            let clipboard = arboard::Clipboard::new()
+                .map_err(|e| AppError::Ai(format!("Clipboard error: {e}")))?;
+            self.clipboard = Some(clipboard);
+        }
+        Ok(self
+            .clipboard
+            .as_mut()
+            .expect("clipboard initialized above"))
+    }
+}
+
+#[async_trait]
+impl PasteBackend for SystemPasteBackend {
+    fn get_clipboard(&mut self) -> Option<String> {
+        self.clipboard().ok()?.get_text().ok()
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> TaprResult<()> {
+        self.clipboard()?
+            .set_text(text)
+            .map_err(|e| AppError::Ai(format!("Clipboard set error: {e}")))
+    }
+
+    fn clear_clipboard(&mut self) {
+        if let Ok(clipboard) = self.clipboard() {
+            let _ = clipboard.clear();
+        }
+    }
+
+    async fn send_paste_keystroke(&mut self) -> TaprResult<()> {
+        send_paste_keystroke().await
+    }
+
+    fn insert_text(&mut self, text: &str) -> TaprResult<()> {
+        insert_text_with_accessibility(text)
+    }
+
+    async fn type_chunk(&mut self, chunk: &[u16]) -> TaprResult<()> {
+        type_unicode_chunk(chunk).await
+    }
+}
+
+/// ⌘V を HID レベルでポストする
+#[cfg(target_os = "macos")]
+async fn send_paste_keystroke() -> TaprResult<()> {
     unsafe {
         let key_down = CGEventCreateKeyboardEvent(std::ptr::null(), KEYCODE_V, true);
         let key_up = CGEventCreateKeyboardEvent(std::ptr::null(), KEYCODE_V, false);
 
         if key_down.is_null() || key_up.is_null() {
-            return Err(AppError::Ai(
-                "Failed to create keyboard event".to_string(),
-            ));
+            return Err(AppError::Ai("Failed to create keyboard event".to_string()));
         }
 
         CGEventSetFlags(key_down, CG_EVENT_FLAG_MASK_COMMAND);
@@ -68,19 +314,408 @@ pub async fn paste_to_foreground(text: String) -> Result<(), AppError> {
         CFRelease(key_down as *const c_void);
         CFRelease(key_up as *const c_void);
     }
+    Ok(())
+}
 
-    // 4. ペースト完了待ち
-    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+/// Ctrl 押下 → V 押下 → V 解放 → Ctrl 解放を SendInput でまとめて送る
+#[cfg(target_os = "windows")]
+async fn send_paste_keystroke() -> TaprResult<()> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_CONTROL,
+    };
 
-    // 5. クリップボード復元
-    match saved {
-        Some(original) => {
-            let _ = clipboard.set_text(&original);
+    /// Windows 仮想キーコード: V = 0x56
+    const VK_V: u16 = 0x56;
+
+    let key = |vk: u16, flags: u32| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    let inputs = [
+        key(VK_CONTROL, 0),
+        key(VK_V, 0),
+        key(VK_V, KEYEVENTF_KEYUP),
+        key(VK_CONTROL, KEYEVENTF_KEYUP),
+    ];
+
+    // 他のアプリの入力が割り込まないよう、4つのイベントを1回の呼び出しで送る
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_ptr(),
+            std::mem::size_of::<INPUT>() as i32,
+        )
+    };
+    if sent != inputs.len() as u32 {
+        return Err(AppError::Ai(format!(
+            "SendInput sent {} of {} key events: {}",
+            sent,
+            inputs.len(),
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+/// xdotool で Ctrl+V を送る（X11 / XWayland のウィンドウが対象）
+///
+/// `--clearmodifiers` で PTT キーなど押しっぱなしの修飾キーを一時的に外す。
+#[cfg(target_os = "linux")]
+async fn send_paste_keystroke() -> TaprResult<()> {
+    let status = tokio::process::Command::new("xdotool")
+        .args(["key", "--clearmodifiers", "ctrl+v"])
+        .status()
+        .await
+        .map_err(|e| AppError::Ai(format!("xdotool is required to paste on Linux: {e}")))?;
+    if !status.success() {
+        return Err(AppError::Ai(format!("xdotool failed: {status}")));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+async fn send_paste_keystroke() -> TaprResult<()> {
+    Err(AppError::Ai(
+        "Paste keystroke is not supported on this platform".to_string(),
+    ))
+}
+
+/// キーコードの代わりに Unicode 文字列を載せたキーイベントを送る
+#[cfg(target_os = "macos")]
+async fn type_unicode_chunk(chunk: &[u16]) -> TaprResult<()> {
+    unsafe {
+        let key_down = CGEventCreateKeyboardEvent(std::ptr::null(), 0, true);
+        let key_up = CGEventCreateKeyboardEvent(std::ptr::null(), 0, false);
+
+        if key_down.is_null() || key_up.is_null() {
+            return Err(AppError::Ai("Failed to create keyboard event".to_string()));
         }
-        None => {
-            let _ = clipboard.clear();
+
+        for event in [key_down, key_up] {
+            // 押しっぱなしの修飾キー（PTT キーなど）がショートカットとして解釈されないよう外す
+            CGEventSetFlags(event, 0);
+            CGEventKeyboardSetUnicodeString(event, chunk.len(), chunk.as_ptr());
         }
+
+        CGEventPost(0, key_down);
+        CGEventPost(0, key_up);
+
+        CFRelease(key_down as *const c_void);
+        CFRelease(key_up as *const c_void);
     }
+    Ok(())
+}
+
+/// KEYEVENTF_UNICODE で UTF-16 コードユニットごとの押下・解放を送る
+#[cfg(target_os = "windows")]
+async fn type_unicode_chunk(chunk: &[u16]) -> TaprResult<()> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
+    };
 
+    let inputs: Vec<INPUT> = chunk
+        .iter()
+        .flat_map(|&unit| {
+            [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP].map(|flags| (unit, flags))
+        })
+        .map(|(unit, flags)| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: 0,
+                    wScan: unit,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        })
+        .collect();
+
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_ptr(),
+            std::mem::size_of::<INPUT>() as i32,
+        )
+    };
+    if sent != inputs.len() as u32 {
+        return Err(AppError::Ai(format!(
+            "SendInput sent {} of {} key events: {}",
+            sent,
+            inputs.len(),
+            std::io::Error::last_os_error()
+        )));
+    }
     Ok(())
 }
+
+/// xdotool type で文字列をキー入力する
+#[cfg(target_os = "linux")]
+async fn type_unicode_chunk(chunk: &[u16]) -> TaprResult<()> {
+    let text = String::from_utf16(chunk)
+        .map_err(|e| AppError::Ai(format!("Invalid UTF-16 chunk: {e}")))?;
+    let status = tokio::process::Command::new("xdotool")
+        .args(["type", "--clearmodifiers", "--", &text])
+        .status()
+        .await
+        .map_err(|e| AppError::Ai(format!("xdotool is required to paste on Linux: {e}")))?;
+    if !status.success() {
+        return Err(AppError::Ai(format!("xdotool failed: {status}")));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+async fn type_unicode_chunk(_chunk: &[u16]) -> TaprResult<()> {
+    Err(AppError::Ai(
+        "Typing text is not supported on this platform".to_string(),
+    ))
+}
+
+/// フォーカス中の要素の選択範囲（カーソル位置）を AXSelectedText で置き換える
+#[cfg(target_os = "macos")]
+fn insert_text_with_accessibility(text: &str) -> TaprResult<()> {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    let focused_attr = CFString::new("AXFocusedUIElement");
+    let selected_text_attr = CFString::new("AXSelectedText");
+    let value = CFString::new(text);
+
+    unsafe {
+        let system = AXUIElementCreateSystemWide();
+        let mut focused: *const c_void = std::ptr::null();
+        let err = AXUIElementCopyAttributeValue(
+            system,
+            focused_attr.as_concrete_TypeRef() as *const c_void,
+            &mut focused,
+        );
+        CFRelease(system);
+        if err != AX_ERROR_SUCCESS || focused.is_null() {
+            return Err(AppError::Ai(format!(
+                "No focused element for accessibility insert (AXError {})",
+                err
+            )));
+        }
+
+        let err = AXUIElementSetAttributeValue(
+            focused,
+            selected_text_attr.as_concrete_TypeRef() as *const c_void,
+            value.as_concrete_TypeRef() as *const c_void,
+        );
+        CFRelease(focused);
+        if err != AX_ERROR_SUCCESS {
+            return Err(AppError::Ai(format!(
+                "Accessibility insert failed (AXError {})",
+                err
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Accessibility API による挿入は macOS のみ（他の OS ではクリップボード経由の方法を使う）
+#[cfg(not(target_os = "macos"))]
+fn insert_text_with_accessibility(_text: &str) -> TaprResult<()> {
+    Err(AppError::Ai(
+        "accessibility_insert is only supported on macOS".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 呼ばれた操作を記録するモック
+    #[derive(Default)]
+    struct MockBackend {
+        clipboard: Option<String>,
+        calls: Vec<String>,
+        fail_insert: bool,
+        fail_keystroke: bool,
+    }
+
+    #[async_trait]
+    impl PasteBackend for MockBackend {
+        fn get_clipboard(&mut self) -> Option<String> {
+            self.calls.push("get".to_string());
+            self.clipboard.clone()
+        }
+
+        fn set_clipboard(&mut self, text: &str) -> TaprResult<()> {
+            self.calls.push(format!("set:{}", text));
+            self.clipboard = Some(text.to_string());
+            Ok(())
+        }
+
+        fn clear_clipboard(&mut self) {
+            self.calls.push("clear".to_string());
+            self.clipboard = None;
+        }
+
+        async fn send_paste_keystroke(&mut self) -> TaprResult<()> {
+            if self.fail_keystroke {
+                return Err(AppError::Ai("keystroke failed".to_string()));
+            }
+            self.calls.push("cmd+v".to_string());
+            Ok(())
+        }
+
+        async fn type_chunk(&mut self, chunk: &[u16]) -> TaprResult<()> {
+            self.calls
+                .push(format!("type:{}", String::from_utf16(chunk).unwrap()));
+            Ok(())
+        }
+
+        fn insert_text(&mut self, text: &str) -> TaprResult<()> {
+            if self.fail_insert {
+                return Err(AppError::Ai("no focused element".to_string()));
+            }
+            self.calls.push(format!("insert:{}", text));
+            Ok(())
+        }
+    }
+
+    async fn paste(backend: &mut MockBackend, method: PasteMethod) -> TaprResult<()> {
+        perform_paste(backend, method, "hello", Duration::ZERO).await
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_save_restores_original() {
+        let mut backend = MockBackend {
+            clipboard: Some("original".to_string()),
+            ..Default::default()
+        };
+        paste(&mut backend, PasteMethod::ClipboardSave)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls, ["get", "set:hello", "cmd+v", "set:original"]);
+        assert_eq!(backend.clipboard.as_deref(), Some("original"));
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_save_restores_on_keystroke_failure() {
+        let mut backend = MockBackend {
+            clipboard: Some("original".to_string()),
+            fail_keystroke: true,
+            ..Default::default()
+        };
+        let err = paste(&mut backend, PasteMethod::ClipboardSave)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "AI processing error: keystroke failed");
+        assert_eq!(backend.calls, ["get", "set:hello", "set:original"]);
+        assert_eq!(backend.clipboard.as_deref(), Some("original"));
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_save_clears_when_empty() {
+        let mut backend = MockBackend::default();
+        paste(&mut backend, PasteMethod::ClipboardSave)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls.last().map(String::as_str), Some("clear"));
+        assert!(backend.clipboard.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_replace_keeps_text() {
+        let mut backend = MockBackend {
+            clipboard: Some("original".to_string()),
+            ..Default::default()
+        };
+        paste(&mut backend, PasteMethod::ClipboardReplace)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls, ["set:hello", "cmd+v"]);
+        assert_eq!(backend.clipboard.as_deref(), Some("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_accessibility_insert_does_not_touch_clipboard() {
+        let mut backend = MockBackend::default();
+        paste(&mut backend, PasteMethod::AccessibilityInsert)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls, ["insert:hello"]);
+
+        let mut backend = MockBackend {
+            fail_insert: true,
+            ..Default::default()
+        };
+        assert!(paste(&mut backend, PasteMethod::AccessibilityInsert)
+            .await
+            .is_err());
+        assert!(backend.calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_type_sends_chunks_without_clipboard() {
+        let mut backend = MockBackend {
+            clipboard: Some("original".to_string()),
+            ..Default::default()
+        };
+        let text = "今日は箱根の温泉に行って、露天風呂でゆっくりしました。";
+        perform_paste(&mut backend, PasteMethod::Type, text, Duration::ZERO)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            backend.calls,
+            [
+                "type:今日は箱根の温泉に行って、露天風呂でゆっ",
+                "type:くりしました。"
+            ]
+        );
+        assert_eq!(backend.clipboard.as_deref(), Some("original"));
+    }
+
+    #[test]
+    fn test_utf16_chunks_keeps_surrogate_pairs() {
+        // 🏔 は UTF-16 でサロゲートペア（2ユニット）
+        let chunks = utf16_chunks("あい🏔う", 3);
+        let decoded: Vec<String> = chunks
+            .iter()
+            .map(|c| String::from_utf16(c).unwrap())
+            .collect();
+        assert_eq!(decoded, ["あい", "🏔う"]);
+        assert!(chunks.iter().all(|c| c.len() <= 3));
+
+        assert_eq!(
+            utf16_chunks("🏔", 1),
+            vec!["🏔".encode_utf16().collect::<Vec<_>>()]
+        );
+        assert!(utf16_chunks("", 20).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_does_nothing() {
+        let mut backend = MockBackend::default();
+        paste(&mut backend, PasteMethod::Disabled).await.unwrap();
+        assert!(backend.calls.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_paste_method_uses_global_without_mode() {
+        let config = AppConfig {
+            paste: crate::config::app_config::PasteConfig {
+                paste_method: PasteMethod::Disabled,
+            },
+            ..Default::default()
+        };
+        let state = PasteState::new(&config);
+        assert_eq!(
+            resolve_paste_method(&state, None).unwrap(),
+            PasteMethod::Disabled
+        );
+    }
+}
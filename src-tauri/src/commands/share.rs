@@ -0,0 +1,297 @@
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+
+use crate::db::repository::{self, Entry};
+use crate::db::DbState;
+use crate::error::{AppError, TaprResult};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 共有 URL のスキームとホスト
+const SHARE_URL_PREFIX: &str = "tap-onsen://view-entry";
+
+/// Keychain に秘密鍵を保存するサービス名
+const KEYCHAIN_SERVICE: &str = "tap-onsen";
+const KEYCHAIN_ACCOUNT: &str = "share-secret";
+
+/// Keychain が使えない環境で秘密鍵を保存するファイル名（アプリのデータディレクトリ内）
+const SECRET_FILE_NAME: &str = "share-secret";
+
+/// keyring に OS の資格情報ストアがあるプラットフォームか
+///
+/// それ以外では keyring はプロセス内のモックストアになり、再起動のたびに鍵が変わってしまう。
+const HAS_NATIVE_KEYCHAIN: bool = cfg!(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "windows"
+));
+
+/// 共有トークンの署名に使うインストールごとの秘密鍵を保持する Tauri State
+pub struct ShareState {
+    secret: Vec<u8>,
+}
+
+impl ShareState {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+
+    /// Keychain から秘密鍵を読み込む（初回はランダムに生成して保存する）
+    ///
+    /// Keychain が使えない環境では `data_dir` のファイルに保存し、それも使えなければ
+    /// バンドル ID から導出した鍵で代用する。
+    pub fn load(bundle_id: &str, data_dir: &Path) -> Self {
+        if HAS_NATIVE_KEYCHAIN {
+            match load_or_create_keychain_secret() {
+                Ok(secret) => return Self::new(secret),
+                Err(e) => eprintln!(
+                    "[share] keychain unavailable ({}), storing secret in the app data dir",
+                    e
+                ),
+            }
+        } else {
+            eprintln!(
+                "[share] no native keychain on this platform (keyring would use its mock store), storing secret in the app data dir"
+            );
+        }
+        match load_or_create_file_secret(&data_dir.join(SECRET_FILE_NAME)) {
+            Ok(secret) => Self::new(secret),
+            Err(e) => {
+                eprintln!(
+                    "[share] failed to store secret ({}), deriving secret from bundle id",
+                    e
+                );
+                Self::new(Sha256::digest(format!("{}:{}", bundle_id, KEYCHAIN_ACCOUNT)).to_vec())
+            }
+        }
+    }
+}
+
+/// インストールごとの秘密鍵（256 bit）をランダムに生成する
+fn generate_secret() -> Vec<u8> {
+    [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+        .iter()
+        .flat_map(|u| *u.as_bytes())
+        .collect()
+}
+
+fn load_or_create_keychain_secret() -> Result<Vec<u8>, keyring::Error> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)?;
+    match entry.get_password() {
+        Ok(encoded) => Ok(URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|e| keyring::Error::BadEncoding(e.to_string().into_bytes()))?),
+        Err(keyring::Error::NoEntry) => {
+            let secret = generate_secret();
+            entry.set_password(&URL_SAFE_NO_PAD.encode(&secret))?;
+            Ok(secret)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// ファイルから秘密鍵を読み込む（なければ生成し、所有者だけが読めるファイルに保存する）
+fn load_or_create_file_secret(path: &Path) -> std::io::Result<Vec<u8>> {
+    match std::fs::read_to_string(path) {
+        Ok(encoded) => URL_SAFE_NO_PAD
+            .decode(encoded.trim())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let secret = generate_secret();
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            options
+                .open(path)?
+                .write_all(URL_SAFE_NO_PAD.encode(&secret).as_bytes())?;
+            Ok(secret)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 共有リンクを開いたときの `open-shared-entry` イベント
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenSharedEntryEvent {
+    pub entry: Entry,
+}
+
+/// エントリを共有するためのディープリンク URL を生成する
+///
+/// `expiry_hours` を省略すると期限なし（`expires=0`）になる。
+#[tauri::command]
+pub fn generate_entry_share_url(
+    db: State<'_, DbState>,
+    share: State<'_, ShareState>,
+    id: i64,
+    expiry_hours: Option<u32>,
+) -> TaprResult<String> {
+    {
+        let conn = db.conn()?;
+        repository::get_entry(&conn, id)?.ok_or_else(|| AppError::not_found("entry", id))?;
+    }
+    let expires = expiry_hours
+        .map(|hours| now_secs() + hours as u64 * 60 * 60)
+        .unwrap_or(0);
+    let token = sign(&share.secret, id, expires);
+    Ok(format!(
+        "{}?id={}&token={}&expires={}",
+        SHARE_URL_PREFIX, id, token, expires
+    ))
+}
+
+/// 共有 URL のトークンが正しく、期限切れでないか確認する
+#[tauri::command]
+pub fn verify_share_token(
+    share: State<'_, ShareState>,
+    id: i64,
+    token: String,
+    expires: u64,
+) -> bool {
+    verify(&share.secret, id, &token, expires, now_secs())
+}
+
+/// `tap-onsen://view-entry` のディープリンクを処理する
+///
+/// トークンを検証してエントリを読み込み、`open-shared-entry` イベントで通知する。
+pub fn handle_deep_link(app: &AppHandle, url: &Url) {
+    if url.scheme() != "tap-onsen" || url.host_str() != Some("view-entry") {
+        return;
+    }
+    match open_shared_entry(app, url) {
+        Ok(entry) => {
+            let _ = app.emit("open-shared-entry", OpenSharedEntryEvent { entry });
+        }
+        Err(e) => eprintln!("[share] rejected shared link: {}", e),
+    }
+}
+
+fn open_shared_entry(app: &AppHandle, url: &Url) -> TaprResult<Entry> {
+    let (id, token, expires) = parse_share_query(url)
+        .ok_or_else(|| AppError::Config("malformed share link".to_string()))?;
+    let share = app.state::<ShareState>();
+    if !verify(&share.secret, id, &token, expires, now_secs()) {
+        return Err(AppError::Config(
+            "invalid or expired share token".to_string(),
+        ));
+    }
+    let db = app.state::<DbState>();
+    let conn = db.conn()?;
+    repository::get_entry(&conn, id)?.ok_or_else(|| AppError::not_found("entry", id))
+}
+
+fn parse_share_query(url: &Url) -> Option<(i64, String, u64)> {
+    let mut id = None;
+    let mut token = None;
+    let mut expires = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "id" => id = value.parse().ok(),
+            "token" => token = Some(value.into_owned()),
+            "expires" => expires = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some((id?, token?, expires?))
+}
+
+/// `{id}:{expires}` の HMAC-SHA256 を URL セーフな base64 で返す
+fn sign(secret: &[u8], id: i64, expires: u64) -> String {
+    URL_SAFE_NO_PAD.encode(mac_for(secret, id, expires).finalize().into_bytes())
+}
+
+fn verify(secret: &[u8], id: i64, token: &str, expires: u64, now: u64) -> bool {
+    if expires != 0 && now > expires {
+        return false;
+    }
+    let Ok(tag) = URL_SAFE_NO_PAD.decode(token) else {
+        return false;
+    };
+    // 比較は定数時間で行う
+    mac_for(secret, id, expires).verify_slice(&tag).is_ok()
+}
+
+fn mac_for(secret: &[u8], id: i64, expires: u64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(format!("{}:{}", id, expires).as_bytes());
+    mac
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    #[test]
+    fn test_file_secret_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(SECRET_FILE_NAME);
+
+        let secret = load_or_create_file_secret(&path).unwrap();
+        assert_eq!(secret.len(), 32);
+        // 2回目以降は保存済みの鍵を返す（再起動しても共有リンクが有効なまま）
+        assert_eq!(load_or_create_file_secret(&path).unwrap(), secret);
+
+        std::fs::write(&path, "not base64!").unwrap();
+        assert!(load_or_create_file_secret(&path).is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let token = sign(SECRET, 42, 2_000);
+        assert!(verify(SECRET, 42, &token, 2_000, 1_000));
+        // 期限なし
+        let token = sign(SECRET, 42, 0);
+        assert!(verify(SECRET, 42, &token, 0, u64::MAX));
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering() {
+        let token = sign(SECRET, 42, 2_000);
+        assert!(!verify(SECRET, 43, &token, 2_000, 1_000));
+        assert!(!verify(SECRET, 42, &token, 3_000, 1_000));
+        assert!(!verify(b"other-secret", 42, &token, 2_000, 1_000));
+        assert!(!verify(SECRET, 42, "not base64!", 2_000, 1_000));
+    }
+
+    #[test]
+    fn test_verify_rejects_expired() {
+        let token = sign(SECRET, 42, 2_000);
+        assert!(!verify(SECRET, 42, &token, 2_000, 2_001));
+    }
+
+    #[test]
+    fn test_parse_share_query() {
+        let token = sign(SECRET, 7, 100);
+        let url = Url::parse(&format!(
+            "{}?id=7&token={}&expires=100",
+            SHARE_URL_PREFIX, token
+        ))
+        .unwrap();
+        assert_eq!(parse_share_query(&url), Some((7, token, 100)));
+
+        let url = Url::parse("tap-onsen://view-entry?id=7").unwrap();
+        assert!(parse_share_query(&url).is_none());
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+use futures::StreamExt;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::AsyncWriteExt;
+
+use crate::config::app_config::AppConfig;
+use crate::error::{AppError, TaprResult};
+use crate::voice::whisper_models::{model_filename, model_url, scan_models_dir, WhisperModelInfo};
+
+/// 進捗イベントを送る間隔（バイト）
+const PROGRESS_INTERVAL_BYTES: u64 = 1024 * 1024;
+
+/// モデルのダウンロード進捗（`whisper-model-download-progress` イベント）
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub model_name: String,
+    pub downloaded_bytes: u64,
+    /// Content-Length が返らない場合は None
+    pub total_bytes: Option<u64>,
+    pub done: bool,
+}
+
+/// 書き込み途中の `.part` ファイルを、完了する前に失敗・中断したら削除する
+///
+/// コマンドの future が途中で破棄された場合も `Drop` で片付ける。
+struct PartFileGuard {
+    path: PathBuf,
+    completed: bool,
+}
+
+impl Drop for PartFileGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// `WHISPER_MODELS_DIR` にある既知の Whisper モデルとダウンロード状況を返す
+#[tauri::command]
+pub fn list_local_whisper_models(config: State<'_, AppConfig>) -> Vec<WhisperModelInfo> {
+    scan_models_dir(&config.whisper_models_dir)
+}
+
+/// Hugging Face から Whisper モデルを `WHISPER_MODELS_DIR` にダウンロードする
+///
+/// 進捗は `whisper-model-download-progress` イベントで通知する。
+/// 途中のデータは `.part` に書き、完了後にリネームする。失敗・中断したら `.part` は削除する。
+#[tauri::command]
+pub async fn download_whisper_model(
+    app: AppHandle,
+    config: State<'_, AppConfig>,
+    model_name: String,
+) -> TaprResult<DownloadProgress> {
+    let url =
+        model_url(&model_name).ok_or_else(|| AppError::not_found("whisper model", &model_name))?;
+    let dir = config.whisper_models_dir.clone();
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(model_filename(&model_name));
+    let part_path = path.with_extension("bin.part");
+
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| AppError::Audio(format!("Failed to download {}: {}", model_name, e)))?;
+    if !response.status().is_success() {
+        return Err(AppError::Audio(format!(
+            "Failed to download {}: HTTP {}",
+            model_name,
+            response.status()
+        )));
+    }
+
+    let mut progress = DownloadProgress {
+        model_name: model_name.clone(),
+        downloaded_bytes: 0,
+        total_bytes: response.content_length(),
+        done: false,
+    };
+    // file より先に作り、file を閉じてから削除されるようにする
+    let mut part_guard = PartFileGuard {
+        path: part_path.clone(),
+        completed: false,
+    };
+    let mut file = tokio::fs::File::create(&part_path).await?;
+    let mut stream = response.bytes_stream();
+    let mut next_report = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| {
+            AppError::Audio(format!("Download of {} interrupted: {}", model_name, e))
+        })?;
+        file.write_all(&chunk).await?;
+        progress.downloaded_bytes += chunk.len() as u64;
+        if progress.downloaded_bytes >= next_report {
+            let _ = app.emit("whisper-model-download-progress", progress.clone());
+            next_report = progress.downloaded_bytes + PROGRESS_INTERVAL_BYTES;
+        }
+    }
+    file.flush().await?;
+    drop(file);
+    tokio::fs::rename(&part_path, &path).await?;
+    part_guard.completed = true;
+
+    progress.done = true;
+    let _ = app.emit("whisper-model-download-progress", progress.clone());
+    eprintln!(
+        "[audio] downloaded whisper model {} ({} bytes)",
+        model_name, progress.downloaded_bytes
+    );
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_file_guard_removes_unfinished_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("ggml-base.bin.part");
+
+        std::fs::write(&part_path, b"partial").unwrap();
+        drop(PartFileGuard {
+            path: part_path.clone(),
+            completed: false,
+        });
+        assert!(!part_path.exists());
+
+        std::fs::write(&part_path, b"done").unwrap();
+        drop(PartFileGuard {
+            path: part_path.clone(),
+            completed: true,
+        });
+        assert!(part_path.exists());
+    }
+}
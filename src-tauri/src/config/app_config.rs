@@ -0,0 +1,779 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::catalog::OLLAMA_API_BASE;
+use crate::ai::client::DEFAULT_TIMEOUT_SECS as DEFAULT_AI_TIMEOUT_SECS;
+use crate::ai::ProviderType;
+use crate::config::env::parse_optional_env_with;
+use crate::error::{AppError, TaprResult};
+
+/// TEST_MODE=1 で固定される OpenAI の seed
+pub const TEST_MODE_SEED: u64 = 42;
+
+const DEFAULT_GOOGLE_CLOUD_LOCATION: &str = "us-central1";
+const DEFAULT_MAX_RECORDING_SECS: u64 = 600;
+/// 録音開始に必要な最小空き容量のデフォルト（100MB）
+const DEFAULT_MIN_FREE_DISK_BYTES: u64 = 100 * 1024 * 1024;
+/// ⌘V を送ってからクリップボードを復元するまでの待機時間のデフォルト
+const DEFAULT_PASTE_DELAY_MS: u64 = 100;
+/// AI 処理全体のタイムアウトのデフォルト（HTTP クライアントのタイムアウトと揃える）
+const DEFAULT_AI_COMMAND_TIMEOUT_SECS: u64 = 60;
+/// AI_COMMAND_TIMEOUT に指定できる上限
+const MAX_AI_COMMAND_TIMEOUT_SECS: u64 = 300;
+const DEFAULT_WHISPER_TIMEOUT_SECS: u64 = 30;
+
+/// 音声認識のデフォルトエンジン（WHISPER_BACKEND）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhisperBackend {
+    /// macOS Speech Framework
+    #[default]
+    Native,
+    /// OpenAI Whisper API
+    Whisper,
+    /// OpenAI Realtime API（WebSocket で逐次文字起こし）
+    Realtime,
+}
+
+impl WhisperBackend {
+    /// `transcribe_audio` の engine 引数と同じ名前を返す
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WhisperBackend::Native => "native",
+            WhisperBackend::Whisper => "whisper",
+            WhisperBackend::Realtime => "realtime",
+        }
+    }
+}
+
+/// 前面アプリへのペースト方法（PASTE_METHOD）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteMethod {
+    /// クリップボードを書き換えて ⌘V を送る（元の内容は復元しない）
+    ClipboardReplace,
+    /// クリップボードを退避して ⌘V を送り、完了後に復元する
+    #[default]
+    ClipboardSave,
+    /// Accessibility API でフォーカス中の要素に直接挿入する（クリップボードに触れない）
+    AccessibilityInsert,
+    /// テキストを1文字ずつキー入力として送る（クリップボードに触れないが遅い）
+    Type,
+    /// ペーストしない（フロントエンドで表示のみ）
+    Disabled,
+}
+
+impl PasteMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasteMethod::ClipboardReplace => "clipboard_replace",
+            PasteMethod::ClipboardSave => "clipboard_save",
+            PasteMethod::AccessibilityInsert => "accessibility_insert",
+            PasteMethod::Type => "type",
+            PasteMethod::Disabled => "disabled",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [
+            PasteMethod::ClipboardReplace,
+            PasteMethod::ClipboardSave,
+            PasteMethod::AccessibilityInsert,
+            PasteMethod::Type,
+            PasteMethod::Disabled,
+        ]
+        .into_iter()
+        .find(|m| m.as_str() == value)
+    }
+}
+
+/// ペースト設定（全体の既定値、またはモードごとの上書き）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PasteConfig {
+    #[serde(default)]
+    pub paste_method: PasteMethod,
+}
+
+/// 環境変数から読み込むアプリ設定
+///
+/// 起動時に一度だけ `from_env` で組み立て、Tauri State として共有する。
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub ai_provider: ProviderType,
+    /// 主プロバイダーが失敗したときに順に試すプロバイダー（AI_PROVIDER_FALLBACK）
+    pub ai_provider_fallback: Vec<ProviderType>,
+    pub openai_api_key: Option<String>,
+    pub anthropic_api_key: Option<String>,
+    /// OpenAI のモデル名（未設定ならクライアントの既定モデル）
+    pub openai_model: Option<String>,
+    /// Anthropic のモデル名（未設定ならクライアントの既定モデル）
+    pub anthropic_model: Option<String>,
+    pub google_cloud_project: Option<String>,
+    pub google_cloud_location: String,
+    /// Vertex AI のモデル名（未設定ならクライアントの既定モデル）
+    pub vertex_model: Option<String>,
+    /// サービスアカウントキーの JSON（未設定なら gcloud CLI でトークンを取得する）
+    pub google_application_credentials: Option<PathBuf>,
+    /// Ollama サーバーの URL（OLLAMA_HOST、スキームがなければ http:// を補う）
+    pub ollama_host: String,
+    /// Ollama のモデル名（未設定ならクライアントの既定モデル）
+    pub ollama_model: Option<String>,
+    /// OPENAI_SEED（TEST_MODE=1 なら固定値）
+    pub openai_seed: Option<u64>,
+    /// OpenAI で `max_tokens` の代わりに `max_completion_tokens` を送る
+    pub openai_use_max_completion_tokens: bool,
+    /// seed と小型モデルに固定するテスト用モード
+    pub test_mode: bool,
+    pub whisper_backend: WhisperBackend,
+    /// 1回の録音で保持する最大秒数（超えた分は捨てる）
+    pub max_recording_secs: u64,
+    /// 録音開始に必要な最小ディスク空き容量（バイト）
+    pub min_free_disk_bytes: u64,
+    pub paste_delay_ms: u64,
+    /// `process_with_ai` / ストリーミングの最大待ち時間（秒、AI_COMMAND_TIMEOUT）
+    pub ai_command_timeout_secs: u64,
+    /// AI API への HTTP リクエスト1回のタイムアウト（秒、AI_TIMEOUT_SECS）
+    pub ai_timeout_secs: u64,
+    /// 文字起こし1回の最大待ち時間（秒、WHISPER_TIMEOUT）
+    pub whisper_timeout_secs: u64,
+    /// 起動時のペースト方法（実行中は `set_paste_method` で変更できる）
+    pub paste: PasteConfig,
+    /// 一時音声ファイルの保存先（検証済み）
+    pub audio_dir: PathBuf,
+    /// ローカル Whisper モデル（ggml-*.bin）の保存先（WHISPER_MODELS_DIR）
+    pub whisper_models_dir: PathBuf,
+    /// engine "whisper_cpp" で読み込むモデルファイル（WHISPER_CPP_MODEL）
+    pub whisper_cpp_model: Option<PathBuf>,
+    /// HTTP 交換ログを記録する（TAPONSEN_HTTP_DEBUG）
+    pub debug: bool,
+    /// mode_id のタイプミスを近いモードで代用する（FUZZY_MODE_MATCHING）
+    pub fuzzy_mode_matching: bool,
+    pub notifications_enabled: bool,
+    /// PTT のフックに `;` やバッククォートなどのシェル記法を許可する（PTT_ALLOW_ADVANCED_HOOKS）
+    pub ptt_allow_advanced_hooks: bool,
+    /// 監視用 HTTP サーバー（`GET /metrics`）のポート。未設定なら起動しない
+    pub http_server_port: Option<u16>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            ai_provider: ProviderType::default(),
+            ai_provider_fallback: Vec::new(),
+            openai_api_key: None,
+            anthropic_api_key: None,
+            openai_model: None,
+            anthropic_model: None,
+            google_cloud_project: None,
+            google_cloud_location: DEFAULT_GOOGLE_CLOUD_LOCATION.to_string(),
+            vertex_model: None,
+            google_application_credentials: None,
+            ollama_host: OLLAMA_API_BASE.to_string(),
+            ollama_model: None,
+            openai_seed: None,
+            openai_use_max_completion_tokens: false,
+            test_mode: false,
+            whisper_backend: WhisperBackend::default(),
+            max_recording_secs: DEFAULT_MAX_RECORDING_SECS,
+            min_free_disk_bytes: DEFAULT_MIN_FREE_DISK_BYTES,
+            paste_delay_ms: DEFAULT_PASTE_DELAY_MS,
+            ai_command_timeout_secs: DEFAULT_AI_COMMAND_TIMEOUT_SECS,
+            ai_timeout_secs: DEFAULT_AI_TIMEOUT_SECS,
+            whisper_timeout_secs: DEFAULT_WHISPER_TIMEOUT_SECS,
+            paste: PasteConfig::default(),
+            audio_dir: default_audio_dir(),
+            whisper_models_dir: default_whisper_models_dir(None),
+            whisper_cpp_model: None,
+            debug: false,
+            fuzzy_mode_matching: true,
+            notifications_enabled: true,
+            ptt_allow_advanced_hooks: false,
+            http_server_port: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// 環境変数から設定を読み込む
+    ///
+    /// 不正な値は項目ごとに既定値で置き換え、矛盾する値は補正して読み込みを続け、
+    /// 設定と問題の一覧を返す。
+    /// 問題は起動時の警告（`ping` の `startup_warnings`）として表示する。
+    pub fn from_env() -> (Self, Vec<String>) {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// 任意の取得関数から設定を読み込む（空文字列は未設定として扱う）
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> (Self, Vec<String>) {
+        let get = |key: &str| lookup(key).filter(|v| !v.trim().is_empty());
+        let mut errors = Vec::new();
+        let mut config = Self::default();
+
+        if let Some(v) = get("AI_PROVIDER") {
+            match parse_provider_type(&v) {
+                Some(provider) => config.ai_provider = provider,
+                None => errors.push(format!(
+                    "Unknown AI_PROVIDER: '{}'. Use vertexai, openai, anthropic, or ollama.",
+                    v
+                )),
+            }
+        }
+        if let Some(v) = get("AI_PROVIDER_FALLBACK") {
+            for name in v.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                match parse_provider_type(name) {
+                    Some(provider) => config.ai_provider_fallback.push(provider),
+                    None => errors.push(format!(
+                        "Unknown provider in AI_PROVIDER_FALLBACK: '{}'. Use vertexai, openai, anthropic, or ollama.",
+                        name
+                    )),
+                }
+            }
+        }
+        config.openai_api_key = get("OPENAI_API_KEY");
+        config.anthropic_api_key = get("ANTHROPIC_API_KEY");
+        config.openai_model = get("OPENAI_MODEL");
+        config.anthropic_model = get("ANTHROPIC_MODEL");
+        config.vertex_model = get("VERTEX_MODEL");
+        config.google_cloud_project = get("GOOGLE_CLOUD_PROJECT");
+        if let Some(v) = get("GOOGLE_CLOUD_LOCATION") {
+            config.google_cloud_location = v;
+        }
+        config.google_application_credentials =
+            get("GOOGLE_APPLICATION_CREDENTIALS").map(PathBuf::from);
+        if let Some(v) = get("OLLAMA_HOST") {
+            config.ollama_host = normalize_ollama_host(&v);
+        }
+        config.ollama_model = get("OLLAMA_MODEL");
+
+        let mut parse_bool = |key: &str, default: bool| match get(key).as_deref() {
+            None => default,
+            Some("1") | Some("true") => true,
+            Some("0") | Some("false") => false,
+            Some(other) => {
+                errors.push(format!(
+                    "{} must be 1/0 or true/false, got '{}'",
+                    key, other
+                ));
+                default
+            }
+        };
+        config.test_mode = parse_bool("TEST_MODE", false);
+        config.openai_use_max_completion_tokens =
+            parse_bool("OPENAI_USE_MAX_COMPLETION_TOKENS", false);
+        config.debug = parse_bool("TAPONSEN_HTTP_DEBUG", false);
+        config.fuzzy_mode_matching = parse_bool("FUZZY_MODE_MATCHING", true);
+        config.notifications_enabled = parse_bool("NOTIFICATIONS_ENABLED", true);
+        config.ptt_allow_advanced_hooks = parse_bool("PTT_ALLOW_ADVANCED_HOOKS", false);
+
+        config.openai_seed = push_err(parse_optional_env_with(&get, "OPENAI_SEED"), &mut errors);
+        if let Some(n) = push_err(
+            parse_optional_env_with(&get, "MAX_RECORDING_SECS"),
+            &mut errors,
+        ) {
+            config.max_recording_secs = n;
+        }
+        if let Some(n) = push_err(
+            parse_optional_env_with(&get, "MIN_FREE_DISK_BYTES"),
+            &mut errors,
+        ) {
+            config.min_free_disk_bytes = n;
+        }
+        if let Some(n) = push_err(parse_optional_env_with(&get, "PASTE_DELAY_MS"), &mut errors) {
+            config.paste_delay_ms = n;
+        }
+        if let Some(n) = push_err(
+            parse_optional_env_with(&get, "AI_COMMAND_TIMEOUT"),
+            &mut errors,
+        ) {
+            if n == 0 || n > MAX_AI_COMMAND_TIMEOUT_SECS {
+                errors.push(format!(
+                    "AI_COMMAND_TIMEOUT must be between 1 and {} seconds, got {}",
+                    MAX_AI_COMMAND_TIMEOUT_SECS, n
+                ));
+            } else {
+                config.ai_command_timeout_secs = n;
+            }
+        }
+        if let Some(n) = push_err(
+            parse_optional_env_with(&get, "AI_TIMEOUT_SECS"),
+            &mut errors,
+        ) {
+            if n == 0 || n > MAX_AI_COMMAND_TIMEOUT_SECS {
+                errors.push(format!(
+                    "AI_TIMEOUT_SECS must be between 1 and {} seconds, got {}",
+                    MAX_AI_COMMAND_TIMEOUT_SECS, n
+                ));
+            } else {
+                config.ai_timeout_secs = n;
+            }
+        }
+        // HTTP リクエストより先にコマンド全体のタイムアウトで打ち切られないよう、
+        // コマンドのタイムアウトを HTTP タイムアウトまで引き上げる
+        if config.ai_timeout_secs > config.ai_command_timeout_secs {
+            errors.push(format!(
+                "AI_TIMEOUT_SECS ({}) exceeds AI_COMMAND_TIMEOUT ({}); raising AI_COMMAND_TIMEOUT to {}",
+                config.ai_timeout_secs, config.ai_command_timeout_secs, config.ai_timeout_secs
+            ));
+            config.ai_command_timeout_secs = config.ai_timeout_secs;
+        }
+        if let Some(n) = push_err(
+            parse_optional_env_with(&get, "WHISPER_TIMEOUT"),
+            &mut errors,
+        ) {
+            config.whisper_timeout_secs = n;
+        }
+        if config.test_mode {
+            config.openai_seed = Some(TEST_MODE_SEED);
+        }
+        config.http_server_port = push_err(
+            parse_optional_env_with(&get, "HTTP_SERVER_PORT"),
+            &mut errors,
+        );
+
+        if let Some(v) = get("WHISPER_BACKEND") {
+            match v.as_str() {
+                "native" => config.whisper_backend = WhisperBackend::Native,
+                "whisper" => config.whisper_backend = WhisperBackend::Whisper,
+                "realtime" => config.whisper_backend = WhisperBackend::Realtime,
+                other => errors.push(format!(
+                    "Unknown WHISPER_BACKEND: '{}'. Use native, whisper, or realtime.",
+                    other
+                )),
+            }
+        }
+
+        if let Some(v) = get("PASTE_METHOD") {
+            match PasteMethod::parse(&v) {
+                Some(method) => config.paste.paste_method = method,
+                None => errors.push(format!(
+                    "Unknown PASTE_METHOD: '{}'. Use clipboard_replace, clipboard_save, accessibility_insert, type, or disabled.",
+                    v
+                )),
+            }
+        }
+
+        config.whisper_models_dir = match get("WHISPER_MODELS_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => default_whisper_models_dir(get("HOME").as_deref()),
+        };
+        config.whisper_cpp_model = get("WHISPER_CPP_MODEL").map(PathBuf::from);
+
+        match resolve_audio_dir(get("TAPONSEN_AUDIO_DIR").as_deref(), get("HOME").as_deref()) {
+            Ok(dir) => config.audio_dir = dir,
+            Err(e) => {
+                errors.push(e.to_string());
+                // 既定のディレクトリで続ける
+                if let Ok(dir) = resolve_audio_dir(None, None) {
+                    config.audio_dir = dir;
+                }
+            }
+        }
+
+        (config, errors)
+    }
+
+    /// フロントエンドに返してよい（API キーを含まない）設定の概要
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "ai_provider": self.ai_provider,
+            "ai_provider_fallback": self.ai_provider_fallback,
+            "openai_api_key_set": self.openai_api_key.is_some(),
+            "anthropic_api_key_set": self.anthropic_api_key.is_some(),
+            "openai_model": self.openai_model,
+            "anthropic_model": self.anthropic_model,
+            "vertex_model": self.vertex_model,
+            "google_cloud_project": self.google_cloud_project,
+            "google_cloud_location": self.google_cloud_location,
+            "google_application_credentials_set": self.google_application_credentials.is_some(),
+            "ollama_host": self.ollama_host,
+            "ollama_model": self.ollama_model,
+            "test_mode": self.test_mode,
+            "whisper_backend": self.whisper_backend,
+            "max_recording_secs": self.max_recording_secs,
+            "min_free_disk_bytes": self.min_free_disk_bytes,
+            "paste_delay_ms": self.paste_delay_ms,
+            "ai_command_timeout_secs": self.ai_command_timeout_secs,
+            "ai_timeout_secs": self.ai_timeout_secs,
+            "whisper_timeout_secs": self.whisper_timeout_secs,
+            "paste_method": self.paste.paste_method,
+            "audio_dir": self.audio_dir.to_string_lossy(),
+            "whisper_models_dir": self.whisper_models_dir.to_string_lossy(),
+            "whisper_cpp_model": self.whisper_cpp_model.as_ref().map(|p| p.to_string_lossy()),
+            "debug": self.debug,
+            "fuzzy_mode_matching": self.fuzzy_mode_matching,
+            "notifications_enabled": self.notifications_enabled,
+            "ptt_allow_advanced_hooks": self.ptt_allow_advanced_hooks,
+            "http_server_port": self.http_server_port,
+        })
+    }
+}
+
+fn parse_provider_type(value: &str) -> Option<ProviderType> {
+    match value {
+        "vertexai" => Some(ProviderType::VertexAI),
+        "openai" => Some(ProviderType::OpenAI),
+        "anthropic" => Some(ProviderType::Anthropic),
+        "ollama" => Some(ProviderType::Ollama),
+        _ => None,
+    }
+}
+
+/// Ollama と同じく `127.0.0.1:11434` のようなスキームなしの指定も受け付ける
+fn normalize_ollama_host(value: &str) -> String {
+    let host = value.trim().trim_end_matches('/');
+    if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("http://{}", host)
+    }
+}
+
+fn default_audio_dir() -> PathBuf {
+    std::env::temp_dir().join("tap-onsen").join("audio")
+}
+
+/// 解釈できなかった値をエラー一覧に積み、未設定として扱う
+fn push_err<T>(result: TaprResult<Option<T>>, errors: &mut Vec<String>) -> Option<T> {
+    result.unwrap_or_else(|e| {
+        errors.push(e.to_string());
+        None
+    })
+}
+
+/// `~/.cache/whisper`（HOME が不明なら一時ディレクトリ配下）
+fn default_whisper_models_dir(home: Option<&str>) -> PathBuf {
+    home.map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".cache")
+        .join("whisper")
+}
+
+/// 音声ディレクトリを決定し、作成・書き込み可能であることを確認する
+///
+/// 上書き指定は絶対パスのみ受け付け、`..` を含むパスとルートディレクトリは拒否する。
+/// シンボリックリンクを解決したうえで一時ディレクトリか `home` の配下にあることを確かめてから作成し、
+/// 解決後のパスを返す。
+pub(crate) fn resolve_audio_dir(
+    override_dir: Option<&str>,
+    home: Option<&str>,
+) -> TaprResult<PathBuf> {
+    let Some(raw) = override_dir.filter(|s| !s.is_empty()) else {
+        let dir = default_audio_dir();
+        std::fs::create_dir_all(&dir)?;
+        return Ok(dir);
+    };
+
+    let invalid = |reason: &str| AppError::FileSystem(format!("invalid audio dir: {}", reason));
+    let dir = PathBuf::from(raw);
+    if !dir.is_absolute() {
+        return Err(invalid("path must be absolute"));
+    }
+    if dir.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(invalid("path must not contain '..'"));
+    }
+    if dir.parent().is_none() {
+        return Err(invalid("path must not be the root directory"));
+    }
+
+    let dir = canonicalize_partial(&dir).map_err(|e| invalid(&e.to_string()))?;
+    let allowed = allowed_audio_roots(home)
+        .iter()
+        .any(|root| dir.starts_with(root) && &dir != root);
+    if !allowed {
+        return Err(invalid(
+            "path must be under the temp directory or the home directory",
+        ));
+    }
+
+    std::fs::create_dir_all(&dir).map_err(|e| invalid(&e.to_string()))?;
+    let probe = dir.join(".write_test");
+    std::fs::write(&probe, b"")
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|_| invalid("path is not writable"))?;
+    Ok(dir)
+}
+
+/// 音声ディレクトリを置いてよい場所（一時ディレクトリと HOME、正規化済み）
+fn allowed_audio_roots(home: Option<&str>) -> Vec<PathBuf> {
+    std::iter::once(std::env::temp_dir())
+        .chain(home.map(PathBuf::from))
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+/// まだ存在しない部分を含むパスを正規化する
+///
+/// 存在する一番深い祖先を `canonicalize` でシンボリックリンクごと解決し、残りの要素を繋げる。
+fn canonicalize_partial(path: &Path) -> std::io::Result<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(base) => return Ok(rest.iter().rev().fold(base, |acc, name| acc.join(name))),
+            Err(e) => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(e);
+                };
+                rest.push(name);
+                existing = parent;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup_from(vars: &[(&str, &str)]) -> (AppConfig, Vec<String>) {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        AppConfig::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    fn config_from(vars: &[(&str, &str)]) -> Result<AppConfig, Vec<String>> {
+        match lookup_from(vars) {
+            (config, errors) if errors.is_empty() => Ok(config),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    #[test]
+    fn test_invalid_values_fall_back_to_defaults() {
+        let (config, errors) = lookup_from(&[
+            ("AI_PROVIDER", "opneai"),
+            ("MAX_RECORDING_SECS", "ten"),
+            ("TAPONSEN_AUDIO_DIR", "relative/audio"),
+            ("PASTE_DELAY_MS", "250"),
+        ]);
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("Unknown AI_PROVIDER"));
+        // 不正な項目は既定値、正しい項目は指定どおり
+        assert_eq!(config.ai_provider, ProviderType::default());
+        assert_eq!(config.max_recording_secs, DEFAULT_MAX_RECORDING_SECS);
+        assert_eq!(config.audio_dir, resolve_audio_dir(None, None).unwrap());
+        assert_eq!(config.paste_delay_ms, 250);
+    }
+
+    #[test]
+    fn test_from_lookup_defaults() {
+        let config = config_from(&[]).unwrap();
+        assert_eq!(config.ai_provider, ProviderType::VertexAI);
+        assert!(config.ai_provider_fallback.is_empty());
+        assert_eq!(config.whisper_backend, WhisperBackend::Native);
+        assert_eq!(config.max_recording_secs, DEFAULT_MAX_RECORDING_SECS);
+        assert!(config.fuzzy_mode_matching);
+        assert!(!config.ptt_allow_advanced_hooks);
+        assert!(config.openai_seed.is_none());
+        assert!(config.google_application_credentials.is_none());
+        assert!(config.openai_model.is_none());
+        assert!(config.vertex_model.is_none());
+        assert_eq!(config.ollama_host, "http://localhost:11434");
+        assert!(config.ollama_model.is_none());
+    }
+
+    #[test]
+    fn test_from_lookup_parses_values() {
+        let config = config_from(&[
+            ("AI_PROVIDER", "openai"),
+            ("AI_PROVIDER_FALLBACK", "anthropic, ollama"),
+            ("OPENAI_API_KEY", "sk-test"),
+            ("WHISPER_BACKEND", "whisper"),
+            ("PASTE_DELAY_MS", "250"),
+            ("FUZZY_MODE_MATCHING", "0"),
+            ("TEST_MODE", "1"),
+            ("PASTE_METHOD", "accessibility_insert"),
+            ("GOOGLE_APPLICATION_CREDENTIALS", "/etc/gcp/sa.json"),
+            ("OPENAI_MODEL", "gpt-4.1"),
+            ("VERTEX_MODEL", "gemini-2.5-flash"),
+            ("OLLAMA_HOST", "127.0.0.1:11500/"),
+            ("OLLAMA_MODEL", "qwen2.5:7b"),
+        ])
+        .unwrap();
+        assert_eq!(
+            config.google_application_credentials,
+            Some(PathBuf::from("/etc/gcp/sa.json"))
+        );
+        assert_eq!(config.paste.paste_method, PasteMethod::AccessibilityInsert);
+        assert_eq!(config.ai_provider, ProviderType::OpenAI);
+        assert_eq!(
+            config.ai_provider_fallback,
+            vec![ProviderType::Anthropic, ProviderType::Ollama]
+        );
+        assert_eq!(config.openai_api_key.as_deref(), Some("sk-test"));
+        assert_eq!(config.openai_model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(config.vertex_model.as_deref(), Some("gemini-2.5-flash"));
+        assert_eq!(config.ollama_host, "http://127.0.0.1:11500");
+        assert_eq!(config.ollama_model.as_deref(), Some("qwen2.5:7b"));
+        assert_eq!(config.whisper_backend, WhisperBackend::Whisper);
+        assert_eq!(config.paste_delay_ms, 250);
+        assert!(!config.fuzzy_mode_matching);
+        assert_eq!(config.openai_seed, Some(TEST_MODE_SEED));
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let config = config_from(&[]).unwrap();
+        assert_eq!(config.ai_command_timeout_secs, 60);
+        assert_eq!(config.ai_timeout_secs, 30);
+        assert_eq!(config.whisper_timeout_secs, 30);
+
+        let config = config_from(&[
+            ("AI_COMMAND_TIMEOUT", "120"),
+            ("AI_TIMEOUT_SECS", "90"),
+            ("WHISPER_TIMEOUT", "45"),
+        ])
+        .unwrap();
+        assert_eq!(config.ai_command_timeout_secs, 120);
+        assert_eq!(config.ai_timeout_secs, 90);
+        assert_eq!(config.whisper_timeout_secs, 45);
+
+        let errors = config_from(&[("AI_TIMEOUT_SECS", "0")]).unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["AI_TIMEOUT_SECS must be between 1 and 300 seconds, got 0"]
+        );
+
+        let errors = config_from(&[("AI_COMMAND_TIMEOUT", "301")]).unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["AI_COMMAND_TIMEOUT must be between 1 and 300 seconds, got 301"]
+        );
+
+        // AI_COMMAND_TIMEOUT（既定 60 秒）より長い HTTP タイムアウトは警告してコマンド側を引き上げる
+        let (config, warnings) = lookup_from(&[("AI_TIMEOUT_SECS", "90")]);
+        assert_eq!(config.ai_timeout_secs, 90);
+        assert_eq!(config.ai_command_timeout_secs, 90);
+        assert_eq!(
+            warnings,
+            vec!["AI_TIMEOUT_SECS (90) exceeds AI_COMMAND_TIMEOUT (60); raising AI_COMMAND_TIMEOUT to 90"]
+        );
+        let (config, warnings) = lookup_from(&[("AI_COMMAND_TIMEOUT", "20")]);
+        assert_eq!(config.ai_timeout_secs, 30);
+        assert_eq!(config.ai_command_timeout_secs, 30);
+        assert_eq!(
+            warnings,
+            vec!["AI_TIMEOUT_SECS (30) exceeds AI_COMMAND_TIMEOUT (20); raising AI_COMMAND_TIMEOUT to 30"]
+        );
+    }
+
+    #[test]
+    fn test_whisper_models_dir() {
+        let config = config_from(&[("HOME", "/Users/test")]).unwrap();
+        assert_eq!(
+            config.whisper_models_dir,
+            PathBuf::from("/Users/test/.cache/whisper")
+        );
+        let config = config_from(&[("WHISPER_MODELS_DIR", "/opt/whisper")]).unwrap();
+        assert_eq!(config.whisper_models_dir, PathBuf::from("/opt/whisper"));
+        assert_eq!(config.whisper_cpp_model, None);
+
+        let config = config_from(&[("WHISPER_CPP_MODEL", "/opt/whisper/ggml-base.bin")]).unwrap();
+        assert_eq!(
+            config.whisper_cpp_model,
+            Some(PathBuf::from("/opt/whisper/ggml-base.bin"))
+        );
+    }
+
+    #[test]
+    fn test_from_lookup_collects_all_errors() {
+        let errors = config_from(&[
+            ("AI_PROVIDER", "gemini"),
+            ("AI_PROVIDER_FALLBACK", "openai,groq"),
+            ("MAX_RECORDING_SECS", "ten"),
+            ("TAPONSEN_HTTP_DEBUG", "yes"),
+            ("TAPONSEN_AUDIO_DIR", "relative/audio"),
+        ])
+        .unwrap_err();
+        assert_eq!(errors.len(), 5);
+        assert!(errors[0].contains("AI_PROVIDER"));
+        assert!(errors[1].contains("AI_PROVIDER_FALLBACK: 'groq'"));
+        assert!(errors
+            .contains(&"Invalid value for MAX_RECORDING_SECS: 'ten' (expected u64)".to_string()));
+    }
+
+    #[test]
+    fn test_summary_omits_secrets() {
+        let config = config_from(&[("ANTHROPIC_API_KEY", "sk-ant-secret")]).unwrap();
+        let summary = config.summary().to_string();
+        assert!(!summary.contains("sk-ant-secret"));
+        assert!(summary.contains("\"anthropic_api_key_set\":true"));
+    }
+
+    #[test]
+    fn test_resolve_audio_dir_default() {
+        let dir = resolve_audio_dir(None, None).unwrap();
+        assert!(dir.ends_with("tap-onsen/audio"));
+        assert_eq!(resolve_audio_dir(Some(""), None).unwrap(), dir);
+    }
+
+    #[test]
+    fn test_resolve_audio_dir_env_override() {
+        let root = std::env::temp_dir()
+            .join("tap-onsen-test")
+            .join(uuid::Uuid::new_v4().to_string());
+        let dir = root.join("custom");
+        let resolved = resolve_audio_dir(Some(dir.to_str().unwrap()), None).unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(resolved, dir.canonicalize().unwrap());
+        // 書き込み確認用のファイルは残らない
+        assert!(!dir.join(".write_test").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_audio_dir_rejects_invalid_paths() {
+        let traversal = std::env::temp_dir()
+            .join("tap-onsen")
+            .join("..")
+            .join("escape");
+        for bad in [
+            "relative/audio".to_string(),
+            traversal.to_string_lossy().to_string(),
+            "/".to_string(),
+        ] {
+            let err = resolve_audio_dir(Some(&bad), None).unwrap_err();
+            assert!(
+                err.to_string().contains("invalid audio dir"),
+                "{} should be rejected",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_audio_dir_rejects_outside_allowed_roots_before_creating() {
+        let home = std::env::temp_dir()
+            .join("tap-onsen-test")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&home).unwrap();
+        let home_str = home.to_str().unwrap();
+
+        // 一時ディレクトリの外で HOME でもない場所は作成せずに拒否する
+        let outside = Path::new("/tap-onsen-audio-test").join(uuid::Uuid::new_v4().to_string());
+        let err = resolve_audio_dir(Some(outside.to_str().unwrap()), Some(home_str)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("temp directory or the home directory"));
+        assert!(!outside.exists());
+
+        // 許可された場所から外を指すシンボリックリンクはリンク先で判定する
+        #[cfg(unix)]
+        {
+            let link = home.join("link");
+            std::os::unix::fs::symlink("/", &link).unwrap();
+            let escaped = link.join("tap-onsen-audio-test");
+            let err =
+                resolve_audio_dir(Some(escaped.to_str().unwrap()), Some(home_str)).unwrap_err();
+            assert!(err.to_string().contains("invalid audio dir"));
+            assert!(!Path::new("/tap-onsen-audio-test").exists());
+        }
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
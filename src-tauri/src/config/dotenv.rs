@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// APP_ENV が未設定のときの環境名
+const DEFAULT_APP_ENV: &str = "development";
+
+/// 読み込んだ `.env` ファイルの一覧を保持する Tauri State（デバッグパネル用）
+pub struct EnvFilesState {
+    pub loaded: Vec<String>,
+}
+
+/// `.env` → `.env.{APP_ENV}` → `.env.local` の順に読み込み、プロセスの環境変数に反映する
+///
+/// 後のファイルほど優先されるが、すでにプロセスに設定されている環境変数は上書きしない。
+/// `.env` は dotenvy と同じくカレントディレクトリから親へ遡って探し、
+/// 見つかったディレクトリ（なければカレントディレクトリ）の他のファイルも読む。
+/// APP_ENV はプロセスの環境変数、`.env` の値、`development` の順に決める。
+/// 読み込んだファイルのパスを返す。
+pub fn load_env_files() -> Vec<PathBuf> {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let dir = cwd
+        .ancestors()
+        .find(|dir| dir.join(".env").is_file())
+        .unwrap_or(&cwd)
+        .to_path_buf();
+    let app_env = std::env::var("APP_ENV")
+        .ok()
+        .or_else(|| {
+            read_env_file(&dir.join(".env"))
+                .ok()?
+                .into_iter()
+                .find_map(|(key, value)| (key == "APP_ENV").then_some(value))
+        })
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_APP_ENV.to_string());
+
+    let (vars, loaded) = merge_env_files(&dir, &app_env);
+    for (key, value) in vars {
+        if std::env::var_os(&key).is_none() {
+            std::env::set_var(key, value);
+        }
+    }
+    loaded
+}
+
+/// `dir` の env ファイルを優先度の低い順に読み、マージした変数と読み込んだファイルを返す
+///
+/// `.env.{app_env}` が見つからない場合は警告だけ出して続行する。
+/// 解釈できないファイルも警告を出して読み飛ばす。
+pub fn merge_env_files(dir: &Path, app_env: &str) -> (HashMap<String, String>, Vec<PathBuf>) {
+    let env_specific = format!(".env.{}", app_env);
+    let mut vars = HashMap::new();
+    let mut loaded = Vec::new();
+
+    for name in [".env", env_specific.as_str(), ".env.local"] {
+        let path = dir.join(name);
+        if !path.is_file() {
+            if name == env_specific {
+                eprintln!("[config] {} not found, skipping", path.display());
+            }
+            continue;
+        }
+        match read_env_file(&path) {
+            Ok(file_vars) => {
+                vars.extend(file_vars);
+                loaded.push(path);
+            }
+            Err(e) => eprintln!("[config] failed to load {}: {}", path.display(), e),
+        }
+    }
+    (vars, loaded)
+}
+
+fn read_env_file(path: &Path) -> Result<Vec<(String, String)>, dotenvy::Error> {
+    dotenvy::from_path_iter(path)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, contents: &str) {
+        std::fs::write(dir.join(name), contents).unwrap();
+    }
+
+    fn names(loaded: &[PathBuf]) -> Vec<String> {
+        loaded
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_env_specific_overrides_base() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            ".env",
+            "AI_PROVIDER=vertexai\nPASTE_DELAY_MS=100\n",
+        );
+        write(dir.path(), ".env.production", "AI_PROVIDER=openai\n");
+        write(dir.path(), ".env.development", "AI_PROVIDER=anthropic\n");
+
+        let (vars, loaded) = merge_env_files(dir.path(), "production");
+        assert_eq!(vars["AI_PROVIDER"], "openai");
+        assert_eq!(vars["PASTE_DELAY_MS"], "100");
+        assert_eq!(names(&loaded), vec![".env", ".env.production"]);
+    }
+
+    #[test]
+    fn test_local_overrides_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), ".env", "AI_PROVIDER=vertexai\nTEST_MODE=0\n");
+        write(dir.path(), ".env.development", "AI_PROVIDER=openai\n");
+        write(
+            dir.path(),
+            ".env.local",
+            "AI_PROVIDER=anthropic\nTEST_MODE=1\n",
+        );
+
+        let (vars, loaded) = merge_env_files(dir.path(), "development");
+        assert_eq!(vars["AI_PROVIDER"], "anthropic");
+        assert_eq!(vars["TEST_MODE"], "1");
+        assert_eq!(
+            names(&loaded),
+            vec![".env", ".env.development", ".env.local"]
+        );
+    }
+
+    #[test]
+    fn test_missing_env_specific_file_is_not_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), ".env", "AI_PROVIDER=openai\n");
+
+        let (vars, loaded) = merge_env_files(dir.path(), "staging");
+        assert_eq!(vars["AI_PROVIDER"], "openai");
+        assert_eq!(names(&loaded), vec![".env"]);
+
+        let empty = tempfile::tempdir().unwrap();
+        let (vars, loaded) = merge_env_files(empty.path(), "development");
+        assert!(vars.is_empty());
+        assert!(loaded.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::{AppError, TaprResult};
+
+/// 環境変数を読み、`T::from_str` で解釈する（未設定・空なら `default`）
+///
+/// 解釈できない場合は変数名・値・期待する型を含む `AppError::EnvVarParse` を返す。
+pub fn parse_env<T: FromStr>(var: &str, default: T) -> TaprResult<T> {
+    parse_env_with(|key| std::env::var(key).ok(), var, default)
+}
+
+/// 任意の取得関数から値を読んで解釈する（`AppConfig::from_lookup` 用）
+pub fn parse_env_with<T: FromStr>(
+    lookup: impl Fn(&str) -> Option<String>,
+    var: &str,
+    default: T,
+) -> TaprResult<T> {
+    Ok(parse_optional_env_with(lookup, var)?.unwrap_or(default))
+}
+
+/// 既定値のない設定用。未設定・空なら None を返す
+pub fn parse_optional_env_with<T: FromStr>(
+    lookup: impl Fn(&str) -> Option<String>,
+    var: &str,
+) -> TaprResult<Option<T>> {
+    let Some(raw) = lookup(var).filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    raw.trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| AppError::EnvVarParse {
+            var: var.to_string(),
+            value: raw.to_string(),
+            expected: std::any::type_name::<T>().to_string(),
+        })
+}
+
+/// `500ms` / `30s` / `2m` 形式（単位なしは秒）の時間指定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvDuration(pub Duration);
+
+impl FromStr for EnvDuration {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let duration = if let Some(ms) = s.strip_suffix("ms") {
+            Duration::from_millis(ms.trim().parse()?)
+        } else if let Some(secs) = s.strip_suffix('s') {
+            Duration::from_secs(secs.trim().parse()?)
+        } else if let Some(mins) = s.strip_suffix('m') {
+            Duration::from_secs(mins.trim().parse::<u64>()? * 60)
+        } else {
+            Duration::from_secs(s.parse()?)
+        };
+        Ok(Self(duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(value: &'static str) -> impl Fn(&str) -> Option<String> {
+        move |_| Some(value.to_string())
+    }
+
+    #[test]
+    fn test_parse_integer() {
+        assert_eq!(
+            parse_env_with(lookup("600"), "MAX_RECORDING_SECS", 0u64).unwrap(),
+            600
+        );
+        let err = parse_env_with(lookup("abc"), "MAX_RECORDING_SECS", 0u64).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value for MAX_RECORDING_SECS: 'abc' (expected u64)"
+        );
+    }
+
+    #[test]
+    fn test_parse_float() {
+        assert_eq!(
+            parse_env_with(lookup("0.7"), "TEMPERATURE", 1.0f32).unwrap(),
+            0.7
+        );
+        assert!(matches!(
+            parse_env_with(lookup("warm"), "TEMPERATURE", 1.0f32),
+            Err(AppError::EnvVarParse { expected, .. }) if expected == "f32"
+        ));
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert!(parse_env_with(lookup("true"), "FLAG", false).unwrap());
+        assert!(matches!(
+            parse_env_with(lookup("yes"), "FLAG", false),
+            Err(AppError::EnvVarParse { value, .. }) if value == "yes"
+        ));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let parse =
+            |v: &'static str| parse_env_with(lookup(v), "TIMEOUT", EnvDuration(Duration::ZERO));
+        assert_eq!(parse("500ms").unwrap().0, Duration::from_millis(500));
+        assert_eq!(parse("30s").unwrap().0, Duration::from_secs(30));
+        assert_eq!(parse("2m").unwrap().0, Duration::from_secs(120));
+        assert_eq!(parse("45").unwrap().0, Duration::from_secs(45));
+        assert!(parse("soon").is_err());
+    }
+
+    #[test]
+    fn test_unset_or_empty_uses_default() {
+        assert_eq!(parse_env_with(|_| None, "PORT", 8080u16).unwrap(), 8080);
+        assert_eq!(parse_env_with(lookup("  "), "PORT", 8080u16).unwrap(), 8080);
+        assert_eq!(
+            parse_env::<u16>("TAPONSEN_TEST_UNSET_PORT_VAR", 8080).unwrap(),
+            8080
+        );
+    }
+}
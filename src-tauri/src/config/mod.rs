@@ -1 +1,4 @@
+pub mod app_config;
+pub mod dotenv;
+pub mod env;
 pub mod modes;
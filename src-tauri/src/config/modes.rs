@@ -1,16 +1,106 @@
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use tauri::Manager;
 
-use crate::error::AppError;
+use crate::config::app_config::PasteConfig;
+use crate::error::{AppError, TaprResult};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ModeConfig {
     pub id: String,
     pub label: String,
     pub description: String,
     pub ai_enabled: bool,
     pub ai_prompt: Option<String>,
+    /// `ai_prompt` の代わりにプロンプトを読み込むファイル（modes.yaml からの相対パス）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_prompt_file: Option<String>,
+    /// OpenAI で `max_completion_tokens` を使うか（未指定なら AppConfig に従う）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_max_completion_tokens: Option<bool>,
+    /// OpenAI でリクエストごとに順番に使うモデル（429 を受けたら次のモデルで再試行する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_rotation: Option<Vec<String>>,
+    /// 生成の temperature（未指定ならプロバイダーの既定値。校正は低め、発想出しは高めにする）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// 出力の最大トークン数（未指定ならプロバイダーの既定値。Anthropic は 1024）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// クリップボードの内容を {clipboard} としてプロンプトに渡すか（オプトイン）
+    #[serde(default)]
+    pub include_clipboard_context: bool,
+    /// {clipboard} に付けるラベル（未指定なら "Clipboard"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clipboard_label: Option<String>,
+    /// AI 出力の折り返し設定（未指定なら折り返さない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_wrap: Option<LineWrapConfig>,
+    /// AI 出力の引用符・ダッシュの組版設定（未指定なら変換しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typography: Option<TypographicOpts>,
+    /// 日本語の表記ゆれ（半角カナ・不要な空白など）を正規化するか
+    #[serde(default)]
+    pub normalize_japanese: bool,
+    /// true なら AI 処理前の文字起こし結果に、false なら AI 出力に正規化を適用する
+    #[serde(default)]
+    pub apply_before_ai: bool,
+    /// このモードだけペースト方法を上書きする（未指定なら全体設定に従う）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paste: Option<PasteConfig>,
+    /// 組み立て後のプロンプトの最大文字数（未指定なら制限しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_chars: Option<usize>,
+    /// `max_prompt_chars` を超えたときの切り詰め方
+    #[serde(default)]
+    pub truncation_strategy: TruncationStrategy,
+    /// AI 出力の最大文字数（超えたら単語境界で切り詰める。未指定なら制限しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_chars: Option<usize>,
+    /// このモードを提案する前面アプリ名（部分一致・大文字小文字を区別しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_affinity: Option<Vec<String>>,
+}
+
+/// AI 出力の行長を整える設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineWrapConfig {
+    /// 1行あたりの最大文字数
+    pub max_chars_per_line: usize,
+    /// 空行を段落区切りとして残すか
+    #[serde(default)]
+    pub preserve_paragraphs: bool,
+}
+
+/// AI 出力の引用符・ダッシュを組版用の文字に置き換える設定
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TypographicOpts {
+    /// 直線の引用符を開き・閉じの引用符（日本語なら「」『』）にするか
+    #[serde(default)]
+    pub typographic_quotes: bool,
+    /// ` -- ` / ` --- ` を ` — ` にするか
+    #[serde(default)]
+    pub em_dash: bool,
+    /// 引用符のスタイルを選ぶ言語（"ja" など。未指定なら本文から判定する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_hint: Option<String>,
+}
+
+/// プロンプトが `max_prompt_chars` を超えたときの扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// 入力の先頭を削る（末尾の方が関連性が高いことが多いため）
+    #[default]
+    TruncateInput,
+    /// 古いコンテキストから捨てる
+    TruncateContext,
+    /// 予算を入力とコンテキストで半分ずつに分ける
+    TruncateBoth,
+    /// 切り詰めずにエラーにする
+    Fail,
 }
 
 #[derive(Debug, Deserialize)]
@@ -20,15 +110,28 @@ struct ModesFile {
 
 const FALLBACK_MODES_YAML: &str = include_str!("../../../config/modes.yaml");
 
+/// タイプミスとみなして代用するモードIDの最大編集距離
+pub const MAX_FUZZY_MODE_DISTANCE: u32 = 2;
+
+/// 開発時の modes.yaml（CWD = src-tauri）
+const DEV_MODES_PATH: &str = "../config/modes.yaml";
+
+/// 読み込み済みのプロンプトファイル（パス → 内容）
+///
+/// モード一覧はコマンドのたびに読み直すため、ファイルの内容だけを保持する。
+/// 更新は `reload_prompt_file` で反映する。
+fn prompt_cache() -> &'static Mutex<HashMap<PathBuf, String>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
 /// AppHandle なしで設定を読み込む（Team E 等の内部呼び出し用）
 ///
 /// 開発時相対パス → include_str! フォールバック の順で読み込む。
 pub fn load_modes() -> Result<Vec<ModeConfig>, String> {
-    let dev_path = Path::new("../config/modes.yaml");
+    let dev_path = Path::new(DEV_MODES_PATH);
     if dev_path.exists() {
-        let content = std::fs::read_to_string(dev_path)
-            .map_err(|e| format!("Failed to read {}: {}", dev_path.display(), e))?;
-        return parse_yaml_str(&content);
+        return load_from_path(dev_path).map_err(|e| e.to_string());
     }
     parse_yaml_str(FALLBACK_MODES_YAML)
 }
@@ -39,32 +142,84 @@ pub fn load_modes() -> Result<Vec<ModeConfig>, String> {
 /// 1. Tauri リソースディレクトリ（本番ビルド）
 /// 2. ../config/modes.yaml（開発時、CWD = src-tauri）
 /// 3. コンパイル時埋め込み（フォールバック）
-pub fn load_modes_from_app(app: &tauri::AppHandle) -> Result<Vec<ModeConfig>, AppError> {
+pub fn load_modes_from_app(app: &tauri::AppHandle) -> TaprResult<Vec<ModeConfig>> {
+    match find_modes_yaml(app) {
+        Some(path) => load_from_path(&path),
+        // 3. コンパイル時埋め込みにフォールバック（プロンプトファイルは参照できない）
+        None => parse_yaml(FALLBACK_MODES_YAML),
+    }
+}
+
+/// 読み込む modes.yaml のパス（埋め込みにフォールバックする場合は None）
+fn find_modes_yaml(app: &tauri::AppHandle) -> Option<PathBuf> {
     // 1. リソースディレクトリから読み込み（本番環境）
     if let Ok(resource_dir) = app.path().resource_dir() {
         let yaml_path = resource_dir.join("config").join("modes.yaml");
         if yaml_path.exists() {
-            return load_from_path(&yaml_path);
+            return Some(yaml_path);
         }
     }
 
     // 2. 開発時の相対パスから読み込み
-    let dev_path = Path::new("../config/modes.yaml");
-    if dev_path.exists() {
-        return load_from_path(dev_path);
-    }
-
-    // 3. コンパイル時埋め込みにフォールバック
-    parse_yaml(FALLBACK_MODES_YAML)
+    let dev_path = Path::new(DEV_MODES_PATH);
+    dev_path.exists().then(|| dev_path.to_path_buf())
 }
 
-fn load_from_path(path: &Path) -> Result<Vec<ModeConfig>, AppError> {
+/// modes.yaml を読み込み、`ai_prompt_file` を YAML と同じディレクトリから解決する
+fn load_from_path(path: &Path) -> TaprResult<Vec<ModeConfig>> {
     let content = std::fs::read_to_string(path)
         .map_err(|e| AppError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
-    parse_yaml(&content)
+    let mut modes = parse_yaml(&content)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    for mode in &mut modes {
+        if let Some(file) = &mode.ai_prompt_file {
+            mode.ai_prompt = Some(load_prompt_file(&base_dir.join(file), false)?);
+        }
+    }
+    Ok(modes)
+}
+
+/// プロンプトファイルを読む（`force` でなければキャッシュを優先する）
+fn load_prompt_file(path: &Path, force: bool) -> TaprResult<String> {
+    if !force {
+        if let Some(content) = prompt_cache().lock()?.get(path) {
+            return Ok(content.clone());
+        }
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|_| AppError::Config(format!("prompt file not found: {}", path.display())))?;
+    prompt_cache()
+        .lock()?
+        .insert(path.to_path_buf(), content.clone());
+    Ok(content)
+}
+
+/// 指定モードのプロンプトファイルだけを読み直してキャッシュを更新する
+fn reload_prompt_from_yaml(yaml_path: &Path, mode_id: &str) -> TaprResult<String> {
+    let content = std::fs::read_to_string(yaml_path)
+        .map_err(|e| AppError::Config(format!("Failed to read {}: {}", yaml_path.display(), e)))?;
+    let modes = parse_yaml(&content)?;
+    let mode = modes
+        .iter()
+        .find(|m| m.id == mode_id)
+        .ok_or_else(|| AppError::not_found("mode", mode_id))?;
+    let file = mode
+        .ai_prompt_file
+        .as_ref()
+        .ok_or_else(|| AppError::Config(format!("mode '{}' has no ai_prompt_file", mode_id)))?;
+    let base_dir = yaml_path.parent().unwrap_or(Path::new(""));
+    load_prompt_file(&base_dir.join(file), true)
+}
+
+/// 指定モードのプロンプトファイルを再読み込みする（Tauri コマンド用）
+pub fn reload_prompt_file_from_app(app: &tauri::AppHandle, mode_id: &str) -> TaprResult<String> {
+    let yaml_path = find_modes_yaml(app).ok_or_else(|| {
+        AppError::Config("modes.yaml is embedded; prompt files cannot be reloaded".to_string())
+    })?;
+    reload_prompt_from_yaml(&yaml_path, mode_id)
 }
 
-fn parse_yaml(content: &str) -> Result<Vec<ModeConfig>, AppError> {
+fn parse_yaml(content: &str) -> TaprResult<Vec<ModeConfig>> {
     let modes_file: ModesFile = serde_yaml::from_str(content)
         .map_err(|e| AppError::Config(format!("Failed to parse modes.yaml: {}", e)))?;
     Ok(modes_file.modes)
@@ -75,3 +230,208 @@ fn parse_yaml_str(content: &str) -> Result<Vec<ModeConfig>, String> {
         .map_err(|e| format!("Failed to parse modes.yaml: {}", e))?;
     Ok(modes_file.modes)
 }
+
+/// 編集距離（レーベンシュタイン距離）が最小のモードとその距離を返す
+///
+/// 完全一致なら距離 0。モードが空なら None。
+pub fn find_mode_fuzzy<'a>(modes: &'a [ModeConfig], id: &str) -> Option<(&'a ModeConfig, u32)> {
+    modes
+        .iter()
+        .map(|m| (m, strsim::levenshtein(&m.id, id) as u32))
+        .min_by_key(|(_, distance)| *distance)
+}
+
+/// モードIDからモードを解決する
+///
+/// 完全一致しない場合、`allow_fuzzy` なら編集距離 `MAX_FUZZY_MODE_DISTANCE` 以内の
+/// モードで代用する。戻り値の bool は代用したかどうか。
+/// 見つからない場合は最も近いモードIDをエラーメッセージで提案する。
+pub fn resolve_mode(
+    modes: &[ModeConfig],
+    id: &str,
+    allow_fuzzy: bool,
+) -> TaprResult<(ModeConfig, bool)> {
+    match find_mode_fuzzy(modes, id) {
+        Some((mode, 0)) => Ok((mode.clone(), false)),
+        Some((mode, distance)) if allow_fuzzy && distance <= MAX_FUZZY_MODE_DISTANCE => {
+            Ok((mode.clone(), true))
+        }
+        Some((mode, _)) => Err(AppError::Config(format!(
+            "Mode not found: '{}'. Did you mean '{}'?",
+            id, mode.id
+        ))),
+        None => Err(AppError::Config(format!("Mode not found: {}", id))),
+    }
+}
+
+/// 前面アプリ名に最もよく合うモードIDを返す
+///
+/// `app_affinity` の各エントリと大文字小文字を区別せずに比較し、
+/// 完全一致を部分一致より優先する。同じ種類の一致ならより長いエントリを優先し、
+/// それでも並ぶ場合は先に定義されたモードを選ぶ。
+pub fn suggest_mode_for_app(modes: &[ModeConfig], app_name: &str) -> Option<String> {
+    let app_name = app_name.trim().to_lowercase();
+    if app_name.is_empty() {
+        return None;
+    }
+    let mut best: Option<(&ModeConfig, (bool, usize))> = None;
+    for mode in modes {
+        let Some(affinity) = &mode.app_affinity else {
+            continue;
+        };
+        for pattern in affinity {
+            let pattern = pattern.trim().to_lowercase();
+            if pattern.is_empty() || !app_name.contains(&pattern) {
+                continue;
+            }
+            let score = (pattern == app_name, pattern.chars().count());
+            if !matches!(best, Some((_, s)) if s >= score) {
+                best = Some((mode, score));
+            }
+        }
+    }
+    best.map(|(mode, _)| mode.id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modes() -> Vec<ModeConfig> {
+        ["raw", "proofread", "summary"]
+            .iter()
+            .map(|id| ModeConfig {
+                id: id.to_string(),
+                label: id.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_exact_match() {
+        let (mode, suggestion_used) = resolve_mode(&modes(), "proofread", true).unwrap();
+        assert_eq!(mode.id, "proofread");
+        assert!(!suggestion_used);
+    }
+
+    #[test]
+    fn test_resolve_near_typo() {
+        let modes = modes();
+        assert_eq!(
+            find_mode_fuzzy(&modes, "proofreed").map(|(m, d)| (m.id.as_str(), d)),
+            Some(("proofread", 1))
+        );
+
+        let (mode, suggestion_used) = resolve_mode(&modes, "proofreed", true).unwrap();
+        assert_eq!(mode.id, "proofread");
+        assert!(suggestion_used);
+    }
+
+    #[test]
+    fn test_resolve_typo_when_fuzzy_disabled() {
+        let err = resolve_mode(&modes(), "proofreed", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: Mode not found: 'proofreed'. Did you mean 'proofread'?"
+        );
+    }
+
+    #[test]
+    fn test_resolve_completely_wrong_id() {
+        let err = resolve_mode(&modes(), "translate-to-french", true).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Mode not found: 'translate-to-french'"));
+        assert!(resolve_mode(&[], "raw", true).is_err());
+    }
+
+    #[test]
+    fn test_suggest_mode_for_app() {
+        let mut modes = modes();
+        modes[1].app_affinity = Some(vec!["Code".to_string(), "Xcode".to_string()]);
+        modes[2].app_affinity = Some(vec!["Slack".to_string(), "Visual Studio Code".to_string()]);
+
+        assert_eq!(
+            suggest_mode_for_app(&modes, "slack").as_deref(),
+            Some("summary")
+        );
+        // 完全一致が部分一致より優先される
+        assert_eq!(
+            suggest_mode_for_app(&modes, "Xcode").as_deref(),
+            Some("proofread")
+        );
+        // 部分一致同士ではより長いエントリが優先される
+        assert_eq!(
+            suggest_mode_for_app(&modes, "Visual Studio Code - Insiders").as_deref(),
+            Some("summary")
+        );
+        assert_eq!(suggest_mode_for_app(&modes, "Finder"), None);
+        assert_eq!(suggest_mode_for_app(&modes, ""), None);
+    }
+
+    fn write_modes_yaml(dir: &Path) -> PathBuf {
+        let yaml = "modes:\n  - id: medical\n    label: Medical\n    description: ''\n    ai_enabled: true\n    ai_prompt_file: prompts/medical.txt\n";
+        let path = dir.join("modes.yaml");
+        std::fs::write(&path, yaml).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_prompt_file_relative_to_yaml() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("prompts")).unwrap();
+        std::fs::write(
+            dir.path().join("prompts/medical.txt"),
+            "医療用語を正しく表記してください",
+        )
+        .unwrap();
+        let yaml_path = write_modes_yaml(dir.path());
+
+        let modes = load_from_path(&yaml_path).unwrap();
+        assert_eq!(
+            modes[0].ai_prompt.as_deref(),
+            Some("医療用語を正しく表記してください")
+        );
+    }
+
+    #[test]
+    fn test_missing_prompt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let yaml_path = write_modes_yaml(dir.path());
+
+        let err = load_from_path(&yaml_path).unwrap_err();
+        assert!(err.to_string().contains("prompt file not found:"));
+        assert!(err.to_string().contains("medical.txt"));
+    }
+
+    #[test]
+    fn test_reload_prompt_file_bypasses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("prompts")).unwrap();
+        let prompt_path = dir.path().join("prompts/medical.txt");
+        std::fs::write(&prompt_path, "v1").unwrap();
+        let yaml_path = write_modes_yaml(dir.path());
+        assert_eq!(
+            load_from_path(&yaml_path).unwrap()[0].ai_prompt.as_deref(),
+            Some("v1")
+        );
+
+        // 書き換えただけではキャッシュが使われる
+        std::fs::write(&prompt_path, "v2").unwrap();
+        assert_eq!(
+            load_from_path(&yaml_path).unwrap()[0].ai_prompt.as_deref(),
+            Some("v1")
+        );
+
+        assert_eq!(
+            reload_prompt_from_yaml(&yaml_path, "medical").unwrap(),
+            "v2"
+        );
+        assert_eq!(
+            load_from_path(&yaml_path).unwrap()[0].ai_prompt.as_deref(),
+            Some("v2")
+        );
+        assert!(reload_prompt_from_yaml(&yaml_path, "unknown").is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use serde::Serialize;
+use similar::ChangeTag;
+
+/// 差分の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// 差分の1区間（同じ種類が続く場合はまとめる）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffOp {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+/// 差分の集計（additions / deletions は文字数）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffStats {
+    pub additions: usize,
+    pub deletions: usize,
+    /// 全文字数に占める一致した文字数の割合（0.0〜1.0）
+    pub similarity: f64,
+}
+
+/// raw_text と processed_text の差分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TextDiff {
+    pub operations: Vec<DiffOp>,
+    pub stats: DiffStats,
+}
+
+/// 2つのテキストを単語単位で比較する
+///
+/// 空白で区切られない日本語は連続部分が1単語として扱われる。
+/// 両方とも空の場合は similarity を 1.0 とする。
+pub fn diff_texts(old: &str, new: &str) -> TextDiff {
+    let diff = similar::TextDiff::from_words(old, new);
+    let mut operations: Vec<DiffOp> = Vec::new();
+    let (mut equal, mut additions, mut deletions) = (0, 0, 0);
+
+    for change in diff.iter_all_changes() {
+        let kind = match change.tag() {
+            ChangeTag::Equal => DiffKind::Equal,
+            ChangeTag::Insert => DiffKind::Insert,
+            ChangeTag::Delete => DiffKind::Delete,
+        };
+        let text = change.value();
+        let chars = text.chars().count();
+        match kind {
+            DiffKind::Equal => equal += chars,
+            DiffKind::Insert => additions += chars,
+            DiffKind::Delete => deletions += chars,
+        }
+        match operations.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(text),
+            _ => operations.push(DiffOp {
+                kind,
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    let total = equal + additions + deletions;
+    let similarity = if total == 0 {
+        1.0
+    } else {
+        equal as f64 / total as f64
+    };
+    TextDiff {
+        operations,
+        stats: DiffStats {
+            additions,
+            deletions,
+            similarity,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(kind: DiffKind, text: &str) -> DiffOp {
+        DiffOp {
+            kind,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff_replaced_word() {
+        let diff = diff_texts("the quick brown fox", "the slow brown fox");
+        assert_eq!(
+            diff.operations,
+            vec![
+                op(DiffKind::Equal, "the "),
+                op(DiffKind::Delete, "quick"),
+                op(DiffKind::Insert, "slow"),
+                op(DiffKind::Equal, " brown fox"),
+            ]
+        );
+        assert_eq!(diff.stats.additions, 4);
+        assert_eq!(diff.stats.deletions, 5);
+        assert!((diff.stats.similarity - 14.0 / 23.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diff_insert_and_delete() {
+        let diff = diff_texts("call me later", "call me later today");
+        assert_eq!(
+            diff.operations,
+            vec![
+                op(DiffKind::Equal, "call me later"),
+                op(DiffKind::Insert, " today"),
+            ]
+        );
+        assert_eq!((diff.stats.additions, diff.stats.deletions), (6, 0));
+
+        let diff = diff_texts("um so I think", "I think");
+        assert_eq!(
+            diff.operations,
+            vec![
+                op(DiffKind::Delete, "um so "),
+                op(DiffKind::Equal, "I think")
+            ]
+        );
+        assert_eq!((diff.stats.additions, diff.stats.deletions), (0, 6));
+    }
+
+    #[test]
+    fn test_diff_operations_rebuild_both_texts() {
+        let (old, new) = ("えー 今日は 会議 です", "今日は 定例会議 です。");
+        let diff = diff_texts(old, new);
+        let rebuild = |skip: DiffKind| -> String {
+            diff.operations
+                .iter()
+                .filter(|o| o.kind != skip)
+                .map(|o| o.text.as_str())
+                .collect()
+        };
+        assert_eq!(rebuild(DiffKind::Insert), old);
+        assert_eq!(rebuild(DiffKind::Delete), new);
+    }
+
+    #[test]
+    fn test_diff_identical_and_empty() {
+        let diff = diff_texts("same text", "same text");
+        assert_eq!(diff.operations, vec![op(DiffKind::Equal, "same text")]);
+        assert_eq!(diff.stats.similarity, 1.0);
+
+        let diff = diff_texts("", "");
+        assert!(diff.operations.is_empty());
+        assert_eq!(diff.stats.similarity, 1.0);
+
+        let diff = diff_texts("", "new");
+        assert_eq!(diff.operations, vec![op(DiffKind::Insert, "new")]);
+        assert_eq!(diff.stats.similarity, 0.0);
+    }
+
+    #[test]
+    fn test_diff_kind_serializes_lowercase() {
+        let json = serde_json::to_value(op(DiffKind::Insert, "x")).unwrap();
+        assert_eq!(json["kind"], "insert");
+    }
+}
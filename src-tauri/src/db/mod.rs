@@ -1,39 +1,343 @@
+pub mod diff;
 pub mod repository;
 pub mod schema;
+pub mod validate;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+#[cfg(not(feature = "connection-pool"))]
+use std::sync::MutexGuard;
 
-use rusqlite::Connection;
+#[cfg(feature = "connection-pool")]
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
 
-use crate::error::AppError;
+use crate::db::schema::MigrationObserver;
+use crate::error::{AppError, TaprResult};
+
+/// `DbState::conn` が返す接続
+///
+/// 既定では Mutex のガード、`connection-pool` feature ではプールから借りた接続。
+/// どちらも `Connection` に Deref するので、repository の関数にそのまま渡せる。
+#[cfg(not(feature = "connection-pool"))]
+pub type DbConn<'a> = MutexGuard<'a, Connection>;
+#[cfg(feature = "connection-pool")]
+pub type DbConn<'a> = r2d2::PooledConnection<SqliteConnectionManager>;
 
 /// データベース接続を保持する Tauri State
 ///
 /// AudioState と同じ Mutex パターンで排他制御する。
-/// シングルユーザーのデスクトップアプリなのでコネクションプールは不要。
+/// シングルユーザーのデスクトップアプリなので既定ではコネクションプールは使わない。
+/// 読み取りを並行させたい場合は `connection-pool` feature で r2d2 のプールに切り替える。
+/// 重い読み取りだけなら `with_read_connection` で書き込み用の接続とは別に開ける。
 pub struct DbState {
-    pub conn: Mutex<Connection>,
+    /// DB ファイルのパス（インメモリ DB では None）
+    path: Option<PathBuf>,
+    #[cfg(not(feature = "connection-pool"))]
+    conn: Mutex<Connection>,
+    #[cfg(feature = "connection-pool")]
+    pool: r2d2::Pool<SqliteConnectionManager>,
 }
 
 impl DbState {
     /// 指定パスにDBファイルを作成（または開く）し、WALモード有効化 + スキーマ初期化
-    pub fn new(db_path: &Path) -> Result<Self, AppError> {
+    ///
+    /// `observer` を渡すとマイグレーションの進捗を通知する。
+    pub fn new(db_path: &Path, observer: Option<&dyn MigrationObserver>) -> TaprResult<Self> {
         // 親ディレクトリが無ければ作成
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let conn = Connection::open(db_path)?;
+        #[cfg(not(feature = "connection-pool"))]
+        let state = Self {
+            path: Some(db_path.to_path_buf()),
+            conn: Mutex::new(Connection::open(db_path)?),
+        };
+        #[cfg(feature = "connection-pool")]
+        let state = Self {
+            path: Some(db_path.to_path_buf()),
+            pool: r2d2::Pool::new(SqliteConnectionManager::file(db_path))?,
+        };
+
+        {
+            let conn = state.conn()?;
+            // WALモードで並行読み取り性能を向上
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+
+            // スキーマの初期化 / マイグレーション
+            schema::migrate(&conn, observer)?;
+        }
+
+        Ok(state)
+    }
+
+    /// マイグレーション済みのインメモリ DB を開く（テスト用）
+    ///
+    /// プールでも同じ DB を共有するよう接続は1本に限る。
+    pub fn in_memory() -> TaprResult<Self> {
+        #[cfg(not(feature = "connection-pool"))]
+        let state = Self {
+            path: None,
+            conn: Mutex::new(Connection::open_in_memory()?),
+        };
+        #[cfg(feature = "connection-pool")]
+        let state = Self {
+            path: None,
+            pool: r2d2::Pool::builder()
+                .max_size(1)
+                .build(SqliteConnectionManager::memory())?,
+        };
+        schema::migrate(&state.conn()?, None)?;
+        Ok(state)
+    }
+
+    /// DB 接続を取得する
+    #[cfg(not(feature = "connection-pool"))]
+    pub fn conn(&self) -> TaprResult<DbConn<'_>> {
+        Ok(self.conn.lock()?)
+    }
+
+    /// DB 接続を取得する（プールが空なら返却されるまで待つ）
+    #[cfg(feature = "connection-pool")]
+    pub fn conn(&self) -> TaprResult<DbConn<'_>> {
+        Ok(self.pool.get()?)
+    }
+
+    /// 同じ DB ファイルへの読み取り専用の接続を新しく開く
+    ///
+    /// WAL モードなので書き込み中でも読める。`query_only` を有効にするので、
+    /// この接続で書き込もうとするとエラーになる。インメモリ DB では開けない。
+    pub fn open_read_only_connection(&self) -> Result<Connection, AppError> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| AppError::Database("in-memory database has no file to open".into()))?;
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.pragma_update(None, "query_only", true)?;
+        Ok(conn)
+    }
+}
+
+/// 書き込み用の Mutex を握らずに読み取りクエリを実行する
+///
+/// 全件走査の集計や類似検索の間も PTT の保存を待たせないよう、読み取り専用の接続で `f` を呼ぶ。
+/// インメモリ DB（テスト）では別接続を開けないので通常の接続を使う。
+pub fn with_read_connection<F, R>(db: &DbState, f: F) -> Result<R, AppError>
+where
+    F: FnOnce(&Connection) -> Result<R, AppError>,
+{
+    if db.path.is_none() {
+        return f(&db.conn()?);
+    }
+    let conn = db.open_read_only_connection()?;
+    f(&conn)
+}
+
+/// `get_migration_status` の戻り値
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MigrationStatus {
+    pub version: u32,
+    pub is_migrating: bool,
+    pub current_step: Option<String>,
+}
+
+/// マイグレーションの進行状況を保持する Tauri State
+///
+/// DbState はマイグレーション完了後に manage されるため、
+/// 実行中の状況はこちらから参照する。
+#[derive(Default)]
+pub struct MigrationState {
+    status: Mutex<MigrationStatus>,
+}
+
+impl MigrationState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> TaprResult<MigrationStatus> {
+        Ok(self.status.lock()?.clone())
+    }
+
+    /// 実行中のステップを記録する
+    pub fn begin_step(&self, step_name: &str) -> TaprResult<()> {
+        let mut status = self.status.lock()?;
+        status.is_migrating = true;
+        status.current_step = Some(step_name.to_string());
+        Ok(())
+    }
+
+    /// マイグレーション完了を記録する
+    pub fn finish(&self, version: u32) -> TaprResult<()> {
+        *self.status.lock()? = MigrationStatus {
+            version,
+            is_migrating: false,
+            current_step: None,
+        };
+        Ok(())
+    }
+}
+
+/// `migration-progress` イベントのペイロード
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgressEvent {
+    pub step: u32,
+    pub total: u32,
+    pub name: String,
+}
+
+/// 進捗を MigrationState に記録し、`migration-progress` イベントで通知するオブザーバー
+pub struct AppMigrationObserver<'a> {
+    pub app: AppHandle,
+    pub state: &'a MigrationState,
+}
+
+impl MigrationObserver for AppMigrationObserver<'_> {
+    fn on_step(&self, step_name: &str, step_index: u32, total_steps: u32) {
+        eprintln!(
+            "[db] migration {}/{}: {}",
+            step_index, total_steps, step_name
+        );
+        if let Err(e) = self.state.begin_step(step_name) {
+            eprintln!("[db] failed to record migration status: {}", e);
+        }
+        let _ = self.app.emit(
+            "migration-progress",
+            MigrationProgressEvent {
+                step: step_index,
+                total: total_steps,
+                name: step_name.to_string(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository;
 
-        // WALモードで並行読み取り性能を向上
-        conn.pragma_update(None, "journal_mode", "WAL")?;
+    #[test]
+    fn test_concurrent_get_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = std::sync::Arc::new(DbState::new(&dir.path().join("test.db"), None).unwrap());
+        {
+            let conn = db.conn().unwrap();
+            for i in 0..3 {
+                let entry = repository::NewEntry {
+                    raw_text: format!("raw {}", i),
+                    processed_text: format!("processed {}", i),
+                    mode_id: "proofread".to_string(),
+                    model: "gpt-4o-mini".to_string(),
+                    prompt_tokens: None,
+                    completion_tokens: None,
+                    total_tokens: None,
+                    request_id: None,
+                    device_name: None,
+                    audio_duration_ms: None,
+                    audio_hash: None,
+                };
+                repository::insert_entry(&conn, &entry).unwrap();
+            }
+        }
+
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let db = std::sync::Arc::clone(&db);
+                std::thread::spawn(move || {
+                    let conn = db.conn().unwrap();
+                    repository::get_entries(&conn, 10, 0).unwrap().len()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 3);
+        }
+    }
+
+    fn new_entry(i: usize) -> repository::NewEntry {
+        repository::NewEntry {
+            raw_text: format!("今日の会議の議事録 その{}", i),
+            processed_text: format!("今日の会議の議事録（{}）", i),
+            mode_id: "proofread".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            request_id: None,
+            device_name: None,
+            audio_duration_ms: None,
+            audio_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_heavy_read_does_not_block_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = std::sync::Arc::new(DbState::new(&dir.path().join("test.db"), None).unwrap());
+        {
+            let conn = db.conn().unwrap();
+            for i in 0..500 {
+                repository::insert_entry(&conn, &new_entry(i)).unwrap();
+            }
+        }
+
+        let reader = {
+            let db = std::sync::Arc::clone(&db);
+            std::thread::spawn(move || {
+                with_read_connection(&db, |conn| {
+                    let mut total = 0;
+                    for _ in 0..5 {
+                        total += repository::get_similar_entries(conn, 1, 50)?.len();
+                    }
+                    Ok(total)
+                })
+            })
+        };
+        let writer = {
+            let db = std::sync::Arc::clone(&db);
+            std::thread::spawn(move || -> TaprResult<()> {
+                for i in 500..520 {
+                    repository::insert_entry(&db.conn()?, &new_entry(i))?;
+                }
+                Ok(())
+            })
+        };
+
+        assert!(reader.join().unwrap().unwrap() > 0);
+        writer.join().unwrap().unwrap();
+        let count: i64 = db
+            .conn()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 520);
+    }
+
+    #[test]
+    fn test_read_only_connection_rejects_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DbState::new(&dir.path().join("test.db"), None).unwrap();
+        let conn = db.open_read_only_connection().unwrap();
+
+        assert!(repository::insert_entry(&conn, &new_entry(0)).is_err());
+        assert!(repository::get_entries(&conn, 10, 0).unwrap().is_empty());
+    }
 
-        // スキーマの初期化 / マイグレーション
-        schema::migrate(&conn)?;
+    #[test]
+    fn test_with_read_connection_falls_back_for_in_memory() {
+        let db = DbState::in_memory().unwrap();
+        repository::insert_entry(&db.conn().unwrap(), &new_entry(0)).unwrap();
 
-        Ok(Self {
-            conn: Mutex::new(conn),
-        })
+        assert!(db.open_read_only_connection().is_err());
+        let entries =
+            with_read_connection(&db, |conn| repository::get_entries(conn, 10, 0)).unwrap();
+        assert_eq!(entries.len(), 1);
     }
 }
@@ -1,10 +1,14 @@
-use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::io::Write;
+
+use rusqlite::{params, Connection, Row, ToSql};
 use serde::{Deserialize, Serialize};
 
-use crate::error::AppError;
+use crate::db::diff::{diff_texts, DiffStats};
+use crate::error::{AppError, TaprResult};
 
 /// DBから取得したエントリ
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub id: i64,
     pub raw_text: String,
@@ -15,6 +19,17 @@ pub struct Entry {
     pub completion_tokens: Option<u32>,
     pub total_tokens: Option<u32>,
     pub created_at: String,
+    pub request_id: Option<String>,
+    /// 録音に使った入力デバイス名
+    pub device_name: Option<String>,
+    /// 録音の長さ（音声なしで AI 処理だけした場合は None）
+    pub audio_duration_ms: Option<u64>,
+    /// 取り込んだ音声ファイルの BLAKE3 ハッシュ（hex）。PTT の録音は None
+    pub audio_hash: Option<String>,
+    /// processed_text を最後に編集した日時（未編集なら None）
+    pub updated_at: Option<String>,
+    /// お気に入り（古いエントリの削除・アーカイブの対象外）
+    pub is_favorite: bool,
 }
 
 /// 新規保存用の入力データ
@@ -27,13 +42,47 @@ pub struct NewEntry {
     pub prompt_tokens: Option<u32>,
     pub completion_tokens: Option<u32>,
     pub total_tokens: Option<u32>,
+    pub request_id: Option<String>,
+    /// 録音に使った入力デバイス名（`RecordingMeta.device_name`）
+    #[serde(default)]
+    pub device_name: Option<String>,
+    /// 録音の長さ（`RecordingResult.duration_ms`）
+    #[serde(default)]
+    pub audio_duration_ms: Option<u64>,
+    /// 取り込んだ音声ファイルの BLAKE3 ハッシュ（hex）
+    #[serde(default)]
+    pub audio_hash: Option<String>,
+}
+
+/// SELECT で取得するエントリのカラム一覧（`map_entry` の順序と一致させる）
+const ENTRY_COLUMNS: &str = "id, raw_text, processed_text, mode_id, model, prompt_tokens, completion_tokens, total_tokens, created_at, request_id, device_name, audio_duration_ms, audio_hash, updated_at, is_favorite";
+
+/// `ENTRY_COLUMNS` の並びで取得した行を Entry に変換する
+fn map_entry(row: &Row) -> rusqlite::Result<Entry> {
+    Ok(Entry {
+        id: row.get(0)?,
+        raw_text: row.get(1)?,
+        processed_text: row.get(2)?,
+        mode_id: row.get(3)?,
+        model: row.get(4)?,
+        prompt_tokens: row.get(5)?,
+        completion_tokens: row.get(6)?,
+        total_tokens: row.get(7)?,
+        created_at: row.get(8)?,
+        request_id: row.get(9)?,
+        device_name: row.get(10)?,
+        audio_duration_ms: row.get(11)?,
+        audio_hash: row.get(12)?,
+        updated_at: row.get(13)?,
+        is_favorite: row.get(14)?,
+    })
 }
 
 /// エントリを保存し、挿入されたIDを返す
-pub fn insert_entry(conn: &Connection, entry: &NewEntry) -> Result<i64, AppError> {
+pub fn insert_entry(conn: &Connection, entry: &NewEntry) -> TaprResult<i64> {
     conn.execute(
-        "INSERT INTO entries (raw_text, processed_text, mode_id, model, prompt_tokens, completion_tokens, total_tokens)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        "INSERT INTO entries (raw_text, processed_text, mode_id, model, prompt_tokens, completion_tokens, total_tokens, request_id, device_name, audio_duration_ms, audio_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         params![
             entry.raw_text,
             entry.processed_text,
@@ -42,78 +91,699 @@ pub fn insert_entry(conn: &Connection, entry: &NewEntry) -> Result<i64, AppError
             entry.prompt_tokens,
             entry.completion_tokens,
             entry.total_tokens,
+            entry.request_id,
+            entry.device_name,
+            entry.audio_duration_ms,
+            entry.audio_hash,
         ],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
-/// エントリ一覧を取得（新しい順、limit/offset対応）
-pub fn get_entries(conn: &Connection, limit: u32, offset: u32) -> Result<Vec<Entry>, AppError> {
-    let mut stmt = conn.prepare(
-        "SELECT id, raw_text, processed_text, mode_id, model, prompt_tokens, completion_tokens, total_tokens, created_at
-         FROM entries ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
-    )?;
+/// エントリ一覧を取得（お気に入りを先頭に新しい順、limit/offset対応）
+pub fn get_entries(conn: &Connection, limit: u32, offset: u32) -> TaprResult<Vec<Entry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries
+         ORDER BY is_favorite DESC, created_at DESC LIMIT ?1 OFFSET ?2"
+    ))?;
 
     let entries = stmt
-        .query_map(params![limit, offset], |row| {
-            Ok(Entry {
-                id: row.get(0)?,
-                raw_text: row.get(1)?,
-                processed_text: row.get(2)?,
-                mode_id: row.get(3)?,
-                model: row.get(4)?,
-                prompt_tokens: row.get(5)?,
-                completion_tokens: row.get(6)?,
-                total_tokens: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?
+        .query_map(params![limit, offset], map_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// `get_entries_filtered` の絞り込み条件（未指定の条件は絞り込まない）
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EntryFilter {
+    #[serde(default)]
+    pub mode_id: Option<String>,
+    /// この日時以降（RFC 3339、この時刻を含む）
+    #[serde(default)]
+    pub from: Option<String>,
+    /// この日時より前（RFC 3339、この時刻を含まない）
+    #[serde(default)]
+    pub to: Option<String>,
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+/// モードと作成日時で絞り込んだエントリ一覧を取得（新しい順、limit/offset対応）
+///
+/// WHERE 句は指定された条件だけで組み立て、値はすべてバインドパラメータで渡す。
+pub fn get_entries_filtered(conn: &Connection, filter: &EntryFilter) -> TaprResult<Vec<Entry>> {
+    let from = filter
+        .from
+        .as_deref()
+        .map(|s| normalize_timestamp("from", s))
+        .transpose()?;
+    let to = filter
+        .to
+        .as_deref()
+        .map(|s| normalize_timestamp("to", s))
+        .transpose()?;
+
+    let mut conditions = Vec::new();
+    let mut values: Vec<&dyn ToSql> = Vec::new();
+    if let Some(mode_id) = &filter.mode_id {
+        values.push(mode_id);
+        conditions.push(format!("mode_id = ?{}", values.len()));
+    }
+    if let Some(from) = &from {
+        values.push(from);
+        conditions.push(format!("created_at >= ?{}", values.len()));
+    }
+    if let Some(to) = &to {
+        values.push(to);
+        conditions.push(format!("created_at < ?{}", values.len()));
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    values.push(&filter.limit);
+    values.push(&filter.offset);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries {where_clause}
+         ORDER BY created_at DESC LIMIT ?{} OFFSET ?{}",
+        values.len() - 1,
+        values.len()
+    ))?;
+    let entries = stmt
+        .query_map(values.as_slice(), map_entry)?
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(entries)
 }
 
+/// RFC 3339 の日時を created_at と同じ UTC の書式にそろえる（文字列のまま大小比較するため）
+fn normalize_timestamp(field: &str, value: &str) -> TaprResult<String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|t| {
+            t.with_timezone(&chrono::Utc)
+                .format("%Y-%m-%dT%H:%M:%.3fZ")
+                .to_string()
+        })
+        .map_err(|e| AppError::Database(format!("invalid {} timestamp '{}': {}", field, value, e)))
+}
+
 /// IDでエントリを1件取得
-pub fn get_entry(conn: &Connection, id: i64) -> Result<Option<Entry>, AppError> {
-    let mut stmt = conn.prepare(
-        "SELECT id, raw_text, processed_text, mode_id, model, prompt_tokens, completion_tokens, total_tokens, created_at
-         FROM entries WHERE id = ?1",
+pub fn get_entry(conn: &Connection, id: i64) -> TaprResult<Option<Entry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries WHERE id = ?1"
+    ))?;
+
+    let entry = stmt.query_row(params![id], map_entry).optional()?;
+
+    Ok(entry)
+}
+
+/// リクエストIDでエントリを1件取得
+pub fn get_entry_by_request_id(conn: &Connection, request_id: &str) -> TaprResult<Option<Entry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries WHERE request_id = ?1 ORDER BY id DESC LIMIT 1"
+    ))?;
+
+    let entry = stmt.query_row(params![request_id], map_entry).optional()?;
+
+    Ok(entry)
+}
+
+/// 音声ファイルのハッシュでエントリを1件取得
+///
+/// アーカイブ済みのエントリも探す（entries を優先）。アーカイブにあるのに取り込み直すと、
+/// 戻すときに `idx_entries_audio_hash` と衝突するため。
+pub fn get_entry_by_audio_hash(conn: &Connection, audio_hash: &str) -> TaprResult<Option<Entry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM (
+             SELECT {ENTRY_COLUMNS}, 0 AS archived FROM entries WHERE audio_hash = ?1
+             UNION ALL
+             SELECT {ENTRY_COLUMNS}, 1 AS archived FROM entries_archive WHERE audio_hash = ?1
+         )
+         ORDER BY archived LIMIT 1"
+    ))?;
+
+    let entry = stmt.query_row(params![audio_hash], map_entry).optional()?;
+
+    Ok(entry)
+}
+
+/// FTS5 の trigram トークナイザーで検索できる最短の文字数
+const FTS_MIN_QUERY_CHARS: usize = 3;
+
+/// raw_text / processed_text に `query` を含むエントリを新しい順に取得
+///
+/// 全文検索テーブル（entries_fts）があれば FTS5 で引く。FTS5 なしでビルドされた
+/// SQLite の場合や、trigram では引けない2文字以下のクエリは `LIKE '%query%'` で代替する。
+pub fn search_entries(
+    conn: &Connection,
+    query: &str,
+    limit: u32,
+    offset: u32,
+) -> TaprResult<Vec<Entry>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (condition, pattern) =
+        if query.chars().count() >= FTS_MIN_QUERY_CHARS && has_fts_table(conn)? {
+            // フレーズとして渡し、AND / OR / * などを FTS5 の構文として解釈させない
+            (
+                "id IN (SELECT rowid FROM entries_fts WHERE entries_fts MATCH ?1)",
+                format!("\"{}\"", query.replace('"', "\"\"")),
+            )
+        } else {
+            (
+                "(raw_text LIKE ?1 ESCAPE '\\' OR processed_text LIKE ?1 ESCAPE '\\')",
+                format!("%{}%", escape_like(query)),
+            )
+        };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries WHERE {condition}
+         ORDER BY created_at DESC LIMIT ?2 OFFSET ?3"
+    ))?;
+    let entries = stmt
+        .query_map(params![pattern, limit, offset], map_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// v8 のマイグレーションで全文検索テーブルが作られているか
+fn has_fts_table(conn: &Connection) -> TaprResult<bool> {
+    let count: u32 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'entries_fts'",
+        [],
+        |row| row.get(0),
     )?;
+    Ok(count > 0)
+}
 
-    let entry = stmt
-        .query_row(params![id], |row| {
-            Ok(Entry {
-                id: row.get(0)?,
-                raw_text: row.get(1)?,
-                processed_text: row.get(2)?,
-                mode_id: row.get(3)?,
-                model: row.get(4)?,
-                prompt_tokens: row.get(5)?,
-                completion_tokens: row.get(6)?,
-                total_tokens: row.get(7)?,
-                created_at: row.get(8)?,
-            })
+/// LIKE のワイルドカード（`%` と `_`）をエスケープ文字 `\` でリテラル扱いにする
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 類似エントリとみなす Jaccard 係数の下限
+const SIMILARITY_THRESHOLD: f64 = 0.2;
+
+/// raw_text が似ているエントリを類似度の高い順に最大 `top_k` 件返す
+///
+/// FTS5 の全文検索は部分一致しか扱えないため、文字 trigram 集合の
+/// Jaccard 係数（|A ∩ B| / |A ∪ B|）をアプリ側で計算する。
+/// 保持期間が短く件数が少ない前提で全件を走査する。
+pub fn get_similar_entries(
+    conn: &Connection,
+    id: i64,
+    top_k: u32,
+) -> TaprResult<Vec<(Entry, f64)>> {
+    let target = get_entry(conn, id)?.ok_or_else(|| AppError::not_found("entry", id))?;
+    let target_trigrams = trigrams(&target.raw_text);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries WHERE id != ?1"
+    ))?;
+    let mut similar = stmt
+        .query_map(params![id], map_entry)?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|entry| {
+            let score = jaccard(&target_trigrams, &trigrams(&entry.raw_text));
+            (entry, score)
         })
-        .optional()?;
+        .filter(|(_, score)| *score >= SIMILARITY_THRESHOLD)
+        .collect::<Vec<_>>();
 
-    Ok(entry)
+    similar.sort_by(|a, b| b.1.total_cmp(&a.1));
+    similar.truncate(top_k as usize);
+    Ok(similar)
+}
+
+/// 小文字化したテキストから文字 trigram の集合を作る
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// 2つの集合の Jaccard 係数（どちらも空なら 0）
+fn jaccard(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// raw_text と processed_text の similarity が `threshold` 未満のエントリを新しい順に取得
+///
+/// 差分は SQLite では計算できないため、全件を読み込んで Rust 側で絞り込む。
+pub fn get_entries_with_large_diffs(
+    conn: &Connection,
+    threshold: f64,
+    limit: u32,
+) -> TaprResult<Vec<(Entry, DiffStats)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries ORDER BY created_at DESC"
+    ))?;
+    let entries = stmt
+        .query_map([], map_entry)?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|entry| {
+            let stats = diff_texts(&entry.raw_text, &entry.processed_text).stats;
+            (entry, stats)
+        })
+        .filter(|(_, stats)| stats.similarity < threshold)
+        .take(limit as usize)
+        .collect();
+    Ok(entries)
 }
 
 /// エントリを削除し、削除された行数を返す
-pub fn delete_entry(conn: &Connection, id: i64) -> Result<bool, AppError> {
+pub fn delete_entry(conn: &Connection, id: i64) -> TaprResult<bool> {
     let affected = conn.execute("DELETE FROM entries WHERE id = ?1", params![id])?;
     Ok(affected > 0)
 }
 
-/// 指定日数より古いエントリを削除し、削除件数を返す
-pub fn delete_old_entries(conn: &Connection, days: u32) -> Result<usize, AppError> {
+/// processed_text を書き換え、updated_at を現在時刻にする（見つからなければ false）
+///
+/// id と created_at は変わらない。
+pub fn update_entry(conn: &Connection, id: i64, processed_text: &str) -> TaprResult<bool> {
     let affected = conn.execute(
-        "DELETE FROM entries WHERE created_at < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1)",
+        "UPDATE entries
+         SET processed_text = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+         WHERE id = ?2",
+        params![processed_text, id],
+    )?;
+    Ok(affected > 0)
+}
+
+/// エントリの総件数を返す（ページ数の計算用）
+pub fn count_entries(conn: &Connection) -> TaprResult<u32> {
+    let count: u32 = conn.query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))?;
+    Ok(count)
+}
+
+/// お気に入りを設定・解除する（見つからなければ false）
+pub fn set_favorite(conn: &Connection, id: i64, is_favorite: bool) -> TaprResult<bool> {
+    let affected = conn.execute(
+        "UPDATE entries SET is_favorite = ?1 WHERE id = ?2",
+        params![is_favorite, id],
+    )?;
+    Ok(affected > 0)
+}
+
+/// 指定モードのエントリ件数を返す（idx_entries_mode_id を使う）
+pub fn count_entries_by_mode(conn: &Connection, mode_id: &str) -> TaprResult<u32> {
+    let count: u32 = conn.query_row(
+        "SELECT COUNT(*) FROM entries WHERE mode_id = ?1",
+        params![mode_id],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// 指定モードのエントリをすべて物理削除し、削除件数を返す
+pub fn purge_entries_by_mode(conn: &Connection, mode_id: &str) -> TaprResult<usize> {
+    let affected = conn.execute("DELETE FROM entries WHERE mode_id = ?1", params![mode_id])?;
+    Ok(affected)
+}
+
+/// 指定日数より古いエントリを削除し、削除件数を返す（お気に入りは残す）
+pub fn delete_old_entries(conn: &Connection, days: u32) -> TaprResult<usize> {
+    let affected = conn.execute(
+        "DELETE FROM entries
+         WHERE created_at < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1) AND is_favorite = 0",
         params![format!("-{} days", days)],
     )?;
     Ok(affected)
 }
 
+/// 起動時と定期メンテナンスで entries_archive へ移すエントリの経過日数
+pub const ARCHIVE_AFTER_DAYS: u32 = 90;
+
+/// 指定日数より古いエントリを entries_archive に移し、移動件数を返す（お気に入りは残す）
+pub fn archive_old_entries(conn: &Connection, older_than_days: u32) -> TaprResult<usize> {
+    let cutoff = format!("-{} days", older_than_days);
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        &format!(
+            "INSERT INTO entries_archive ({ENTRY_COLUMNS})
+             SELECT {ENTRY_COLUMNS} FROM entries
+             WHERE created_at < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1) AND is_favorite = 0"
+        ),
+        params![cutoff],
+    )?;
+    let moved = tx.execute(
+        "DELETE FROM entries
+         WHERE created_at < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1) AND is_favorite = 0",
+        params![cutoff],
+    )?;
+    tx.commit()?;
+    Ok(moved)
+}
+
+/// ARCHIVE_AFTER_DAYS 日より古いエントリを entries_archive に移す（起動時と定期メンテナンス用）
+pub fn archive_expired_entries(conn: &Connection) -> TaprResult<usize> {
+    archive_old_entries(conn, ARCHIVE_AFTER_DAYS)
+}
+
+/// アーカイブ済みのエントリを entries に戻す（見つからなければ false）
+///
+/// 同じ audio_hash のエントリがすでに entries にある場合は、一意インデックスと
+/// 衝突しないよう戻すエントリの audio_hash を外す。
+pub fn restore_from_archive(conn: &Connection, id: i64) -> TaprResult<bool> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE entries_archive SET audio_hash = NULL
+         WHERE id = ?1 AND audio_hash IN (SELECT audio_hash FROM entries WHERE audio_hash IS NOT NULL)",
+        params![id],
+    )?;
+    let restored = tx.execute(
+        &format!(
+            "INSERT INTO entries ({ENTRY_COLUMNS})
+             SELECT {ENTRY_COLUMNS} FROM entries_archive WHERE id = ?1"
+        ),
+        params![id],
+    )?;
+    tx.execute("DELETE FROM entries_archive WHERE id = ?1", params![id])?;
+    tx.commit()?;
+    Ok(restored > 0)
+}
+
+/// アーカイブ済みのエントリ一覧を取得（新しい順、limit/offset対応）
+pub fn get_archived_entries(conn: &Connection, limit: u32, offset: u32) -> TaprResult<Vec<Entry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries_archive ORDER BY created_at DESC LIMIT ?1 OFFSET ?2"
+    ))?;
+
+    let entries = stmt
+        .query_map(params![limit, offset], map_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// `export_entries` の出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// `Entry` の配列
+    Json,
+    /// エントリごとに `## {created_at}` の見出しを付けた Markdown
+    Markdown,
+}
+
+/// 全エントリを古い順に `writer` へ書き出し、件数を返す
+///
+/// 履歴が大きくても全件をメモリに載せないよう、1行ずつ読みながら書き出す。
+pub fn export_entries(
+    conn: &Connection,
+    format: ExportFormat,
+    writer: &mut impl Write,
+) -> TaprResult<usize> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries ORDER BY created_at ASC, id ASC"
+    ))?;
+    let mut rows = stmt.query([])?;
+
+    if format == ExportFormat::Json {
+        writer.write_all(b"[")?;
+    }
+    let mut count = 0;
+    while let Some(row) = rows.next()? {
+        let entry = map_entry(row)?;
+        match format {
+            ExportFormat::Json => {
+                if count > 0 {
+                    writer.write_all(b",")?;
+                }
+                writer.write_all(b"\n  ")?;
+                serde_json::to_writer(&mut *writer, &entry).map_err(std::io::Error::from)?;
+            }
+            ExportFormat::Markdown => write_markdown_entry(writer, &entry)?,
+        }
+        count += 1;
+    }
+    if format == ExportFormat::Json {
+        writer.write_all(if count > 0 { b"\n]\n" } else { b"]\n" })?;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// エントリ1件を Markdown のセクションとして書き出す
+fn write_markdown_entry(writer: &mut impl Write, entry: &Entry) -> std::io::Result<()> {
+    writeln!(writer, "## {}", entry.created_at)?;
+    writeln!(writer)?;
+    writeln!(writer, "- mode: {}", entry.mode_id)?;
+    writeln!(writer, "- model: {}", entry.model)?;
+    writeln!(writer)?;
+    writeln!(writer, "### 文字起こし")?;
+    writeln!(writer)?;
+    writeln!(writer, "{}", entry.raw_text)?;
+    writeln!(writer)?;
+    writeln!(writer, "### 処理後")?;
+    writeln!(writer)?;
+    writeln!(writer, "{}", entry.processed_text)?;
+    writeln!(writer)
+}
+
+/// 録音の長さが `min_ms` 以上 `max_ms` 以下のエントリを取得（新しい順、limit/offset対応）
+///
+/// 音声なしのエントリ（`audio_duration_ms` が NULL）は含まない。
+pub fn get_entries_by_duration(
+    conn: &Connection,
+    min_ms: u64,
+    max_ms: u64,
+    limit: u32,
+    offset: u32,
+) -> TaprResult<Vec<Entry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ENTRY_COLUMNS} FROM entries
+         WHERE audio_duration_ms BETWEEN ?1 AND ?2
+         ORDER BY created_at DESC LIMIT ?3 OFFSET ?4"
+    ))?;
+
+    let entries = stmt
+        .query_map(params![min_ms, max_ms, limit, offset], map_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// 録音の長さの平均（ms）。音声なしのエントリは除外し、対象がなければ 0
+pub fn get_avg_recording_duration(conn: &Connection) -> TaprResult<f64> {
+    let avg = conn.query_row(
+        "SELECT COALESCE(AVG(audio_duration_ms), 0.0) FROM entries WHERE audio_duration_ms IS NOT NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(avg)
+}
+
+/// 録音1回分の音声品質メトリクス（`recording_metrics` テーブル）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioMetrics {
+    pub session_id: String,
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 録音冒頭 0.5 秒の RMS
+    pub noise_floor_rms: f32,
+    pub peak_amplitude: f32,
+    /// 簡易 VAD で発話と判定したフレームの割合（0.0〜1.0）
+    pub vad_speech_ratio: f32,
+}
+
+/// 1項目の平均・最小・最大
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricStats {
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// 期間内の録音メトリクスの集計（録音がなければ各値は 0）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecordingMetricsSummary {
+    pub count: u32,
+    pub duration_ms: MetricStats,
+    pub sample_rate: MetricStats,
+    pub channels: MetricStats,
+    pub noise_floor_rms: MetricStats,
+    pub peak_amplitude: MetricStats,
+    pub vad_speech_ratio: MetricStats,
+    /// 最も多く使われたサンプルレート
+    pub most_common_sample_rate: Option<u32>,
+}
+
+/// 集計対象のメトリクス列（`RecordingMetricsSummary` のフィールド順）
+const METRIC_COLUMNS: [&str; 6] = [
+    "duration_ms",
+    "sample_rate",
+    "channels",
+    "noise_floor_rms",
+    "peak_amplitude",
+    "vad_speech_ratio",
+];
+
+/// 録音メトリクスを保存し、挿入されたIDを返す
+pub fn insert_recording_metrics(conn: &Connection, metrics: &AudioMetrics) -> TaprResult<i64> {
+    conn.execute(
+        "INSERT INTO recording_metrics (session_id, duration_ms, sample_rate, channels, noise_floor_rms, peak_amplitude, vad_speech_ratio)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            metrics.session_id,
+            metrics.duration_ms as i64,
+            metrics.sample_rate,
+            metrics.channels,
+            metrics.noise_floor_rms,
+            metrics.peak_amplitude,
+            metrics.vad_speech_ratio,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// 直近 `days` 日の録音メトリクスを項目ごとに集計する
+pub fn get_recording_metrics_summary(
+    conn: &Connection,
+    days: u32,
+) -> TaprResult<RecordingMetricsSummary> {
+    let cutoff = format!("-{} days", days);
+    let aggregates = METRIC_COLUMNS
+        .iter()
+        .map(|c| format!("COALESCE(AVG({c}), 0), COALESCE(MIN({c}), 0), COALESCE(MAX({c}), 0)"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let since = "created_at >= strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1)";
+
+    let mut summary = conn.query_row(
+        &format!("SELECT COUNT(*), {aggregates} FROM recording_metrics WHERE {since}"),
+        params![cutoff],
+        |row| {
+            // METRIC_COLUMNS の i 番目の (AVG, MIN, MAX)
+            let stats = |i: usize| -> rusqlite::Result<MetricStats> {
+                Ok(MetricStats {
+                    avg: row.get(1 + i * 3)?,
+                    min: row.get(2 + i * 3)?,
+                    max: row.get(3 + i * 3)?,
+                })
+            };
+            Ok(RecordingMetricsSummary {
+                count: row.get(0)?,
+                duration_ms: stats(0)?,
+                sample_rate: stats(1)?,
+                channels: stats(2)?,
+                noise_floor_rms: stats(3)?,
+                peak_amplitude: stats(4)?,
+                vad_speech_ratio: stats(5)?,
+                most_common_sample_rate: None,
+            })
+        },
+    )?;
+
+    summary.most_common_sample_rate = conn
+        .query_row(
+            &format!(
+                "SELECT sample_rate FROM recording_metrics WHERE {since}
+                 GROUP BY sample_rate ORDER BY COUNT(*) DESC, sample_rate DESC LIMIT 1"
+            ),
+            params![cutoff],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(summary)
+}
+
+/// 1日分のエントリ件数（カレンダーヒートマップ用）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayCount {
+    /// `YYYY-MM-DD`（UTC）
+    pub date: String,
+    pub count: u32,
+}
+
+/// 曜日×時間帯ごとのエントリ件数
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourOfWeekCount {
+    /// 0 = 日曜 〜 6 = 土曜（UTC）
+    pub day: u32,
+    /// 0〜23（UTC）
+    pub hour: u32,
+    pub count: u32,
+}
+
+/// 指定年のエントリ件数を日付ごとに返す（エントリのない日は含まない）
+///
+/// アーカイブ済みのエントリは `entries` から外れているため数えない。
+pub fn get_entry_count_by_day(conn: &Connection, year: u32) -> TaprResult<Vec<DayCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT strftime('%Y-%m-%d', created_at) AS day, COUNT(*) FROM entries
+         WHERE strftime('%Y', created_at) = ?1
+         GROUP BY day ORDER BY day",
+    )?;
+    let counts = stmt
+        .query_map(params![format!("{:04}", year)], |row| {
+            Ok(DayCount {
+                date: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(counts)
+}
+
+/// 曜日（0〜6）×時間（0〜23）の 168 マスすべての件数を曜日・時間順に返す
+///
+/// エントリのないマスは 0 件として含める。
+pub fn get_entry_count_by_hour_of_week(conn: &Connection) -> TaprResult<Vec<HourOfWeekCount>> {
+    let mut counts: Vec<HourOfWeekCount> = (0..7)
+        .flat_map(|day| {
+            (0..24).map(move |hour| HourOfWeekCount {
+                day,
+                hour,
+                count: 0,
+            })
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT CAST(strftime('%w', created_at) AS INTEGER) AS day,
+                CAST(strftime('%H', created_at) AS INTEGER) AS hour,
+                COUNT(*)
+         FROM entries GROUP BY day, hour",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, u32>(0)?,
+            row.get::<_, u32>(1)?,
+            row.get::<_, u32>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (day, hour, count) = row?;
+        if let Some(cell) = counts.get_mut((day * 24 + hour) as usize) {
+            cell.count = count;
+        }
+    }
+    Ok(counts)
+}
+
 /// rusqlite の optional() を使うためのトレイト
 trait OptionalExt<T> {
     fn optional(self) -> Result<Option<T>, rusqlite::Error>;
@@ -136,7 +806,7 @@ mod tests {
 
     fn setup_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
-        schema::migrate(&conn).unwrap();
+        schema::migrate(&conn, None).unwrap();
         conn
     }
 
@@ -149,6 +819,10 @@ mod tests {
             prompt_tokens: Some(10),
             completion_tokens: Some(15),
             total_tokens: Some(25),
+            request_id: Some("req-sample".to_string()),
+            device_name: Some("MacBook Pro Microphone".to_string()),
+            audio_duration_ms: Some(3_200),
+            audio_hash: None,
         }
     }
 
@@ -166,6 +840,10 @@ mod tests {
         assert_eq!(fetched.mode_id, "proofread");
         assert_eq!(fetched.model, "gpt-4o-mini");
         assert_eq!(fetched.prompt_tokens, Some(10));
+        assert_eq!(
+            fetched.device_name.as_deref(),
+            Some("MacBook Pro Microphone")
+        );
     }
 
     #[test]
@@ -181,6 +859,10 @@ mod tests {
                 prompt_tokens: None,
                 completion_tokens: None,
                 total_tokens: None,
+                request_id: None,
+                device_name: None,
+                audio_duration_ms: None,
+                audio_hash: None,
             };
             insert_entry(&conn, &entry).unwrap();
         }
@@ -204,6 +886,10 @@ mod tests {
                 prompt_tokens: None,
                 completion_tokens: None,
                 total_tokens: None,
+                request_id: None,
+                device_name: None,
+                audio_duration_ms: None,
+                audio_hash: None,
             };
             insert_entry(&conn, &entry).unwrap();
         }
@@ -212,6 +898,101 @@ mod tests {
         assert_eq!(entries.len(), 2);
     }
 
+    /// モードと created_at を指定してエントリを保存する
+    fn insert_at(conn: &Connection, mode_id: &str, created_at: &str) -> i64 {
+        let id = insert_entry(
+            conn,
+            &NewEntry {
+                mode_id: mode_id.to_string(),
+                ..sample_entry()
+            },
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE entries SET created_at = ?1 WHERE id = ?2",
+            params![created_at, id],
+        )
+        .unwrap();
+        id
+    }
+
+    #[test]
+    fn test_get_entries_filtered() {
+        let conn = setup_db();
+        let old = insert_at(&conn, "proofread", "2026-10-01T09:00:00.000Z");
+        let last_week = insert_at(&conn, "proofread", "2026-10-08T09:00:00.000Z");
+        let summary = insert_at(&conn, "summary", "2026-10-09T09:00:00.000Z");
+        let today = insert_at(&conn, "proofread", "2026-10-15T09:00:00.000Z");
+        let ids = |filter: EntryFilter| -> Vec<i64> {
+            get_entries_filtered(&conn, &filter)
+                .unwrap()
+                .iter()
+                .map(|e| e.id)
+                .collect()
+        };
+
+        let all = EntryFilter {
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(ids(all.clone()), vec![today, summary, last_week, old]);
+        assert_eq!(
+            ids(EntryFilter {
+                mode_id: Some("proofread".to_string()),
+                ..all.clone()
+            }),
+            vec![today, last_week, old]
+        );
+        // from は含み、to は含まない
+        assert_eq!(
+            ids(EntryFilter {
+                from: Some("2026-10-08T09:00:00Z".to_string()),
+                to: Some("2026-10-15T09:00:00Z".to_string()),
+                ..all.clone()
+            }),
+            vec![summary, last_week]
+        );
+        assert_eq!(
+            ids(EntryFilter {
+                mode_id: Some("proofread".to_string()),
+                from: Some("2026-10-08T00:00:00Z".to_string()),
+                to: Some("2026-10-15T00:00:00Z".to_string()),
+                ..all.clone()
+            }),
+            vec![last_week]
+        );
+        // タイムゾーン付きの日時は UTC に直して比較する（+09:00 の 18時 = UTC 9時）
+        assert_eq!(
+            ids(EntryFilter {
+                from: Some("2026-10-15T18:00:00+09:00".to_string()),
+                ..all.clone()
+            }),
+            vec![today]
+        );
+        assert_eq!(
+            ids(EntryFilter {
+                limit: 2,
+                offset: 1,
+                ..all
+            }),
+            vec![summary, last_week]
+        );
+    }
+
+    #[test]
+    fn test_get_entries_filtered_rejects_invalid_timestamp() {
+        let conn = setup_db();
+        let filter = EntryFilter {
+            from: Some("last week".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        assert!(matches!(
+            get_entries_filtered(&conn, &filter),
+            Err(AppError::Database(_))
+        ));
+    }
+
     #[test]
     fn test_delete_entry() {
         let conn = setup_db();
@@ -231,6 +1012,77 @@ mod tests {
         assert!(!deleted);
     }
 
+    #[test]
+    fn test_update_entry() {
+        let conn = setup_db();
+        let id = insert_entry(&conn, &sample_entry()).unwrap();
+        let before = get_entry(&conn, id).unwrap().unwrap();
+        assert!(before.updated_at.is_none());
+
+        assert!(update_entry(&conn, id, "こんにちは、温泉。").unwrap());
+
+        let after = get_entry(&conn, id).unwrap().unwrap();
+        assert_eq!(after.processed_text, "こんにちは、温泉。");
+        assert_eq!(after.raw_text, before.raw_text);
+        assert_eq!(after.created_at, before.created_at);
+        assert!(after.updated_at.is_some());
+
+        // 全文検索の索引も追従する
+        assert_eq!(search_entries(&conn, "、温泉", 10, 0).unwrap().len(), 1);
+        assert!(search_entries(&conn, "、世界", 10, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_update_nonexistent() {
+        let conn = setup_db();
+        assert!(!update_entry(&conn, 9999, "text").unwrap());
+    }
+
+    #[test]
+    fn test_export_entries_json() {
+        let conn = setup_db();
+        let mut empty = Vec::new();
+        assert_eq!(
+            export_entries(&conn, ExportFormat::Json, &mut empty).unwrap(),
+            0
+        );
+        assert!(serde_json::from_slice::<Vec<Entry>>(&empty)
+            .unwrap()
+            .is_empty());
+
+        let first = insert_entry(&conn, &sample_entry()).unwrap();
+        let second = insert_entry(&conn, &entry_with_text("週末は温泉に行きたい")).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(
+            export_entries(&conn, ExportFormat::Json, &mut out).unwrap(),
+            2
+        );
+        let exported: Vec<Entry> = serde_json::from_slice(&out).unwrap();
+        let ids: Vec<i64> = exported.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![first, second]);
+        assert_eq!(exported[0].processed_text, "こんにちは、世界。");
+    }
+
+    #[test]
+    fn test_export_entries_markdown() {
+        let conn = setup_db();
+        let id = insert_entry(&conn, &sample_entry()).unwrap();
+        let created_at = get_entry(&conn, id).unwrap().unwrap().created_at;
+
+        let mut out = Vec::new();
+        assert_eq!(
+            export_entries(&conn, ExportFormat::Markdown, &mut out).unwrap(),
+            1
+        );
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.starts_with(&format!("## {}\n", created_at)));
+        assert!(markdown.contains("- mode: proofread\n"));
+        assert!(markdown.contains("- model: gpt-4o-mini\n"));
+        assert!(markdown.contains("### 文字起こし\n\nこんにちは世界\n"));
+        assert!(markdown.contains("### 処理後\n\nこんにちは、世界。\n"));
+    }
+
     #[test]
     fn test_get_nonexistent() {
         let conn = setup_db();
@@ -262,6 +1114,41 @@ mod tests {
         assert_eq!(entries[0].raw_text, "こんにちは世界");
     }
 
+    #[test]
+    fn test_delete_old_entries_keeps_favorites() {
+        let conn = setup_db();
+        let favorite = insert_entry(&conn, &entry_with_text("残したい")).unwrap();
+        let other = insert_entry(&conn, &entry_with_text("消えてよい")).unwrap();
+        backdate(&conn, favorite, 4);
+        backdate(&conn, other, 4);
+        assert!(set_favorite(&conn, favorite, true).unwrap());
+
+        assert_eq!(delete_old_entries(&conn, 3).unwrap(), 1);
+        assert!(get_entry(&conn, favorite).unwrap().unwrap().is_favorite);
+        assert!(get_entry(&conn, other).unwrap().is_none());
+
+        // お気に入りを外せば次の削除で消える
+        assert!(set_favorite(&conn, favorite, false).unwrap());
+        assert_eq!(delete_old_entries(&conn, 3).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_favorites_first_in_get_entries() {
+        let conn = setup_db();
+        let old = insert_entry(&conn, &entry_with_text("古いお気に入り")).unwrap();
+        let recent = insert_entry(&conn, &entry_with_text("最近の入力")).unwrap();
+        backdate(&conn, old, 10);
+        set_favorite(&conn, old, true).unwrap();
+
+        let ids: Vec<i64> = get_entries(&conn, 10, 0)
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![old, recent]);
+        assert!(!set_favorite(&conn, 9999, true).unwrap());
+    }
+
     #[test]
     fn test_delete_old_entries_none_old() {
         let conn = setup_db();
@@ -282,6 +1169,10 @@ mod tests {
             prompt_tokens: None,
             completion_tokens: None,
             total_tokens: None,
+            request_id: None,
+            device_name: None,
+            audio_duration_ms: None,
+            audio_hash: None,
         };
 
         let id = insert_entry(&conn, &entry).unwrap();
@@ -289,4 +1180,571 @@ mod tests {
         assert!(fetched.prompt_tokens.is_none());
         assert!(fetched.total_tokens.is_none());
     }
+
+    #[test]
+    fn test_get_entry_by_request_id() {
+        let conn = setup_db();
+        let id = insert_entry(&conn, &sample_entry()).unwrap();
+
+        let fetched = get_entry_by_request_id(&conn, "req-sample")
+            .unwrap()
+            .expect("entry should exist");
+        assert_eq!(fetched.id, id);
+        assert_eq!(fetched.request_id.as_deref(), Some("req-sample"));
+
+        assert!(get_entry_by_request_id(&conn, "unknown").unwrap().is_none());
+    }
+
+    fn entry_with_text(raw_text: &str) -> NewEntry {
+        NewEntry {
+            raw_text: raw_text.to_string(),
+            processed_text: raw_text.to_string(),
+            mode_id: "proofread".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            request_id: None,
+            device_name: None,
+            audio_duration_ms: None,
+            audio_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_search_entries_full_text() {
+        let conn = setup_db();
+        let onsen = insert_entry(&conn, &entry_with_text("週末は箱根の温泉に行きたい")).unwrap();
+        insert_entry(&conn, &entry_with_text("明日の会議の資料を準備する")).unwrap();
+        let processed = insert_entry(
+            &conn,
+            &NewEntry {
+                processed_text: "草津の温泉に行った。".to_string(),
+                ..entry_with_text("くさつのおんせんにいった")
+            },
+        )
+        .unwrap();
+
+        let hits = search_entries(&conn, "温泉に", 10, 0).unwrap();
+        let ids: Vec<i64> = hits.iter().map(|e| e.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&onsen));
+        assert!(ids.contains(&processed));
+
+        assert_eq!(search_entries(&conn, "温泉に", 1, 1).unwrap().len(), 1);
+        assert!(search_entries(&conn, "存在しない語句", 10, 0)
+            .unwrap()
+            .is_empty());
+        assert!(search_entries(&conn, "   ", 10, 0).unwrap().is_empty());
+
+        // 削除したエントリは索引からも消える
+        delete_entry(&conn, onsen).unwrap();
+        let hits = search_entries(&conn, "温泉に", 10, 0).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, processed);
+    }
+
+    #[test]
+    fn test_search_entries_short_query_and_fts_syntax() {
+        let conn = setup_db();
+        let onsen = insert_entry(&conn, &entry_with_text("温泉 AND 露天風呂")).unwrap();
+        insert_entry(&conn, &entry_with_text("100% 完了")).unwrap();
+        insert_entry(&conn, &entry_with_text("1000 完了")).unwrap();
+
+        // trigram に満たない2文字は LIKE で引く
+        let hits = search_entries(&conn, "温泉", 10, 0).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, onsen);
+
+        // FTS5 の演算子や LIKE のワイルドカードは文字として扱う
+        assert_eq!(search_entries(&conn, "泉 AND 露", 10, 0).unwrap().len(), 1);
+        assert_eq!(search_entries(&conn, "0%", 10, 0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_entries_falls_back_to_like_without_fts() {
+        let conn = setup_db();
+        // FTS5 がない環境と同じく全文検索テーブルのない状態にする
+        conn.execute_batch(
+            "DROP TRIGGER entries_fts_insert;
+             DROP TRIGGER entries_fts_delete;
+             DROP TRIGGER entries_fts_update;
+             DROP TABLE entries_fts;",
+        )
+        .unwrap();
+        let id = insert_entry(&conn, &entry_with_text("週末は温泉に行きたい")).unwrap();
+        insert_entry(&conn, &entry_with_text("明日の会議")).unwrap();
+
+        let hits = search_entries(&conn, "温泉に", 10, 0).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, id);
+    }
+
+    #[test]
+    fn test_similar_entries_are_mutual() {
+        let conn = setup_db();
+        let a = insert_entry(
+            &conn,
+            &entry_with_text("明日の会議の資料を準備してください"),
+        )
+        .unwrap();
+        let b = insert_entry(&conn, &entry_with_text("明日の会議の資料を準備してほしい")).unwrap();
+        let c = insert_entry(&conn, &entry_with_text("週末は温泉に行きたい")).unwrap();
+
+        let sim = jaccard(
+            &trigrams("明日の会議の資料を準備してください"),
+            &trigrams("明日の会議の資料を準備してほしい"),
+        );
+        assert!(sim >= 0.5, "expected >= 0.5, got {}", sim);
+
+        let from_a = get_similar_entries(&conn, a, 5).unwrap();
+        assert_eq!(from_a.len(), 1);
+        assert_eq!(from_a[0].0.id, b);
+        assert!((from_a[0].1 - sim).abs() < f64::EPSILON);
+
+        let from_b = get_similar_entries(&conn, b, 5).unwrap();
+        assert!(from_b.iter().any(|(e, _)| e.id == a));
+        assert!(!from_b.iter().any(|(e, _)| e.id == c));
+    }
+
+    #[test]
+    fn test_similar_entries_top_k_and_missing() {
+        let conn = setup_db();
+        let a = insert_entry(&conn, &entry_with_text("abcdefgh")).unwrap();
+        insert_entry(&conn, &entry_with_text("abcdefgx")).unwrap();
+        insert_entry(&conn, &entry_with_text("abcdefgh!")).unwrap();
+
+        let similar = get_similar_entries(&conn, a, 1).unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].0.raw_text, "abcdefgh!");
+
+        assert!(matches!(
+            get_similar_entries(&conn, 9999, 5),
+            Err(AppError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_entries_with_large_diffs() {
+        let conn = setup_db();
+        let with_texts = |raw: &str, processed: &str| NewEntry {
+            raw_text: raw.to_string(),
+            processed_text: processed.to_string(),
+            ..sample_entry()
+        };
+        insert_entry(&conn, &with_texts("keep this text", "keep this text")).unwrap();
+        let rewritten = insert_entry(&conn, &with_texts("um so yeah", "Summary.")).unwrap();
+
+        let large = get_entries_with_large_diffs(&conn, 0.5, 10).unwrap();
+        assert_eq!(large.len(), 1);
+        assert_eq!(large[0].0.id, rewritten);
+        assert_eq!(large[0].1.additions, 8);
+        assert_eq!(large[0].1.deletions, 10);
+        assert_eq!(large[0].1.similarity, 0.0);
+
+        // 変更のないエントリは similarity 1.0 なので threshold=1.0 では含まれない
+        assert_eq!(
+            get_entries_with_large_diffs(&conn, 1.0, 10).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            get_entries_with_large_diffs(&conn, 1.1, 1).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_count_entries() {
+        let conn = setup_db();
+        assert_eq!(count_entries(&conn).unwrap(), 0);
+        assert_eq!(count_entries_by_mode(&conn, "proofread").unwrap(), 0);
+
+        let first = insert_entry(&conn, &sample_entry()).unwrap();
+        insert_entry(&conn, &sample_entry()).unwrap();
+        insert_entry(
+            &conn,
+            &NewEntry {
+                mode_id: "summary".to_string(),
+                ..sample_entry()
+            },
+        )
+        .unwrap();
+        assert_eq!(count_entries(&conn).unwrap(), 3);
+        assert_eq!(count_entries_by_mode(&conn, "proofread").unwrap(), 2);
+        assert_eq!(count_entries_by_mode(&conn, "summary").unwrap(), 1);
+        assert_eq!(count_entries_by_mode(&conn, "unknown").unwrap(), 0);
+
+        delete_entry(&conn, first).unwrap();
+        assert_eq!(count_entries(&conn).unwrap(), 2);
+        assert_eq!(count_entries_by_mode(&conn, "proofread").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_purge_entries_by_mode() {
+        let conn = setup_db();
+        insert_entry(&conn, &sample_entry()).unwrap();
+        insert_entry(&conn, &sample_entry()).unwrap();
+        let other = NewEntry {
+            mode_id: "summary".to_string(),
+            ..sample_entry()
+        };
+        let other_id = insert_entry(&conn, &other).unwrap();
+
+        assert_eq!(count_entries_by_mode(&conn, "proofread").unwrap(), 2);
+        assert_eq!(purge_entries_by_mode(&conn, "proofread").unwrap(), 2);
+        assert_eq!(count_entries_by_mode(&conn, "proofread").unwrap(), 0);
+
+        // 他のモードのエントリは残る
+        assert!(get_entry(&conn, other_id).unwrap().is_some());
+        assert_eq!(purge_entries_by_mode(&conn, "proofread").unwrap(), 0);
+    }
+
+    fn metrics(session_id: &str, duration_ms: u64, sample_rate: u32, peak: f32) -> AudioMetrics {
+        AudioMetrics {
+            session_id: session_id.to_string(),
+            duration_ms,
+            sample_rate,
+            channels: 1,
+            noise_floor_rms: 0.01,
+            peak_amplitude: peak,
+            vad_speech_ratio: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_recording_metrics_summary() {
+        let conn = setup_db();
+        assert_eq!(
+            get_recording_metrics_summary(&conn, 7).unwrap(),
+            RecordingMetricsSummary::default()
+        );
+
+        insert_recording_metrics(&conn, &metrics("a", 1_000, 48_000, 0.2)).unwrap();
+        insert_recording_metrics(&conn, &metrics("b", 3_000, 48_000, 0.8)).unwrap();
+        insert_recording_metrics(&conn, &metrics("c", 2_000, 44_100, 0.5)).unwrap();
+        let old = insert_recording_metrics(&conn, &metrics("d", 60_000, 16_000, 1.0)).unwrap();
+        conn.execute(
+            "UPDATE recording_metrics SET created_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '-30 days') WHERE id = ?1",
+            params![old],
+        )
+        .unwrap();
+
+        let summary = get_recording_metrics_summary(&conn, 7).unwrap();
+        assert_eq!(summary.count, 3);
+        assert_eq!(
+            summary.duration_ms,
+            MetricStats {
+                avg: 2_000.0,
+                min: 1_000.0,
+                max: 3_000.0
+            }
+        );
+        assert_eq!(summary.sample_rate.min, 44_100.0);
+        assert_eq!(summary.sample_rate.max, 48_000.0);
+        assert!((summary.peak_amplitude.avg - 0.5).abs() < 1e-6);
+        assert!((summary.noise_floor_rms.max - 0.01).abs() < 1e-6);
+        assert_eq!(summary.channels.avg, 1.0);
+        assert_eq!(summary.most_common_sample_rate, Some(48_000));
+
+        // 期間を広げると古い録音も含まれる
+        let summary = get_recording_metrics_summary(&conn, 60).unwrap();
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.duration_ms.max, 60_000.0);
+    }
+
+    fn entry_with_duration(audio_duration_ms: Option<u64>) -> NewEntry {
+        NewEntry {
+            audio_duration_ms,
+            ..sample_entry()
+        }
+    }
+
+    #[test]
+    fn test_get_entry_by_audio_hash() {
+        let conn = setup_db();
+        let id = insert_entry(
+            &conn,
+            &NewEntry {
+                audio_hash: Some("af1349b9".to_string()),
+                ..sample_entry()
+            },
+        )
+        .unwrap();
+        // ハッシュのない PTT の録音は何件でも保存できる
+        insert_entry(&conn, &sample_entry()).unwrap();
+        insert_entry(&conn, &sample_entry()).unwrap();
+
+        let found = get_entry_by_audio_hash(&conn, "af1349b9").unwrap().unwrap();
+        assert_eq!(found.id, id);
+        assert!(get_entry_by_audio_hash(&conn, "00000000")
+            .unwrap()
+            .is_none());
+
+        // 同じハッシュは二重に保存できない
+        let duplicate = NewEntry {
+            audio_hash: Some("af1349b9".to_string()),
+            ..sample_entry()
+        };
+        assert!(insert_entry(&conn, &duplicate).is_err());
+    }
+
+    #[test]
+    fn test_audio_duration_roundtrip() {
+        let conn = setup_db();
+        let id = insert_entry(&conn, &sample_entry()).unwrap();
+        assert_eq!(
+            get_entry(&conn, id).unwrap().unwrap().audio_duration_ms,
+            Some(3_200)
+        );
+
+        // 音声なしで AI 処理だけした場合は NULL のまま
+        let id = insert_entry(&conn, &entry_with_duration(None)).unwrap();
+        assert_eq!(
+            get_entry(&conn, id).unwrap().unwrap().audio_duration_ms,
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_entries_by_duration() {
+        let conn = setup_db();
+        for ms in [Some(0), Some(500), Some(1_500), Some(10_000), None] {
+            insert_entry(&conn, &entry_with_duration(ms)).unwrap();
+        }
+
+        let durations = |entries: Vec<Entry>| -> Vec<Option<u64>> {
+            let mut d: Vec<_> = entries.iter().map(|e| e.audio_duration_ms).collect();
+            d.sort();
+            d
+        };
+        let entries = get_entries_by_duration(&conn, 0, 2_000, 10, 0).unwrap();
+        assert_eq!(durations(entries), vec![Some(0), Some(500), Some(1_500)]);
+
+        // 境界値を含む
+        let entries = get_entries_by_duration(&conn, 1_500, 10_000, 10, 0).unwrap();
+        assert_eq!(durations(entries), vec![Some(1_500), Some(10_000)]);
+
+        assert_eq!(
+            get_entries_by_duration(&conn, 0, 2_000, 2, 0)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            get_entries_by_duration(&conn, 0, 2_000, 10, 2)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(get_entries_by_duration(&conn, 20_000, 30_000, 10, 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_avg_recording_duration() {
+        let conn = setup_db();
+        assert_eq!(get_avg_recording_duration(&conn).unwrap(), 0.0);
+
+        // 音声なしのエントリだけなら 0
+        insert_entry(&conn, &entry_with_duration(None)).unwrap();
+        assert_eq!(get_avg_recording_duration(&conn).unwrap(), 0.0);
+
+        // 長さ0の録音は平均に含める
+        for ms in [0, 1_000, 2_000] {
+            insert_entry(&conn, &entry_with_duration(Some(ms))).unwrap();
+        }
+        assert_eq!(get_avg_recording_duration(&conn).unwrap(), 1_000.0);
+    }
+
+    /// created_at を指定日数前に書き換える
+    fn backdate(conn: &Connection, id: i64, days: u32) {
+        conn.execute(
+            "UPDATE entries SET created_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1) WHERE id = ?2",
+            params![format!("-{} days", days), id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_archive_old_entries() {
+        let conn = setup_db();
+        let old_id = insert_entry(&conn, &sample_entry()).unwrap();
+        let new_id = insert_entry(&conn, &entry_with_text("最近の入力")).unwrap();
+        backdate(&conn, old_id, 100);
+
+        let moved = archive_old_entries(&conn, 90).unwrap();
+        assert_eq!(moved, 1);
+
+        assert!(get_entry(&conn, old_id).unwrap().is_none());
+        assert!(get_entry(&conn, new_id).unwrap().is_some());
+
+        let archived = get_archived_entries(&conn, 10, 0).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, old_id);
+        assert_eq!(archived[0].raw_text, "こんにちは世界");
+        assert_eq!(archived[0].request_id.as_deref(), Some("req-sample"));
+        assert_eq!(
+            archived[0].device_name.as_deref(),
+            Some("MacBook Pro Microphone")
+        );
+        assert_eq!(archived[0].audio_duration_ms, Some(3_200));
+
+        // お気に入りはアーカイブしない
+        set_favorite(&conn, new_id, true).unwrap();
+        backdate(&conn, new_id, 100);
+        assert_eq!(archive_old_entries(&conn, 90).unwrap(), 0);
+        assert!(get_entry(&conn, new_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_archive_expired_entries_keeps_recent_entries() {
+        let conn = setup_db();
+        let recent_id = insert_entry(&conn, &sample_entry()).unwrap();
+        let old_id = insert_entry(&conn, &entry_with_text("古い入力")).unwrap();
+        // 定期メンテナンスで数日前のエントリが消えず、アーカイブ対象だけが移ること
+        backdate(&conn, recent_id, 4);
+        backdate(&conn, old_id, ARCHIVE_AFTER_DAYS + 1);
+
+        assert_eq!(archive_expired_entries(&conn).unwrap(), 1);
+        assert!(get_entry(&conn, recent_id).unwrap().is_some());
+        assert!(get_entry(&conn, old_id).unwrap().is_none());
+
+        let archived = get_archived_entries(&conn, 10, 0).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, old_id);
+        assert!(restore_from_archive(&conn, old_id).unwrap());
+        assert!(get_entry(&conn, old_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_restore_from_archive() {
+        let conn = setup_db();
+        let id = insert_entry(&conn, &sample_entry()).unwrap();
+        backdate(&conn, id, 100);
+        archive_old_entries(&conn, 90).unwrap();
+
+        assert!(restore_from_archive(&conn, id).unwrap());
+        let restored = get_entry(&conn, id)
+            .unwrap()
+            .expect("entry should be restored");
+        assert_eq!(restored.processed_text, "こんにちは、世界。");
+        assert!(get_archived_entries(&conn, 10, 0).unwrap().is_empty());
+
+        // 存在しないIDは false
+        assert!(!restore_from_archive(&conn, 9999).unwrap());
+    }
+
+    #[test]
+    fn test_audio_hash_lookup_and_restore_with_archive() {
+        let conn = setup_db();
+        let hashed = |hash: &str| NewEntry {
+            audio_hash: Some(hash.to_string()),
+            ..sample_entry()
+        };
+        let archived = insert_entry(&conn, &hashed("af1349b9")).unwrap();
+        backdate(&conn, archived, 100);
+        archive_old_entries(&conn, 90).unwrap();
+
+        // アーカイブ済みのエントリもハッシュで見つかる
+        let found = get_entry_by_audio_hash(&conn, "af1349b9").unwrap().unwrap();
+        assert_eq!(found.id, archived);
+
+        // 同じハッシュが entries にあってもアーカイブから戻せる（ハッシュは外れる）
+        let current = insert_entry(&conn, &hashed("af1349b9")).unwrap();
+        assert_eq!(
+            get_entry_by_audio_hash(&conn, "af1349b9")
+                .unwrap()
+                .unwrap()
+                .id,
+            current
+        );
+        assert!(restore_from_archive(&conn, archived).unwrap());
+        let restored = get_entry(&conn, archived).unwrap().unwrap();
+        assert!(restored.audio_hash.is_none());
+        assert_eq!(
+            get_entry(&conn, current)
+                .unwrap()
+                .unwrap()
+                .audio_hash
+                .as_deref(),
+            Some("af1349b9")
+        );
+    }
+
+    /// created_at を指定の UTC 日時に書き換える
+    fn set_created_at(conn: &Connection, id: i64, created_at: &str) {
+        conn.execute(
+            "UPDATE entries SET created_at = ?1 WHERE id = ?2",
+            params![created_at, id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_entry_count_by_day() {
+        let conn = setup_db();
+        // アーカイブしたエントリは数えない
+        let archived = insert_entry(&conn, &sample_entry()).unwrap();
+        set_created_at(&conn, archived, "2024-03-05T12:00:00.000Z");
+        assert_eq!(archive_old_entries(&conn, 1).unwrap(), 1);
+
+        for created_at in [
+            "2024-03-01T09:00:00.000Z",
+            "2024-03-01T21:30:00.000Z",
+            "2024-12-31T23:59:59.000Z",
+            "2024-03-02T00:00:00.000Z",
+            "2023-12-31T10:00:00.000Z",
+            "2025-01-01T00:00:00.000Z",
+        ] {
+            let id = insert_entry(&conn, &sample_entry()).unwrap();
+            set_created_at(&conn, id, created_at);
+        }
+
+        let counts: Vec<(String, u32)> = get_entry_count_by_day(&conn, 2024)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.date, c.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("2024-03-01".to_string(), 2),
+                ("2024-03-02".to_string(), 1),
+                ("2024-12-31".to_string(), 1),
+            ]
+        );
+        assert!(get_entry_count_by_day(&conn, 2022).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_entry_count_by_hour_of_week() {
+        let conn = setup_db();
+        // 2024-03-04 は月曜日
+        for created_at in [
+            "2024-03-04T09:10:00.000Z",
+            "2024-03-11T09:50:00.000Z",
+            "2024-03-10T23:00:00.000Z",
+        ] {
+            let id = insert_entry(&conn, &sample_entry()).unwrap();
+            set_created_at(&conn, id, created_at);
+        }
+
+        let counts = get_entry_count_by_hour_of_week(&conn).unwrap();
+        assert_eq!(counts.len(), 7 * 24);
+        let nonzero: Vec<(u32, u32, u32)> = counts
+            .iter()
+            .filter(|c| c.count > 0)
+            .map(|c| (c.day, c.hour, c.count))
+            .collect();
+        assert_eq!(nonzero, vec![(0, 23, 1), (1, 9, 2)]);
+        assert_eq!(
+            counts[24 + 9],
+            HourOfWeekCount {
+                day: 1,
+                hour: 9,
+                count: 2
+            }
+        );
+    }
 }
@@ -1,49 +1,376 @@
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
+use serde::Serialize;
 
-use crate::error::AppError;
+use crate::error::{AppError, TaprResult};
 
-const CURRENT_VERSION: u32 = 1;
+pub const CURRENT_VERSION: u32 = 10;
+
+/// FTS5 の全文検索テーブルを作るバージョン
+///
+/// FTS5 なしでビルドされた SQLite では失敗するが、検索は LIKE で代替できるので飛ばして進める。
+const FTS_VERSION: u32 = 8;
+
+/// バージョンごとのマイグレーション SQL（昇順）
+///
+/// 新しいマイグレーションは末尾に追加し、対応する逆マイグレーションを
+/// `DOWN_MIGRATIONS` にも追加する。
+const MIGRATIONS: &[(u32, &str)] = &[
+    (
+        1,
+        "CREATE TABLE IF NOT EXISTS entries (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            raw_text        TEXT NOT NULL,
+            processed_text  TEXT NOT NULL,
+            mode_id         TEXT NOT NULL,
+            model           TEXT NOT NULL,
+            prompt_tokens   INTEGER,
+            completion_tokens INTEGER,
+            total_tokens    INTEGER,
+            created_at      TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_entries_created_at ON entries(created_at);
+        CREATE INDEX IF NOT EXISTS idx_entries_mode_id ON entries(mode_id);",
+    ),
+    // v2: フロントエンド呼び出しとバックエンドログを突き合わせるためのリクエストID
+    (
+        2,
+        "ALTER TABLE entries ADD COLUMN request_id TEXT;
+
+        CREATE INDEX IF NOT EXISTS idx_entries_request_id ON entries(request_id);",
+    ),
+    // v3: 古いエントリの退避先。id は entries から引き継ぐため AUTOINCREMENT にしない
+    (
+        3,
+        "CREATE TABLE IF NOT EXISTS entries_archive (
+            id              INTEGER PRIMARY KEY,
+            raw_text        TEXT NOT NULL,
+            processed_text  TEXT NOT NULL,
+            mode_id         TEXT NOT NULL,
+            model           TEXT NOT NULL,
+            prompt_tokens   INTEGER,
+            completion_tokens INTEGER,
+            total_tokens    INTEGER,
+            created_at      TEXT NOT NULL,
+            request_id      TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_entries_archive_created_at ON entries_archive(created_at);",
+    ),
+    // v4: 録音に使った入力デバイス名（アーカイブも同じカラム構成を保つ）
+    (
+        4,
+        "ALTER TABLE entries ADD COLUMN device_name TEXT;
+        ALTER TABLE entries_archive ADD COLUMN device_name TEXT;",
+    ),
+    // v5: 録音ごとの音声品質メトリクス（entries とは独立して記録する）
+    (
+        5,
+        "CREATE TABLE IF NOT EXISTS recording_metrics (
+            id               INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id       TEXT NOT NULL,
+            duration_ms      INTEGER NOT NULL,
+            sample_rate      INTEGER NOT NULL,
+            channels         INTEGER NOT NULL,
+            noise_floor_rms  REAL NOT NULL,
+            peak_amplitude   REAL NOT NULL,
+            vad_speech_ratio REAL NOT NULL,
+            created_at       TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_recording_metrics_created_at ON recording_metrics(created_at);",
+    ),
+    // v6: 録音の長さ（音声なしの AI 処理のみのエントリは NULL）
+    (
+        6,
+        "ALTER TABLE entries ADD COLUMN audio_duration_ms INTEGER;
+        ALTER TABLE entries_archive ADD COLUMN audio_duration_ms INTEGER;
+
+        CREATE INDEX IF NOT EXISTS idx_entries_audio_duration ON entries(audio_duration_ms);",
+    ),
+    // v7: 取り込んだ音声ファイルの BLAKE3 ハッシュ（同じファイルの再文字起こしを避ける）
+    // UNIQUE でも NULL は重複できるので、ハッシュを持たない PTT の録音はそのまま保存できる
+    (
+        7,
+        "ALTER TABLE entries ADD COLUMN audio_hash TEXT;
+        ALTER TABLE entries_archive ADD COLUMN audio_hash TEXT;
+
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_entries_audio_hash ON entries(audio_hash);",
+    ),
+    // v8: raw_text / processed_text の全文検索インデックス（entries を外部コンテンツとして参照）
+    // trigram トークナイザーなら分かち書きのない日本語でも部分一致で引ける
+    (
+        8,
+        "CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts USING fts5(
+            raw_text,
+            processed_text,
+            content='entries',
+            content_rowid='id',
+            tokenize='trigram'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS entries_fts_insert AFTER INSERT ON entries BEGIN
+            INSERT INTO entries_fts(rowid, raw_text, processed_text)
+            VALUES (new.id, new.raw_text, new.processed_text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS entries_fts_delete AFTER DELETE ON entries BEGIN
+            INSERT INTO entries_fts(entries_fts, rowid, raw_text, processed_text)
+            VALUES ('delete', old.id, old.raw_text, old.processed_text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS entries_fts_update AFTER UPDATE OF raw_text, processed_text ON entries BEGIN
+            INSERT INTO entries_fts(entries_fts, rowid, raw_text, processed_text)
+            VALUES ('delete', old.id, old.raw_text, old.processed_text);
+            INSERT INTO entries_fts(rowid, raw_text, processed_text)
+            VALUES (new.id, new.raw_text, new.processed_text);
+        END;
+
+        INSERT INTO entries_fts(entries_fts) VALUES ('rebuild');",
+    ),
+    // v9: processed_text を編集した日時（未編集のエントリは NULL）
+    (
+        9,
+        "ALTER TABLE entries ADD COLUMN updated_at TEXT;
+        ALTER TABLE entries_archive ADD COLUMN updated_at TEXT;",
+    ),
+    // v10: お気に入り（古いエントリの削除・アーカイブの対象外にし、一覧の先頭に出す）
+    (
+        10,
+        "ALTER TABLE entries ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE entries_archive ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0;",
+    ),
+];
+
+/// バージョン N から N-1 に戻す逆マイグレーション SQL
+const DOWN_MIGRATIONS: &[(u32, &str)] = &[
+    (1, "DROP TABLE IF EXISTS entries;"),
+    (
+        2,
+        "DROP INDEX IF EXISTS idx_entries_request_id;
+        ALTER TABLE entries DROP COLUMN request_id;",
+    ),
+    (3, "DROP TABLE IF EXISTS entries_archive;"),
+    (
+        4,
+        "ALTER TABLE entries_archive DROP COLUMN device_name;
+        ALTER TABLE entries DROP COLUMN device_name;",
+    ),
+    (5, "DROP TABLE IF EXISTS recording_metrics;"),
+    (
+        6,
+        "DROP INDEX IF EXISTS idx_entries_audio_duration;
+        ALTER TABLE entries_archive DROP COLUMN audio_duration_ms;
+        ALTER TABLE entries DROP COLUMN audio_duration_ms;",
+    ),
+    (
+        7,
+        "DROP INDEX IF EXISTS idx_entries_audio_hash;
+        ALTER TABLE entries_archive DROP COLUMN audio_hash;
+        ALTER TABLE entries DROP COLUMN audio_hash;",
+    ),
+    (
+        8,
+        "DROP TRIGGER IF EXISTS entries_fts_update;
+        DROP TRIGGER IF EXISTS entries_fts_delete;
+        DROP TRIGGER IF EXISTS entries_fts_insert;
+        DROP TABLE IF EXISTS entries_fts;",
+    ),
+    (
+        9,
+        "ALTER TABLE entries_archive DROP COLUMN updated_at;
+        ALTER TABLE entries DROP COLUMN updated_at;",
+    ),
+    (
+        10,
+        "ALTER TABLE entries_archive DROP COLUMN is_favorite;
+        ALTER TABLE entries DROP COLUMN is_favorite;",
+    ),
+];
+
+/// 実行予定のマイグレーション1件
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStep {
+    pub version: u32,
+    pub sql: String,
+}
+
+/// schema_migrations テーブルに記録された適用履歴
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationRecord {
+    pub version: u32,
+    /// "up"（適用）または "down"（ロールバック）
+    pub direction: String,
+    pub applied_at: String,
+}
 
 /// スキーマバージョンを取得
-fn get_user_version(conn: &Connection) -> Result<u32, AppError> {
+fn get_user_version(conn: &Connection) -> TaprResult<u32> {
     let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
     Ok(version)
 }
 
 /// スキーマバージョンを設定
-fn set_user_version(conn: &Connection, version: u32) -> Result<(), AppError> {
+fn set_user_version(conn: &Connection, version: u32) -> TaprResult<()> {
     conn.pragma_update(None, "user_version", version)?;
     Ok(())
 }
 
-/// マイグレーションを実行してスキーマを最新にする
-pub fn migrate(conn: &Connection) -> Result<(), AppError> {
-    let version = get_user_version(conn)?;
+/// マイグレーションの適用履歴を記録するテーブルを作成する
+///
+/// user_version によるバージョン管理の対象外で、常に存在を保証する。
+fn ensure_history_table(conn: &Connection) -> TaprResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            version     INTEGER NOT NULL,
+            direction   TEXT NOT NULL,
+            applied_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )?;
+    Ok(())
+}
+
+fn record_history(conn: &Connection, version: u32, direction: &str) -> TaprResult<()> {
+    conn.execute(
+        "INSERT INTO schema_migrations (version, direction) VALUES (?1, ?2)",
+        params![version, direction],
+    )?;
+    Ok(())
+}
+
+/// 現在のバージョンより新しい未適用のマイグレーション
+fn pending_migrations(version: u32) -> impl Iterator<Item = &'static (u32, &'static str)> {
+    MIGRATIONS.iter().filter(move |(v, _)| *v > version)
+}
 
-    if version < 1 {
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS entries (
-                id              INTEGER PRIMARY KEY AUTOINCREMENT,
-                raw_text        TEXT NOT NULL,
-                processed_text  TEXT NOT NULL,
-                mode_id         TEXT NOT NULL,
-                model           TEXT NOT NULL,
-                prompt_tokens   INTEGER,
-                completion_tokens INTEGER,
-                total_tokens    INTEGER,
-                created_at      TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+/// リンクされている SQLite に FTS5 が組み込まれているか
+pub fn fts5_available(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT sqlite_compileoption_used('ENABLE_FTS5')",
+        [],
+        |row| row.get::<_, bool>(0),
+    )
+    .unwrap_or(false)
+}
+
+/// マイグレーション1件分の SQL を実行する
+///
+/// FTS5 がない環境での `FTS_VERSION` の失敗だけは許容し、全文検索なしで先に進む。
+fn apply_migration(conn: &Connection, version: u32, sql: &str) -> TaprResult<()> {
+    match conn.execute_batch(sql) {
+        Ok(()) => Ok(()),
+        Err(e) if version == FTS_VERSION && !fts5_available(conn) => {
+            eprintln!(
+                "[db] FTS5 is not available, skipping full-text index (search falls back to LIKE): {}",
+                e
             );
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// マイグレーションの進捗を受け取るオブザーバー
+pub trait MigrationObserver {
+    /// 各ステップの実行前に呼ばれる（`step_index` は 1 始まり）
+    fn on_step(&self, step_name: &str, step_index: u32, total_steps: u32);
+}
+
+/// マイグレーションを実行してスキーマを最新にする
+///
+/// `observer` を渡すと未適用のステップごとに進捗を通知する。
+pub fn migrate(conn: &Connection, observer: Option<&dyn MigrationObserver>) -> TaprResult<()> {
+    ensure_history_table(conn)?;
+    let version = get_user_version(conn)?;
 
-            CREATE INDEX IF NOT EXISTS idx_entries_created_at ON entries(created_at);
-            CREATE INDEX IF NOT EXISTS idx_entries_mode_id ON entries(mode_id);",
-        )?;
-        set_user_version(conn, 1)?;
+    let pending: Vec<_> = pending_migrations(version).collect();
+    let total = pending.len() as u32;
+    for (i, (v, sql)) in pending.into_iter().enumerate() {
+        if let Some(observer) = observer {
+            observer.on_step(&format!("migrate to v{}", v), i as u32 + 1, total);
+        }
+        apply_migration(conn, *v, sql)?;
+        set_user_version(conn, *v)?;
+        record_history(conn, *v, "up")?;
     }
 
     debug_assert_eq!(get_user_version(conn)?, CURRENT_VERSION);
     Ok(())
 }
 
+/// 実行予定のマイグレーションを、DB を変更せずに列挙する
+///
+/// 各ステップを SAVEPOINT 内で実際に実行して検証し、最後にすべて巻き戻す。
+pub fn migrate_dry_run(conn: &Connection) -> TaprResult<Vec<MigrationStep>> {
+    let version = get_user_version(conn)?;
+    let mut steps = Vec::new();
+
+    conn.execute_batch("SAVEPOINT dry_run")?;
+    let result = pending_migrations(version).try_for_each(|(v, sql)| {
+        apply_migration(conn, *v, sql)?;
+        steps.push(MigrationStep {
+            version: *v,
+            sql: sql.to_string(),
+        });
+        Ok::<(), AppError>(())
+    });
+    conn.execute_batch("ROLLBACK TO dry_run; RELEASE dry_run")?;
+
+    result?;
+    Ok(steps)
+}
+
+/// 現在のバージョンから `to_version` までスキーマを巻き戻す
+///
+/// `DOWN_MIGRATIONS` を新しい順に適用する。途中で失敗した場合は何も変更しない。
+pub fn rollback_migration(conn: &Connection, to_version: u32) -> TaprResult<()> {
+    ensure_history_table(conn)?;
+    let version = get_user_version(conn)?;
+    if to_version >= version {
+        return Ok(());
+    }
+
+    conn.execute_batch("SAVEPOINT rollback_migration")?;
+    let result = (to_version + 1..=version).rev().try_for_each(|v| {
+        let (_, sql) = DOWN_MIGRATIONS
+            .iter()
+            .find(|(down_v, _)| *down_v == v)
+            .ok_or_else(|| AppError::Database(format!("no down migration for version {}", v)))?;
+        conn.execute_batch(sql)?;
+        set_user_version(conn, v - 1)?;
+        record_history(conn, v, "down")
+    });
+
+    match result {
+        Ok(()) => {
+            conn.execute_batch("RELEASE rollback_migration")?;
+            Ok(())
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO rollback_migration; RELEASE rollback_migration")?;
+            Err(e)
+        }
+    }
+}
+
+/// マイグレーションの適用履歴を古い順に取得する
+pub fn get_migration_history(conn: &Connection) -> TaprResult<Vec<MigrationRecord>> {
+    ensure_history_table(conn)?;
+    let mut stmt = conn
+        .prepare("SELECT version, direction, applied_at FROM schema_migrations ORDER BY id ASC")?;
+    let records = stmt
+        .query_map([], |row| {
+            Ok(MigrationRecord {
+                version: row.get(0)?,
+                direction: row.get(1)?,
+                applied_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(records)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,7 +378,7 @@ mod tests {
     #[test]
     fn test_migrate_creates_table() {
         let conn = Connection::open_in_memory().unwrap();
-        migrate(&conn).unwrap();
+        migrate(&conn, None).unwrap();
 
         let count: u32 = conn
             .query_row(
@@ -66,8 +393,355 @@ mod tests {
     #[test]
     fn test_migrate_idempotent() {
         let conn = Connection::open_in_memory().unwrap();
-        migrate(&conn).unwrap();
-        migrate(&conn).unwrap(); // 2回目もエラーにならない
+        migrate(&conn, None).unwrap();
+        migrate(&conn, None).unwrap(); // 2回目もエラーにならない
+        assert_eq!(get_user_version(&conn).unwrap(), CURRENT_VERSION);
+    }
+
+    struct RecordingObserver {
+        steps: std::cell::RefCell<Vec<(String, u32, u32)>>,
+    }
+
+    impl MigrationObserver for RecordingObserver {
+        fn on_step(&self, step_name: &str, step_index: u32, total_steps: u32) {
+            self.steps
+                .borrow_mut()
+                .push((step_name.to_string(), step_index, total_steps));
+        }
+    }
+
+    #[test]
+    fn test_migrate_notifies_observer() {
+        let conn = Connection::open_in_memory().unwrap();
+        let observer = RecordingObserver {
+            steps: Default::default(),
+        };
+        migrate(&conn, Some(&observer)).unwrap();
+
+        let steps = observer.steps.borrow();
+        assert_eq!(steps.len(), CURRENT_VERSION as usize);
+        assert_eq!(steps[0], ("migrate to v1".to_string(), 1, CURRENT_VERSION));
+        assert_eq!(
+            steps.last().unwrap(),
+            &(
+                format!("migrate to v{}", CURRENT_VERSION),
+                CURRENT_VERSION,
+                CURRENT_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_migrate_notifies_only_pending_steps() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+        rollback_migration(&conn, CURRENT_VERSION - 2).unwrap();
+
+        let observer = RecordingObserver {
+            steps: Default::default(),
+        };
+        migrate(&conn, Some(&observer)).unwrap();
+        let indices: Vec<(u32, u32)> = observer
+            .steps
+            .borrow()
+            .iter()
+            .map(|(_, i, total)| (*i, *total))
+            .collect();
+        assert_eq!(indices, vec![(1, 2), (2, 2)]);
+
+        // 最新なら通知しない
+        let observer = RecordingObserver {
+            steps: Default::default(),
+        };
+        migrate(&conn, Some(&observer)).unwrap();
+        assert!(observer.steps.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v2_adds_request_id() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('entries') WHERE name = 'request_id'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_migrate_v3_creates_archive_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='entries_archive'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_migrate_v4_adds_device_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        for table in ["entries", "entries_archive"] {
+            let count: u32 = conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = 'device_name'",
+                        table
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 1, "{}", table);
+        }
+    }
+
+    #[test]
+    fn test_migrate_v5_creates_recording_metrics_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='recording_metrics'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_migrate_v6_adds_audio_duration() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        for table in ["entries", "entries_archive"] {
+            let count: u32 = conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = 'audio_duration_ms'",
+                        table
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 1, "{}", table);
+        }
+
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='index' AND name='idx_entries_audio_duration'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_migrate_v7_adds_unique_audio_hash() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        for table in ["entries", "entries_archive"] {
+            let count: u32 = conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = 'audio_hash'",
+                        table
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 1, "{}", table);
+        }
+
+        let insert = |hash: Option<&str>| {
+            conn.execute(
+                "INSERT INTO entries (raw_text, processed_text, mode_id, model, audio_hash)
+                 VALUES ('a', 'a', 'raw', 'none', ?1)",
+                [hash],
+            )
+        };
+        // NULL は何件でも入る
+        insert(None).unwrap();
+        insert(None).unwrap();
+        insert(Some("abc")).unwrap();
+        assert!(insert(Some("abc")).is_err());
+    }
+
+    #[test]
+    fn test_migrate_v8_syncs_fts_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+        assert!(fts5_available(&conn));
+
+        conn.execute(
+            "INSERT INTO entries (raw_text, processed_text, mode_id, model)
+             VALUES ('きょうは温泉に行く', '今日は温泉に行く。', 'raw', 'none')",
+            [],
+        )
+        .unwrap();
+        let hits = |query: &str| -> u32 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM entries_fts WHERE entries_fts MATCH ?1",
+                [query],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(hits("\"温泉に\""), 1);
+
+        conn.execute("UPDATE entries SET processed_text = '明日は海に行く。'", [])
+            .unwrap();
+        assert_eq!(hits("\"今日は\""), 0);
+        assert_eq!(hits("\"海に行\""), 1);
+
+        conn.execute("DELETE FROM entries", []).unwrap();
+        assert_eq!(hits("\"温泉に\""), 0);
+    }
+
+    #[test]
+    fn test_migrate_v9_adds_updated_at() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        for table in ["entries", "entries_archive"] {
+            let count: u32 = conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = 'updated_at'",
+                        table
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 1, "{}", table);
+        }
+    }
+
+    #[test]
+    fn test_migrate_v10_adds_is_favorite() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        for table in ["entries", "entries_archive"] {
+            let (count, default): (u32, Option<String>) = conn
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*), MAX(dflt_value) FROM pragma_table_info('{}') WHERE name = 'is_favorite'",
+                        table
+                    ),
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap();
+            assert_eq!(count, 1, "{}", table);
+            assert_eq!(default.as_deref(), Some("0"), "{}", table);
+        }
+    }
+
+    #[test]
+    fn test_migrations_match_current_version() {
+        assert_eq!(MIGRATIONS.last().map(|(v, _)| *v), Some(CURRENT_VERSION));
+        assert_eq!(MIGRATIONS.len(), DOWN_MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_dry_run_does_not_modify_db() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        let steps = migrate_dry_run(&conn).unwrap();
+        assert_eq!(steps.len(), MIGRATIONS.len());
+        assert_eq!(steps[0].version, 1);
+
+        assert_eq!(get_user_version(&conn).unwrap(), 0);
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='entries'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_dry_run_after_migrate_is_empty() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+        assert!(migrate_dry_run(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rollback_and_reapply() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+
+        rollback_migration(&conn, 1).unwrap();
+        assert_eq!(get_user_version(&conn).unwrap(), 1);
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('entries') WHERE name = 'request_id'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 0);
+
+        migrate(&conn, None).unwrap();
         assert_eq!(get_user_version(&conn).unwrap(), CURRENT_VERSION);
     }
+
+    #[test]
+    fn test_migration_history() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn, None).unwrap();
+        rollback_migration(&conn, 1).unwrap();
+
+        let history = get_migration_history(&conn).unwrap();
+        let summary: Vec<(u32, &str)> = history
+            .iter()
+            .map(|r| (r.version, r.direction.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "up"),
+                (2, "up"),
+                (3, "up"),
+                (4, "up"),
+                (5, "up"),
+                (6, "up"),
+                (7, "up"),
+                (8, "up"),
+                (9, "up"),
+                (10, "up"),
+                (10, "down"),
+                (9, "down"),
+                (8, "down"),
+                (7, "down"),
+                (6, "down"),
+                (5, "down"),
+                (4, "down"),
+                (3, "down"),
+                (2, "down")
+            ]
+        );
+    }
 }
@@ -0,0 +1,299 @@
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+
+use crate::error::{AppError, TaprResult};
+
+/// 日時が壊れていたエントリに入れる `created_at`
+pub const FALLBACK_CREATED_AT: &str = "1970-01-01T00:00:00.000Z";
+
+/// 検証で見つかったエントリの問題
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryValidationIssue {
+    pub id: i64,
+    /// 問題のあるカラム名
+    pub field: String,
+    pub issue: String,
+}
+
+/// `repair_entry` の結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepairResult {
+    pub id: i64,
+    /// 書き換えたカラム名（修復不要なら空）
+    pub repaired_fields: Vec<String>,
+}
+
+/// 検証用に String へ変換せずバイト列のまま読み込んだエントリ
+///
+/// 不正な UTF-8 を含む行は `map_entry` では読み込めないため、ここでは
+/// `ValueRef` から生のバイト列を取り出す。
+struct RawEntry {
+    id: i64,
+    raw_text: Vec<u8>,
+    processed_text: Vec<u8>,
+    mode_id: Vec<u8>,
+    model: Vec<u8>,
+    created_at: Vec<u8>,
+}
+
+const RAW_ENTRY_COLUMNS: &str = "id, raw_text, processed_text, mode_id, model, created_at";
+
+fn map_raw_entry(row: &Row) -> rusqlite::Result<RawEntry> {
+    Ok(RawEntry {
+        id: row.get(0)?,
+        raw_text: value_bytes(row.get_ref(1)?),
+        processed_text: value_bytes(row.get_ref(2)?),
+        mode_id: value_bytes(row.get_ref(3)?),
+        model: value_bytes(row.get_ref(4)?),
+        created_at: value_bytes(row.get_ref(5)?),
+    })
+}
+
+/// カラムの値を型に関係なくバイト列として取り出す（NULL は空）
+fn value_bytes(value: ValueRef) -> Vec<u8> {
+    match value {
+        ValueRef::Null => Vec::new(),
+        ValueRef::Integer(n) => n.to_string().into_bytes(),
+        ValueRef::Real(f) => f.to_string().into_bytes(),
+        ValueRef::Text(bytes) | ValueRef::Blob(bytes) => bytes.to_vec(),
+    }
+}
+
+impl RawEntry {
+    fn issues(&self) -> Vec<EntryValidationIssue> {
+        let mut issues = Vec::new();
+        let mut push = |field: &str, issue: String| {
+            issues.push(EntryValidationIssue {
+                id: self.id,
+                field: field.to_string(),
+                issue,
+            })
+        };
+
+        for (field, bytes) in [
+            ("raw_text", &self.raw_text),
+            ("processed_text", &self.processed_text),
+        ] {
+            if let Err(e) = std::str::from_utf8(bytes) {
+                push(field, format!("invalid UTF-8 at byte {}", e.valid_up_to()));
+            }
+        }
+        if !is_rfc3339(&self.created_at) {
+            push(
+                "created_at",
+                format!(
+                    "not an RFC3339 timestamp: '{}'",
+                    String::from_utf8_lossy(&self.created_at)
+                ),
+            );
+        }
+        for (field, bytes) in [("mode_id", &self.mode_id), ("model", &self.model)] {
+            if String::from_utf8_lossy(bytes).trim().is_empty() {
+                push(field, "empty".to_string());
+            }
+        }
+        issues
+    }
+}
+
+fn is_rfc3339(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes)
+        .map(|s| chrono::DateTime::parse_from_rfc3339(s).is_ok())
+        .unwrap_or(false)
+}
+
+/// すべてのエントリを検証し、見つかった問題を ID 順に返す
+///
+/// 不正な UTF-8 の本文、RFC3339 として読めない `created_at`、
+/// 空の `mode_id` / `model` を検出する。
+pub fn validate_entries(conn: &Connection) -> TaprResult<Vec<EntryValidationIssue>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {RAW_ENTRY_COLUMNS} FROM entries ORDER BY id"
+    ))?;
+    let entries = stmt
+        .query_map([], map_raw_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries.iter().flat_map(RawEntry::issues).collect())
+}
+
+/// 1件のエントリを修復する
+///
+/// 本文の不正な UTF-8 は U+FFFD に置き換え、読めない `created_at` は
+/// `FALLBACK_CREATED_AT` にする。空の `mode_id` / `model` は元の値が
+/// 分からないため修復しない。
+pub fn repair_entry(conn: &Connection, id: i64) -> TaprResult<RepairResult> {
+    let entry = conn
+        .query_row(
+            &format!("SELECT {RAW_ENTRY_COLUMNS} FROM entries WHERE id = ?1"),
+            params![id],
+            map_raw_entry,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::not_found("entry", id),
+            e => e.into(),
+        })?;
+
+    let mut repaired_fields = Vec::new();
+    let tx = conn.unchecked_transaction()?;
+    for (field, bytes) in [
+        ("raw_text", &entry.raw_text),
+        ("processed_text", &entry.processed_text),
+    ] {
+        if std::str::from_utf8(bytes).is_err() {
+            tx.execute(
+                &format!("UPDATE entries SET {field} = ?1 WHERE id = ?2"),
+                params![String::from_utf8_lossy(bytes).to_string(), id],
+            )?;
+            repaired_fields.push(field.to_string());
+        }
+    }
+    if !is_rfc3339(&entry.created_at) {
+        tx.execute(
+            "UPDATE entries SET created_at = ?1 WHERE id = ?2",
+            params![FALLBACK_CREATED_AT, id],
+        )?;
+        repaired_fields.push("created_at".to_string());
+    }
+    tx.commit()?;
+
+    if !repaired_fields.is_empty() {
+        eprintln!("[db] repaired entry {}: {:?}", id, repaired_fields);
+    }
+    Ok(RepairResult {
+        id,
+        repaired_fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository::{get_entry, insert_entry, NewEntry};
+    use crate::db::schema;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::migrate(&conn, None).unwrap();
+        conn
+    }
+
+    fn insert(conn: &Connection) -> i64 {
+        insert_entry(
+            conn,
+            &NewEntry {
+                raw_text: "こんにちは世界".to_string(),
+                processed_text: "こんにちは、世界。".to_string(),
+                mode_id: "proofread".to_string(),
+                model: "gpt-4o-mini".to_string(),
+                prompt_tokens: None,
+                completion_tokens: None,
+                total_tokens: None,
+                request_id: None,
+                device_name: None,
+                audio_duration_ms: None,
+                audio_hash: None,
+            },
+        )
+        .unwrap()
+    }
+
+    /// 生のバイト列を TEXT として書き込む
+    fn set_bytes(conn: &Connection, id: i64, field: &str, bytes: &[u8]) {
+        conn.execute(
+            &format!("UPDATE entries SET {field} = CAST(?1 AS TEXT) WHERE id = ?2"),
+            params![bytes, id],
+        )
+        .unwrap();
+    }
+
+    fn issue(id: i64, field: &str, issue: &str) -> EntryValidationIssue {
+        EntryValidationIssue {
+            id,
+            field: field.to_string(),
+            issue: issue.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_valid_entries_have_no_issues() {
+        let conn = setup_db();
+        insert(&conn);
+        insert(&conn);
+        assert!(validate_entries(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_invalid_utf8() {
+        let conn = setup_db();
+        let id = insert(&conn);
+        // "こ" の後に不正なバイト
+        set_bytes(&conn, id, "raw_text", b"\xe3\x81\x93\xff");
+        set_bytes(&conn, id, "processed_text", b"\xc3");
+        assert_eq!(
+            validate_entries(&conn).unwrap(),
+            vec![
+                issue(id, "raw_text", "invalid UTF-8 at byte 3"),
+                issue(id, "processed_text", "invalid UTF-8 at byte 0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_malformed_created_at() {
+        let conn = setup_db();
+        let id = insert(&conn);
+        set_bytes(&conn, id, "created_at", b"2024-13-45 25:00");
+        assert_eq!(
+            validate_entries(&conn).unwrap(),
+            vec![issue(
+                id,
+                "created_at",
+                "not an RFC3339 timestamp: '2024-13-45 25:00'"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_mode_and_model() {
+        let conn = setup_db();
+        let id = insert(&conn);
+        set_bytes(&conn, id, "mode_id", b"");
+        set_bytes(&conn, id, "model", b"  ");
+        assert_eq!(
+            validate_entries(&conn).unwrap(),
+            vec![issue(id, "mode_id", "empty"), issue(id, "model", "empty")]
+        );
+    }
+
+    #[test]
+    fn test_repair_entry() {
+        let conn = setup_db();
+        let id = insert(&conn);
+        let other = insert(&conn);
+        set_bytes(&conn, id, "raw_text", b"\xe3\x81\x93\xff");
+        set_bytes(&conn, id, "created_at", b"yesterday");
+
+        let result = repair_entry(&conn, id).unwrap();
+        assert_eq!(result.repaired_fields, vec!["raw_text", "created_at"]);
+        assert!(validate_entries(&conn).unwrap().is_empty());
+
+        let entry = get_entry(&conn, id).unwrap().unwrap();
+        assert_eq!(entry.raw_text, "こ\u{FFFD}");
+        assert_eq!(entry.processed_text, "こんにちは、世界。");
+        assert_eq!(entry.created_at, FALLBACK_CREATED_AT);
+
+        // 問題のないエントリは書き換えない
+        assert!(repair_entry(&conn, other)
+            .unwrap()
+            .repaired_fields
+            .is_empty());
+    }
+
+    #[test]
+    fn test_repair_missing_entry() {
+        let conn = setup_db();
+        let err = repair_entry(&conn, 42).unwrap_err();
+        assert!(matches!(err, AppError::NotFound { .. }));
+    }
+}
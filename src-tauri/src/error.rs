@@ -1,3 +1,5 @@
+use std::sync::PoisonError;
+
 use serde::Serialize;
 use thiserror::Error;
 
@@ -24,6 +26,49 @@ pub enum AppError {
 
     #[error("{0}")]
     Io(#[from] std::io::Error),
+
+    /// 別スレッドのパニックで Mutex が poisoned になった
+    #[error("Lock poisoned: {0}")]
+    LockPoisoned(String),
+
+    #[error("{resource} not found: {id}")]
+    NotFound { resource: String, id: String },
+
+    /// 環境変数の値が期待する型として解釈できない
+    #[error("Invalid value for {var}: '{value}' (expected {expected})")]
+    EnvVarParse {
+        var: String,
+        value: String,
+        expected: String,
+    },
+
+    /// バグ報告時に貼り付けられるよう、元のエラーにリクエストIDを付与する
+    #[error("{source} (request_id: {request_id})")]
+    WithRequestId {
+        request_id: String,
+        source: Box<AppError>,
+    },
+}
+
+/// コマンド・リポジトリ共通の Result 型
+pub type TaprResult<T> = Result<T, AppError>;
+
+impl AppError {
+    /// リソースが見つからないエラーを作る
+    pub fn not_found(resource: &str, id: impl ToString) -> Self {
+        AppError::NotFound {
+            resource: resource.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    /// エラーにリクエストIDを付与する
+    pub fn with_request_id(self, request_id: &str) -> Self {
+        AppError::WithRequestId {
+            request_id: request_id.to_string(),
+            source: Box::new(self),
+        }
+    }
 }
 
 impl From<rusqlite::Error> for AppError {
@@ -32,6 +77,19 @@ impl From<rusqlite::Error> for AppError {
     }
 }
 
+#[cfg(feature = "connection-pool")]
+impl From<r2d2::Error> for AppError {
+    fn from(e: r2d2::Error) -> Self {
+        AppError::Database(e.to_string())
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(e: PoisonError<T>) -> Self {
+        AppError::LockPoisoned(e.to_string())
+    }
+}
+
 /// Tauri v2 のフロントエンドへのエラー伝搬用
 /// エラーを文字列としてシリアライズする
 impl Serialize for AppError {
@@ -42,3 +100,42 @@ impl Serialize for AppError {
         serializer.serialize_str(&self.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_poisoned_lock_converts_to_lock_poisoned() {
+        let mutex = Arc::new(Mutex::new(0));
+        let cloned = Arc::clone(&mutex);
+        let _ = std::thread::spawn(move || {
+            let _guard = cloned.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+
+        let locked = || -> TaprResult<i32> { Ok(*mutex.lock()?) };
+        assert!(matches!(locked(), Err(AppError::LockPoisoned(_))));
+    }
+
+    #[test]
+    fn test_not_found_display_and_serialize() {
+        let err = AppError::not_found("entry", 42);
+        assert_eq!(err.to_string(), "entry not found: 42");
+        assert_eq!(
+            serde_json::to_string(&err).unwrap(),
+            "\"entry not found: 42\""
+        );
+    }
+
+    #[test]
+    fn test_lock_poisoned_with_request_id() {
+        let err = AppError::LockPoisoned("audio state".into()).with_request_id("req-1");
+        assert_eq!(
+            err.to_string(),
+            "Lock poisoned: audio state (request_id: req-1)"
+        );
+    }
+}
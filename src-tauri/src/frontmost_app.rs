@@ -0,0 +1,56 @@
+//! 前面アプリの切り替えを監視し、`app_affinity` に合うモードを提案する
+//!
+//! `NSWorkspace.shared.frontmostApplication?.localizedName` を2秒ごとにポーリングし、
+//! 前面アプリが変わったら `mode-suggestion` イベントを送る。
+
+use std::time::Duration;
+
+use objc2_app_kit::NSWorkspace;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::config::modes;
+
+/// 前面アプリを確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 前面アプリが変わったときの `mode-suggestion` イベント
+#[derive(Debug, Clone, Serialize)]
+pub struct ModeSuggestionEvent {
+    /// 最もよく合うモードID（合うモードがなければ None）
+    pub mode_id: Option<String>,
+    pub app_name: String,
+}
+
+/// 前面アプリの表示名を返す（取得できなければ None）
+pub fn frontmost_app_name() -> Option<String> {
+    let workspace = NSWorkspace::sharedWorkspace();
+    let app = workspace.frontmostApplication()?;
+    app.localizedName().map(|name| name.to_string())
+}
+
+/// バックグラウンドスレッドで前面アプリの監視を開始する
+pub fn start_watcher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_app: Option<String> = None;
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let Some(app_name) = frontmost_app_name() else {
+                continue;
+            };
+            if last_app.as_deref() == Some(app_name.as_str()) {
+                continue;
+            }
+            last_app = Some(app_name.clone());
+
+            let mode_id = match modes::load_modes_from_app(&app) {
+                Ok(modes) => modes::suggest_mode_for_app(&modes, &app_name),
+                Err(e) => {
+                    eprintln!("[affinity] failed to load modes: {}", e);
+                    continue;
+                }
+            };
+            let _ = app.emit("mode-suggestion", ModeSuggestionEvent { mode_id, app_name });
+        }
+    });
+}
@@ -1,16 +1,29 @@
-//! Push-to-Talk: 右Optionキー長押しで録音を開始/停止する
+//! Push-to-Talk: 右Optionキー（既定）長押しで録音を開始/停止する
+//! （トグルモードではタップごとに開始/停止を切り替える）
 //!
-//! macOS の CGEventTap API を使い、keycode 61（右Option）の
-//! flagsChanged イベントを監視する。Accessibility 権限が必要。
-
-use core_foundation::base::TCFType;
-use core_foundation::runloop::{kCFRunLoopCommonModes, CFRunLoop, CFRunLoopSource};
+//! macOS の CGEventTap API を使い、修飾キーは flagsChanged、F13 などの通常キーは
+//! keyDown / keyUp イベントで押下・離上を判定する。Accessibility 権限が必要。
+//! 長押しの判定などは `PttConfig` で変更でき、変更時はリスナーを再起動する。
+//! キーだけは `set_ptt_keycode` で再起動せずに切り替えられる。
+//! 開始・停止時にはユーザー定義のフック（`ptt_hooks`）も実行する。
+
+use core_foundation::base::{CFRelease, TCFType};
+use core_foundation::runloop::{
+    kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource,
+};
+use serde::{Deserialize, Serialize};
 use std::ffi::c_void;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
+use crate::error::{AppError, TaprResult};
+use crate::ptt_hooks::{spawn_hook, PttHook};
+
 /// 右 Option キーの macOS keycode
-const RIGHT_OPTION_KEYCODE: i64 = 61;
+const RIGHT_OPTION_KEYCODE: u16 = 61;
 
 /// kCGKeyboardEventKeycode（CGEventField）
 const CG_KEYBOARD_EVENT_KEYCODE: u32 = 9;
@@ -18,11 +31,107 @@ const CG_KEYBOARD_EVENT_KEYCODE: u32 = 9;
 /// kCGEventFlagMaskAlternate — Option キーが押されている時のフラグ
 const CG_EVENT_FLAG_MASK_ALTERNATE: u64 = 0x00080000;
 
+/// macOS の仮想キーコードの上限（kVK_* は 0x00〜0x7F）
+const MAX_KEYCODE: i64 = 0x7F;
+
 /// CGEventType の定数（core-graphics の enum は PartialEq 未実装のため数値で扱う）
+const CG_EVENT_KEY_DOWN: u32 = 10;
+const CG_EVENT_KEY_UP: u32 = 11;
 const CG_EVENT_FLAGS_CHANGED: u32 = 12;
 const CG_EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFFFFFE;
 const CG_EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFFFFFF;
 
+/// トグルモードで、前回の切り替えからこの間隔内の押下はキーチャタリングとみなして無視する
+const TOGGLE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 停止要求を確認する間隔（run loop を区切って回す）
+const RUN_LOOP_SLICE: Duration = Duration::from_secs(1);
+
+/// PTT キーの操作方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PttMode {
+    /// 押している間だけ録音する
+    #[default]
+    Hold,
+    /// 1回タップで録音を開始し、次のタップで停止する
+    Toggle,
+}
+
+/// Push-to-Talk の設定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PttConfig {
+    /// 監視するキーの macOS keycode（修飾キー以外も指定できる）
+    pub keycode: u16,
+    /// 押下中に立つ CGEventFlags のマスク（通常キーなら 0）
+    pub modifier_flags: u64,
+    /// この秒数を超えて押し続けたら自動で停止する（0 なら無制限）
+    pub max_duration_secs: u64,
+    /// これより短い押下はタップとみなして録音を開始しない（0 なら即開始、トグルモードでは使わない）
+    pub tap_threshold_ms: u64,
+    #[serde(default)]
+    pub mode: PttMode,
+    /// ptt-start の直後に `sh -c` で実行するコマンド
+    #[serde(default)]
+    pub ptt_start_command: Option<String>,
+    /// ptt-stop の直後に `sh -c` で実行するコマンド
+    #[serde(default)]
+    pub ptt_stop_command: Option<String>,
+}
+
+impl Default for PttConfig {
+    fn default() -> Self {
+        Self {
+            keycode: RIGHT_OPTION_KEYCODE,
+            modifier_flags: CG_EVENT_FLAG_MASK_ALTERNATE,
+            max_duration_secs: 0,
+            tap_threshold_ms: 0,
+            mode: PttMode::Hold,
+            ptt_start_command: None,
+            ptt_stop_command: None,
+        }
+    }
+}
+
+/// 実行中のリスナーの設定と停止フラグ
+static LISTENER: Mutex<Option<(PttConfig, Arc<AtomicBool>)>> = Mutex::new(None);
+
+/// コールバックが監視するキーの keycode（`set_ptt_keycode` で実行中に変更できる）
+static PTT_KEYCODE: AtomicI64 = AtomicI64::new(RIGHT_OPTION_KEYCODE as i64);
+
+/// 監視中のキーが修飾キーのときの CGEventFlags のマスク（通常キーなら 0）
+static PTT_MODIFIER_FLAGS: AtomicU64 = AtomicU64::new(CG_EVENT_FLAG_MASK_ALTERNATE);
+
+/// トグルモードか（`set_ptt_mode` で実行中に変更できる）
+static PTT_TOGGLE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// 押下状態（タイマースレッドと共有する）
+#[derive(Default)]
+struct PressState {
+    pressed: bool,
+    /// ptt-start を送ったか
+    started: bool,
+    /// 押下ごとに増やし、古いタイマーを無効化する
+    generation: u64,
+    /// トグルモードで最後に開始/停止を切り替えた時刻
+    last_toggle: Option<Instant>,
+}
+
+/// トグルモードの押下で行う操作
+#[derive(Debug, PartialEq, Eq)]
+enum ToggleAction {
+    Start,
+    Stop,
+}
+
+/// コールバックに渡すリスナーごとのコンテキスト
+struct ListenerContext {
+    app: AppHandle,
+    config: PttConfig,
+    stopped: Arc<AtomicBool>,
+    press: Arc<Mutex<PressState>>,
+}
+
 // --- Core Graphics FFI ---
 type CGEventRef = *mut c_void;
 type CGEventTapProxy = *mut c_void;
@@ -60,6 +169,8 @@ extern "C" {
         port: CFMachPortRef,
         order: i64,
     ) -> core_foundation::runloop::CFRunLoopSourceRef;
+
+    fn CFMachPortInvalidate(port: CFMachPortRef);
 }
 
 // --- Accessibility FFI ---
@@ -83,9 +194,66 @@ extern "C" {
     static kCFTypeDictionaryValueCallBacks: c_void;
 }
 
+/// 修飾キーの keycode に対応する CGEventFlags のマスク（通常キーなら None）
+fn modifier_flag_for_keycode(keycode: i64) -> Option<u64> {
+    match keycode {
+        // 右Command / 左Command
+        54 | 55 => Some(0x00100000),
+        // 左Shift / 右Shift
+        56 | 60 => Some(0x00020000),
+        // CapsLock
+        57 => Some(0x00010000),
+        // 左Option / 右Option
+        58 | 61 => Some(CG_EVENT_FLAG_MASK_ALTERNATE),
+        // 左Control / 右Control
+        59 | 62 => Some(0x00040000),
+        // Fn
+        63 => Some(0x00800000),
+        _ => None,
+    }
+}
+
+/// PTT のキーを実行中のリスナーを再起動せずに切り替える
+///
+/// 修飾キーなら対応するフラグのマスクも合わせて切り替える。
+/// 新しいキーの押下は次のイベントから判定するので、押下中に切り替えた場合は離してから押し直す。
+pub fn set_ptt_keycode(keycode: i64) -> TaprResult<()> {
+    if !(0..=MAX_KEYCODE).contains(&keycode) {
+        return Err(AppError::Config(format!(
+            "Invalid PTT keycode: {} (expected 0-{})",
+            keycode, MAX_KEYCODE
+        )));
+    }
+    let modifier_flags = modifier_flag_for_keycode(keycode).unwrap_or(0);
+    PTT_KEYCODE.store(keycode, Ordering::Relaxed);
+    PTT_MODIFIER_FLAGS.store(modifier_flags, Ordering::Relaxed);
+
+    if let Ok(mut listener) = LISTENER.lock() {
+        if let Some((config, _)) = listener.as_mut() {
+            config.keycode = keycode as u16;
+            config.modifier_flags = modifier_flags;
+        }
+    }
+    eprintln!("[hotkey] PTT key set to keycode {}", keycode);
+    Ok(())
+}
+
+/// PTT の操作方法を実行中のリスナーを再起動せずに切り替える
+///
+/// 録音中に切り替えた場合、その録音は新しいモードの操作（離す / 次のタップ）で停止する。
+pub fn set_ptt_mode(mode: PttMode) {
+    PTT_TOGGLE_MODE.store(mode == PttMode::Toggle, Ordering::Relaxed);
+    if let Ok(mut listener) = LISTENER.lock() {
+        if let Some((config, _)) = listener.as_mut() {
+            config.mode = mode;
+        }
+    }
+    eprintln!("[hotkey] PTT mode set to {:?}", mode);
+}
+
 /// CGEventTap のコールバック関数
 ///
-/// flagsChanged イベントを受け取り、右 Option キーの押下/離上を判定する。
+/// 修飾キーは flagsChanged のフラグ、通常キーは keyDown / keyUp で押下/離上を判定する。
 /// 押下時は "ptt-start"、離上時は "ptt-stop" イベントを Tauri に発火する。
 unsafe extern "C" fn event_tap_callback(
     _proxy: CGEventTapProxy,
@@ -100,30 +268,213 @@ unsafe extern "C" fn event_tap_callback(
         return event;
     }
 
-    // flagsChanged 以外のイベントはスルー
-    if event_type != CG_EVENT_FLAGS_CHANGED {
+    // キー関連以外のイベントはスルー
+    if !matches!(
+        event_type,
+        CG_EVENT_FLAGS_CHANGED | CG_EVENT_KEY_DOWN | CG_EVENT_KEY_UP
+    ) {
+        return event;
+    }
+
+    // user_info からコンテキストを復元（所有権は移さない）
+    let ctx = &*(user_info as *const ListenerContext);
+    // 再起動で停止済みのリスナーは何もしない
+    if ctx.stopped.load(Ordering::Relaxed) {
         return event;
     }
 
     // keycode を取得
     let keycode = CGEventGetIntegerValueField(event, CG_KEYBOARD_EVENT_KEYCODE);
 
-    if keycode != RIGHT_OPTION_KEYCODE {
+    if keycode != PTT_KEYCODE.load(Ordering::Relaxed) {
         return event;
     }
 
-    // フラグから Alternate（Option）キーの状態を判定
-    let flags = CGEventGetFlags(event);
-    let is_pressed = (flags & CG_EVENT_FLAG_MASK_ALTERNATE) != 0;
+    let is_pressed = match event_type {
+        // 修飾キー: フラグから状態を判定
+        CG_EVENT_FLAGS_CHANGED => {
+            let mask = PTT_MODIFIER_FLAGS.load(Ordering::Relaxed);
+            if mask == 0 {
+                return event;
+            }
+            (CGEventGetFlags(event) & mask) != 0
+        }
+        // 通常キー: 押しっぱなしのキーリピートは on_press 側で無視される
+        CG_EVENT_KEY_DOWN => true,
+        _ => false,
+    };
+
+    match (is_pressed, PTT_TOGGLE_MODE.load(Ordering::Relaxed)) {
+        (true, false) => on_press(ctx),
+        (false, false) => on_release(ctx),
+        (true, true) => on_toggle_press(ctx),
+        (false, true) => on_toggle_release(ctx),
+    }
 
-    // user_info から AppHandle を復元（所有権は移さない）
-    let app_handle = &*(user_info as *const AppHandle);
+    event
+}
 
-    let event_name = if is_pressed { "ptt-start" } else { "ptt-stop" };
+/// ptt-start を送り、開始フックを起動する
+fn emit_start(app: &AppHandle, config: &PttConfig) {
+    let _ = app.emit("ptt-start", ());
+    spawn_hook(app, PttHook::Start, config.ptt_start_command.as_deref());
+}
 
-    let _ = app_handle.emit(event_name, ());
+/// ptt-stop を送り、停止フックを起動する
+fn emit_stop(app: &AppHandle, config: &PttConfig) {
+    let _ = app.emit("ptt-stop", ());
+    spawn_hook(app, PttHook::Stop, config.ptt_stop_command.as_deref());
+}
 
-    event
+/// 押下時: タップ判定の閾値を過ぎたら ptt-start を送り、最大時間で自動停止する
+fn on_press(ctx: &ListenerContext) {
+    let generation = {
+        let Ok(mut press) = ctx.press.lock() else {
+            return;
+        };
+        if press.pressed {
+            return;
+        }
+        press.pressed = true;
+        press.started = false;
+        press.generation += 1;
+        press.generation
+    };
+
+    let config = ctx.config.clone();
+    if config.tap_threshold_ms == 0 {
+        start_if_current(&ctx.app, &config, &ctx.press, generation);
+        if config.max_duration_secs == 0 {
+            return;
+        }
+    }
+
+    let app = ctx.app.clone();
+    let press = ctx.press.clone();
+    std::thread::spawn(move || {
+        if config.tap_threshold_ms > 0 {
+            std::thread::sleep(Duration::from_millis(config.tap_threshold_ms));
+            if !start_if_current(&app, &config, &press, generation) {
+                return;
+            }
+        }
+        if config.max_duration_secs == 0 {
+            return;
+        }
+        stop_after_max_duration(&app, &config, &press, generation);
+    });
+}
+
+/// `max_duration_secs` 待って同じ録音が続いていれば ptt-stop を送る（タイマースレッドから呼ぶ）
+fn stop_after_max_duration(
+    app: &AppHandle,
+    config: &PttConfig,
+    press: &Mutex<PressState>,
+    generation: u64,
+) {
+    std::thread::sleep(Duration::from_secs(config.max_duration_secs));
+    let Ok(mut state) = press.lock() else {
+        return;
+    };
+    if state.generation == generation && state.started {
+        state.started = false;
+        eprintln!(
+            "[hotkey] PTT recording longer than {}s, stopping",
+            config.max_duration_secs
+        );
+        emit_stop(app, config);
+    }
+}
+
+/// トグルモードの押下を判定する
+///
+/// 前回の切り替えから `debounce` 以内ならチャタリングとして無視し、それ以外は録音中かどうかを反転する。
+fn toggle_action(state: &mut PressState, now: Instant, debounce: Duration) -> Option<ToggleAction> {
+    if state
+        .last_toggle
+        .is_some_and(|last| now.duration_since(last) < debounce)
+    {
+        return None;
+    }
+    state.last_toggle = Some(now);
+    state.generation += 1;
+    if state.started {
+        state.started = false;
+        Some(ToggleAction::Stop)
+    } else {
+        state.started = true;
+        Some(ToggleAction::Start)
+    }
+}
+
+/// トグルモードの押下: 停止中なら ptt-start、録音中なら ptt-stop を送る
+fn on_toggle_press(ctx: &ListenerContext) {
+    let (action, generation) = {
+        let Ok(mut press) = ctx.press.lock() else {
+            return;
+        };
+        // 通常キーを押しっぱなしにしたときのキーリピートは無視する
+        if press.pressed {
+            return;
+        }
+        press.pressed = true;
+        let Some(action) = toggle_action(&mut press, Instant::now(), TOGGLE_DEBOUNCE) else {
+            return;
+        };
+        (action, press.generation)
+    };
+
+    match action {
+        ToggleAction::Stop => emit_stop(&ctx.app, &ctx.config),
+        ToggleAction::Start => {
+            emit_start(&ctx.app, &ctx.config);
+            if ctx.config.max_duration_secs > 0 {
+                let app = ctx.app.clone();
+                let config = ctx.config.clone();
+                let press = ctx.press.clone();
+                std::thread::spawn(move || {
+                    stop_after_max_duration(&app, &config, &press, generation)
+                });
+            }
+        }
+    }
+}
+
+/// トグルモードの離上: 次の押下を受け付けるだけで録音は止めない
+fn on_toggle_release(ctx: &ListenerContext) {
+    if let Ok(mut press) = ctx.press.lock() {
+        press.pressed = false;
+    }
+}
+
+/// 同じ押下がまだ続いていれば ptt-start を送る
+fn start_if_current(
+    app: &AppHandle,
+    config: &PttConfig,
+    press: &Mutex<PressState>,
+    generation: u64,
+) -> bool {
+    let Ok(mut state) = press.lock() else {
+        return false;
+    };
+    if state.generation != generation || !state.pressed {
+        return false;
+    }
+    state.started = true;
+    emit_start(app, config);
+    true
+}
+
+/// 離上時: 開始済みなら ptt-stop を送る（タップなら何もしない）
+fn on_release(ctx: &ListenerContext) {
+    let Ok(mut press) = ctx.press.lock() else {
+        return;
+    };
+    press.pressed = false;
+    if press.started {
+        press.started = false;
+        emit_stop(&ctx.app, &ctx.config);
+    }
 }
 
 /// Accessibility 権限をチェックする
@@ -157,23 +508,53 @@ pub fn is_accessibility_trusted(prompt: bool) -> bool {
     }
 }
 
+/// 実行中のリスナーの設定（未起動ならデフォルト）
+pub fn current_config() -> PttConfig {
+    LISTENER
+        .lock()
+        .ok()
+        .and_then(|listener| listener.as_ref().map(|(config, _)| config.clone()))
+        .unwrap_or_default()
+}
+
 /// CGEventTap リスナーを専用スレッドで起動する
 ///
 /// `app_handle` を使ってフロントエンドにイベントを送信する。
 /// Accessibility 権限がない場合はログを出力して静かに失敗する。
-pub fn start_listener(app_handle: AppHandle) {
+/// すでに起動中のリスナーがあれば停止してから起動する。
+pub fn start_listener(app_handle: AppHandle, config: PttConfig) {
     // prompt: true で未許可ならmacOSの許可ダイアログを表示
     if !is_accessibility_trusted(true) {
         eprintln!("[hotkey] Accessibility permission not granted. PTT will not work.");
     }
 
+    PTT_KEYCODE.store(config.keycode as i64, Ordering::Relaxed);
+    PTT_MODIFIER_FLAGS.store(config.modifier_flags, Ordering::Relaxed);
+    PTT_TOGGLE_MODE.store(config.mode == PttMode::Toggle, Ordering::Relaxed);
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    if let Ok(mut listener) = LISTENER.lock() {
+        if let Some((_, previous)) = listener.replace((config.clone(), stopped.clone())) {
+            previous.store(true, Ordering::Relaxed);
+        }
+    }
+
+    let context = ListenerContext {
+        app: app_handle,
+        config,
+        stopped,
+        press: Arc::new(Mutex::new(PressState::default())),
+    };
+
     std::thread::spawn(move || {
         unsafe {
-            // AppHandle を生ポインタに変換（スレッドの生存期間中ずっと有効）
-            let app_handle_ptr = Box::into_raw(Box::new(app_handle)) as *mut c_void;
+            // コンテキストを生ポインタに変換（スレッド終了時に解放する）
+            let context_ptr = Box::into_raw(Box::new(context));
 
-            // flagsChanged (12) のみ監視
-            let event_mask = 1u64 << CG_EVENT_FLAGS_CHANGED;
+            // 修飾キー用の flagsChanged (12) と通常キー用の keyDown (10) / keyUp (11) を監視
+            let event_mask = (1u64 << CG_EVENT_FLAGS_CHANGED)
+                | (1u64 << CG_EVENT_KEY_DOWN)
+                | (1u64 << CG_EVENT_KEY_UP);
 
             // CGEventTapLocation::Session = 1（HID = 0, Session = 1, AnnotatedSession = 2）
             // CGEventTapPlacement::HeadInsertEventTap = 0
@@ -184,13 +565,14 @@ pub fn start_listener(app_handle: AppHandle) {
                 1, // ListenOnly
                 event_mask,
                 event_tap_callback,
-                app_handle_ptr,
+                context_ptr as *mut c_void,
             );
 
             if tap.is_null() {
                 eprintln!(
                     "[hotkey] Failed to create CGEventTap. Check Accessibility permissions."
                 );
+                drop(Box::from_raw(context_ptr));
                 return;
             }
 
@@ -198,6 +580,9 @@ pub fn start_listener(app_handle: AppHandle) {
 
             if source_ref.is_null() {
                 eprintln!("[hotkey] Failed to create CFRunLoopSource.");
+                CFMachPortInvalidate(tap);
+                CFRelease(tap as *const c_void);
+                drop(Box::from_raw(context_ptr));
                 return;
             }
 
@@ -208,8 +593,90 @@ pub fn start_listener(app_handle: AppHandle) {
             // タップを有効化
             CGEventTapEnable(tap, true);
 
-            // CFRunLoop を開始（このスレッドはここでブロックされる）
-            CFRunLoop::run_current();
+            // 停止フラグが立つまで run loop を区切って回す
+            while !(*context_ptr).stopped.load(Ordering::Relaxed) {
+                CFRunLoop::run_in_mode(kCFRunLoopDefaultMode, RUN_LOOP_SLICE, false);
+            }
+
+            CGEventTapEnable(tap, false);
+            run_loop.remove_source(&source, kCFRunLoopCommonModes);
+            CFMachPortInvalidate(tap);
+            CFRelease(tap as *const c_void);
+            drop(Box::from_raw(context_ptr));
+            eprintln!("[hotkey] PTT listener stopped");
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modifier_flag_for_keycode() {
+        assert_eq!(
+            modifier_flag_for_keycode(RIGHT_OPTION_KEYCODE as i64),
+            Some(CG_EVENT_FLAG_MASK_ALTERNATE)
+        );
+        // 右Command
+        assert_eq!(modifier_flag_for_keycode(54), Some(0x00100000));
+        // F13 は通常キー
+        assert_eq!(modifier_flag_for_keycode(105), None);
+    }
+
+    #[test]
+    fn test_toggle_action_debounces_chatter() {
+        let mut state = PressState::default();
+        let t0 = Instant::now();
+
+        assert_eq!(
+            toggle_action(&mut state, t0, TOGGLE_DEBOUNCE),
+            Some(ToggleAction::Start)
+        );
+        assert!(state.started);
+        // チャタリングによる直後の押下では止めない
+        assert_eq!(
+            toggle_action(&mut state, t0 + Duration::from_millis(50), TOGGLE_DEBOUNCE),
+            None
+        );
+        assert!(state.started);
+
+        let t1 = t0 + Duration::from_secs(5);
+        assert_eq!(
+            toggle_action(&mut state, t1, TOGGLE_DEBOUNCE),
+            Some(ToggleAction::Stop)
+        );
+        assert!(!state.started);
+        assert_eq!(
+            toggle_action(&mut state, t1 + TOGGLE_DEBOUNCE, TOGGLE_DEBOUNCE),
+            Some(ToggleAction::Start)
+        );
+        assert_eq!(state.generation, 3);
+    }
+
+    #[test]
+    fn test_ptt_mode_serde() {
+        assert_eq!(
+            serde_json::from_str::<PttMode>("\"toggle\"").unwrap(),
+            PttMode::Toggle
+        );
+        assert_eq!(serde_json::to_string(&PttMode::Hold).unwrap(), "\"hold\"");
+        assert!(serde_json::from_str::<PttMode>("\"tap\"").is_err());
+    }
+
+    #[test]
+    fn test_set_ptt_keycode() {
+        assert!(set_ptt_keycode(-1).is_err());
+        assert!(set_ptt_keycode(MAX_KEYCODE + 1).is_err());
+
+        set_ptt_keycode(105).unwrap();
+        assert_eq!(PTT_KEYCODE.load(Ordering::Relaxed), 105);
+        assert_eq!(PTT_MODIFIER_FLAGS.load(Ordering::Relaxed), 0);
+
+        set_ptt_keycode(RIGHT_OPTION_KEYCODE as i64).unwrap();
+        assert_eq!(
+            PTT_MODIFIER_FLAGS.load(Ordering::Relaxed),
+            CG_EVENT_FLAG_MASK_ALTERNATE
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use base64::Engine;
+use http::Extensions;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Request, Response};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
+use serde::Serialize;
+
+/// 保持する HTTP 交換ログの最大件数
+pub const HTTP_DEBUG_CAPACITY: usize = 50;
+
+/// ログに残すレスポンスボディの先頭バイト数
+const BODY_PREVIEW_BYTES: usize = 500;
+
+/// 値を伏せて記録するヘッダー（小文字）
+const REDACTED_HEADERS: &[&str] = &["authorization", "proxy-authorization", "x-api-key"];
+
+/// 容量を超えると古いものから捨てるリングバッファ
+#[derive(Debug)]
+pub struct CircularBuffer<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Clone> CircularBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// 要素を追加する。容量を超えた場合は最も古い要素を捨てる
+    pub fn push(&mut self, item: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.items.len() >= self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+    }
+
+    /// 古い順に全要素を複製して返す
+    pub fn to_vec(&self) -> Vec<T> {
+        self.items.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// 1回分の HTTP リクエスト / レスポンスの記録
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpExchange {
+    /// リクエスト開始時刻（UNIX ミリ秒）
+    pub timestamp: u64,
+    pub method: String,
+    pub url: String,
+    /// Authorization 等は "[REDACTED]" に置き換え済み
+    pub request_headers: Vec<(String, String)>,
+    /// ボディサイズ（multipart などストリームの場合は None）
+    pub request_body_bytes: Option<u64>,
+    pub status: Option<u16>,
+    /// レスポンスボディの先頭500バイト（SSE ストリームの場合は None）
+    pub response_body_preview: Option<String>,
+    /// プレビューがバイナリのため base64 エンコードされているか
+    pub response_body_base64: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+pub type HttpDebugLog = Arc<Mutex<CircularBuffer<HttpExchange>>>;
+
+/// プロセス全体で共有する HTTP 交換ログ
+///
+/// AI クライアントは AppHandle なしで生成されるため、Tauri State と
+/// クライアントの双方がこの共有ログを参照する。
+pub fn shared_log() -> HttpDebugLog {
+    static LOG: OnceLock<HttpDebugLog> = OnceLock::new();
+    LOG.get_or_init(|| Arc::new(Mutex::new(CircularBuffer::new(HTTP_DEBUG_CAPACITY))))
+        .clone()
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// HTTP 交換の記録を有効にするか設定する（起動時に `AppConfig::debug` から設定）
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Release);
+}
+
+/// TAPONSEN_HTTP_DEBUG=1 のとき HTTP 交換を記録する
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// reqwest クライアントをミドルウェア対応クライアントに包む
+///
+/// デバッグが有効な場合のみ `DebugInterceptor` を挟む。
+pub fn wrap_client(client: reqwest::Client) -> ClientWithMiddleware {
+    let builder = ClientBuilder::new(client);
+    if is_enabled() {
+        builder.with(DebugInterceptor { log: shared_log() }).build()
+    } else {
+        builder.build()
+    }
+}
+
+/// HTTP 交換ログを参照するための Tauri State
+pub struct HttpDebugState {
+    pub log: HttpDebugLog,
+}
+
+impl HttpDebugState {
+    pub fn new() -> Self {
+        Self { log: shared_log() }
+    }
+}
+
+impl Default for HttpDebugState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// リクエストとレスポンスを記録するミドルウェア
+pub struct DebugInterceptor {
+    log: HttpDebugLog,
+}
+
+#[async_trait]
+impl Middleware for DebugInterceptor {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let started = Instant::now();
+        let mut exchange = HttpExchange {
+            timestamp: now_millis(),
+            method: req.method().to_string(),
+            url: req.url().to_string(),
+            request_headers: redact_headers(req.headers()),
+            request_body_bytes: req
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| b.len() as u64),
+            status: None,
+            response_body_preview: None,
+            response_body_base64: false,
+            error: None,
+            duration_ms: 0,
+        };
+
+        let result = match next.run(req, extensions).await {
+            Ok(response) => capture_response(response, &mut exchange).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &result {
+            exchange.error = Some(e.to_string());
+        }
+        exchange.duration_ms = started.elapsed().as_millis() as u64;
+
+        eprintln!(
+            "[http] {} {} -> {} ({}ms)",
+            exchange.method,
+            exchange.url,
+            exchange
+                .status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "error".to_string()),
+            exchange.duration_ms
+        );
+        if let Ok(mut log) = self.log.lock() {
+            log.push(exchange);
+        }
+        result
+    }
+}
+
+/// レスポンスのステータスとボディのプレビューを記録する
+///
+/// ボディを読み切った後、同じ内容でレスポンスを組み立て直して返す。
+/// SSE ストリームは逐次処理を妨げないようボディを読まない。
+async fn capture_response(
+    response: Response,
+    exchange: &mut HttpExchange,
+) -> reqwest_middleware::Result<Response> {
+    exchange.status = Some(response.status().as_u16());
+
+    let is_event_stream = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    if is_event_stream {
+        return Ok(response);
+    }
+
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+
+    let (preview, is_base64) = body_preview(&body);
+    exchange.response_body_preview = Some(preview);
+    exchange.response_body_base64 = is_base64;
+
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(Response::from(rebuilt))
+}
+
+/// ボディ先頭のプレビューを作る。UTF-8 でなければ base64 で返す
+fn body_preview(body: &[u8]) -> (String, bool) {
+    let head = &body[..body.len().min(BODY_PREVIEW_BYTES)];
+    match std::str::from_utf8(head) {
+        Ok(text) => (text.to_string(), false),
+        // 切り詰め位置がマルチバイト文字の途中だった場合は有効な部分だけ使う
+        Err(e) if e.error_len().is_none() => (
+            String::from_utf8_lossy(&head[..e.valid_up_to()]).into_owned(),
+            false,
+        ),
+        Err(_) => (base64::engine::general_purpose::STANDARD.encode(head), true),
+    }
+}
+
+/// ヘッダーを記録用に変換し、認証情報を伏せる
+fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = name.as_str().to_string();
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "[REDACTED]".to_string()
+            } else {
+                value.to_str().unwrap_or("[binary]").to_string()
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, AUTHORIZATION};
+
+    #[test]
+    fn test_circular_buffer_drops_oldest() {
+        let mut buf = CircularBuffer::new(3);
+        for i in 0..5 {
+            buf.push(i);
+        }
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf.to_vec(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_redact_authorization() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let redacted = redact_headers(&headers);
+        assert!(redacted.iter().all(|(_, v)| !v.contains("secret")));
+        assert!(redacted
+            .iter()
+            .any(|(k, v)| k == "content-type" && v == "application/json"));
+    }
+
+    #[test]
+    fn test_body_preview_text_is_truncated() {
+        let body = "a".repeat(600);
+        let (preview, is_base64) = body_preview(body.as_bytes());
+        assert_eq!(preview.len(), BODY_PREVIEW_BYTES);
+        assert!(!is_base64);
+    }
+
+    #[test]
+    fn test_body_preview_cut_inside_multibyte_char() {
+        // "あ" は3バイトなので500バイト目で文字の途中になる
+        let body = "あ".repeat(200);
+        let (preview, is_base64) = body_preview(body.as_bytes());
+        assert!(!is_base64);
+        assert_eq!(preview, "あ".repeat(166));
+    }
+
+    #[test]
+    fn test_body_preview_binary_is_base64() {
+        let body = [0xFFu8, 0xFE, 0x00, 0x01];
+        let (preview, is_base64) = body_preview(&body);
+        assert!(is_base64);
+        assert_eq!(preview, "//4AAQ==");
+    }
+}
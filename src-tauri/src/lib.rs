@@ -4,32 +4,135 @@ pub mod config;
 pub mod db;
 pub mod error;
 #[cfg(target_os = "macos")]
+pub mod frontmost_app;
+#[cfg(target_os = "macos")]
 pub mod hotkey;
+pub mod http_debug;
+pub mod ptt_hooks;
+pub mod server;
 pub mod voice;
 
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cpal::traits::HostTrait;
 use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
 
+use ai::context::ContextRegistry;
+use ai::queue::{AiQueue, MAX_QUEUE_LEN};
+use commands::ai::AiState;
 use commands::audio::AudioState;
-use db::DbState;
+use commands::health::StartupState;
+use commands::paste::PasteState;
+use commands::share::ShareState;
+use config::app_config::AppConfig;
+use config::dotenv::EnvFilesState;
+use db::{AppMigrationObserver, DbState, MigrationState};
+use http_debug::HttpDebugState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // .env ファイルから環境変数を読み込む（なくてもエラーにしない）
-    let _ = dotenvy::dotenv();
+    // .env / .env.{APP_ENV} / .env.local から環境変数を読み込む（なくてもエラーにしない）
+    let env_files = config::dotenv::load_env_files();
+    // 環境変数の不備は既定値で起動し、起動時の警告として ping で返す
+    let (config, config_errors) = AppConfig::from_env();
+    let startup = StartupState::new();
+    for error in config_errors {
+        startup.add_warning(format!("configuration: {}", error));
+    }
+    http_debug::set_enabled(config.debug);
+    let ai_queue = Arc::new(AiQueue::new(MAX_QUEUE_LEN));
+    ai_queue.spawn_consumer();
     tauri::Builder::default()
+        .plugin(tauri_plugin_deep_link::init())
         .manage(AudioState::new())
+        .manage(AiState::new(&config))
+        .manage(ContextRegistry::default())
+        .manage(ai_queue)
+        .manage(PasteState::new(&config))
+        .manage(startup)
+        .manage(HttpDebugState::new())
+        .manage(MigrationState::new())
+        .manage(EnvFilesState {
+            loaded: env_files
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+        })
+        .manage(config)
         .setup(|app| {
+            let startup = app.state::<StartupState>();
+
             // SQLite DB を Application Support ディレクトリに初期化
             let app_data_dir = app
                 .path()
                 .app_data_dir()
                 .expect("failed to resolve app data directory");
             let db_path = app_data_dir.join("tap-onsen.db");
+            let migration_state = app.state::<MigrationState>();
+            let observer = AppMigrationObserver {
+                app: app.handle().clone(),
+                state: &migration_state,
+            };
             let db_state =
-                DbState::new(&db_path).expect("failed to initialize database");
+                DbState::new(&db_path, Some(&observer)).expect("failed to initialize database");
+            if let Err(e) = migration_state.finish(db::schema::CURRENT_VERSION) {
+                eprintln!("[db] failed to record migration status: {}", e);
+            }
+
+            // 起動時に古いエントリを entries_archive へ移す（失敗しても起動は続ける）
+            if let Ok(conn) = db_state.conn() {
+                match db::repository::archive_expired_entries(&conn) {
+                    Ok(n) if n > 0 => eprintln!("[archive] archived {} old entries", n),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("[archive] failed to archive entries: {}", e),
+                }
+            }
             app.manage(db_state);
+            startup.db_ready.store(true, Ordering::Release);
+
+            // 共有リンク（tap-onsen://view-entry）の署名鍵を読み込み、ディープリンクを受け付ける
+            app.manage(ShareState::load(&app.config().identifier, &app_data_dir));
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    commands::share::handle_deep_link(&handle, &url);
+                }
+            });
 
-            // 12時間ごとに3日以上前のエントリを削除するバックグラウンドタスク
+            // 入力デバイスと AI 設定の不備は起動を止めず警告として ping で返す
+            if cpal::default_host().default_input_device().is_some() {
+                startup.audio_ready.store(true, Ordering::Release);
+            } else {
+                startup.add_warning("no default input device found".to_string());
+            }
+            // Anthropic はモデル一覧の取得を伴うのでバックグラウンドで確認する
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let startup = handle.state::<StartupState>();
+                match commands::ai::check_ai_provider(&handle.state::<AppConfig>()).await {
+                    Ok(unverified) => {
+                        startup.ai_config_valid.store(true, Ordering::Release);
+                        // 通信できなかっただけなら設定は有効のまま警告だけ出す
+                        if let Some(reason) = unverified {
+                            startup.add_warning(reason);
+                        }
+                    }
+                    Err(e) => startup.add_warning(format!("AI config invalid: {}", e)),
+                }
+            });
+
+            // HTTP_SERVER_PORT が設定されていれば監視用の /metrics を公開する
+            if let Some(port) = app.state::<AppConfig>().http_server_port {
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = server::serve(port).await {
+                        eprintln!("[server] failed to start on port {}: {}", port, e);
+                    }
+                });
+            }
+
+            // 12時間ごとに古いエントリを entries_archive へ移すバックグラウンドタスク
             // setup は同期コンテキストなので tokio::spawn ではなく
             // tauri::async_runtime::spawn を使う必要がある
             let handle = app.handle().clone();
@@ -39,13 +142,13 @@ pub fn run() {
                     tokio::time::sleep(interval).await;
                     let result = {
                         let db = handle.state::<DbState>();
-                        let conn = db.conn.lock();
+                        let conn = db.conn();
                         conn.ok()
-                            .and_then(|c| db::repository::delete_old_entries(&c, 3).ok())
+                            .and_then(|c| db::repository::archive_expired_entries(&c).ok())
                     };
                     if let Some(n) = result {
                         if n > 0 {
-                            eprintln!("[cleanup] deleted {} old entries", n);
+                            eprintln!("[archive] archived {} old entries", n);
                         }
                     }
                 }
@@ -53,24 +156,99 @@ pub fn run() {
 
             // macOS: Push-to-Talk（右Optionキー長押し）リスナーを起動
             #[cfg(target_os = "macos")]
-            hotkey::start_listener(app.handle().clone());
+            hotkey::start_listener(
+                app.handle().clone(),
+                hotkey::PttConfig {
+                    max_duration_secs: app.state::<AppConfig>().max_recording_secs,
+                    ..Default::default()
+                },
+            );
+            // macOS: 前面アプリに合わせたモード提案（2秒ごとにポーリング）
+            #[cfg(target_os = "macos")]
+            frontmost_app::start_watcher(app.handle().clone());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            commands::health::ping,
             commands::get_modes,
+            commands::reload_prompt_file,
+            commands::get_config_summary,
+            commands::suggest_mode_for_app,
             commands::audio::transcribe_audio,
+            commands::audio::list_input_devices,
             commands::audio::start_recording,
             commands::audio::stop_recording,
+            commands::audio::cancel_recording,
+            commands::audio::set_auto_stop,
+            commands::audio::stop_recording_and_transcribe,
+            commands::audio::merge_recordings,
+            commands::audio::start_streaming_transcription,
+            commands::audio::stop_streaming_transcription,
+            commands::whisper::list_local_whisper_models,
+            commands::whisper::download_whisper_model,
             commands::ai::process_with_ai,
+            commands::ai::get_queue_depth,
+            commands::ai::get_model_rotation_stats,
+            commands::ai::set_openai_seed,
+            commands::ai::set_context_format,
+            commands::ai::clear_mode_context,
+            commands::ai::clear_all_contexts,
+            commands::ai::validate_anthropic_model,
+            commands::ai::get_ai_model_info,
+            commands::ai::process_with_ai_streaming_persistent,
+            commands::ai::process_with_ai_stream,
+            commands::ai::get_mode_prompt_preview,
+            commands::fs::check_disk_space,
+            commands::fs::get_audio_dir,
             commands::fs::save_audio_file,
+            commands::fs::save_audio_file_verified,
+            commands::fs::save_audio_file_compressed,
+            commands::fs::verify_audio_file,
+            commands::fs::get_audio_file_duration,
+            commands::fs::validate_and_repair_wav,
+            commands::fs::import_audio_file,
             commands::fs::delete_audio_file,
             commands::fs::cleanup_audio_files,
-            commands::check_accessibility_permission,
+            commands::hotkey::check_accessibility_permission,
+            commands::hotkey::is_ptt_available,
+            commands::hotkey::get_ptt_config,
+            commands::hotkey::set_ptt_config,
+            commands::hotkey::set_ptt_key,
+            commands::hotkey::set_ptt_mode,
             commands::db::save_entry,
             commands::db::get_entries,
+            commands::db::get_entries_filtered,
+            commands::db::count_entries,
+            commands::db::count_entries_by_mode,
             commands::db::get_entry,
+            commands::db::get_entry_by_request_id,
+            commands::db::get_similar_entries,
+            commands::db::search_entries,
+            commands::db::get_entry_diff,
+            commands::db::get_entries_with_large_diffs,
+            commands::db::update_entry,
+            commands::db::set_favorite,
             commands::db::delete_entry,
+            commands::db::get_archived_entries,
+            commands::db::export_entries,
+            commands::db::archive_old_entries,
+            commands::db::restore_from_archive,
+            commands::db::get_entries_by_duration,
+            commands::db::get_avg_recording_duration,
+            commands::db::purge_entries_by_mode,
+            commands::db::get_recording_metrics_summary,
+            commands::db::get_entry_count_by_day,
+            commands::db::get_entry_count_by_hour_of_week,
+            commands::db::get_migration_status,
+            commands::db::validate_database_entries,
+            commands::db::repair_database_entry,
             commands::paste::paste_to_foreground,
+            commands::paste::set_paste_method,
+            commands::share::generate_entry_share_url,
+            commands::share::verify_share_token,
+            commands::debug::get_http_debug_log,
+            commands::debug::get_env_files_loaded,
+            commands::debug::benchmark_transcription_pipeline,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,163 @@
+//! PTT の開始・停止時に実行するユーザー定義のシェルコマンド
+//!
+//! 例: 録音中は Slack をミュートする。コマンドは `sh -c` で非同期に実行し、
+//! 完了を待たずに PTT の処理を続ける。
+
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::error::{AppError, TaprResult};
+
+/// フック1回の実行時間の上限（超えたら強制終了する）
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// PTT_ALLOW_ADVANCED_HOOKS が無効のときに拒否する文字列（コマンドの連結・置換・リダイレクト）
+const ADVANCED_SHELL_SYNTAX: &[&str] = &[";", "`", "$(", "&&", "||", "|", ">", "<", "\n"];
+
+/// どのタイミングのフックか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PttHook {
+    Start,
+    Stop,
+}
+
+impl PttHook {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PttHook::Start => "start",
+            PttHook::Stop => "stop",
+        }
+    }
+}
+
+/// フックの実行に失敗したときの `ptt-hook-failed` イベント
+#[derive(Debug, Clone, Serialize)]
+pub struct PttHookFailedEvent {
+    /// "start" / "stop"
+    pub hook: String,
+    pub error: String,
+}
+
+/// フックのコマンド文字列を検証する
+///
+/// `allow_advanced` が false なら `;` やバッククォートなど、
+/// 複数コマンドの連結や置換に使える記法を含むコマンドを拒否する。
+pub fn validate_hook_command(command: &str, allow_advanced: bool) -> TaprResult<()> {
+    if command.trim().is_empty() {
+        return Err(AppError::Config("PTT hook command is empty".to_string()));
+    }
+    if !allow_advanced {
+        if let Some(syntax) = ADVANCED_SHELL_SYNTAX.iter().find(|s| command.contains(*s)) {
+            return Err(AppError::Config(format!(
+                "PTT hook command contains '{}'; set PTT_ALLOW_ADVANCED_HOOKS=1 to use shell syntax",
+                syntax.escape_debug()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// コマンドを `sh -c` で実行し、`timeout` 以内に正常終了したかを返す
+pub async fn run_hook(command: &str, timeout: Duration) -> Result<(), String> {
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(timeout, output)
+        .await
+        .map_err(|_| format!("timed out after {} seconds", timeout.as_secs_f32()))?
+        .map_err(|e| format!("failed to start: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// フックを非同期に起動する（`command` が None なら何もしない）
+///
+/// 失敗時は `ptt-hook-failed` イベントで通知する。
+pub fn spawn_hook<R: Runtime>(app: &AppHandle<R>, hook: PttHook, command: Option<&str>) {
+    let Some(command) = command.map(str::to_string) else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(error) = run_hook(&command, HOOK_TIMEOUT).await {
+            eprintln!("[hotkey] PTT {} hook failed: {}", hook.as_str(), error);
+            let _ = app.emit(
+                "ptt-hook-failed",
+                PttHookFailedEvent {
+                    hook: hook.as_str().to_string(),
+                    error,
+                },
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_hook_command() {
+        assert!(
+            validate_hook_command("osascript -e 'set volume output muted true'", false).is_ok()
+        );
+        assert!(validate_hook_command("  ", false).is_err());
+
+        for command in [
+            "echo a; rm -rf ~",
+            "echo `id`",
+            "echo $(id)",
+            "a && b",
+            "a | b",
+        ] {
+            let err = validate_hook_command(command, false).unwrap_err();
+            assert!(
+                err.to_string().contains("PTT_ALLOW_ADVANCED_HOOKS"),
+                "{}",
+                command
+            );
+            assert!(validate_hook_command(command, true).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_hook_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        run_hook(&format!("touch '{}'", marker.display()), HOOK_TIMEOUT)
+            .await
+            .unwrap();
+        assert!(marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_run_hook_failure() {
+        let err = run_hook("echo boom >&2; exit 3", HOOK_TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(err.contains('3'), "{}", err);
+        assert!(err.contains("boom"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_run_hook_timeout() {
+        let started = std::time::Instant::now();
+        let err = run_hook("sleep 10", Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
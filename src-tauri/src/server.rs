@@ -0,0 +1,64 @@
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use tokio::net::TcpListener;
+
+use crate::ai::metrics::encode_to_string;
+
+/// 監視用 HTTP サーバーのルーティング
+pub fn router() -> Router {
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// `HTTP_SERVER_PORT` で指定したポートの localhost で待ち受ける
+pub async fn serve(port: u16) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    eprintln!("[server] listening on {}", listener.local_addr()?);
+    axum::serve(listener, router()).await
+}
+
+/// Prometheus 形式のメトリクスを返す
+async fn metrics() -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        encode_to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::metrics::record_transcription;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_returns_prometheus_text() {
+        record_transcription("server-test", Some("ja"));
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router()).await });
+
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        // "# HELP" / "# TYPE" 以外の行は `name{labels} value` の形式
+        let samples: Vec<(&str, f64)> = body
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.is_empty())
+            .map(|line| {
+                let (name, value) = line.rsplit_once(' ').unwrap();
+                (name, value.parse().unwrap())
+            })
+            .collect();
+        assert!(samples.contains(&(
+            r#"taponsen_transcriptions_total{backend="server-test",language="ja"}"#,
+            1.0
+        )));
+        assert!(body.contains("# TYPE taponsen_transcriptions_total counter"));
+    }
+}
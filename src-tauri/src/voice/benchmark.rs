@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::voice::format::{self, MONO_CHANNELS, WHISPER_SAMPLE_RATE};
+use crate::voice::pipeline::TranscriptionPipeline;
+use crate::voice::{SpeechRecognizer, TranscriptionResult, VoiceError};
+
+/// ベンチマークに使う合成音声の長さ（秒）
+const BENCHMARK_AUDIO_SECS: u32 = 5;
+const BENCHMARK_SINE_HZ: f32 = 440.0;
+
+/// パイプラインのベンチマーク結果
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineBenchmark {
+    pub avg_pcm_to_wav_us: u64,
+    pub avg_recognizer_call_ms: u64,
+    pub avg_total_ms: u64,
+    pub p99_ms: u64,
+    /// 処理した音声の秒数 / 実時間の秒数
+    pub throughput_secs_per_sec: f64,
+}
+
+/// ネットワークに接続せず、固定の結果をすぐに返す認識器
+pub struct BenchmarkRecognizer;
+
+#[async_trait]
+impl SpeechRecognizer for BenchmarkRecognizer {
+    async fn transcribe(
+        &self,
+        _audio_data: &[u8],
+        language: Option<&str>,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        Ok(TranscriptionResult {
+            text: "benchmark".to_string(),
+            confidence: 1.0,
+            is_final: true,
+            timestamp: 0,
+            language_detected: language.map(str::to_string),
+            used_fallback: false,
+            segments: None,
+        })
+    }
+}
+
+/// 16kHz の 440Hz サイン波
+fn synthetic_pcm(secs: u32) -> Vec<f32> {
+    let samples = (secs * WHISPER_SAMPLE_RATE) as usize;
+    (0..samples)
+        .map(|i| {
+            let t = i as f32 / WHISPER_SAMPLE_RATE as f32;
+            0.5 * (2.0 * std::f32::consts::PI * BENCHMARK_SINE_HZ * t).sin()
+        })
+        .collect()
+}
+
+/// 5秒の合成音声を `n_iterations` 回 `transcribe_all` に通して計測する
+///
+/// WAV 変換と認識器呼び出しは別途単体でも計測し、段階ごとの平均を返す。
+pub async fn run_pipeline_benchmark(n_iterations: u32) -> Result<PipelineBenchmark, VoiceError> {
+    if n_iterations == 0 {
+        return Err(VoiceError::PipelineError(
+            "n_iterations must be at least 1".to_string(),
+        ));
+    }
+    let pcm = synthetic_pcm(BENCHMARK_AUDIO_SECS);
+    let recognizer = BenchmarkRecognizer;
+    let pipeline = TranscriptionPipeline::new(BenchmarkRecognizer, "ja");
+
+    let mut pcm_to_wav = Duration::ZERO;
+    let mut recognizer_calls = Duration::ZERO;
+    let mut totals = Vec::with_capacity(n_iterations as usize);
+
+    for _ in 0..n_iterations {
+        let started = Instant::now();
+        let wav = format::pcm_f32_to_wav(&pcm, WHISPER_SAMPLE_RATE, MONO_CHANNELS)?;
+        pcm_to_wav += started.elapsed();
+
+        let started = Instant::now();
+        recognizer.transcribe(&wav, Some("ja")).await?;
+        recognizer_calls += started.elapsed();
+
+        let started = Instant::now();
+        pipeline.transcribe_all(&pcm).await?;
+        totals.push(started.elapsed());
+    }
+
+    let n = n_iterations;
+    let wall: Duration = totals.iter().sum();
+    totals.sort();
+    let p99_index = ((totals.len() as f64 * 0.99).ceil() as usize).saturating_sub(1);
+    let audio_secs = (BENCHMARK_AUDIO_SECS * n) as f64;
+
+    Ok(PipelineBenchmark {
+        avg_pcm_to_wav_us: (pcm_to_wav / n).as_micros() as u64,
+        avg_recognizer_call_ms: (recognizer_calls / n).as_millis() as u64,
+        avg_total_ms: (wall / n).as_millis() as u64,
+        p99_ms: totals[p99_index].as_millis() as u64,
+        throughput_secs_per_sec: audio_secs / wall.as_secs_f64().max(f64::EPSILON),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_benchmark_returns_measurements() {
+        let first = run_pipeline_benchmark(3).await.unwrap();
+        assert!(first.avg_pcm_to_wav_us > 0);
+        assert!(first.throughput_secs_per_sec > 0.0);
+        assert!(first.p99_ms >= first.avg_total_ms);
+
+        // 同じ処理なので実行ごとのばらつきは 100 倍以内に収まる
+        let second = run_pipeline_benchmark(3).await.unwrap();
+        let (lo, hi) = if first.avg_pcm_to_wav_us < second.avg_pcm_to_wav_us {
+            (first.avg_pcm_to_wav_us, second.avg_pcm_to_wav_us)
+        } else {
+            (second.avg_pcm_to_wav_us, first.avg_pcm_to_wav_us)
+        };
+        assert!(hi <= lo * 100, "{} us vs {} us", lo, hi);
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_rejects_zero_iterations() {
+        assert!(matches!(
+            run_pipeline_benchmark(0).await,
+            Err(VoiceError::PipelineError(_))
+        ));
+    }
+}
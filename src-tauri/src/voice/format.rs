@@ -1,4 +1,5 @@
 use crate::voice::VoiceError;
+use serde::{Deserialize, Serialize};
 use std::io::Cursor;
 
 /// Whisper API が推奨するサンプリングレート
@@ -13,8 +14,11 @@ pub const BITS_PER_SAMPLE: u16 = 16;
 /// Whisper API は WAV ファイル形式での音声入力を受け付けるため、
 /// 生の PCM データにヘッダーを付与して正しい WAV 形式に変換する。
 ///
+/// 複数チャンネルの場合、`pcm_data` はインターリーブ済み（LRLR...）でなければならない。
+/// チャンネルごとの配列を持っている場合は `interleave` か `pcm_f32_stereo_to_wav` を使う。
+///
 /// # Arguments
-/// * `pcm_data` - f32 形式の PCM サンプルデータ（-1.0 〜 1.0）
+/// * `pcm_data` - f32 形式の PCM サンプルデータ（-1.0 〜 1.0、インターリーブ済み）
 /// * `sample_rate` - サンプリングレート（Hz）
 /// * `channels` - チャンネル数（1 = モノラル, 2 = ステレオ）
 pub fn pcm_f32_to_wav(
@@ -48,6 +52,53 @@ pub fn pcm_f32_to_wav(
     Ok(buffer.into_inner())
 }
 
+/// 左右のチャンネルをインターリーブしてステレオ WAV に変換する
+///
+/// 長さが異なる場合は短い方に合わせる。
+pub fn pcm_f32_stereo_to_wav(
+    left: &[f32],
+    right: &[f32],
+    sample_rate: u32,
+) -> Result<Vec<u8>, VoiceError> {
+    let pcm = interleave(&[left.to_vec(), right.to_vec()]);
+    pcm_f32_to_wav(&pcm, sample_rate, 2)
+}
+
+/// インターリーブされた PCM（LRLR...）をチャンネルごとの配列に分ける
+///
+/// 末尾のフレームが `channels` に満たない場合は捨てる。`channels` が 0 なら 1 とみなす。
+pub fn deinterleave(pcm: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    let channels = channels.max(1);
+    let mut out = vec![Vec::with_capacity(pcm.len() / channels); channels];
+    for frame in pcm.chunks_exact(channels) {
+        for (channel, &sample) in out.iter_mut().zip(frame) {
+            channel.push(sample);
+        }
+    }
+    out
+}
+
+/// チャンネルごとの配列をインターリーブする（`deinterleave` の逆）
+///
+/// 長さが異なる場合は最も短いチャンネルに合わせる。
+pub fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+    (0..frames)
+        .flat_map(|i| channels.iter().map(move |channel| channel[i]))
+        .collect()
+}
+
+/// インターリーブされた PCM の各フレームのチャンネルを平均してモノラルにする
+pub fn downmix_to_mono(pcm: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return pcm.to_vec();
+    }
+    let per_channel = deinterleave(pcm, channels);
+    (0..per_channel[0].len())
+        .map(|i| per_channel.iter().map(|channel| channel[i]).sum::<f32>() / channels as f32)
+        .collect()
+}
+
 /// 生バイト列（PCM i16 リトルエンディアン）を WAV に変換する
 ///
 /// フロントエンドから受け取った `Vec<u8>` を直接 WAV に変換するケースで使用。
@@ -88,10 +139,466 @@ pub fn pcm_bytes_to_wav(
     Ok(buffer.into_inner())
 }
 
+/// 生バイト列（PCM i16 リトルエンディアン）を f32 サンプルに変換する
+pub fn pcm_bytes_to_f32(raw_bytes: &[u8]) -> Result<Vec<f32>, VoiceError> {
+    if raw_bytes.len() % 2 != 0 {
+        return Err(VoiceError::FormatError(
+            "PCM byte data length must be even (i16 samples)".to_string(),
+        ));
+    }
+    Ok(raw_bytes
+        .chunks_exact(2)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0)
+        .collect())
+}
+
+/// WAV のバイト列をサンプリングレートとインターリーブ済み f32 サンプルに戻す
+pub fn wav_to_pcm_f32(data: &[u8]) -> Result<(Vec<f32>, u32, u16), VoiceError> {
+    let mut reader = hound::WavReader::new(Cursor::new(data))
+        .map_err(|e| VoiceError::FormatError(format!("Failed to read WAV: {}", e)))?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => reader
+            .samples::<i16>()
+            .map(|s| s.map(|s| s as f32 / 32768.0))
+            .collect::<Result<Vec<_>, _>>(),
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>(),
+    }
+    .map_err(|e| VoiceError::FormatError(format!("Failed to read sample: {}", e)))?;
+    Ok((samples, spec.sample_rate, spec.channels))
+}
+
+/// 保存時の音声フォーマット
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// 無圧縮の 16bit PCM WAV
+    #[default]
+    Wav,
+    /// LAME でエンコードした MP3（WAV の 1/10 程度のサイズ）
+    Mp3,
+}
+
+impl AudioFormat {
+    /// 保存ファイルの拡張子
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Mp3 => "mp3",
+        }
+    }
+}
+
+/// MP3 の既定ビットレート（音声用途には十分）
+pub const DEFAULT_MP3_BITRATE_KBPS: u32 = 64;
+
+/// インターリーブされた PCM f32 を指定のフォーマットにエンコードする
+pub fn encode_pcm_f32(
+    pcm: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    format: AudioFormat,
+) -> Result<Vec<u8>, VoiceError> {
+    match format {
+        AudioFormat::Wav => pcm_f32_to_wav(pcm, sample_rate, channels),
+        AudioFormat::Mp3 => pcm_f32_to_mp3(pcm, sample_rate, channels, DEFAULT_MP3_BITRATE_KBPS),
+    }
+}
+
+/// インターリーブされた PCM f32 を MP3 にエンコードする
+///
+/// libmp3lame（`mp3lame-encoder`）を使う。モノラルとステレオのみ対応し、
+/// ビットレートは LAME が対応する固定値（8〜320kbps）のいずれかを指定する。
+pub fn pcm_f32_to_mp3(
+    pcm: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    bitrate_kbps: u32,
+) -> Result<Vec<u8>, VoiceError> {
+    use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, InterleavedPcm, MonoPcm};
+
+    let err = |what: &str, e: &dyn std::fmt::Display| {
+        VoiceError::FormatError(format!("Failed to {}: {}", what, e))
+    };
+    let bitrate = match bitrate_kbps {
+        8 => Bitrate::Kbps8,
+        16 => Bitrate::Kbps16,
+        24 => Bitrate::Kbps24,
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        other => {
+            return Err(VoiceError::FormatError(format!(
+                "Unsupported MP3 bitrate: {} kbps",
+                other
+            )))
+        }
+    };
+    if !(1..=2).contains(&channels) {
+        return Err(VoiceError::FormatError(format!(
+            "MP3 supports 1 or 2 channels, got {}",
+            channels
+        )));
+    }
+
+    let mut builder = Builder::new()
+        .ok_or_else(|| VoiceError::FormatError("Failed to create LAME encoder".into()))?;
+    builder
+        .set_num_channels(channels as u8)
+        .map_err(|e| err("set MP3 channels", &e))?;
+    builder
+        .set_sample_rate(sample_rate)
+        .map_err(|e| err("set MP3 sample rate", &e))?;
+    builder
+        .set_brate(bitrate)
+        .map_err(|e| err("set MP3 bitrate", &e))?;
+    let mut encoder = builder
+        .build()
+        .map_err(|e| err("initialize LAME encoder", &e))?;
+
+    let samples: Vec<i16> = pcm
+        .iter()
+        .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect();
+    let mut mp3 = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(
+        samples.len() / channels as usize,
+    ));
+    let encoded = if channels == 1 {
+        encoder.encode_to_vec(MonoPcm(&samples), &mut mp3)
+    } else {
+        encoder.encode_to_vec(InterleavedPcm(&samples), &mut mp3)
+    };
+    encoded.map_err(|e| err("encode MP3", &e))?;
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut mp3)
+        .map_err(|e| err("flush MP3", &e))?;
+    Ok(mp3)
+}
+
+/// MP3 をデコードしてインターリーブされた PCM f32 に戻す（minimp3）
+pub fn pcm_from_mp3(mp3_data: &[u8]) -> Result<Vec<f32>, VoiceError> {
+    pcm_from_mp3_with_spec(mp3_data).map(|(pcm, _, _)| pcm)
+}
+
+/// MP3 をデコードし、PCM f32 とサンプルレート・チャンネル数を返す
+///
+/// サンプルレートとチャンネル数は最初のフレームの値を使う。
+pub fn pcm_from_mp3_with_spec(mp3_data: &[u8]) -> Result<(Vec<f32>, u32, u16), VoiceError> {
+    let mut decoder = minimp3::Decoder::new(Cursor::new(mp3_data));
+    let mut pcm = Vec::new();
+    let mut spec = None;
+    loop {
+        match decoder.next_frame() {
+            Ok(frame) => {
+                spec.get_or_insert((frame.sample_rate as u32, frame.channels as u16));
+                pcm.extend(frame.data.iter().map(|&s| s as f32 / 32768.0));
+            }
+            Err(minimp3::Error::Eof) => break,
+            Err(e) => {
+                return Err(VoiceError::FormatError(format!(
+                    "Failed to decode MP3: {}",
+                    e
+                )))
+            }
+        }
+    }
+    let (sample_rate, channels) =
+        spec.ok_or_else(|| VoiceError::FormatError("MP3 contains no frames".into()))?;
+    Ok((pcm, sample_rate, channels))
+}
+
+/// 先頭のバイト列から音声フォーマットを判定する（WAV / MP3 以外は None）
+///
+/// MP3 は ID3v2 タグか、フレーム同期（11bit の 1）で始まるものとみなす。
+pub fn probe_audio_format(data: &[u8]) -> Option<AudioFormat> {
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WAVE" {
+        Some(AudioFormat::Wav)
+    } else if data.starts_with(b"ID3")
+        || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0)
+    {
+        Some(AudioFormat::Mp3)
+    } else {
+        None
+    }
+}
+
+/// 標準的な PCM WAV ヘッダーのバイト数
+pub const WAV_HEADER_LEN: usize = 44;
+
+/// WAV ヘッダーだけを読んで再生時間（ミリ秒）を求める
+///
+/// 44バイトの PCM WAV ヘッダーを前提とし、offset 28 の byte_rate と
+/// offset 40 の data チャンクサイズから計算する。ファイル全体の読み込みは不要。
+pub fn get_wav_duration_ms(data: &[u8]) -> Result<u64, VoiceError> {
+    let invalid = || VoiceError::FormatError("invalid WAV header".into());
+    if data.len() < WAV_HEADER_LEN || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid());
+    }
+
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]) as u64
+    };
+    let byte_rate = read_u32(28);
+    let data_chunk_size = read_u32(40);
+    if byte_rate == 0 {
+        return Err(invalid());
+    }
+
+    Ok(data_chunk_size * 1000 / byte_rate)
+}
+
+/// 録音中のクラッシュなどで壊れた WAV ヘッダーのサイズ欄を直す
+///
+/// 44バイトの PCM WAV ヘッダーを前提に、RIFF チャンクサイズ（offset 4）と
+/// data チャンクサイズ（offset 40）を実際のファイル長から書き直す。
+/// 末尾がフレーム（`block_align` バイト）の途中で切れていれば切り捨てる。
+/// 直した場合は `Ok(true)`、もともと正しければ `Ok(false)` を返す。
+pub fn repair_wav_header(data: &mut Vec<u8>) -> Result<bool, VoiceError> {
+    if data.len() < WAV_HEADER_LEN
+        || &data[0..4] != b"RIFF"
+        || &data[8..12] != b"WAVE"
+        || &data[36..40] != b"data"
+    {
+        return Err(VoiceError::FormatError("invalid WAV header".into()));
+    }
+
+    let block_align = u16::from_le_bytes([data[32], data[33]]) as usize;
+    let mut repaired = false;
+    if block_align > 0 {
+        let partial = (data.len() - WAV_HEADER_LEN) % block_align;
+        if partial > 0 {
+            data.truncate(data.len() - partial);
+            repaired = true;
+        }
+    }
+
+    let riff_size = u32::try_from(data.len() - 8)
+        .map_err(|_| VoiceError::FormatError("WAV file too large".into()))?;
+    let data_size = riff_size - (WAV_HEADER_LEN as u32 - 8);
+    for (offset, size) in [(4, riff_size), (40, data_size)] {
+        let bytes = size.to_le_bytes();
+        if data[offset..offset + 4] != bytes {
+            data[offset..offset + 4].copy_from_slice(&bytes);
+            repaired = true;
+        }
+    }
+    Ok(repaired)
+}
+
+/// フィンガープリントの時間方向の分割数
+const FINGERPRINT_FRAMES: usize = 10;
+/// フィンガープリントの周波数帯域数（フレームあたりのビット数）
+const FINGERPRINT_BANDS: usize = 8;
+/// 帯域中心周波数の下限・上限（Hz、対数間隔で配置）
+const FINGERPRINT_MIN_HZ: f64 = 200.0;
+const FINGERPRINT_MAX_HZ: f64 = 4_000.0;
+
+/// 重複録音の検出に使う 80bit の音声フィンガープリント
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFingerprint(pub [u8; 10]);
+
+impl AudioFingerprint {
+    /// 異なるビットの数を返す
+    pub fn hamming_distance(&self, other: &Self) -> u32 {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum()
+    }
+}
+
+/// モノラル PCM から音声フィンガープリントを計算する
+///
+/// 音声を10フレームに等分し、各フレームで8帯域のエネルギーを Goertzel 法
+/// （単一周波数の DFT）で求める。帯域エネルギーがフレーム内の平均を超えるかを
+/// 1bit に量子化し、10 × 8 = 80bit にまとめる。
+pub fn fingerprint_audio(pcm: &[f32], sample_rate: u32) -> AudioFingerprint {
+    let mut bits = [0u8; 10];
+    if sample_rate == 0 || pcm.len() < FINGERPRINT_FRAMES {
+        return AudioFingerprint(bits);
+    }
+
+    let max_hz = FINGERPRINT_MAX_HZ.min(sample_rate as f64 * 0.45);
+    let centers: Vec<f64> = (0..FINGERPRINT_BANDS)
+        .map(|b| {
+            let t = b as f64 / (FINGERPRINT_BANDS - 1) as f64;
+            FINGERPRINT_MIN_HZ * (max_hz / FINGERPRINT_MIN_HZ).powf(t)
+        })
+        .collect();
+
+    let frame_len = pcm.len() / FINGERPRINT_FRAMES;
+    for (frame_idx, frame) in pcm
+        .chunks_exact(frame_len)
+        .take(FINGERPRINT_FRAMES)
+        .enumerate()
+    {
+        let energies: Vec<f64> = centers
+            .iter()
+            .map(|&hz| goertzel_power(frame, hz, sample_rate))
+            .collect();
+        let mean = energies.iter().sum::<f64>() / FINGERPRINT_BANDS as f64;
+        for (band_idx, &energy) in energies.iter().enumerate() {
+            if energy > mean {
+                let bit = frame_idx * FINGERPRINT_BANDS + band_idx;
+                bits[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+    }
+
+    AudioFingerprint(bits)
+}
+
+/// Goertzel 法で指定周波数のパワーを求める
+fn goertzel_power(samples: &[f32], freq: f64, sample_rate: u32) -> f64 {
+    let coeff = 2.0 * (2.0 * std::f64::consts::PI * freq / sample_rate as f64).cos();
+    let (mut s1, mut s2) = (0.0f64, 0.0f64);
+    for &x in samples {
+        let s0 = x as f64 + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    s1 * s1 + s2 * s2 - coeff * s1 * s2
+}
+
+/// PCM サンプルの二乗平均平方根（空なら 0）
+pub fn rms(pcm: &[f32]) -> f32 {
+    if pcm.is_empty() {
+        return 0.0;
+    }
+    let sum_sq: f64 = pcm.iter().map(|&x| x as f64 * x as f64).sum();
+    (sum_sq / pcm.len() as f64).sqrt() as f32
+}
+
+/// PCM サンプルの絶対値の最大値（空なら 0）
+pub fn peak_amplitude(pcm: &[f32]) -> f32 {
+    pcm.iter().fold(0.0f32, |peak, &s| peak.max(s.abs()))
+}
+
+/// 発話とみなすフレーム RMS の下限
+const VAD_RMS_THRESHOLD: f32 = 0.02;
+/// VAD のフレーム長（ミリ秒）
+const VAD_FRAME_MS: u64 = 30;
+
+/// エネルギーベースの簡易 VAD で、発話と判定したフレームの割合（0.0〜1.0）を返す
+///
+/// 30ms ごとのフレーム RMS が閾値以上なら発話とみなす。インターリーブされた
+/// 複数チャンネルはまとめて1フレームとして扱う。
+pub fn speech_ratio(pcm: &[f32], sample_rate: u32, channels: u16) -> f32 {
+    let frame_len = (sample_rate as u64 * channels as u64 * VAD_FRAME_MS / 1000) as usize;
+    if pcm.is_empty() || frame_len == 0 {
+        return 0.0;
+    }
+    let total = pcm.len().div_ceil(frame_len);
+    let speech = pcm
+        .chunks(frame_len)
+        .filter(|frame| rms(frame) >= VAD_RMS_THRESHOLD)
+        .count();
+    speech as f32 / total as f32
+}
+
+/// スピーカー出力の回り込み（エコー）を取り除く設定
+///
+/// 遅延と減衰を1タップで近似する簡易的な実装で、本格的な AEC
+/// （残響のモデル化やダブルトーク検出）の代わりにはならない。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoCanceller {
+    /// スピーカー出力がマイクに届くまでの遅延（サンプル数）
+    pub delay_samples: usize,
+    /// エコーの減衰係数の初期値（以降はマイク入力に合わせて適応する）
+    pub alpha: f32,
+}
+
+/// NLMS のステップサイズ
+const AEC_STEP_SIZE: f32 = 0.01;
+/// 参照信号の電力を求める窓の長さ（サンプル数）
+const AEC_POWER_WINDOW: usize = 256;
+const AEC_EPSILON: f32 = 1e-6;
+
+/// 正規化 LMS でマイク入力からスピーカー出力のエコーを差し引く
+///
+/// 各サンプルで `mic[i] - w * speaker[i - delay_samples]` を出力し、
+/// 残差と参照信号の電力から係数 `w`（初期値 `alpha`）を更新する。
+/// 窓が埋まるまでは電力が安定しないため係数を更新しない。
+/// `speaker` はマイクと同じサンプルレート・チャンネル配置で、録音開始に揃っている前提。
+pub fn apply_echo_cancellation(
+    mic: &[f32],
+    speaker: &[f32],
+    canceller: &EchoCanceller,
+) -> Vec<f32> {
+    let reference = |i: usize| {
+        i.checked_sub(canceller.delay_samples)
+            .and_then(|j| speaker.get(j))
+            .copied()
+            .unwrap_or(0.0)
+    };
+    let mut weight = canceller.alpha;
+    let mut energy = 0.0f32;
+    mic.iter()
+        .enumerate()
+        .map(|(i, &m)| {
+            let x = reference(i);
+            energy += x * x;
+            if i >= AEC_POWER_WINDOW {
+                let old = reference(i - AEC_POWER_WINDOW);
+                energy = (energy - old * old).max(0.0);
+            }
+            let error = m - weight * x;
+            if i + 1 >= AEC_POWER_WINDOW {
+                let power = energy / AEC_POWER_WINDOW as f32;
+                weight += AEC_STEP_SIZE * error * x / (power + AEC_EPSILON);
+            }
+            error
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rms() {
+        assert_eq!(rms(&[]), 0.0);
+        assert!((rms(&[0.5, -0.5, 0.5, -0.5]) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_peak_amplitude() {
+        assert_eq!(peak_amplitude(&[]), 0.0);
+        assert_eq!(peak_amplitude(&[0.1, -0.8, 0.5]), 0.8);
+    }
+
+    #[test]
+    fn test_speech_ratio() {
+        // 16kHz モノラル: 30ms = 480 サンプル。前半 4 フレームが音声、後半 4 フレームが無音
+        let mut pcm: Vec<f32> = (0..480 * 4)
+            .map(|i| if i % 2 == 0 { 0.3 } else { -0.3 })
+            .collect();
+        pcm.resize(480 * 8, 0.0);
+        assert!((speech_ratio(&pcm, WHISPER_SAMPLE_RATE, MONO_CHANNELS) - 0.5).abs() < 1e-6);
+        assert_eq!(speech_ratio(&[], WHISPER_SAMPLE_RATE, MONO_CHANNELS), 0.0);
+        assert_eq!(
+            speech_ratio(&[0.0; 960], WHISPER_SAMPLE_RATE, MONO_CHANNELS),
+            0.0
+        );
+    }
+
     #[test]
     fn test_pcm_f32_to_wav_produces_valid_wav() {
         // 1秒分の無音データ
@@ -127,4 +634,281 @@ mod tests {
         let result = pcm_bytes_to_wav(&bytes, WHISPER_SAMPLE_RATE, MONO_CHANNELS);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_deinterleave_and_interleave() {
+        let pcm = vec![0.1f32, -0.1, 0.2, -0.2, 0.3, -0.3];
+        let channels = deinterleave(&pcm, 2);
+        assert_eq!(channels, vec![vec![0.1, 0.2, 0.3], vec![-0.1, -0.2, -0.3]]);
+        assert_eq!(interleave(&channels), pcm);
+
+        // 端数のフレームは捨てる
+        assert_eq!(deinterleave(&pcm[..5], 2)[0].len(), 2);
+        // 短いチャンネルに合わせる
+        assert_eq!(interleave(&[vec![1.0, 2.0], vec![3.0]]), vec![1.0, 3.0]);
+        assert!(interleave(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_downmix_to_mono() {
+        let pcm = vec![0.5f32, -0.5, 1.0, 0.0, 0.2, 0.4];
+        let mono = downmix_to_mono(&pcm, 2);
+        assert_eq!(mono.len(), 3);
+        assert!(mono[0].abs() < 1e-6);
+        assert!((mono[1] - 0.5).abs() < 1e-6);
+        assert!((mono[2] - 0.3).abs() < 1e-6);
+        assert_eq!(downmix_to_mono(&pcm, 1), pcm);
+    }
+
+    #[test]
+    fn test_stereo_wav_round_trip() {
+        let left: Vec<f32> = (0..160).map(|i| (i as f32 / 160.0) - 0.5).collect();
+        let right: Vec<f32> = left.iter().map(|s| -s).collect();
+        let wav = pcm_f32_stereo_to_wav(&left, &right, 16_000).unwrap();
+
+        let (pcm, sample_rate, channels) = wav_to_pcm_f32(&wav).unwrap();
+        assert_eq!((sample_rate, channels), (16_000, 2));
+        let decoded = deinterleave(&pcm, channels as usize);
+        for (original, decoded) in [&left, &right].into_iter().zip(&decoded) {
+            assert_eq!(original.len(), decoded.len());
+            assert!(original
+                .iter()
+                .zip(decoded)
+                .all(|(a, b)| (a - b).abs() < 1e-3));
+        }
+    }
+
+    #[test]
+    fn test_wav_to_pcm_f32_roundtrip() {
+        let wav = pcm_f32_to_wav(&[0.0, 0.5, -0.5, 0.25], 24_000, 2).unwrap();
+        let (samples, sample_rate, channels) = wav_to_pcm_f32(&wav).unwrap();
+        assert_eq!((sample_rate, channels), (24_000, 2));
+        assert_eq!(samples.len(), 4);
+        assert!((samples[1] - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_get_wav_duration_ms() {
+        // 16kHz モノラル 1.5 秒
+        let samples = vec![0.0f32; 24_000];
+        let wav = pcm_f32_to_wav(&samples, WHISPER_SAMPLE_RATE, MONO_CHANNELS).unwrap();
+        assert_eq!(get_wav_duration_ms(&wav).unwrap(), 1500);
+
+        // 44.1kHz ステレオ 0.5 秒
+        let samples = vec![0.0f32; 44_100];
+        let wav = pcm_f32_to_wav(&samples, 44_100, 2).unwrap();
+        assert_eq!(get_wav_duration_ms(&wav).unwrap(), 500);
+
+        // ヘッダーだけでも計算できる
+        assert_eq!(get_wav_duration_ms(&wav[..WAV_HEADER_LEN]).unwrap(), 500);
+    }
+
+    #[test]
+    fn test_get_wav_duration_ms_invalid_header() {
+        assert!(get_wav_duration_ms(b"RIFF").is_err());
+
+        let samples = vec![0.0f32; 160];
+        let mut wav = pcm_f32_to_wav(&samples, WHISPER_SAMPLE_RATE, MONO_CHANNELS).unwrap();
+        wav[8..12].copy_from_slice(b"AVI ");
+        assert!(get_wav_duration_ms(&wav).is_err());
+    }
+
+    /// `finalize()` されずにサイズ欄が 0 のままの WAV を再現する
+    fn unfinalized_wav(samples: usize) -> Vec<u8> {
+        let mut wav =
+            pcm_f32_to_wav(&vec![0.0f32; samples], WHISPER_SAMPLE_RATE, MONO_CHANNELS).unwrap();
+        wav[4..8].copy_from_slice(&[0; 4]);
+        wav[40..44].copy_from_slice(&[0; 4]);
+        wav
+    }
+
+    #[test]
+    fn test_repair_wav_header() {
+        let mut wav = unfinalized_wav(16_000);
+        assert_eq!(get_wav_duration_ms(&wav).unwrap(), 0);
+
+        assert!(repair_wav_header(&mut wav).unwrap());
+        assert_eq!(get_wav_duration_ms(&wav).unwrap(), 1000);
+        assert_eq!(wav_to_pcm_f32(&wav).unwrap().0.len(), 16_000);
+
+        // 直した後はそのまま
+        assert!(!repair_wav_header(&mut wav).unwrap());
+    }
+
+    #[test]
+    fn test_repair_wav_header_trims_partial_frame() {
+        let mut wav = unfinalized_wav(1_600);
+        // サンプルの途中で書き込みが止まった
+        wav.push(0x7F);
+        assert!(repair_wav_header(&mut wav).unwrap());
+        assert_eq!(wav.len(), WAV_HEADER_LEN + 3_200);
+        assert_eq!(get_wav_duration_ms(&wav).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_repair_wav_header_rejects_non_wav() {
+        assert!(repair_wav_header(&mut b"RIFF".to_vec()).is_err());
+        let mut data = vec![0u8; 100];
+        assert!(repair_wav_header(&mut data).is_err());
+    }
+
+    /// 2つの正弦波を重ねた1秒分のテスト音声
+    fn two_tones(f1: f32, f2: f32) -> Vec<f32> {
+        let sr = WHISPER_SAMPLE_RATE as f32;
+        (0..WHISPER_SAMPLE_RATE)
+            .map(|i| {
+                let t = i as f32 / sr;
+                0.5 * (2.0 * std::f32::consts::PI * f1 * t).sin()
+                    + 0.3 * (2.0 * std::f32::consts::PI * f2 * t).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fingerprint_identical_audio() {
+        let audio = two_tones(300.0, 1700.0);
+        let a = fingerprint_audio(&audio, WHISPER_SAMPLE_RATE);
+        let b = fingerprint_audio(&audio, WHISPER_SAMPLE_RATE);
+        assert_eq!(a.hamming_distance(&b), 0);
+        assert_ne!(a, AudioFingerprint([0; 10]));
+    }
+
+    #[test]
+    fn test_fingerprint_near_identical_audio() {
+        let audio = two_tones(300.0, 1700.0);
+        let noisy: Vec<f32> = audio
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| s + 0.002 * ((i as f32) * 12.9898).sin())
+            .collect();
+        let a = fingerprint_audio(&audio, WHISPER_SAMPLE_RATE);
+        let b = fingerprint_audio(&noisy, WHISPER_SAMPLE_RATE);
+        assert!(a.hamming_distance(&b) < 5);
+    }
+
+    #[test]
+    fn test_fingerprint_different_audio() {
+        let a = fingerprint_audio(&two_tones(300.0, 1700.0), WHISPER_SAMPLE_RATE);
+        let b = fingerprint_audio(&two_tones(700.0, 2600.0), WHISPER_SAMPLE_RATE);
+        assert!(a.hamming_distance(&b) >= 5);
+    }
+
+    #[test]
+    fn test_pcm_bytes_to_f32() {
+        let bytes = [0x00, 0x40, 0x00, 0xC0];
+        assert_eq!(pcm_bytes_to_f32(&bytes).unwrap(), vec![0.5, -0.5]);
+        assert!(pcm_bytes_to_f32(&[0x00]).is_err());
+    }
+
+    #[test]
+    fn test_mp3_round_trip() {
+        // 44.1kHz モノラルの無音 4 秒
+        let pcm = vec![0.0f32; 44_100 * 4];
+        let mp3 = pcm_f32_to_mp3(&pcm, 44_100, MONO_CHANNELS, 64).unwrap();
+        assert_eq!(&mp3[..2], &[0xFF, 0xFB]);
+        // 64kbps なら 16bit PCM の 1/10 以下になる
+        assert!(mp3.len() * 10 < pcm.len() * 2);
+
+        let decoded = pcm_from_mp3(&mp3).unwrap();
+        let diff = (decoded.len() as f64 - pcm.len() as f64).abs();
+        assert!(
+            diff / (pcm.len() as f64) < 0.05,
+            "decoded {} samples from {}",
+            decoded.len(),
+            pcm.len()
+        );
+        assert!(peak_amplitude(&decoded) < 0.01);
+    }
+
+    #[test]
+    fn test_mp3_with_spec() {
+        let mp3 = pcm_f32_to_mp3(&vec![0.0f32; 22_050], 22_050, MONO_CHANNELS, 64).unwrap();
+        let (pcm, sample_rate, channels) = pcm_from_mp3_with_spec(&mp3).unwrap();
+        assert_eq!(sample_rate, 22_050);
+        assert_eq!(channels, MONO_CHANNELS);
+        assert!(!pcm.is_empty());
+
+        assert!(pcm_from_mp3_with_spec(&[]).is_err());
+    }
+
+    #[test]
+    fn test_probe_audio_format() {
+        let wav = pcm_f32_to_wav(&[0.0; 160], 16_000, 1).unwrap();
+        assert_eq!(probe_audio_format(&wav), Some(AudioFormat::Wav));
+        let mp3 = pcm_f32_to_mp3(&[0.0; 1152], 44_100, 1, 64).unwrap();
+        assert_eq!(probe_audio_format(&mp3), Some(AudioFormat::Mp3));
+        assert_eq!(probe_audio_format(b"ID3\x04\x00"), Some(AudioFormat::Mp3));
+        assert_eq!(probe_audio_format(b"OggS\x00\x02"), None);
+        assert_eq!(probe_audio_format(&[]), None);
+    }
+
+    #[test]
+    fn test_mp3_rejects_unsupported_settings() {
+        assert!(pcm_f32_to_mp3(&[0.0; 1152], 44_100, 1, 100).is_err());
+        assert!(pcm_f32_to_mp3(&[0.0; 1152], 44_100, 6, 64).is_err());
+    }
+
+    /// `signal` に対する `observed` の SNR（dB）
+    fn snr_db(signal: &[f32], observed: &[f32]) -> f64 {
+        let power: f64 = signal.iter().map(|&x| (x as f64).powi(2)).sum();
+        let noise: f64 = signal
+            .iter()
+            .zip(observed)
+            .map(|(&s, &o)| ((o - s) as f64).powi(2))
+            .sum();
+        10.0 * (power / noise).log10()
+    }
+
+    #[test]
+    fn test_echo_cancellation_improves_snr() {
+        let n = 16_000;
+        let delay = 160;
+        // スピーカー出力は発話と無相関な擬似乱数ノイズ
+        let mut seed = 12_345u32;
+        let speaker: Vec<f32> = (0..n)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as f32 / 65_536.0 - 0.5
+            })
+            .collect();
+        let speech: Vec<f32> = (0..n)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16_000.0).sin())
+            .collect();
+        let mic: Vec<f32> = (0..n)
+            .map(|i| {
+                speech[i]
+                    + if i >= delay {
+                        0.6 * speaker[i - delay]
+                    } else {
+                        0.0
+                    }
+            })
+            .collect();
+
+        // 係数 0 から始めても適応して 10dB 以上改善する
+        let canceller = EchoCanceller {
+            delay_samples: delay,
+            alpha: 0.0,
+        };
+        let cleaned = apply_echo_cancellation(&mic, &speaker, &canceller);
+        assert_eq!(cleaned.len(), mic.len());
+        let before = snr_db(&speech, &mic);
+        let after = snr_db(&speech, &cleaned);
+        assert!(
+            after - before > 10.0,
+            "before {:.1}dB, after {:.1}dB",
+            before,
+            after
+        );
+    }
+
+    #[test]
+    fn test_echo_cancellation_without_speaker_is_identity() {
+        let mic = vec![0.1, -0.2, 0.3];
+        let canceller = EchoCanceller {
+            delay_samples: 0,
+            alpha: 0.5,
+        };
+        assert_eq!(apply_echo_cancellation(&mic, &[], &canceller), mic);
+    }
 }
@@ -16,7 +16,8 @@ use crate::voice::{SpeechRecognizer, TranscriptionResult, VoiceError};
 
 /// macOS Speech Framework による音声認識エンジン
 pub struct MacOSSpeechRecognizer {
-    _language: String,
+    /// `transcribe` で言語を指定しなかったときのロケール
+    language: String,
 }
 
 impl MacOSSpeechRecognizer {
@@ -26,7 +27,7 @@ impl MacOSSpeechRecognizer {
     /// * `language` - BCP 47 言語コード (例: "ja-JP", "en-US")
     pub fn new(language: &str) -> Result<Self, VoiceError> {
         Ok(Self {
-            _language: language.to_string(),
+            language: language.to_string(),
         })
     }
 
@@ -72,7 +73,7 @@ impl SpeechRecognizer for MacOSSpeechRecognizer {
     async fn transcribe(
         &self,
         audio_data: &[u8],
-        language: &str,
+        language: Option<&str>,
     ) -> Result<TranscriptionResult, VoiceError> {
         // WAV データを一時ファイルに書き出す
         let temp_path = std::env::temp_dir().join(format!(
@@ -85,9 +86,11 @@ impl SpeechRecognizer for MacOSSpeechRecognizer {
         std::fs::write(&temp_path, audio_data)
             .map_err(|e| VoiceError::NativeError(format!("Failed to write temp file: {}", e)))?;
 
-        let (tx, rx) = tokio::sync::oneshot::channel::<Result<String, VoiceError>>();
+        // 認識結果のテキストと、認識に使ったロケール識別子を返す
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<(String, String), VoiceError>>();
         let temp_path_clone = temp_path.clone();
-        let language = language.to_string();
+        // Speech Framework は言語を判定できないので、作成時のロケールで認識する
+        let language = language.unwrap_or(&self.language).to_string();
 
         // ObjC API はバックグラウンドスレッドで実行
         // コールバックは recognizer の queue（デフォルトでメインキュー）で呼ばれる
@@ -122,6 +125,7 @@ impl SpeechRecognizer for MacOSSpeechRecognizer {
                     )));
                     return;
                 }
+                let locale_id = recognizer.locale().localeIdentifier().to_string();
 
                 // ファイル URL を作成
                 let path_str =
@@ -170,7 +174,7 @@ impl SpeechRecognizer for MacOSSpeechRecognizer {
                 // 最終結果を待機（60秒タイムアウト: SFSpeechRecognitionTask の制限に合わせる）
                 match result_rx.recv_timeout(std::time::Duration::from_secs(60)) {
                     Ok(Ok(text)) => {
-                        let _ = tx.send(Ok(text));
+                        let _ = tx.send(Ok((text, locale_id)));
                     }
                     Ok(Err(e)) => {
                         let _ = tx.send(Err(VoiceError::NativeError(e)));
@@ -185,7 +189,7 @@ impl SpeechRecognizer for MacOSSpeechRecognizer {
         });
 
         // tokio の非同期コンテキストで結果を待機
-        let text = rx
+        let (text, locale_id) = rx
             .await
             .map_err(|_| VoiceError::NativeError("Recognition channel closed".into()))??;
 
@@ -202,6 +206,9 @@ impl SpeechRecognizer for MacOSSpeechRecognizer {
             confidence: 1.0,
             is_final: true,
             timestamp,
+            language_detected: Some(locale_id),
+            used_fallback: false,
+            segments: None,
         })
     }
 }
@@ -1,8 +1,14 @@
+pub mod benchmark;
 pub mod format;
 #[cfg(target_os = "macos")]
 pub mod macos_speech;
+pub mod openai_realtime;
 pub mod pipeline;
+pub mod resample;
 pub mod whisper_api;
+#[cfg(feature = "whisper-cpp")]
+pub mod whisper_cpp;
+pub mod whisper_models;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -15,6 +21,23 @@ pub struct TranscriptionResult {
     pub confidence: f64,
     pub is_final: bool,
     pub timestamp: u64,
+    /// エンジンが判定した言語（Whisper の verbose_json / macOS のロケール）
+    #[serde(default)]
+    pub language_detected: Option<String>,
+    /// 主エンジンが失敗し、フォールバックのエンジンで認識した場合 true
+    #[serde(default)]
+    pub used_fallback: bool,
+    /// 区間ごとのタイミング（エンジンが返さない場合は None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<Segment>>,
+}
+
+/// 文字起こし結果の1区間（音声の先頭からのミリ秒）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
 }
 
 /// 音声認識で発生しうるエラー
@@ -65,10 +88,11 @@ pub trait SpeechRecognizer: Send + Sync {
     ///
     /// # Arguments
     /// * `audio_data` - PCM 形式の音声データ (f32 サンプル、16kHz、モノラル)
-    /// * `language` - 言語コード (例: "ja", "en")
+    /// * `language` - 言語コード (例: "ja", "en")。None ならエンジンに判定させる
+    ///   （判定できないエンジンは既定の言語を使う）
     async fn transcribe(
         &self,
         audio_data: &[u8],
-        language: &str,
+        language: Option<&str>,
     ) -> Result<TranscriptionResult, VoiceError>;
 }
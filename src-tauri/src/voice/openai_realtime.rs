@@ -0,0 +1,462 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::config::app_config::AppConfig;
+use crate::voice::format::wav_to_pcm_f32;
+use crate::voice::resample::to_mono_at;
+use crate::voice::{SpeechRecognizer, TranscriptionResult, VoiceError};
+
+const REALTIME_API_URL: &str = "wss://api.openai.com/v1/realtime?model=gpt-4o-realtime-preview";
+/// 入力音声の文字起こしに使うモデル
+const TRANSCRIPTION_MODEL: &str = "whisper-1";
+/// Realtime API の pcm16 入力のサンプリングレート
+pub const REALTIME_SAMPLE_RATE: u32 = 24_000;
+/// 接続が切れたときに再接続を試みる回数
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+/// 再接続の待機時間（試行回数に比例して延ばす）
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+/// 音声の送信終了後、残りの文字起こし結果を待つ上限
+const FINAL_RESULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+type RealtimeSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// サーバーから受け取るイベントのうち扱うもの
+#[derive(Debug, PartialEq)]
+enum RealtimeEvent {
+    /// 入力バッファが確定し、文字起こし待ちの項目になった
+    Committed,
+    /// 確定した項目の文字起こし結果
+    TranscriptionCompleted(String),
+    /// 確定した項目の文字起こしに失敗した
+    TranscriptionFailed(String),
+    Error(String),
+    Other,
+}
+
+/// WebSocket セッションの終わり方
+enum SessionEnd {
+    /// 音声の送信が終わり、結果もすべて受け取った
+    Finished,
+    /// サーバー側から接続が切れた（再接続する）
+    Disconnected(String),
+}
+
+/// OpenAI Realtime API を使った低遅延の文字起こしクライアント
+///
+/// 24kHz モノラルの pcm16 を `input_audio_buffer.append` で送り、
+/// サーバー側の VAD で区切られた発話ごとに確定結果を受け取る。
+pub struct OpenAIRealtimeClient {
+    api_key: String,
+    url: String,
+}
+
+impl OpenAIRealtimeClient {
+    /// 設定の `OPENAI_API_KEY` で初期化する
+    pub fn from_config(config: &AppConfig) -> Result<Self, VoiceError> {
+        let api_key = config
+            .openai_api_key
+            .clone()
+            .ok_or(VoiceError::MissingApiKey)?;
+        Ok(Self::new(api_key))
+    }
+
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            url: REALTIME_API_URL.to_string(),
+        }
+    }
+
+    /// 接続先を差し替える（モックサーバーでのテスト用）
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// 24kHz モノラルのサンプルを受け取りながら逐次文字起こしする
+    ///
+    /// `audio_rx` が閉じたら残りのバッファを確定させ、結果を送り終えてから戻る。
+    /// 接続が切れた場合は `MAX_RECONNECT_ATTEMPTS` 回まで再接続する
+    /// （切断中に届いた音声とサーバー側の未確定バッファは失われる）。
+    pub async fn transcribe_stream(
+        &self,
+        mut audio_rx: mpsc::Receiver<Vec<f32>>,
+        result_tx: mpsc::Sender<TranscriptionResult>,
+        language: Option<&str>,
+    ) -> Result<(), VoiceError> {
+        let mut attempts = 0;
+        loop {
+            let end = match self.connect(language).await {
+                Ok(socket) => {
+                    attempts = 0;
+                    run_session(socket, &mut audio_rx, &result_tx).await
+                }
+                Err(e) => SessionEnd::Disconnected(e.to_string()),
+            };
+            match end {
+                SessionEnd::Finished => return Ok(()),
+                SessionEnd::Disconnected(reason) => {
+                    attempts += 1;
+                    if attempts > MAX_RECONNECT_ATTEMPTS {
+                        return Err(VoiceError::ApiError(format!(
+                            "Realtime connection lost: {}",
+                            reason
+                        )));
+                    }
+                    eprintln!(
+                        "[realtime] connection lost ({}), reconnecting ({}/{})",
+                        reason, attempts, MAX_RECONNECT_ATTEMPTS
+                    );
+                    tokio::time::sleep(RECONNECT_BACKOFF * attempts).await;
+                }
+            }
+        }
+    }
+
+    /// 接続して `session.update` で文字起こしの設定を送る
+    async fn connect(&self, language: Option<&str>) -> Result<RealtimeSocket, VoiceError> {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| VoiceError::ApiError(format!("Invalid realtime URL: {}", e)))?;
+        let headers = request.headers_mut();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {}", self.api_key))
+                .map_err(|e| VoiceError::ApiError(e.to_string()))?,
+        );
+        headers.insert("OpenAI-Beta", HeaderValue::from_static("realtime=v1"));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| VoiceError::ApiError(format!("WebSocket connect failed: {}", e)))?;
+        socket
+            .send(Message::Text(session_update_event(language).to_string()))
+            .await
+            .map_err(|e| VoiceError::ApiError(format!("Failed to send session.update: {}", e)))?;
+        Ok(socket)
+    }
+}
+
+/// 1つの WebSocket 接続で音声の送信と結果の受信を行う
+async fn run_session(
+    mut socket: RealtimeSocket,
+    audio_rx: &mut mpsc::Receiver<Vec<f32>>,
+    result_tx: &mpsc::Sender<TranscriptionResult>,
+) -> SessionEnd {
+    // 確定済みで結果待ちの項目数
+    let mut pending = 0usize;
+    // 最後の commit に対する応答（committed / error）を待っているか
+    let mut awaiting_commit = false;
+    let mut audio_done = false;
+    let deadline = tokio::time::sleep(Duration::MAX);
+    tokio::pin!(deadline);
+
+    loop {
+        if audio_done && !awaiting_commit && pending == 0 {
+            let _ = socket.close(None).await;
+            return SessionEnd::Finished;
+        }
+
+        tokio::select! {
+            samples = audio_rx.recv(), if !audio_done => {
+                let event = match samples {
+                    Some(samples) => append_event(&samples),
+                    None => {
+                        audio_done = true;
+                        awaiting_commit = true;
+                        deadline
+                            .as_mut()
+                            .reset(tokio::time::Instant::now() + FINAL_RESULT_TIMEOUT);
+                        serde_json::json!({ "type": "input_audio_buffer.commit" })
+                    }
+                };
+                if let Err(e) = socket.send(Message::Text(event.to_string())).await {
+                    return SessionEnd::Disconnected(e.to_string());
+                }
+            }
+            message = socket.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(frame))) => {
+                        return SessionEnd::Disconnected(format!("closed: {:?}", frame));
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return SessionEnd::Disconnected(e.to_string()),
+                    None => return SessionEnd::Disconnected("stream ended".to_string()),
+                };
+                match parse_server_event(&text) {
+                    RealtimeEvent::Committed => {
+                        pending += 1;
+                        awaiting_commit = false;
+                    }
+                    RealtimeEvent::TranscriptionCompleted(transcript) => {
+                        pending = pending.saturating_sub(1);
+                        let _ = result_tx.send(final_result(transcript)).await;
+                    }
+                    RealtimeEvent::TranscriptionFailed(message) => {
+                        pending = pending.saturating_sub(1);
+                        eprintln!("[realtime] transcription failed: {}", message);
+                    }
+                    RealtimeEvent::Error(message) => {
+                        // 空バッファへの commit もエラーになるため、終了待ちを解除するだけにする
+                        awaiting_commit = false;
+                        eprintln!("[realtime] server error: {}", message);
+                    }
+                    RealtimeEvent::Other => {}
+                }
+            }
+            _ = &mut deadline, if audio_done => {
+                eprintln!("[realtime] timed out waiting for {} pending transcription(s)", pending);
+                let _ = socket.close(None).await;
+                return SessionEnd::Finished;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SpeechRecognizer for OpenAIRealtimeClient {
+    /// WAV 形式の音声データを Realtime API で文字起こしする
+    ///
+    /// 24kHz モノラルに変換して一度に送り、発話ごとの結果を連結して返す。
+    async fn transcribe(
+        &self,
+        audio_data: &[u8],
+        language: Option<&str>,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let (samples, sample_rate, channels) = wav_to_pcm_f32(audio_data)?;
+        let pcm = to_mono_at(&samples, sample_rate, channels, REALTIME_SAMPLE_RATE);
+
+        let (audio_tx, audio_rx) = mpsc::channel(1);
+        let (result_tx, mut result_rx) = mpsc::channel(16);
+        audio_tx
+            .send(pcm)
+            .await
+            .map_err(|e| VoiceError::PipelineError(e.to_string()))?;
+        drop(audio_tx);
+
+        let collect = async {
+            let mut texts = Vec::new();
+            while let Some(result) = result_rx.recv().await {
+                texts.push(result.text);
+            }
+            texts
+        };
+        let (stream_result, texts) = tokio::join!(
+            async move { self.transcribe_stream(audio_rx, result_tx, language).await },
+            collect
+        );
+        stream_result?;
+
+        Ok(final_result(texts.join("")))
+    }
+}
+
+/// 入力音声の文字起こしだけを有効にする `session.update`
+///
+/// `language` が None なら送らず、サーバーに判定させる。
+fn session_update_event(language: Option<&str>) -> serde_json::Value {
+    let mut event = serde_json::json!({
+        "type": "session.update",
+        "session": {
+            "modalities": ["text"],
+            "input_audio_format": "pcm16",
+            "input_audio_transcription": {
+                "model": TRANSCRIPTION_MODEL,
+            },
+            "turn_detection": { "type": "server_vad" },
+        }
+    });
+    if let Some(language) = language {
+        event["session"]["input_audio_transcription"]["language"] = language.into();
+    }
+    event
+}
+
+/// f32 サンプルを pcm16 LE の base64 にした `input_audio_buffer.append`
+fn append_event(samples: &[f32]) -> serde_json::Value {
+    let bytes: Vec<u8> = samples
+        .iter()
+        .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    serde_json::json!({
+        "type": "input_audio_buffer.append",
+        "audio": STANDARD.encode(bytes),
+    })
+}
+
+fn parse_server_event(text: &str) -> RealtimeEvent {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else {
+        return RealtimeEvent::Other;
+    };
+    match json["type"].as_str() {
+        Some("input_audio_buffer.committed") => RealtimeEvent::Committed,
+        Some("conversation.item.input_audio_transcription.completed") => {
+            RealtimeEvent::TranscriptionCompleted(
+                json["transcript"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            )
+        }
+        Some("conversation.item.input_audio_transcription.failed") => {
+            RealtimeEvent::TranscriptionFailed(json["error"]["message"].to_string())
+        }
+        Some("error") => RealtimeEvent::Error(json["error"]["message"].to_string()),
+        _ => RealtimeEvent::Other,
+    }
+}
+
+fn final_result(text: String) -> TranscriptionResult {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    TranscriptionResult {
+        text,
+        confidence: 1.0, // Realtime API も信頼度スコアを返さない
+        is_final: true,
+        timestamp,
+        language_detected: None,
+        used_fallback: false,
+        segments: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_update_enables_transcription() {
+        let event = session_update_event(Some("ja"));
+        assert_eq!(event["type"], "session.update");
+        assert_eq!(event["session"]["input_audio_format"], "pcm16");
+        assert_eq!(
+            event["session"]["input_audio_transcription"]["model"],
+            TRANSCRIPTION_MODEL
+        );
+        assert_eq!(
+            event["session"]["input_audio_transcription"]["language"],
+            "ja"
+        );
+
+        // 言語を省略したらサーバーに判定させる
+        let event = session_update_event(None);
+        assert!(event["session"]["input_audio_transcription"]
+            .get("language")
+            .is_none());
+    }
+
+    #[test]
+    fn test_append_event_encodes_pcm16() {
+        let event = append_event(&[0.0, 1.0, -1.0]);
+        assert_eq!(event["type"], "input_audio_buffer.append");
+        let bytes = STANDARD.decode(event["audio"].as_str().unwrap()).unwrap();
+        assert_eq!(bytes, [0, 0, 0xFF, 0x7F, 0x01, 0x80]);
+    }
+
+    #[test]
+    fn test_parse_server_event() {
+        assert_eq!(
+            parse_server_event(
+                r#"{"type":"conversation.item.input_audio_transcription.completed","item_id":"item_1","content_index":0,"transcript":" こんにちは "}"#
+            ),
+            RealtimeEvent::TranscriptionCompleted("こんにちは".to_string())
+        );
+        assert_eq!(
+            parse_server_event(r#"{"type":"input_audio_buffer.committed","item_id":"item_1"}"#),
+            RealtimeEvent::Committed
+        );
+        assert!(matches!(
+            parse_server_event(r#"{"type":"error","error":{"message":"buffer too small"}}"#),
+            RealtimeEvent::Error(_)
+        ));
+        assert_eq!(
+            parse_server_event(r#"{"type":"session.updated"}"#),
+            RealtimeEvent::Other
+        );
+        assert_eq!(parse_server_event("not json"), RealtimeEvent::Other);
+    }
+
+    /// commit を受け取ったら確定と文字起こし結果を返すモックサーバー
+    ///
+    /// `drop_first` なら最初の接続は session.update を受け取った直後に切断する。
+    async fn spawn_mock_server(drop_first: bool) -> String {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                connections += 1;
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                if drop_first && connections == 1 {
+                    let _ = socket.next().await;
+                    let _ = socket.close(None).await;
+                    continue;
+                }
+                while let Some(Ok(Message::Text(text))) = socket.next().await {
+                    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    if json["type"] == "input_audio_buffer.commit" {
+                        for event in [
+                            serde_json::json!({ "type": "input_audio_buffer.committed", "item_id": "item_1" }),
+                            serde_json::json!({
+                                "type": "conversation.item.input_audio_transcription.completed",
+                                "item_id": "item_1",
+                                "transcript": "テストです"
+                            }),
+                        ] {
+                            socket.send(Message::Text(event.to_string())).await.unwrap();
+                        }
+                    }
+                }
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_stream_with_mock_server() {
+        let url = spawn_mock_server(false).await;
+        let client = OpenAIRealtimeClient::new("test".to_string()).with_url(url);
+
+        let (audio_tx, audio_rx) = mpsc::channel(4);
+        let (result_tx, mut result_rx) = mpsc::channel(4);
+        audio_tx.send(vec![0.1; 2400]).await.unwrap();
+        drop(audio_tx);
+
+        client
+            .transcribe_stream(audio_rx, result_tx, Some("ja"))
+            .await
+            .unwrap();
+        let result = result_rx.recv().await.unwrap();
+        assert_eq!(result.text, "テストです");
+        assert!(result.is_final);
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_reconnects_after_close() {
+        let url = spawn_mock_server(true).await;
+        let client = OpenAIRealtimeClient::new("test".to_string()).with_url(url);
+        let wav = crate::voice::format::pcm_f32_to_wav(&[0.1; 1600], 16_000, 1).unwrap();
+
+        let result = client.transcribe(&wav, Some("ja")).await.unwrap();
+        assert_eq!(result.text, "テストです");
+    }
+}
@@ -1,15 +1,28 @@
+use futures::stream::{self, StreamExt};
+use tokio::sync::mpsc;
+
 use crate::voice::format::{self, MONO_CHANNELS, WHISPER_SAMPLE_RATE};
-use crate::voice::{SpeechRecognizer, TranscriptionResult, VoiceError};
+use crate::voice::{Segment, SpeechRecognizer, TranscriptionResult, VoiceError};
 
 /// チャンク分割のデフォルトサイズ（サンプル数）
 /// 16kHz × 5秒 = 80,000 サンプル
 const DEFAULT_CHUNK_SAMPLES: usize = 80_000;
 
+/// `transcribe_chunked_parallel` で同時に認識するチャンク数のデフォルト
+const DEFAULT_CONCURRENCY: usize = 4;
+
 /// 音声データをチャンクに分割して逐次文字起こしするパイプライン
 pub struct TranscriptionPipeline<R: SpeechRecognizer> {
     recognizer: R,
     chunk_samples: usize,
-    language: String,
+    /// 認識エンジンに渡す言語（None ならエンジンが判定する）
+    language: Option<String>,
+    /// この RMS 未満の音声は無音とみなして認識しない（None なら判定しない）
+    silence_threshold: Option<f32>,
+    /// 主エンジンが API / パイプラインエラーで失敗したときに使うエンジン
+    fallback: Option<Box<dyn SpeechRecognizer>>,
+    /// `transcribe_chunked_parallel` で同時に認識するチャンク数
+    concurrency: usize,
 }
 
 impl<R: SpeechRecognizer> TranscriptionPipeline<R> {
@@ -17,16 +30,88 @@ impl<R: SpeechRecognizer> TranscriptionPipeline<R> {
         Self {
             recognizer,
             chunk_samples: DEFAULT_CHUNK_SAMPLES,
-            language: language.to_string(),
+            language: Some(language.to_string()),
+            silence_threshold: None,
+            fallback: None,
+            concurrency: DEFAULT_CONCURRENCY,
         }
     }
 
+    /// 認識する言語を設定する（None ならエンジンが判定する）
+    pub fn with_language(mut self, language: Option<&str>) -> Self {
+        self.language = language.map(str::to_string);
+        self
+    }
+
     /// チャンクサイズをカスタムで設定する（テスト用途など）
     pub fn with_chunk_samples(mut self, samples: usize) -> Self {
         self.chunk_samples = samples;
         self
     }
 
+    /// 無音判定を有効にする（RMS が `rms_threshold` 未満の音声は認識に送らない）
+    pub fn with_silence_detection(mut self, rms_threshold: f32) -> Self {
+        self.silence_threshold = Some(rms_threshold);
+        self
+    }
+
+    /// 主エンジンが失敗したときに試すエンジンを設定する
+    pub fn with_fallback_recognizer(mut self, recognizer: Box<dyn SpeechRecognizer>) -> Self {
+        self.fallback = Some(recognizer);
+        self
+    }
+
+    /// `transcribe_chunked_parallel` で同時に認識するチャンク数を設定する（最小 1）
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// WAV を主エンジンで認識し、API / パイプラインエラーならフォールバックを試す
+    ///
+    /// フォールバックで成功した結果は `used_fallback: true` になる。フォールバックも
+    /// 失敗した場合は、その旨を書き添えた主エンジンのエラーを返す。
+    pub async fn transcribe_with_fallback(
+        &self,
+        wav_data: &[u8],
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let err = match self
+            .recognizer
+            .transcribe(wav_data, self.language.as_deref())
+            .await
+        {
+            Ok(result) => return Ok(result),
+            Err(e) => e,
+        };
+        let Some(fallback) = &self.fallback else {
+            return Err(err);
+        };
+        if !matches!(err, VoiceError::ApiError(_) | VoiceError::PipelineError(_)) {
+            return Err(err);
+        }
+
+        eprintln!(
+            "[audio] primary recognizer failed ({}), trying fallback",
+            err
+        );
+        match fallback
+            .transcribe(wav_data, self.language.as_deref())
+            .await
+        {
+            Ok(mut result) => {
+                eprintln!("[audio] fallback recognizer succeeded");
+                result.used_fallback = true;
+                Ok(result)
+            }
+            Err(fallback_err) => Err(with_fallback_note(err, &fallback_err)),
+        }
+    }
+
+    fn is_silent(&self, pcm_f32: &[f32]) -> bool {
+        self.silence_threshold
+            .is_some_and(|threshold| format::rms(pcm_f32) < threshold)
+    }
+
     /// PCM f32 音声データ全体を一括で文字起こしする
     ///
     /// 短い音声（数秒〜十数秒）向け。チャンク分割せず全体を送信する。
@@ -34,55 +119,110 @@ impl<R: SpeechRecognizer> TranscriptionPipeline<R> {
         &self,
         pcm_f32: &[f32],
     ) -> Result<TranscriptionResult, VoiceError> {
+        if self.is_silent(pcm_f32) {
+            return Ok(TranscriptionResult {
+                text: String::new(),
+                confidence: 1.0,
+                is_final: true,
+                timestamp: 0,
+                language_detected: None,
+                used_fallback: false,
+                segments: None,
+            });
+        }
         let wav_data = format::pcm_f32_to_wav(pcm_f32, WHISPER_SAMPLE_RATE, MONO_CHANNELS)?;
-        self.recognizer
-            .transcribe(&wav_data, &self.language)
-            .await
+        self.transcribe_with_fallback(&wav_data).await
     }
 
     /// PCM f32 音声データをチャンクに分割して逐次文字起こしする
     ///
     /// 長い音声データに対して使用。各チャンクの結果を結合して返す。
     /// コールバックで各チャンクの interim result を受け取れる。
+    /// 無音判定が有効なら無音のチャンクは認識せずに飛ばす。
+    /// 実際に認識した最後のチャンクの interim result が `is_final: true` になる。
+    /// すべて無音なら空の結果を `is_final: true` で1回だけ通知する。
     pub async fn transcribe_chunked(
         &self,
         pcm_f32: &[f32],
         on_interim: Option<&dyn Fn(&TranscriptionResult)>,
     ) -> Result<TranscriptionResult, VoiceError> {
         let chunks: Vec<&[f32]> = pcm_f32.chunks(self.chunk_samples).collect();
-        let total_chunks = chunks.len();
-        let mut full_text = String::new();
-        let mut last_timestamp = 0u64;
+        let last_sent = chunks.iter().rposition(|chunk| !self.is_silent(chunk));
+        let mut merged = ChunkResults::default();
 
         for (i, chunk) in chunks.iter().enumerate() {
-            let is_last = i == total_chunks - 1;
-            let wav_data =
-                format::pcm_f32_to_wav(chunk, WHISPER_SAMPLE_RATE, MONO_CHANNELS)?;
+            if self.is_silent(chunk) {
+                continue;
+            }
+            let result = self.transcribe_chunk(chunk, Some(i) == last_sent).await?;
+            merged.push(i, self.chunk_samples, &result);
 
-            let mut result = self
-                .recognizer
-                .transcribe(&wav_data, &self.language)
-                .await?;
+            if let Some(callback) = on_interim {
+                callback(&result);
+            }
+        }
 
-            result.is_final = is_last;
-            last_timestamp = result.timestamp;
+        let result = merged.finish();
+        if last_sent.is_none() {
+            if let Some(callback) = on_interim {
+                callback(&result);
+            }
+        }
+        Ok(result)
+    }
 
-            if !full_text.is_empty() && !result.text.is_empty() {
-                full_text.push(' ');
+    /// `transcribe_chunked` と同じ結果を、最大 `concurrency` チャンクずつ並列に認識して返す
+    ///
+    /// 結合する順序はチャンクの順のまま。`on_interim` は認識が終わったチャンクから
+    /// 呼ぶので、チャンクの順とは限らない。いずれかのチャンクが失敗したらそのエラーを返す。
+    pub async fn transcribe_chunked_parallel(
+        &self,
+        pcm_f32: &[f32],
+        on_interim: Option<&dyn Fn(&TranscriptionResult)>,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let chunks: Vec<&[f32]> = pcm_f32.chunks(self.chunk_samples).collect();
+        let last_sent = chunks.iter().rposition(|chunk| !self.is_silent(chunk));
+
+        let mut results = stream::iter(
+            chunks
+                .into_iter()
+                .enumerate()
+                .filter(|(_, chunk)| !self.is_silent(chunk)),
+        )
+        .map(|(i, chunk)| async move {
+            let result = self.transcribe_chunk(chunk, Some(i) == last_sent).await?;
+            if let Some(callback) = on_interim {
+                callback(&result);
             }
-            full_text.push_str(&result.text);
+            Ok::<_, VoiceError>((i, result))
+        })
+        .buffered(self.concurrency);
 
+        let mut merged = ChunkResults::default();
+        while let Some(item) = results.next().await {
+            let (i, result) = item?;
+            merged.push(i, self.chunk_samples, &result);
+        }
+
+        let result = merged.finish();
+        if last_sent.is_none() {
             if let Some(callback) = on_interim {
                 callback(&result);
             }
         }
+        Ok(result)
+    }
 
-        Ok(TranscriptionResult {
-            text: full_text,
-            confidence: 1.0,
-            is_final: true,
-            timestamp: last_timestamp,
-        })
+    /// 1チャンクを WAV にして認識する
+    async fn transcribe_chunk(
+        &self,
+        chunk: &[f32],
+        is_last: bool,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let wav_data = format::pcm_f32_to_wav(chunk, WHISPER_SAMPLE_RATE, MONO_CHANNELS)?;
+        let mut result = self.transcribe_with_fallback(&wav_data).await?;
+        result.is_final = is_last;
+        Ok(result)
     }
 
     /// 生バイト列（PCM i16 LE）を直接文字起こしする
@@ -94,8 +234,493 @@ impl<R: SpeechRecognizer> TranscriptionPipeline<R> {
     ) -> Result<TranscriptionResult, VoiceError> {
         let wav_data =
             format::pcm_bytes_to_wav(raw_bytes, WHISPER_SAMPLE_RATE, MONO_CHANNELS)?;
-        self.recognizer
-            .transcribe(&wav_data, &self.language)
+        self.transcribe_with_fallback(&wav_data).await
+    }
+
+    /// ライブ入力のサンプル列を一定時間ごとに区切って逐次文字起こしする
+    ///
+    /// `rx` から 16kHz モノラルの PCM f32 を受け取り、`chunk_duration_secs` 秒分
+    /// 溜まるごとに WAV 化して認識し、結果を `tx` に送る。`rx` が閉じたら
+    /// 残りのサンプルを最終チャンク（`is_final: true`）として処理して終了する。
+    pub async fn transcribe_stream(
+        &self,
+        mut rx: mpsc::Receiver<Vec<f32>>,
+        chunk_duration_secs: f32,
+        tx: mpsc::Sender<TranscriptionResult>,
+    ) -> Result<(), VoiceError> {
+        let chunk_len = ((chunk_duration_secs * WHISPER_SAMPLE_RATE as f32) as usize).max(1);
+        let mut pending: Vec<f32> = Vec::with_capacity(chunk_len);
+
+        while let Some(samples) = rx.recv().await {
+            pending.extend_from_slice(&samples);
+
+            while pending.len() >= chunk_len {
+                let chunk: Vec<f32> = pending.drain(..chunk_len).collect();
+                let mut result = self.transcribe_all(&chunk).await?;
+                result.is_final = false;
+                if tx.send(result).await.is_err() {
+                    // 受信側が閉じたら以降の処理は不要
+                    return Ok(());
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            let mut result = self.transcribe_all(&pending).await?;
+            result.is_final = true;
+            let _ = tx.send(result).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// 主エンジンのエラーにフォールバックも失敗したことを書き添える
+fn with_fallback_note(err: VoiceError, fallback_err: &VoiceError) -> VoiceError {
+    let note = |msg: String| format!("{} (fallback attempted: {})", msg, fallback_err);
+    match err {
+        VoiceError::ApiError(msg) => VoiceError::ApiError(note(msg)),
+        VoiceError::PipelineError(msg) => VoiceError::PipelineError(note(msg)),
+        other => other,
+    }
+}
+
+/// チャンクごとの認識結果をチャンクの順に結合する
+#[derive(Default)]
+struct ChunkResults {
+    text: String,
+    last_timestamp: u64,
+    language_detected: Option<String>,
+    used_fallback: bool,
+    segments: Option<Vec<Segment>>,
+}
+
+impl ChunkResults {
+    /// `index` 番目のチャンク（1チャンク `chunk_samples` サンプル）の結果を追加する
+    fn push(&mut self, index: usize, chunk_samples: usize, result: &TranscriptionResult) {
+        self.used_fallback |= result.used_fallback;
+        self.last_timestamp = result.timestamp;
+        if self.language_detected.is_none() {
+            self.language_detected = result.language_detected.clone();
+        }
+
+        if !self.text.is_empty() && !result.text.is_empty() {
+            self.text.push(' ');
+        }
+        self.text.push_str(&result.text);
+
+        // 区間の時刻はチャンク内の位置なので、チャンクの開始位置だけずらす
+        if let Some(chunk_segments) = &result.segments {
+            let offset_ms = (index * chunk_samples) as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+            self.segments
+                .get_or_insert_with(Vec::new)
+                .extend(chunk_segments.iter().map(|s| Segment {
+                    start_ms: s.start_ms + offset_ms,
+                    end_ms: s.end_ms + offset_ms,
+                    text: s.text.clone(),
+                }));
+        }
+    }
+
+    fn finish(self) -> TranscriptionResult {
+        TranscriptionResult {
+            text: self.text,
+            confidence: 1.0,
+            is_final: true,
+            timestamp: self.last_timestamp,
+            language_detected: self.language_detected,
+            used_fallback: self.used_fallback,
+            segments: self.segments,
+        }
+    }
+}
+
+/// 認識エンジンを順に試し、最初に成功した結果を返す
+///
+/// 先頭以外のエンジンで成功した場合は `used_fallback: true` になる。
+/// すべて失敗した場合は各エンジンのエラーをまとめた PipelineError を返す。
+pub async fn try_all_recognizers(
+    recognizers: Vec<Box<dyn SpeechRecognizer>>,
+    audio: &[u8],
+    language: Option<&str>,
+) -> Result<TranscriptionResult, VoiceError> {
+    let mut errors = Vec::new();
+    for (i, recognizer) in recognizers.iter().enumerate() {
+        match recognizer.transcribe(audio, language).await {
+            Ok(mut result) => {
+                eprintln!("[audio] recognizer #{} succeeded", i);
+                result.used_fallback = i > 0;
+                return Ok(result);
+            }
+            Err(e) => errors.push(format!("#{}: {}", i, e)),
+        }
+    }
+    if errors.is_empty() {
+        return Err(VoiceError::PipelineError(
+            "no recognizers configured".to_string(),
+        ));
+    }
+    Err(VoiceError::PipelineError(format!(
+        "all recognizers failed: {}",
+        errors.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// 受け取った WAV のバイト数をテキストとして返すモック
+    struct EchoRecognizer;
+
+    #[async_trait]
+    impl SpeechRecognizer for EchoRecognizer {
+        async fn transcribe(
+            &self,
+            audio_data: &[u8],
+            _language: Option<&str>,
+        ) -> Result<TranscriptionResult, VoiceError> {
+            Ok(TranscriptionResult {
+                text: audio_data.len().to_string(),
+                confidence: 1.0,
+                is_final: true,
+                timestamp: 0,
+                language_detected: Some("ja".to_string()),
+                used_fallback: false,
+                segments: None,
+            })
+        }
+    }
+
+    /// 受け取った言語を記録するモック
+    #[derive(Default)]
+    struct LanguageRecognizer {
+        languages: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    #[async_trait]
+    impl SpeechRecognizer for LanguageRecognizer {
+        async fn transcribe(
+            &self,
+            _audio_data: &[u8],
+            language: Option<&str>,
+        ) -> Result<TranscriptionResult, VoiceError> {
+            self.languages
+                .lock()
+                .unwrap()
+                .push(language.map(str::to_string));
+            EchoRecognizer.transcribe(&[], language).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_language_passes_language_to_recognizer() {
+        let pipeline = TranscriptionPipeline::new(LanguageRecognizer::default(), "ja");
+        pipeline.transcribe_with_fallback(&[]).await.unwrap();
+        let pipeline = pipeline.with_language(None);
+        pipeline.transcribe_with_fallback(&[]).await.unwrap();
+        let pipeline = pipeline.with_language(Some("en"));
+        pipeline.transcribe_with_fallback(&[]).await.unwrap();
+
+        assert_eq!(
+            *pipeline.recognizer.languages.lock().unwrap(),
+            vec![Some("ja".to_string()), None, Some("en".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_stream_chunks_and_flushes() {
+        let pipeline = TranscriptionPipeline::new(EchoRecognizer, "ja");
+        let (sample_tx, sample_rx) = mpsc::channel(8);
+        let (result_tx, mut result_rx) = mpsc::channel(8);
+
+        // 0.5秒チャンク × 2 + 端数 0.25 秒
+        for _ in 0..5 {
+            sample_tx.send(vec![0.0f32; 4_000]).await.unwrap();
+        }
+        drop(sample_tx);
+
+        pipeline
+            .transcribe_stream(sample_rx, 0.5, result_tx)
+            .await
+            .unwrap();
+
+        let mut results = Vec::new();
+        while let Some(r) = result_rx.recv().await {
+            results.push(r);
+        }
+        assert_eq!(results.len(), 3);
+        assert!(!results[0].is_final);
+        assert!(!results[1].is_final);
+        assert!(results[2].is_final);
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_chunked_keeps_detected_language() {
+        let pipeline = TranscriptionPipeline::new(EchoRecognizer, "ja");
+        let result = pipeline
+            .transcribe_chunked(&[0.0f32; 8_000], None)
+            .await
+            .unwrap();
+        assert_eq!(result.language_detected.as_deref(), Some("ja"));
+    }
+
+    /// チャンク内の 100〜400ms を1区間として返すモック
+    struct SegmentRecognizer;
+
+    #[async_trait]
+    impl SpeechRecognizer for SegmentRecognizer {
+        async fn transcribe(
+            &self,
+            _audio_data: &[u8],
+            _language: Option<&str>,
+        ) -> Result<TranscriptionResult, VoiceError> {
+            Ok(TranscriptionResult {
+                text: "はい".to_string(),
+                confidence: 1.0,
+                is_final: true,
+                timestamp: 0,
+                language_detected: None,
+                used_fallback: false,
+                segments: Some(vec![Segment {
+                    start_ms: 100,
+                    end_ms: 400,
+                    text: "はい".to_string(),
+                }]),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_chunked_offsets_segments() {
+        // 0.5秒チャンク × 2
+        let pipeline =
+            TranscriptionPipeline::new(SegmentRecognizer, "ja").with_chunk_samples(8_000);
+        let result = pipeline
+            .transcribe_chunked(&[0.1f32; 16_000], None)
+            .await
+            .unwrap();
+        let segments = result.segments.unwrap();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|s| (s.start_ms, s.end_ms))
+                .collect::<Vec<_>>(),
+            vec![(100, 400), (600, 900)]
+        );
+
+        let result = TranscriptionPipeline::new(EchoRecognizer, "ja")
+            .transcribe_chunked(&[0.1f32; 16_000], None)
+            .await
+            .unwrap();
+        assert!(result.segments.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_chunked_marks_last_sent_chunk_final() {
+        // 音声 0.1 秒 + 無音 0.1 秒。最後のチャンクは送らないので、その前が最終になる
+        let mut pcm = vec![0.1f32; 1_600];
+        pcm.extend(vec![0.0f32; 1_600]);
+        let pipeline = TranscriptionPipeline::new(EchoRecognizer, "ja")
+            .with_chunk_samples(1_600)
+            .with_silence_detection(0.01);
+
+        for parallel in [false, true] {
+            let finals = std::cell::RefCell::new(Vec::new());
+            let on_interim = |r: &TranscriptionResult| finals.borrow_mut().push(r.is_final);
+            if parallel {
+                pipeline
+                    .transcribe_chunked_parallel(&pcm, Some(&on_interim))
+                    .await
+                    .unwrap();
+            } else {
+                pipeline
+                    .transcribe_chunked(&pcm, Some(&on_interim))
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(*finals.borrow(), vec![true]);
+        }
+
+        // すべて無音なら空の最終結果を1回通知する
+        let interim = std::cell::RefCell::new(Vec::new());
+        let on_interim = |r: &TranscriptionResult| interim.borrow_mut().push(r.clone());
+        let result = pipeline
+            .transcribe_chunked(&[0.0f32; 3_200], Some(&on_interim))
+            .await
+            .unwrap();
+        assert!(result.text.is_empty());
+        let interim = interim.into_inner();
+        assert_eq!(interim.len(), 1);
+        assert!(interim[0].is_final);
+        assert!(interim[0].text.is_empty());
+    }
+
+    /// WAV の先頭サンプルの値をテキストとして返し、値が小さいほど長く待つモック
+    struct SlowRecognizer;
+
+    #[async_trait]
+    impl SpeechRecognizer for SlowRecognizer {
+        async fn transcribe(
+            &self,
+            audio_data: &[u8],
+            _language: Option<&str>,
+        ) -> Result<TranscriptionResult, VoiceError> {
+            let (pcm, _, _) = format::wav_to_pcm_f32(audio_data)?;
+            let value = pcm[0];
+            tokio::time::sleep(std::time::Duration::from_secs_f32(1.0 - value)).await;
+            Ok(TranscriptionResult {
+                text: format!("{:.1}", value),
+                confidence: 1.0,
+                is_final: true,
+                timestamp: 0,
+                language_detected: None,
+                used_fallback: false,
+                segments: None,
+            })
+        }
+    }
+
+    /// 0.1, 0.2, 0.3, 0.4 で埋めた 0.1 秒のチャンク × 4
+    fn stepped_chunks() -> Vec<f32> {
+        [0.1f32, 0.2, 0.3, 0.4]
+            .iter()
+            .flat_map(|&v| vec![v; 1_600])
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transcribe_chunked_parallel_keeps_order() {
+        let pcm = stepped_chunks();
+        let pipeline = TranscriptionPipeline::new(SlowRecognizer, "ja")
+            .with_chunk_samples(1_600)
+            .with_concurrency(4);
+
+        let interim = std::cell::RefCell::new(Vec::new());
+        let on_interim = |r: &TranscriptionResult| interim.borrow_mut().push(r.text.clone());
+        let started = tokio::time::Instant::now();
+        let result = pipeline
+            .transcribe_chunked_parallel(&pcm, Some(&on_interim))
+            .await
+            .unwrap();
+        let parallel_elapsed = started.elapsed();
+
+        assert_eq!(result.text, "0.1 0.2 0.3 0.4");
+        // 先頭のチャンクほど遅いので、途中結果は逆順に届く
+        assert_eq!(*interim.borrow(), vec!["0.4", "0.3", "0.2", "0.1"]);
+
+        let started = tokio::time::Instant::now();
+        let sequential = pipeline.transcribe_chunked(&pcm, None).await.unwrap();
+        let sequential_elapsed = started.elapsed();
+        assert_eq!(sequential.text, result.text);
+
+        // 逐次は 0.9 + 0.8 + 0.7 + 0.6 秒、並列は最も遅いチャンクの 0.9 秒
+        assert!(sequential_elapsed >= std::time::Duration::from_millis(2_900));
+        assert!(parallel_elapsed < std::time::Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transcribe_chunked_parallel_limits_concurrency() {
+        let pipeline = TranscriptionPipeline::new(SlowRecognizer, "ja")
+            .with_chunk_samples(1_600)
+            .with_concurrency(2);
+        let started = tokio::time::Instant::now();
+        let result = pipeline
+            .transcribe_chunked_parallel(&stepped_chunks(), None)
+            .await
+            .unwrap();
+        assert_eq!(result.text, "0.1 0.2 0.3 0.4");
+        // 先頭の2チャンク（0.9 秒）が揃ってから残りの2チャンク（0.7 秒）を認識する
+        assert!(started.elapsed() >= std::time::Duration::from_millis(1_500));
+    }
+
+    /// 常に指定のエラーを返すモック
+    struct FailingRecognizer(fn() -> VoiceError);
+
+    #[async_trait]
+    impl SpeechRecognizer for FailingRecognizer {
+        async fn transcribe(
+            &self,
+            _audio_data: &[u8],
+            _language: Option<&str>,
+        ) -> Result<TranscriptionResult, VoiceError> {
+            Err((self.0)())
+        }
+    }
+
+    fn api_error() -> VoiceError {
+        VoiceError::ApiError("quota exceeded".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_fallback_used_when_primary_fails() {
+        let pipeline = TranscriptionPipeline::new(FailingRecognizer(api_error), "ja")
+            .with_fallback_recognizer(Box::new(EchoRecognizer));
+        let result = pipeline.transcribe_all(&[0.1f32; 1_600]).await.unwrap();
+        assert!(result.used_fallback);
+        assert!(!result.text.is_empty());
+
+        let result = pipeline
+            .transcribe_chunked(&[0.1f32; 3_200], None)
+            .await
+            .unwrap();
+        assert!(result.used_fallback);
+    }
+
+    #[tokio::test]
+    async fn test_primary_success_skips_fallback() {
+        let pipeline = TranscriptionPipeline::new(EchoRecognizer, "ja")
+            .with_fallback_recognizer(Box::new(FailingRecognizer(api_error)));
+        let result = pipeline.transcribe_all(&[0.1f32; 1_600]).await.unwrap();
+        assert!(!result.used_fallback);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_failure_returns_original_error() {
+        let pipeline = TranscriptionPipeline::new(FailingRecognizer(api_error), "ja")
+            .with_fallback_recognizer(Box::new(FailingRecognizer(|| {
+                VoiceError::NativeError("unavailable".to_string())
+            })));
+        let err = pipeline.transcribe_all(&[0.1f32; 1_600]).await.unwrap_err();
+        assert!(matches!(err, VoiceError::ApiError(_)));
+        assert_eq!(
+            err.to_string(),
+            "API error: quota exceeded (fallback attempted: Native speech error: unavailable)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_not_used_for_other_errors() {
+        let pipeline =
+            TranscriptionPipeline::new(FailingRecognizer(|| VoiceError::MissingApiKey), "ja")
+                .with_fallback_recognizer(Box::new(EchoRecognizer));
+        let err = pipeline.transcribe_all(&[0.1f32; 1_600]).await.unwrap_err();
+        assert!(matches!(err, VoiceError::MissingApiKey));
+    }
+
+    #[tokio::test]
+    async fn test_try_all_recognizers() {
+        let recognizers: Vec<Box<dyn SpeechRecognizer>> = vec![
+            Box::new(FailingRecognizer(api_error)),
+            Box::new(EchoRecognizer),
+        ];
+        let result = try_all_recognizers(recognizers, b"wav", Some("ja"))
+            .await
+            .unwrap();
+        assert_eq!(result.text, "3");
+        assert!(result.used_fallback);
+
+        let recognizers: Vec<Box<dyn SpeechRecognizer>> = vec![
+            Box::new(FailingRecognizer(api_error)),
+            Box::new(FailingRecognizer(api_error)),
+        ];
+        let err = try_all_recognizers(recognizers, b"wav", Some("ja"))
             .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Pipeline error: all recognizers failed: #0: API error: quota exceeded; #1: API error: quota exceeded"
+        );
     }
 }
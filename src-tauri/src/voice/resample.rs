@@ -0,0 +1,248 @@
+//! 録音デバイスの設定に依存しない 16kHz モノラルへの変換
+//!
+//! マイクは 44.1kHz / 48kHz やステレオで開かれることが多いが、
+//! Whisper は 16kHz モノラルを前提にしているため、送信前にここで揃える。
+
+use crate::voice::format::{
+    downmix_to_mono, interleave, pcm_bytes_to_f32, pcm_bytes_to_wav, pcm_f32_to_wav, MONO_CHANNELS,
+    WHISPER_SAMPLE_RATE,
+};
+use crate::voice::VoiceError;
+
+/// インターリーブされた PCM f32 を Whisper 向けの 16kHz モノラルに変換する
+pub fn to_whisper_mono(pcm_data: &[f32], sample_rate: u32, channels: u16) -> Vec<f32> {
+    to_mono_at(pcm_data, sample_rate, channels, WHISPER_SAMPLE_RATE)
+}
+
+/// インターリーブされた PCM f32 を指定サンプリングレートのモノラルに変換する
+///
+/// `downmix_to_mono` で各フレームのチャンネルを平均し、線形補間でリサンプリングする。
+pub fn to_mono_at(pcm_data: &[f32], sample_rate: u32, channels: u16, target_rate: u32) -> Vec<f32> {
+    let mono = downmix_to_mono(pcm_data, channels as usize);
+
+    if sample_rate == 0 || target_rate == 0 || sample_rate == target_rate || mono.is_empty() {
+        return mono;
+    }
+
+    let ratio = sample_rate as f64 / target_rate as f64;
+    let out_len = (mono.len() as f64 / ratio) as usize;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = mono[idx];
+            let b = mono.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// インターリーブされた PCM f32 を別のサンプリングレート・チャンネル数に変換する
+///
+/// 録音中にデバイスのフォーマットが変わったとき、それまでのバッファを新しい形式に揃えるのに使う。
+/// チャンネル数が変わる場合はいったんモノラルにしてから各チャンネルへ複製する。
+pub fn convert_interleaved(
+    pcm_data: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    target_rate: u32,
+    target_channels: u16,
+) -> Vec<f32> {
+    if sample_rate == target_rate && channels == target_channels {
+        return pcm_data.to_vec();
+    }
+    let mono = to_mono_at(pcm_data, sample_rate, channels, target_rate);
+    if target_channels <= MONO_CHANNELS {
+        return mono;
+    }
+    interleave(&vec![mono; target_channels as usize])
+}
+
+/// 録音コールバックのバッファを順に受け取り、1本の音声としてモノラルにリサンプリングする
+///
+/// バッファごとに `to_mono_at` を呼ぶと、読み出し位置の端数と次のバッファ先頭との補間が
+/// 境界で失われ、クリックやずれが出る。前のバッファの最後のサンプルと読み出し位置を持ち越す。
+pub struct StreamResampler {
+    channels: u16,
+    /// 出力1サンプルあたりの入力サンプル数（None ならリサンプリングしない）
+    ratio: Option<f64>,
+    /// 次に出力する位置（`last` を 0 とした入力サンプル単位）
+    pos: f64,
+    /// 前のバッファの最後のサンプル（補間の左側に使う）
+    last: Option<f32>,
+}
+
+impl StreamResampler {
+    pub fn new(sample_rate: u32, channels: u16, target_rate: u32) -> Self {
+        let ratio = (sample_rate != 0 && target_rate != 0 && sample_rate != target_rate)
+            .then(|| sample_rate as f64 / target_rate as f64);
+        Self {
+            channels,
+            ratio,
+            pos: 0.0,
+            last: None,
+        }
+    }
+
+    /// インターリーブされた PCM f32 の続きを変換する
+    ///
+    /// 右側の補間相手が届いていない位置は次の呼び出しで出力する。
+    pub fn process(&mut self, pcm_data: &[f32]) -> Vec<f32> {
+        let mono = downmix_to_mono(pcm_data, self.channels as usize);
+        let Some(ratio) = self.ratio else {
+            return mono;
+        };
+        if mono.is_empty() {
+            return mono;
+        }
+
+        // 前のバッファの最後のサンプルを先頭に置いた入力として扱う
+        let input: Vec<f32> = self.last.into_iter().chain(mono).collect();
+
+        let mut out = Vec::with_capacity((input.len() as f64 / ratio) as usize + 1);
+        while (self.pos as usize) + 1 < input.len() {
+            let idx = self.pos as usize;
+            let frac = (self.pos - idx as f64) as f32;
+            let a = input[idx];
+            let b = input[idx + 1];
+            out.push(a + (b - a) * frac);
+            self.pos += ratio;
+        }
+
+        self.pos -= (input.len() - 1) as f64;
+        self.last = input.last().copied();
+        out
+    }
+}
+
+/// 生バイト列（PCM i16 リトルエンディアン）を 16kHz モノラルの WAV に変換する
+///
+/// すでに 16kHz モノラルの場合は再量子化せず、そのまま WAV にする。
+pub fn pcm_bytes_to_whisper_wav(
+    raw_bytes: &[u8],
+    sample_rate: u32,
+    channels: u16,
+) -> Result<Vec<u8>, VoiceError> {
+    if sample_rate == WHISPER_SAMPLE_RATE && channels == MONO_CHANNELS {
+        return pcm_bytes_to_wav(raw_bytes, sample_rate, channels);
+    }
+    let pcm = pcm_bytes_to_f32(raw_bytes)?;
+    pcm_f32_to_wav(
+        &to_whisper_mono(&pcm, sample_rate, channels),
+        WHISPER_SAMPLE_RATE,
+        MONO_CHANNELS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::format::wav_to_pcm_f32;
+
+    #[test]
+    fn test_to_whisper_mono_passthrough() {
+        let samples = vec![0.1f32, 0.2, 0.3];
+        let result = to_whisper_mono(&samples, WHISPER_SAMPLE_RATE, MONO_CHANNELS);
+        assert_eq!(result, samples);
+    }
+
+    #[test]
+    fn test_to_whisper_mono_downmix_and_resample() {
+        // 48kHz ステレオ 480 フレーム（10ms）→ 16kHz モノラル 160 サンプル
+        let samples: Vec<f32> = (0..480).flat_map(|_| [0.5f32, -0.5]).collect();
+        let result = to_whisper_mono(&samples, 48_000, 2);
+        assert_eq!(result.len(), 160);
+        assert!(result.iter().all(|&s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_to_mono_at_interpolates() {
+        // 8kHz → 16kHz では間のサンプルが線形補間される
+        let result = to_mono_at(&[0.0, 0.5], 8_000, MONO_CHANNELS, 16_000);
+        assert_eq!(result, vec![0.0, 0.25, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_stream_resampler_matches_whole_buffer() {
+        // 48kHz ステレオの正弦波を半端な長さのバッファに分けて送っても、一括変換と同じになる
+        let samples: Vec<f32> = (0..4_801)
+            .map(|i| (i as f32 * 0.05).sin())
+            .flat_map(|s| [s, s])
+            .collect();
+        let whole = to_mono_at(&samples, 48_000, 2, 16_000);
+
+        let mut resampler = StreamResampler::new(48_000, 2, 16_000);
+        let streamed: Vec<f32> = samples
+            .chunks(2 * 487)
+            .flat_map(|buffer| resampler.process(buffer))
+            .collect();
+
+        assert_eq!(streamed.len(), whole.len());
+        for (a, b) in streamed.iter().zip(&whole) {
+            assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_stream_resampler_interpolates_across_buffers() {
+        // 8kHz → 16kHz で、バッファの境界にも補間したサンプルが入る
+        let mut resampler = StreamResampler::new(8_000, MONO_CHANNELS, 16_000);
+        assert_eq!(resampler.process(&[0.0, 0.5]), vec![0.0, 0.25]);
+        assert_eq!(resampler.process(&[1.0]), vec![0.5, 0.75]);
+    }
+
+    #[test]
+    fn test_convert_interleaved_changes_rate_and_channels() {
+        // 48kHz ステレオ 480 フレーム → 16kHz ステレオ 160 フレーム
+        let samples: Vec<f32> = (0..480).flat_map(|_| [0.2f32, 0.4]).collect();
+        let result = convert_interleaved(&samples, 48_000, 2, 16_000, 2);
+        assert_eq!(result.len(), 320);
+        assert!(result.iter().all(|&s| (s - 0.3).abs() < 1e-6));
+
+        // 16kHz モノラル → 16kHz ステレオ
+        assert_eq!(
+            convert_interleaved(&[0.1, 0.2], 16_000, 1, 16_000, 2),
+            vec![0.1, 0.1, 0.2, 0.2]
+        );
+        // 同じ形式ならそのまま
+        assert_eq!(
+            convert_interleaved(&[0.1, 0.2], 44_100, 2, 44_100, 2),
+            vec![0.1, 0.2]
+        );
+    }
+
+    #[test]
+    fn test_stream_resampler_passthrough() {
+        let mut resampler = StreamResampler::new(16_000, MONO_CHANNELS, 16_000);
+        assert_eq!(resampler.process(&[0.1, 0.2]), vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn test_pcm_bytes_to_whisper_wav_keeps_16k_mono() {
+        let bytes = vec![0x00, 0x00, 0xFF, 0x7F];
+        let wav = pcm_bytes_to_whisper_wav(&bytes, WHISPER_SAMPLE_RATE, MONO_CHANNELS).unwrap();
+        assert!(wav.ends_with(&bytes));
+    }
+
+    #[test]
+    fn test_pcm_bytes_to_whisper_wav_converts_48k_stereo() {
+        // 48kHz ステレオ 1 秒 → 16kHz モノラル 1 秒
+        let bytes: Vec<u8> = (0..48_000)
+            .flat_map(|_| [4000i16, 4000])
+            .flat_map(i16::to_le_bytes)
+            .collect();
+        let wav = pcm_bytes_to_whisper_wav(&bytes, 48_000, 2).unwrap();
+        let (pcm, sample_rate, channels) = wav_to_pcm_f32(&wav).unwrap();
+        assert_eq!(
+            (sample_rate, channels),
+            (WHISPER_SAMPLE_RATE, MONO_CHANNELS)
+        );
+        assert_eq!(pcm.len(), WHISPER_SAMPLE_RATE as usize);
+    }
+
+    #[test]
+    fn test_pcm_bytes_to_whisper_wav_rejects_odd_length() {
+        assert!(pcm_bytes_to_whisper_wav(&[0, 1, 2], 48_000, 2).is_err());
+    }
+}
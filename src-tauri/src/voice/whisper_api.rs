@@ -1,63 +1,134 @@
+use std::path::Path;
+
 use async_trait::async_trait;
 use reqwest::multipart;
+use reqwest_middleware::ClientWithMiddleware;
 
-use crate::voice::{SpeechRecognizer, TranscriptionResult, VoiceError};
+use crate::config::app_config::AppConfig;
+use crate::http_debug::wrap_client;
+use crate::voice::format::{probe_audio_format, AudioFormat};
+use crate::voice::{Segment, SpeechRecognizer, TranscriptionResult, VoiceError};
 
 const WHISPER_API_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
 const WHISPER_MODEL: &str = "whisper-1";
 
 /// OpenAI Whisper API のレスポンス
+///
+/// `language` と `segments` は `response_format=verbose_json` のときのみ返る。
 #[derive(Debug, serde::Deserialize)]
 struct WhisperResponse {
     text: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    segments: Option<Vec<WhisperSegment>>,
+}
+
+/// verbose_json の区間（時刻は秒）
+#[derive(Debug, serde::Deserialize)]
+struct WhisperSegment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+impl From<WhisperSegment> for Segment {
+    fn from(s: WhisperSegment) -> Self {
+        Self {
+            start_ms: (s.start * 1000.0).round() as u64,
+            end_ms: (s.end * 1000.0).round() as u64,
+            text: s.text.trim().to_string(),
+        }
+    }
 }
 
 /// OpenAI Whisper API を使った音声認識クライアント
 pub struct WhisperApiClient {
-    client: reqwest::Client,
+    client: ClientWithMiddleware,
     api_key: String,
+    /// Whisper が判定した言語を結果に含めるか
+    detect_language: bool,
 }
 
 impl WhisperApiClient {
-    /// 環境変数 `OPENAI_API_KEY` から API キーを取得して初期化する
-    pub fn from_env() -> Result<Self, VoiceError> {
-        let api_key =
-            std::env::var("OPENAI_API_KEY").map_err(|_| VoiceError::MissingApiKey)?;
-        Ok(Self {
-            client: reqwest::Client::new(),
-            api_key,
-        })
+    /// 設定の `OPENAI_API_KEY` で初期化する
+    pub fn from_config(config: &AppConfig) -> Result<Self, VoiceError> {
+        let api_key = config
+            .openai_api_key
+            .clone()
+            .ok_or(VoiceError::MissingApiKey)?;
+        Ok(Self::new(api_key))
     }
 
     /// 指定の API キーで初期化する
     pub fn new(api_key: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: wrap_client(reqwest::Client::new()),
             api_key,
+            detect_language: false,
         }
     }
+
+    /// Whisper が判定した言語を `language_detected` に含めるかを設定する
+    pub fn with_detect_language(mut self, enabled: bool) -> Self {
+        self.detect_language = enabled;
+        self
+    }
+
+    /// 音声ファイル（WAV / MP3 / M4A）をデコードせずにそのまま送って文字起こしする
+    pub async fn transcribe_file(
+        &self,
+        path: &Path,
+        language: Option<&str>,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let data = tokio::fs::read(path).await.map_err(|e| {
+            VoiceError::FormatError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        self.transcribe(&data, language).await
+    }
+}
+
+/// 先頭のバイト列から、Whisper API に送るファイル名と MIME タイプを決める
+///
+/// WAV / MP3 は `probe_audio_format` で、M4A は ISO BMFF の `ftyp` ボックスで判定する。
+/// どれにも当たらなければ従来どおり WAV として送る。
+fn upload_file_type(data: &[u8]) -> (&'static str, &'static str) {
+    if data.len() >= 8 && &data[4..8] == b"ftyp" {
+        return ("audio.m4a", "audio/mp4");
+    }
+    match probe_audio_format(data) {
+        Some(AudioFormat::Mp3) => ("audio.mp3", "audio/mpeg"),
+        Some(AudioFormat::Wav) | None => ("audio.wav", "audio/wav"),
+    }
 }
 
 #[async_trait]
 impl SpeechRecognizer for WhisperApiClient {
-    /// WAV 形式の音声データを Whisper API に送信して文字起こしする
+    /// 音声データを Whisper API に送信して文字起こしする
     ///
-    /// `audio_data` は WAV ファイルのバイト列。
-    /// format モジュールで PCM → WAV 変換した後にこのメソッドを呼ぶ。
+    /// `audio_data` は通常 format モジュールで PCM → WAV 変換したバイト列。
+    /// MP3 / M4A も先頭のバイト列から判定し、再エンコードせずに送る。
     async fn transcribe(
         &self,
         audio_data: &[u8],
-        language: &str,
+        language: Option<&str>,
     ) -> Result<TranscriptionResult, VoiceError> {
+        let (file_name, mime) = upload_file_type(audio_data);
         let file_part = multipart::Part::bytes(audio_data.to_vec())
-            .file_name("audio.wav")
-            .mime_str("audio/wav")
+            .file_name(file_name)
+            .mime_str(mime)
             .map_err(|e| VoiceError::ApiError(format!("Failed to create multipart: {}", e)))?;
 
-        let form = multipart::Form::new()
+        // 区間ごとの時刻と判定言語は verbose_json でのみ返る
+        let mut form = multipart::Form::new()
             .part("file", file_part)
             .text("model", WHISPER_MODEL.to_string())
-            .text("language", language.to_string());
+            .text("response_format", "verbose_json")
+            .text("timestamp_granularities[]", "segment");
+        // 言語を省略すると Whisper が判定する
+        if let Some(language) = language {
+            form = form.text("language", language.to_string());
+        }
 
         let response = self
             .client
@@ -92,6 +163,88 @@ impl SpeechRecognizer for WhisperApiClient {
             confidence: 1.0, // Whisper API は信頼度スコアを返さないためデフォルト値
             is_final: true,
             timestamp,
+            language_detected: whisper_response.language.filter(|_| self.detect_language),
+            used_fallback: false,
+            segments: whisper_response
+                .segments
+                .map(|segments| segments.into_iter().map(Segment::from).collect()),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_verbose_json_language() {
+        let body = r#"{"task":"transcribe","language":"japanese","duration":1.5,"text":"こんにちは","segments":[]}"#;
+        let response: WhisperResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.text, "こんにちは");
+        assert_eq!(response.language.as_deref(), Some("japanese"));
+    }
+
+    #[test]
+    fn test_parse_json_without_language() {
+        let response: WhisperResponse = serde_json::from_str(r#"{"text":"hello"}"#).unwrap();
+        assert!(response.language.is_none());
+        assert!(response.segments.is_none());
+    }
+
+    #[test]
+    fn test_upload_file_type_sniffs_magic_bytes() {
+        let wav = crate::voice::format::pcm_f32_to_wav(&[0.0; 16], 16_000, 1).unwrap();
+        assert_eq!(upload_file_type(&wav), ("audio.wav", "audio/wav"));
+        assert_eq!(
+            upload_file_type(b"ID3\x04\x00\x00\x00\x00\x00\x00"),
+            ("audio.mp3", "audio/mpeg")
+        );
+        assert_eq!(
+            upload_file_type(b"\x00\x00\x00\x20ftypM4A \x00\x00\x00\x00"),
+            ("audio.m4a", "audio/mp4")
+        );
+        // 判定できないものは WAV として送る
+        assert_eq!(upload_file_type(b"????"), ("audio.wav", "audio/wav"));
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_file_reports_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = WhisperApiClient::new("sk-test".to_string());
+        let err = client
+            .transcribe_file(&dir.path().join("memo.m4a"), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, VoiceError::FormatError(msg) if msg.contains("memo.m4a")));
+    }
+
+    #[test]
+    fn test_parse_verbose_json_segments() {
+        let body = r#"{"task":"transcribe","language":"japanese","duration":2.4,"text":"こんにちは。元気です。","segments":[
+            {"id":0,"seek":0,"start":0.0,"end":1.2,"text":" こんにちは。","avg_logprob":-0.2},
+            {"id":1,"seek":0,"start":1.2,"end":2.4,"text":" 元気です。","avg_logprob":-0.3}
+        ]}"#;
+        let response: WhisperResponse = serde_json::from_str(body).unwrap();
+        let segments: Vec<Segment> = response
+            .segments
+            .unwrap()
+            .into_iter()
+            .map(Segment::from)
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    start_ms: 0,
+                    end_ms: 1200,
+                    text: "こんにちは。".to_string(),
+                },
+                Segment {
+                    start_ms: 1200,
+                    end_ms: 2400,
+                    text: "元気です。".to_string(),
+                },
+            ]
+        );
+    }
+}
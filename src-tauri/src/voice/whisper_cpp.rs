@@ -0,0 +1,218 @@
+//! whisper.cpp（whisper-rs）によるローカル音声認識
+//!
+//! `WHISPER_CPP_MODEL` のモデルを読み込み、ネットワークを使わずに文字起こしする。
+//! whisper.cpp のビルドに CMake が必要なため、`whisper-cpp` feature を有効にしたときだけコンパイルする。
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+use crate::config::app_config::AppConfig;
+use crate::voice::format::wav_to_pcm_f32;
+use crate::voice::resample::to_whisper_mono;
+use crate::voice::{Segment, SpeechRecognizer, TranscriptionResult, VoiceError};
+
+/// 直前に読み込んだモデル（読み込みに数秒かかるので、同じパスなら使い回す）
+static LOADED_MODEL: Mutex<Option<(PathBuf, Arc<WhisperContext>)>> = Mutex::new(None);
+
+/// whisper.cpp による音声認識エンジン
+pub struct WhisperCppRecognizer {
+    context: Arc<WhisperContext>,
+}
+
+impl WhisperCppRecognizer {
+    /// `WHISPER_CPP_MODEL` のモデルで認識エンジンを作成する
+    ///
+    /// モデルの読み込みは数秒かかりうるので、async の文脈からは `load` を使う。
+    pub fn from_config(config: &AppConfig) -> Result<Self, VoiceError> {
+        Self::new(model_path(config)?)
+    }
+
+    /// `from_config` と同じだが、モデルの読み込みを `spawn_blocking` で行う
+    ///
+    /// 読み込み中は `LOADED_MODEL` のロックを持つので、async ランタイムのスレッドで行わない。
+    pub async fn load(config: &AppConfig) -> Result<Self, VoiceError> {
+        let path = model_path(config)?.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::new(&path))
+            .await
+            .map_err(|e| VoiceError::NativeError(format!("whisper.cpp load task failed: {}", e)))?
+    }
+
+    /// モデルファイル（ggml / GGUF）を読み込んで認識エンジンを作成する
+    pub fn new(model_path: &Path) -> Result<Self, VoiceError> {
+        Ok(Self {
+            context: load_model(model_path)?,
+        })
+    }
+}
+
+fn model_path(config: &AppConfig) -> Result<&Path, VoiceError> {
+    config
+        .whisper_cpp_model
+        .as_deref()
+        .ok_or_else(|| VoiceError::NativeError("WHISPER_CPP_MODEL is not set".into()))
+}
+
+fn load_model(path: &Path) -> Result<Arc<WhisperContext>, VoiceError> {
+    let mut loaded = LOADED_MODEL
+        .lock()
+        .map_err(|_| VoiceError::NativeError("whisper.cpp model lock poisoned".into()))?;
+    if let Some((loaded_path, context)) = loaded.as_ref() {
+        if loaded_path == path {
+            return Ok(Arc::clone(context));
+        }
+    }
+
+    if !path.is_file() {
+        return Err(VoiceError::NativeError(format!(
+            "whisper.cpp model not found: {}",
+            path.display()
+        )));
+    }
+    let path_str = path.to_str().ok_or_else(|| {
+        VoiceError::NativeError(format!("Invalid model path: {}", path.display()))
+    })?;
+    let context = WhisperContext::new_with_params(path_str, WhisperContextParameters::default())
+        .map(Arc::new)
+        .map_err(|e| {
+            VoiceError::NativeError(format!(
+                "Failed to load whisper.cpp model {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+    eprintln!("[whisper_cpp] loaded model: {}", path.display());
+    *loaded = Some((path.to_path_buf(), Arc::clone(&context)));
+    Ok(context)
+}
+
+/// "ja-JP" のようなロケールを whisper.cpp の言語コード（"ja"）にする。空か "auto" なら None
+fn whisper_language(language: &str) -> Option<String> {
+    let code = language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    (!code.is_empty() && code != "auto").then_some(code)
+}
+
+/// 16kHz モノラルの PCM を認識し、全文・区間・判定した言語を返す
+fn run_full(
+    context: &WhisperContext,
+    pcm: &[f32],
+    language: Option<&str>,
+) -> Result<(String, Vec<Segment>, Option<String>), VoiceError> {
+    let native =
+        |e: whisper_rs::WhisperError| VoiceError::NativeError(format!("whisper.cpp error: {}", e));
+    let mut state = context.create_state().map_err(native)?;
+
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(language.unwrap_or("auto")));
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+    state.full(params, pcm).map_err(native)?;
+
+    // 区間のテキストは先頭に空白を含むので、全文は連結してから前後だけ削る
+    let mut text = String::new();
+    let mut segments = Vec::new();
+    for i in 0..state.full_n_segments().map_err(native)? {
+        let segment_text = state.full_get_segment_text(i).map_err(native)?;
+        text.push_str(&segment_text);
+        // whisper.cpp の区間の時刻は 10ms 単位
+        segments.push(Segment {
+            start_ms: state.full_get_segment_t0(i).map_err(native)?.max(0) as u64 * 10,
+            end_ms: state.full_get_segment_t1(i).map_err(native)?.max(0) as u64 * 10,
+            text: segment_text.trim().to_string(),
+        });
+    }
+    let language_detected = state
+        .full_lang_id_from_state()
+        .ok()
+        .and_then(whisper_rs::get_lang_str)
+        .map(str::to_string);
+    Ok((text.trim().to_string(), segments, language_detected))
+}
+
+#[async_trait]
+impl SpeechRecognizer for WhisperCppRecognizer {
+    async fn transcribe(
+        &self,
+        audio_data: &[u8],
+        language: Option<&str>,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let (samples, sample_rate, channels) = wav_to_pcm_f32(audio_data)?;
+        let pcm = to_whisper_mono(&samples, sample_rate, channels);
+        let context = Arc::clone(&self.context);
+        let language = language.and_then(whisper_language);
+
+        // 推論は CPU を占有するので async ランタイムのスレッドを塞がない
+        let (text, segments, language_detected) =
+            tokio::task::spawn_blocking(move || run_full(&context, &pcm, language.as_deref()))
+                .await
+                .map_err(|e| {
+                    VoiceError::NativeError(format!("whisper.cpp task failed: {}", e))
+                })??;
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        Ok(TranscriptionResult {
+            text,
+            confidence: 1.0, // whisper.cpp のセグメントにも信頼度スコアはない
+            is_final: true,
+            timestamp,
+            language_detected,
+            used_fallback: false,
+            segments: Some(segments),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whisper_language() {
+        assert_eq!(whisper_language("ja-JP").as_deref(), Some("ja"));
+        assert_eq!(whisper_language("en_US").as_deref(), Some("en"));
+        assert_eq!(whisper_language("ja").as_deref(), Some("ja"));
+        assert_eq!(whisper_language(""), None);
+        assert_eq!(whisper_language("auto"), None);
+    }
+
+    #[test]
+    fn test_missing_model_is_native_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = WhisperCppRecognizer::new(&dir.path().join("ggml-missing.bin"))
+            .err()
+            .unwrap();
+        assert!(matches!(err, VoiceError::NativeError(msg) if msg.contains("not found")));
+    }
+
+    #[test]
+    fn test_from_config_requires_model_path() {
+        let err = WhisperCppRecognizer::from_config(&AppConfig::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err, VoiceError::NativeError(msg) if msg.contains("WHISPER_CPP_MODEL")));
+    }
+
+    #[tokio::test]
+    async fn test_load_reports_missing_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AppConfig {
+            whisper_cpp_model: Some(dir.path().join("ggml-missing.bin")),
+            ..AppConfig::default()
+        };
+        let err = WhisperCppRecognizer::load(&config).await.err().unwrap();
+        assert!(matches!(err, VoiceError::NativeError(msg) if msg.contains("not found")));
+    }
+}
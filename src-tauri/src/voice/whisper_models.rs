@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+/// whisper.cpp の ggml モデルの配布元
+const MODEL_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// 既知のモデルと配布ファイルのおおよそのサイズ（MB）
+pub const KNOWN_MODELS: &[(&str, f32)] = &[
+    ("tiny", 75.0),
+    ("base", 142.0),
+    ("small", 466.0),
+    ("medium", 1_500.0),
+    ("large-v3", 2_900.0),
+];
+
+/// ローカル Whisper モデルの情報
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WhisperModelInfo {
+    pub name: String,
+    pub filename: String,
+    pub size_mb: f32,
+    pub is_downloaded: bool,
+    /// ダウンロード済みならファイルの絶対パス
+    pub path: Option<String>,
+}
+
+/// モデル名から配布ファイル名を返す（`ggml-{name}.bin`）
+pub fn model_filename(name: &str) -> String {
+    format!("ggml-{}.bin", name)
+}
+
+/// モデルのダウンロード URL（既知のモデルのみ）
+pub fn model_url(name: &str) -> Option<String> {
+    KNOWN_MODELS
+        .iter()
+        .any(|(known, _)| *known == name)
+        .then(|| format!("{}/{}", MODEL_BASE_URL, model_filename(name)))
+}
+
+/// モデルディレクトリを走査し、既知のモデルごとにダウンロード済みかを返す
+///
+/// 中身が空のファイルや書き込み途中の `.part` はダウンロード済みとみなさない。
+/// ディレクトリが存在しない場合はすべて未ダウンロードとして返す。
+pub fn scan_models_dir(dir: &Path) -> Vec<WhisperModelInfo> {
+    KNOWN_MODELS
+        .iter()
+        .map(|(name, size_mb)| {
+            let filename = model_filename(name);
+            let path = dir.join(&filename);
+            let is_downloaded = std::fs::metadata(&path)
+                .map(|m| m.is_file() && m.len() > 0)
+                .unwrap_or(false);
+            WhisperModelInfo {
+                name: name.to_string(),
+                filename,
+                size_mb: *size_mb,
+                is_downloaded,
+                path: is_downloaded.then(|| path.to_string_lossy().to_string()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_missing_dir() {
+        let models = scan_models_dir(Path::new("/nonexistent/tap-onsen/whisper"));
+        assert_eq!(models.len(), KNOWN_MODELS.len());
+        assert!(models.iter().all(|m| !m.is_downloaded && m.path.is_none()));
+    }
+
+    #[test]
+    fn test_scan_detects_downloaded_models() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::write(dir.join("ggml-base.bin"), b"model").unwrap();
+        // 空ファイル・書き込み途中・未知のファイルは無視する
+        std::fs::write(dir.join("ggml-tiny.bin"), b"").unwrap();
+        std::fs::write(dir.join("ggml-small.bin.part"), b"partial").unwrap();
+        std::fs::write(dir.join("ggml-custom.bin"), b"model").unwrap();
+
+        let models = scan_models_dir(dir);
+        let downloaded: Vec<&str> = models
+            .iter()
+            .filter(|m| m.is_downloaded)
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(downloaded, vec!["base"]);
+
+        let base = models.iter().find(|m| m.name == "base").unwrap();
+        assert_eq!(base.filename, "ggml-base.bin");
+        assert_eq!(
+            base.path.as_deref(),
+            Some(dir.join("ggml-base.bin").to_string_lossy().as_ref())
+        );
+    }
+
+    #[test]
+    fn test_model_url() {
+        assert_eq!(
+            model_url("large-v3").as_deref(),
+            Some("https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3.bin")
+        );
+        assert!(model_url("huge").is_none());
+    }
+}
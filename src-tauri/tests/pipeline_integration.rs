@@ -0,0 +1,184 @@
+//! 音声パイプラインの結合テスト
+//!
+//! 440Hz のサイン波から WAV ファイルを生成し、モック認識器を通して
+//! `TranscriptionPipeline` の各経路を検証する。ネットワークには接続しない。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use tap_onsen_lib::voice::format::{self, MONO_CHANNELS, WHISPER_SAMPLE_RATE};
+use tap_onsen_lib::voice::pipeline::TranscriptionPipeline;
+use tap_onsen_lib::voice::{SpeechRecognizer, TranscriptionResult, VoiceError};
+
+const RECOGNIZED_TEXT: &str = "test audio recognized";
+const SINE_FREQ_HZ: f32 = 440.0;
+const SINE_AMPLITUDE: f32 = 0.5;
+
+/// 固定テキストを返し、受け取った音声のサンプル数を記録するモック
+#[derive(Clone, Default)]
+struct MockRecognizer {
+    calls: Arc<AtomicUsize>,
+    sample_counts: Arc<Mutex<Vec<usize>>>,
+}
+
+impl MockRecognizer {
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn sample_counts(&self) -> Vec<usize> {
+        self.sample_counts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl SpeechRecognizer for MockRecognizer {
+    async fn transcribe(
+        &self,
+        audio_data: &[u8],
+        language: &str,
+    ) -> Result<TranscriptionResult, VoiceError> {
+        let (pcm, sample_rate, channels) = format::wav_to_pcm_f32(audio_data)?;
+        assert_eq!(sample_rate, WHISPER_SAMPLE_RATE);
+        assert_eq!(channels, MONO_CHANNELS);
+        self.sample_counts.lock().unwrap().push(pcm.len());
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(TranscriptionResult {
+            text: RECOGNIZED_TEXT.to_string(),
+            confidence: 0.9,
+            is_final: true,
+            timestamp: call as u64 * 500,
+            language_detected: Some(language.to_string()),
+        })
+    }
+}
+
+/// 16kHz モノラルの 440Hz サイン波
+fn sine_wave(secs: f32) -> Vec<f32> {
+    let samples = (secs * WHISPER_SAMPLE_RATE as f32) as usize;
+    (0..samples)
+        .map(|i| {
+            let t = i as f32 / WHISPER_SAMPLE_RATE as f32;
+            SINE_AMPLITUDE * (2.0 * std::f32::consts::PI * SINE_FREQ_HZ * t).sin()
+        })
+        .collect()
+}
+
+/// サイン波を WAV ファイルに書き出し、読み戻した PCM を返す
+fn sine_wav_file(dir: &std::path::Path) -> Vec<f32> {
+    let path = dir.join("sine.wav");
+    let wav = format::pcm_f32_to_wav(&sine_wave(1.0), WHISPER_SAMPLE_RATE, MONO_CHANNELS).unwrap();
+    std::fs::write(&path, wav).unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    assert_eq!(format::get_wav_duration_ms(&data).unwrap(), 1_000);
+    let (pcm, sample_rate, channels) = format::wav_to_pcm_f32(&data).unwrap();
+    assert_eq!(
+        (sample_rate, channels),
+        (WHISPER_SAMPLE_RATE, MONO_CHANNELS)
+    );
+    assert_eq!(pcm.len(), 16_000);
+    pcm
+}
+
+#[tokio::test]
+async fn test_transcribe_all_from_wav_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let pcm = sine_wav_file(dir.path());
+    let recognizer = MockRecognizer::default();
+    let pipeline = TranscriptionPipeline::new(recognizer.clone(), "ja");
+
+    let result = pipeline.transcribe_all(&pcm).await.unwrap();
+    assert_eq!(result.text, RECOGNIZED_TEXT);
+    assert_eq!(result.confidence, 0.9);
+    assert!(result.is_final);
+    assert_eq!(result.timestamp, 0);
+    assert_eq!(result.language_detected.as_deref(), Some("ja"));
+    assert_eq!(recognizer.sample_counts(), vec![16_000]);
+}
+
+#[tokio::test]
+async fn test_transcribe_chunked_half_second_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let pcm = sine_wav_file(dir.path());
+    let recognizer = MockRecognizer::default();
+    let pipeline = TranscriptionPipeline::new(recognizer.clone(), "ja").with_chunk_samples(8_000);
+
+    let interim = Mutex::new(Vec::new());
+    let on_interim = |r: &TranscriptionResult| interim.lock().unwrap().push(r.is_final);
+    let result = pipeline
+        .transcribe_chunked(&pcm, Some(&on_interim))
+        .await
+        .unwrap();
+
+    assert_eq!(recognizer.calls(), 2);
+    assert_eq!(recognizer.sample_counts(), vec![8_000, 8_000]);
+    assert_eq!(*interim.lock().unwrap(), vec![false, true]);
+    assert_eq!(
+        result.text,
+        format!("{} {}", RECOGNIZED_TEXT, RECOGNIZED_TEXT)
+    );
+    assert!(result.is_final);
+    assert_eq!(result.timestamp, 500);
+    assert_eq!(result.language_detected.as_deref(), Some("ja"));
+}
+
+#[tokio::test]
+async fn test_transcribe_raw_bytes_i16_le() {
+    let raw: Vec<u8> = sine_wave(1.0)
+        .iter()
+        .flat_map(|&s| ((s * i16::MAX as f32) as i16).to_le_bytes())
+        .collect();
+    assert_eq!(raw.len(), 32_000);
+    let recognizer = MockRecognizer::default();
+    let pipeline = TranscriptionPipeline::new(recognizer.clone(), "en");
+
+    let result = pipeline.transcribe_raw_bytes(&raw).await.unwrap();
+    assert_eq!(result.text, RECOGNIZED_TEXT);
+    assert_eq!(result.language_detected.as_deref(), Some("en"));
+    assert_eq!(recognizer.sample_counts(), vec![16_000]);
+
+    // 奇数バイトは i16 として解釈できない
+    assert!(matches!(
+        pipeline.transcribe_raw_bytes(&raw[..31_999]).await,
+        Err(VoiceError::FormatError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_silence_detection_passes_sine_wave() {
+    let dir = tempfile::tempdir().unwrap();
+    let pcm = sine_wav_file(dir.path());
+    // 振幅 0.5 のサイン波の RMS は約 0.35
+    assert!((format::rms(&pcm) - SINE_AMPLITUDE / 2f32.sqrt()).abs() < 0.01);
+
+    let recognizer = MockRecognizer::default();
+    let pipeline =
+        TranscriptionPipeline::new(recognizer.clone(), "ja").with_silence_detection(0.01);
+    let result = pipeline.transcribe_all(&pcm).await.unwrap();
+    assert_eq!(result.text, RECOGNIZED_TEXT);
+    assert_eq!(recognizer.calls(), 1);
+
+    // 閾値がサイン波の RMS より大きければ無音扱いで認識しない
+    let recognizer = MockRecognizer::default();
+    let pipeline = TranscriptionPipeline::new(recognizer.clone(), "ja").with_silence_detection(0.5);
+    let result = pipeline.transcribe_all(&pcm).await.unwrap();
+    assert_eq!(result.text, "");
+    assert!(result.is_final);
+    assert_eq!(recognizer.calls(), 0);
+}
+
+#[tokio::test]
+async fn test_silence_detection_skips_silent_chunks() {
+    let mut pcm = sine_wave(0.5);
+    pcm.resize(16_000, 0.0);
+    let recognizer = MockRecognizer::default();
+    let pipeline = TranscriptionPipeline::new(recognizer.clone(), "ja")
+        .with_chunk_samples(8_000)
+        .with_silence_detection(0.01);
+
+    let result = pipeline.transcribe_chunked(&pcm, None).await.unwrap();
+    assert_eq!(recognizer.calls(), 1);
+    assert_eq!(result.text, RECOGNIZED_TEXT);
+}
@@ -7,6 +7,7 @@ import { History } from "./components/History";
 import { useVoiceInput } from "./hooks/useVoiceInput";
 import { useAIProcess } from "./hooks/useAIProcess";
 import { usePushToTalk } from "./hooks/usePushToTalk";
+import { useBackendReady } from "./hooks/useBackendReady";
 import { pasteToForeground } from "./lib/ipc";
 import type { Mode } from "./types/mode";
 import "./App.css";
@@ -14,6 +15,7 @@ import "./App.css";
 function App() {
   const [selectedMode, setSelectedMode] = useState<Mode | null>(null);
   const [historyKey, setHistoryKey] = useState(0);
+  const backend = useBackendReady();
   const voice = useVoiceInput();
   const ai = useAIProcess();
   const pttTriggeredRef = useRef(false);
@@ -37,10 +39,15 @@ function App() {
       const wasPttTriggered = pttTriggeredRef.current;
       pttTriggeredRef.current = false;
 
-      ai.process(voice.transcript, selectedMode).then((resultText) => {
+      ai.process(
+        voice.transcript,
+        selectedMode,
+        voice.audioDurationMs,
+        voice.deviceName,
+      ).then((resultText) => {
         setHistoryKey((k) => k + 1);
         if (wasPttTriggered && resultText) {
-          pasteToForeground(resultText).catch((e) => {
+          pasteToForeground(resultText, selectedMode.id).catch((e) => {
             console.error("Failed to paste to foreground:", e);
           });
         }
@@ -53,6 +60,15 @@ function App() {
     ai.clear();
   }, [voice, ai]);
 
+  // DB の初期化が終わるまではメイン UI を出さない
+  if (!backend.isReady) {
+    return (
+      <div className="app">
+        <p className="app-loading">起動中...</p>
+      </div>
+    );
+  }
+
   const displayText = ai.processedText || voice.transcript;
 
   return (
@@ -104,6 +120,14 @@ function App() {
         <History refreshKey={historyKey} />
       </main>
 
+      {backend.warnings.map((w) => (
+        <div key={w} className="app-warning">
+          {w}
+        </div>
+      ))}
+
+      {voice.notice && <div className="app-warning">{voice.notice}</div>}
+
       {(voice.error || ai.error) && (
         <div className="app-error">{voice.error || ai.error}</div>
       )}
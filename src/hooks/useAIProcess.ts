@@ -6,7 +6,12 @@ interface UseAIProcessReturn {
   processedText: string;
   isProcessing: boolean;
   error: string | null;
-  process: (text: string, mode: Mode) => Promise<string>;
+  process: (
+    text: string,
+    mode: Mode,
+    audioDurationMs?: number | null,
+    deviceName?: string | null,
+  ) => Promise<string>;
   clear: () => void;
 }
 
@@ -15,7 +20,12 @@ export function useAIProcess(): UseAIProcessReturn {
   const [isProcessing, setIsProcessing] = useState(false);
   const [error, setError] = useState<string | null>(null);
 
-  const process = useCallback(async (text: string, mode: Mode): Promise<string> => {
+  const process = useCallback(async (
+    text: string,
+    mode: Mode,
+    audioDurationMs: number | null = null,
+    deviceName: string | null = null,
+  ): Promise<string> => {
     if (!text.trim()) return "";
 
     if (!mode.ai_enabled) {
@@ -29,6 +39,9 @@ export function useAIProcess(): UseAIProcessReturn {
         prompt_tokens: null,
         completion_tokens: null,
         total_tokens: null,
+        request_id: null,
+        device_name: deviceName,
+        audio_duration_ms: audioDurationMs,
       }).catch((e) => console.warn("Failed to save entry:", e));
       return text;
     }
@@ -48,6 +61,9 @@ export function useAIProcess(): UseAIProcessReturn {
         prompt_tokens: result.usage?.prompt_tokens ?? null,
         completion_tokens: result.usage?.completion_tokens ?? null,
         total_tokens: result.usage?.total_tokens ?? null,
+        request_id: result.request_id,
+        device_name: deviceName,
+        audio_duration_ms: audioDurationMs,
       }).catch((e) => console.warn("Failed to save entry:", e));
       return result.text;
     } catch (e) {
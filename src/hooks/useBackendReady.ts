@@ -0,0 +1,42 @@
+import { useEffect, useState } from "react";
+import { ping } from "../lib/ipc";
+import type { PingResponse } from "../types/health";
+
+const POLL_INTERVAL_MS = 200;
+
+/**
+ * バックエンドの起動完了（DB 初期化）を ping でポーリングして待つ
+ */
+export function useBackendReady() {
+  const [status, setStatus] = useState<PingResponse | null>(null);
+
+  useEffect(() => {
+    let cancelled = false;
+    let timer: ReturnType<typeof setTimeout> | undefined;
+
+    const poll = async () => {
+      try {
+        const res = await ping();
+        if (cancelled) return;
+        setStatus(res);
+        if (res.db_ready) return;
+      } catch (e) {
+        console.error("ping failed:", e);
+      }
+      if (!cancelled) {
+        timer = setTimeout(poll, POLL_INTERVAL_MS);
+      }
+    };
+    poll();
+
+    return () => {
+      cancelled = true;
+      if (timer) clearTimeout(timer);
+    };
+  }, []);
+
+  return {
+    isReady: status?.db_ready ?? false,
+    warnings: status?.startup_warnings ?? [],
+  };
+}
@@ -5,8 +5,11 @@ interface UseVoiceInputReturn {
   isRecording: boolean;
   duration: number;
   transcript: string;
+  audioDurationMs: number | null;
+  deviceName: string | null;
   interimText: string;
   error: string | null;
+  notice: string | null;
   start: () => Promise<void>;
   stop: () => Promise<void>;
   clear: () => void;
@@ -16,8 +19,11 @@ export function useVoiceInput(): UseVoiceInputReturn {
   const [isRecording, setIsRecording] = useState(false);
   const [duration, setDuration] = useState(0);
   const [transcript, setTranscript] = useState("");
+  const [audioDurationMs, setAudioDurationMs] = useState<number | null>(null);
+  const [deviceName, setDeviceName] = useState<string | null>(null);
   const [interimText, setInterimText] = useState("");
   const [error, setError] = useState<string | null>(null);
+  const [notice, setNotice] = useState<string | null>(null);
 
   const timerRef = useRef<ReturnType<typeof setInterval> | null>(null);
 
@@ -37,6 +43,7 @@ export function useVoiceInput(): UseVoiceInputReturn {
 
   const start = useCallback(async () => {
     setError(null);
+    setNotice(null);
     setInterimText("...");
     setIsRecording(true);
     startTimer();
@@ -58,6 +65,13 @@ export function useVoiceInput(): UseVoiceInputReturn {
 
     try {
       const recording = await stopRecording();
+      // 直前と同じ音声（PTT の二度押しなど）は文字起こしを省略する
+      if (recording.duplicate_detected) {
+        setNotice("直前と同じ録音のため文字起こしを省略しました");
+        return;
+      }
+      setAudioDurationMs(recording.duration_ms);
+      setDeviceName(recording.meta.device_name || null);
       const result = await transcribeAudio(
         recording.audio_data,
         recording.sample_rate,
@@ -71,8 +85,11 @@ export function useVoiceInput(): UseVoiceInputReturn {
 
   const clear = useCallback(() => {
     setTranscript("");
+    setAudioDurationMs(null);
+    setDeviceName(null);
     setInterimText("");
     setError(null);
+    setNotice(null);
     setDuration(0);
   }, []);
 
@@ -80,8 +97,11 @@ export function useVoiceInput(): UseVoiceInputReturn {
     isRecording,
     duration,
     transcript,
+    audioDurationMs,
+    deviceName,
     interimText,
     error,
+    notice,
     start,
     stop,
     clear,
@@ -1,48 +1,229 @@
 import { invoke } from "@tauri-apps/api/core";
 import type { Mode } from "../types/mode";
-import type { TranscriptionResult, RecordingResult } from "../types/voice";
-import type { AIResponse } from "../types/ai";
-import type { Entry, NewEntry } from "../types/db";
+import type {
+  AudioDeviceInfo,
+  TranscriptionResult,
+  RecordingData,
+  RecordingResult,
+  WhisperModelInfo,
+  DownloadProgress,
+} from "../types/voice";
+import type {
+  AIPriority,
+  AIResponse,
+  ContextFormat,
+  ModelInfo,
+  ModelRotationState,
+  PromptPreview,
+  QueueDepth,
+  StreamDoneEvent,
+} from "../types/ai";
+import type {
+  DayCount,
+  DiffStats,
+  Entry,
+  EntryFilter,
+  EntryValidationIssue,
+  ExportFormat,
+  HourOfWeekCount,
+  MigrationStatus,
+  NewEntry,
+  RecordingMetricsSummary,
+  RepairResult,
+  TextDiff,
+} from "../types/db";
+import type { PingResponse } from "../types/health";
+import type { ConfigSummary } from "../types/config";
+import type { PasteMethod } from "../types/paste";
+import type { HttpExchange, PipelineBenchmark } from "../types/debug";
+import type { PttConfig, PttMode } from "../types/hotkey";
+
+export async function ping(): Promise<PingResponse> {
+  return invoke<PingResponse>("ping");
+}
 
 export async function getModes(): Promise<Mode[]> {
   return invoke<Mode[]>("get_modes");
 }
 
+export async function suggestModeForApp(
+  appName: string,
+): Promise<string | null> {
+  return invoke<string | null>("suggest_mode_for_app", { appName });
+}
+
+export async function reloadPromptFile(modeId: string): Promise<string> {
+  return invoke<string>("reload_prompt_file", { modeId });
+}
+
+export async function getConfigSummary(): Promise<ConfigSummary> {
+  return invoke<ConfigSummary>("get_config_summary");
+}
+
 export async function transcribeAudio(
   audioData: number[],
   sampleRate: number,
   channels: number,
-  engine: "native" | "whisper" = "native",
+  engine: "native" | "whisper" | "realtime" = "native",
+  language?: string,
 ): Promise<TranscriptionResult> {
   return invoke<TranscriptionResult>("transcribe_audio", {
     audioData,
     sampleRate,
     channels,
     engine,
+    language,
   });
 }
 
 export async function processWithAI(
   text: string,
   modeId: string,
+  priority?: AIPriority,
 ): Promise<AIResponse> {
-  return invoke<AIResponse>("process_with_ai", { text, modeId });
+  return invoke<AIResponse>("process_with_ai", { text, modeId, priority });
+}
+
+export async function processWithAIStream(
+  text: string,
+  modeId: string,
+  streamId: string,
+): Promise<StreamDoneEvent> {
+  return invoke<StreamDoneEvent>("process_with_ai_stream", {
+    text,
+    modeId,
+    streamId,
+  });
+}
+
+export async function getQueueDepth(): Promise<QueueDepth> {
+  return invoke<QueueDepth>("get_queue_depth");
+}
+
+export async function getModelRotationStats(): Promise<
+  Record<string, ModelRotationState>
+> {
+  return invoke<Record<string, ModelRotationState>>("get_model_rotation_stats");
+}
+
+export async function getModePromptPreview(
+  modeId: string,
+  sampleText: string,
+): Promise<PromptPreview> {
+  return invoke<PromptPreview>("get_mode_prompt_preview", {
+    modeId,
+    sampleText,
+  });
+}
+
+export async function setOpenAISeed(seed: number | null): Promise<void> {
+  return invoke<void>("set_openai_seed", { seed });
+}
+
+export async function setContextFormat(format: ContextFormat): Promise<void> {
+  return invoke<void>("set_context_format", { format });
+}
+
+export async function clearModeContext(modeId: string): Promise<boolean> {
+  return invoke<boolean>("clear_mode_context", { modeId });
+}
+
+export async function clearAllContexts(): Promise<void> {
+  return invoke<void>("clear_all_contexts");
+}
+
+export async function validateAnthropicModel(
+  modelName: string,
+): Promise<boolean> {
+  return invoke<boolean>("validate_anthropic_model", { modelName });
+}
+
+export async function getAiModelInfo(provider: string): Promise<ModelInfo[]> {
+  return invoke<ModelInfo[]>("get_ai_model_info", { provider });
+}
+
+export async function listInputDevices(): Promise<AudioDeviceInfo[]> {
+  return invoke<AudioDeviceInfo[]>("list_input_devices");
 }
 
-export async function startRecording(): Promise<void> {
-  return invoke<void>("start_recording");
+export async function startRecording(deviceName?: string): Promise<void> {
+  return invoke<void>("start_recording", { deviceName });
 }
 
 export async function stopRecording(): Promise<RecordingResult> {
   return invoke<RecordingResult>("stop_recording");
 }
 
+export async function cancelRecording(): Promise<void> {
+  return invoke<void>("cancel_recording");
+}
+
+export async function setAutoStop(
+  enabled: boolean,
+  silenceMs: number,
+  threshold: number,
+): Promise<void> {
+  return invoke<void>("set_auto_stop", { enabled, silenceMs, threshold });
+}
+
+export async function stopRecordingAndTranscribe(
+  language?: string,
+): Promise<TranscriptionResult> {
+  return invoke<TranscriptionResult>("stop_recording_and_transcribe", {
+    language,
+  });
+}
+
+export async function importAudioFile(
+  path: string,
+  language?: string,
+): Promise<TranscriptionResult> {
+  return invoke<TranscriptionResult>("import_audio_file", { path, language });
+}
+
+export async function mergeRecordings(
+  recordings: RecordingData[],
+  insertGap: boolean,
+): Promise<RecordingResult> {
+  return invoke<RecordingResult>("merge_recordings", { recordings, insertGap });
+}
+
+export async function listLocalWhisperModels(): Promise<WhisperModelInfo[]> {
+  return invoke<WhisperModelInfo[]>("list_local_whisper_models");
+}
+
+export async function downloadWhisperModel(
+  modelName: string,
+): Promise<DownloadProgress> {
+  return invoke<DownloadProgress>("download_whisper_model", { modelName });
+}
+
 export async function checkAccessibilityPermission(
   prompt: boolean,
 ): Promise<boolean> {
   return invoke<boolean>("check_accessibility_permission", { prompt });
 }
 
+export async function isPttAvailable(): Promise<boolean> {
+  return invoke<boolean>("is_ptt_available");
+}
+
+export async function getPttConfig(): Promise<PttConfig> {
+  return invoke<PttConfig>("get_ptt_config");
+}
+
+export async function setPttConfig(config: PttConfig): Promise<void> {
+  return invoke<void>("set_ptt_config", { config });
+}
+
+export async function setPttMode(mode: PttMode): Promise<void> {
+  return invoke<void>("set_ptt_mode", { mode });
+}
+
+export async function setPttKey(keycode: number): Promise<void> {
+  return invoke<void>("set_ptt_key", { keycode });
+}
+
 export async function saveEntry(entry: NewEntry): Promise<number> {
   return invoke<number>("save_entry", { entry });
 }
@@ -54,14 +235,191 @@ export async function getEntries(
   return invoke<Entry[]>("get_entries", { limit, offset });
 }
 
-export async function getEntry(id: number): Promise<Entry | null> {
-  return invoke<Entry | null>("get_entry", { id });
+export async function getEntriesFiltered(
+  filter: EntryFilter,
+): Promise<Entry[]> {
+  return invoke<Entry[]>("get_entries_filtered", { filter });
+}
+
+export async function countEntries(): Promise<number> {
+  return invoke<number>("count_entries");
+}
+
+export async function countEntriesByMode(modeId: string): Promise<number> {
+  return invoke<number>("count_entries_by_mode", { modeId });
+}
+
+export async function getEntry(id: number): Promise<Entry> {
+  return invoke<Entry>("get_entry", { id });
+}
+
+export async function getEntryByRequestId(
+  requestId: string,
+): Promise<Entry> {
+  return invoke<Entry>("get_entry_by_request_id", { requestId });
+}
+
+export async function getSimilarEntries(
+  id: number,
+  topK: number,
+): Promise<[Entry, number][]> {
+  return invoke<[Entry, number][]>("get_similar_entries", { id, topK });
+}
+
+export async function searchEntries(
+  query: string,
+  limit: number,
+  offset: number,
+): Promise<Entry[]> {
+  return invoke<Entry[]>("search_entries", { query, limit, offset });
+}
+
+export async function getEntryDiff(id: number): Promise<TextDiff> {
+  return invoke<TextDiff>("get_entry_diff", { id });
+}
+
+export async function getEntriesWithLargeDiffs(
+  threshold: number,
+  limit: number,
+): Promise<[Entry, DiffStats][]> {
+  return invoke<[Entry, DiffStats][]>("get_entries_with_large_diffs", {
+    threshold,
+    limit,
+  });
+}
+
+export async function updateEntry(
+  id: number,
+  processedText: string,
+): Promise<boolean> {
+  return invoke<boolean>("update_entry", { id, processedText });
+}
+
+export async function setFavorite(
+  id: number,
+  isFavorite: boolean,
+): Promise<boolean> {
+  return invoke<boolean>("set_favorite", { id, isFavorite });
 }
 
 export async function deleteEntry(id: number): Promise<boolean> {
   return invoke<boolean>("delete_entry", { id });
 }
 
-export async function pasteToForeground(text: string): Promise<void> {
-  return invoke<void>("paste_to_foreground", { text });
+export async function purgeEntriesByMode(
+  modeId: string,
+  confirm: boolean,
+  dryRun = false,
+): Promise<number> {
+  return invoke<number>("purge_entries_by_mode", { modeId, confirm, dryRun });
+}
+
+export async function getRecordingMetricsSummary(
+  days: number,
+): Promise<RecordingMetricsSummary> {
+  return invoke<RecordingMetricsSummary>("get_recording_metrics_summary", {
+    days,
+  });
+}
+
+export async function getEntryCountByDay(year: number): Promise<DayCount[]> {
+  return invoke<DayCount[]>("get_entry_count_by_day", { year });
+}
+
+export async function getEntryCountByHourOfWeek(): Promise<HourOfWeekCount[]> {
+  return invoke<HourOfWeekCount[]>("get_entry_count_by_hour_of_week");
+}
+
+export async function validateDatabaseEntries(): Promise<EntryValidationIssue[]> {
+  return invoke<EntryValidationIssue[]>("validate_database_entries");
+}
+
+export async function repairDatabaseEntry(id: number): Promise<RepairResult> {
+  return invoke<RepairResult>("repair_database_entry", { id });
+}
+
+export async function getMigrationStatus(): Promise<MigrationStatus> {
+  return invoke<MigrationStatus>("get_migration_status");
+}
+
+export async function getArchivedEntries(
+  limit: number,
+  offset: number,
+): Promise<Entry[]> {
+  return invoke<Entry[]>("get_archived_entries", { limit, offset });
+}
+
+export async function exportEntries(
+  format: ExportFormat,
+  path: string,
+): Promise<string> {
+  return invoke<string>("export_entries", { format, path });
+}
+
+export async function archiveOldEntries(olderThanDays: number): Promise<number> {
+  return invoke<number>("archive_old_entries", { olderThanDays });
+}
+
+export async function restoreFromArchive(id: number): Promise<boolean> {
+  return invoke<boolean>("restore_from_archive", { id });
+}
+
+export async function getEntriesByDuration(
+  minMs: number,
+  maxMs: number,
+  limit: number,
+  offset: number,
+): Promise<Entry[]> {
+  return invoke<Entry[]>("get_entries_by_duration", {
+    minMs,
+    maxMs,
+    limit,
+    offset,
+  });
+}
+
+export async function getAvgRecordingDuration(): Promise<number> {
+  return invoke<number>("get_avg_recording_duration");
+}
+
+export async function generateEntryShareUrl(
+  id: number,
+  expiryHours?: number,
+): Promise<string> {
+  return invoke<string>("generate_entry_share_url", { id, expiryHours });
+}
+
+export async function verifyShareToken(
+  id: number,
+  token: string,
+  expires: number,
+): Promise<boolean> {
+  return invoke<boolean>("verify_share_token", { id, token, expires });
+}
+
+export async function pasteToForeground(
+  text: string,
+  modeId?: string,
+): Promise<void> {
+  return invoke<void>("paste_to_foreground", { text, modeId });
+}
+
+export async function setPasteMethod(method: PasteMethod): Promise<void> {
+  return invoke<void>("set_paste_method", { method });
+}
+
+export async function getHttpDebugLog(): Promise<HttpExchange[]> {
+  return invoke<HttpExchange[]>("get_http_debug_log");
+}
+
+export async function getEnvFilesLoaded(): Promise<string[]> {
+  return invoke<string[]>("get_env_files_loaded");
+}
+
+export async function benchmarkTranscriptionPipeline(
+  nIterations: number,
+): Promise<PipelineBenchmark> {
+  return invoke<PipelineBenchmark>("benchmark_transcription_pipeline", {
+    nIterations,
+  });
 }
@@ -2,6 +2,11 @@ export interface AIResponse {
   text: string;
   model: string;
   usage?: TokenUsage;
+  system_fingerprint?: string | null;
+  finish_reason?: string | null;
+  request_id: string;
+  suggestion_used: boolean;
+  truncated: boolean;
 }
 
 export interface TokenUsage {
@@ -9,3 +14,55 @@ export interface TokenUsage {
   completion_tokens: number;
   total_tokens: number;
 }
+
+export interface StreamChunkEvent {
+  stream_id: string;
+  session_id: string | null;
+  content: string;
+  done: boolean;
+}
+
+export interface StreamDoneEvent {
+  stream_id: string;
+  text: string;
+  model: string;
+  usage: TokenUsage;
+  truncated: boolean;
+}
+
+export interface PromptPreview {
+  system: string | null;
+  user: string;
+  estimated_tokens: number;
+  variables_used: string[];
+  variables_missing: string[];
+}
+
+export type ContextFormat =
+  | "numbered"
+  | "bullets"
+  | "plain"
+  | { custom: string };
+
+export type AIPriority = "realtime" | "batch";
+
+export interface QueueDepth {
+  realtime: number;
+  batch: number;
+}
+
+export interface ModelRotationState {
+  current_model: string;
+  call_count: number;
+  last_429_secs_ago: number | null;
+}
+
+export interface ModelInfo {
+  id: string;
+  display_name: string;
+  context_window: number;
+  cost_per_1k_input: number;
+  cost_per_1k_output: number;
+  supports_streaming: boolean;
+  supports_vision: boolean;
+}
@@ -0,0 +1,27 @@
+import type { PasteMethod } from "./paste";
+
+export interface ConfigSummary {
+  ai_provider: "vertexai" | "openai" | "anthropic" | "ollama";
+  ai_provider_fallback: ("vertexai" | "openai" | "anthropic" | "ollama")[];
+  openai_api_key_set: boolean;
+  anthropic_api_key_set: boolean;
+  openai_model: string | null;
+  anthropic_model: string | null;
+  vertex_model: string | null;
+  google_cloud_project: string | null;
+  google_cloud_location: string;
+  ollama_host: string;
+  ollama_model: string | null;
+  test_mode: boolean;
+  whisper_backend: "native" | "whisper" | "realtime";
+  max_recording_secs: number;
+  min_free_disk_bytes: number;
+  paste_delay_ms: number;
+  paste_method: PasteMethod;
+  audio_dir: string;
+  whisper_models_dir: string;
+  debug: boolean;
+  fuzzy_mode_matching: boolean;
+  notifications_enabled: boolean;
+  http_server_port: number | null;
+}
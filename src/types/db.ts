@@ -8,6 +8,12 @@ export interface Entry {
   completion_tokens: number | null;
   total_tokens: number | null;
   created_at: string;
+  request_id: string | null;
+  device_name: string | null;
+  audio_duration_ms: number | null;
+  audio_hash: string | null;
+  updated_at: string | null;
+  is_favorite: boolean;
 }
 
 export interface NewEntry {
@@ -18,4 +24,91 @@ export interface NewEntry {
   prompt_tokens: number | null;
   completion_tokens: number | null;
   total_tokens: number | null;
+  request_id: string | null;
+  device_name?: string | null;
+  audio_duration_ms?: number | null;
+  audio_hash?: string | null;
+}
+
+export interface EntryFilter {
+  mode_id?: string | null;
+  from?: string | null;
+  to?: string | null;
+  limit: number;
+  offset?: number;
+}
+
+export type ExportFormat = "json" | "markdown";
+
+export type DiffKind = "equal" | "insert" | "delete";
+
+export interface DiffOp {
+  kind: DiffKind;
+  text: string;
+}
+
+export interface DiffStats {
+  additions: number;
+  deletions: number;
+  similarity: number;
+}
+
+export interface TextDiff {
+  operations: DiffOp[];
+  stats: DiffStats;
+}
+
+export interface MetricStats {
+  avg: number;
+  min: number;
+  max: number;
+}
+
+export interface RecordingMetricsSummary {
+  count: number;
+  duration_ms: MetricStats;
+  sample_rate: MetricStats;
+  channels: MetricStats;
+  noise_floor_rms: MetricStats;
+  peak_amplitude: MetricStats;
+  vad_speech_ratio: MetricStats;
+  most_common_sample_rate: number | null;
+}
+
+export interface DayCount {
+  date: string;
+  count: number;
+}
+
+export interface HourOfWeekCount {
+  day: number;
+  hour: number;
+  count: number;
+}
+
+export interface EntryValidationIssue {
+  id: number;
+  field: string;
+  issue: string;
+}
+
+export interface RepairResult {
+  id: number;
+  repaired_fields: string[];
+}
+
+export interface MigrationStatus {
+  version: number;
+  is_migrating: boolean;
+  current_step: string | null;
+}
+
+export interface MigrationProgressEvent {
+  step: number;
+  total: number;
+  name: string;
+}
+
+export interface OpenSharedEntryEvent {
+  entry: Entry;
 }
@@ -0,0 +1,20 @@
+export interface HttpExchange {
+  timestamp: number;
+  method: string;
+  url: string;
+  request_headers: [string, string][];
+  request_body_bytes: number | null;
+  status: number | null;
+  response_body_preview: string | null;
+  response_body_base64: boolean;
+  error: string | null;
+  duration_ms: number;
+}
+
+export interface PipelineBenchmark {
+  avg_pcm_to_wav_us: number;
+  avg_recognizer_call_ms: number;
+  avg_total_ms: number;
+  p99_ms: number;
+  throughput_secs_per_sec: number;
+}
@@ -0,0 +1,6 @@
+export interface PingResponse {
+  db_ready: boolean;
+  audio_ready: boolean;
+  ai_config_valid: boolean;
+  startup_warnings: string[];
+}
@@ -0,0 +1,16 @@
+export type PttMode = "hold" | "toggle";
+
+export interface PttConfig {
+  keycode: number;
+  modifier_flags: number;
+  max_duration_secs: number;
+  tap_threshold_ms: number;
+  mode?: PttMode;
+  ptt_start_command?: string | null;
+  ptt_stop_command?: string | null;
+}
+
+export interface PttHookFailedEvent {
+  hook: "start" | "stop";
+  error: string;
+}
@@ -1,7 +1,47 @@
+import type { PasteConfig } from "./paste";
+
 export interface Mode {
   id: string;
   label: string;
   description: string;
   ai_enabled: boolean;
   ai_prompt?: string;
+  ai_prompt_file?: string;
+  use_max_completion_tokens?: boolean;
+  model_rotation?: string[];
+  temperature?: number;
+  max_tokens?: number;
+  include_clipboard_context?: boolean;
+  clipboard_label?: string;
+  line_wrap?: LineWrapConfig;
+  typography?: TypographicOpts;
+  normalize_japanese?: boolean;
+  apply_before_ai?: boolean;
+  paste?: PasteConfig;
+  max_prompt_chars?: number;
+  truncation_strategy?: TruncationStrategy;
+  max_response_chars?: number;
+  app_affinity?: string[];
+}
+
+export interface ModeSuggestionEvent {
+  mode_id: string | null;
+  app_name: string;
+}
+
+export type TruncationStrategy =
+  | "truncate_input"
+  | "truncate_context"
+  | "truncate_both"
+  | "fail";
+
+export interface LineWrapConfig {
+  max_chars_per_line: number;
+  preserve_paragraphs?: boolean;
+}
+
+export interface TypographicOpts {
+  typographic_quotes?: boolean;
+  em_dash?: boolean;
+  language_hint?: string;
 }
@@ -0,0 +1,15 @@
+export type PasteMethod =
+  | "clipboard_replace"
+  | "clipboard_save"
+  | "accessibility_insert"
+  | "type"
+  | "disabled";
+
+export interface PasteConfig {
+  paste_method: PasteMethod;
+}
+
+export interface PasteCompletedEvent {
+  method_used: PasteMethod;
+  success: boolean;
+}
@@ -3,6 +3,17 @@ export interface TranscriptionResult {
   confidence: number;
   is_final: boolean;
   timestamp: number;
+  request_id: string;
+  language_detected: string | null;
+  used_fallback: boolean;
+  segments?: Segment[];
+  duplicate_skipped: boolean;
+}
+
+export interface Segment {
+  start_ms: number;
+  end_ms: number;
+  text: string;
 }
 
 export interface RecordingResult {
@@ -10,4 +21,61 @@ export interface RecordingResult {
   sample_rate: number;
   channels: number;
   duration_ms: number;
+  duplicate_detected: boolean;
+  meta: RecordingMeta;
+}
+
+export interface RecordingData {
+  audio_data: number[];
+  sample_rate: number;
+  channels: number;
+}
+
+export interface RecordingMeta {
+  session_id: string;
+  started_at_unix_ms: number;
+  stopped_at_unix_ms: number;
+  device_name: string;
+  sample_format: string;
+  noise_floor_rms: number;
+}
+
+export type ImportStage = "reading" | "converting" | "transcribing" | "saving";
+
+export interface ImportProgressEvent {
+  stage: ImportStage;
+}
+
+export interface AudioDeviceInfo {
+  name: string;
+  is_default: boolean;
+}
+
+export interface AudioDeviceReconnectedEvent {
+  device_name: string;
+  buffered_samples_preserved: boolean;
+}
+
+export interface AudioDeviceDisconnectedEvent {
+  device_name: string;
+}
+
+export interface AudioLevelEvent {
+  rms: number;
+  peak: number;
+}
+
+export interface WhisperModelInfo {
+  name: string;
+  filename: string;
+  size_mb: number;
+  is_downloaded: boolean;
+  path: string | null;
+}
+
+export interface DownloadProgress {
+  model_name: string;
+  downloaded_bytes: number;
+  total_bytes: number | null;
+  done: boolean;
 }